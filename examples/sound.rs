@@ -6,6 +6,7 @@ extern crate unrust;
 extern crate unrust_derive;
 
 use unrust::world::{Actor, Camera, World, WorldBuilder};
+use unrust::engine::sound::synth::SfxrParams;
 use unrust::engine::{GameObject, SoundHandle};
 use unrust::world::events::AppEvent;
 
@@ -16,6 +17,8 @@ use unrust::imgui;
 struct SoundEmitter {
     flute_id: SoundHandle,
     sword_id: SoundHandle,
+    coin_id: SoundHandle,
+    laser_seed: u32,
     mouse_pos: f64,
     flute_on: bool,
 }
@@ -24,9 +27,13 @@ impl SoundEmitter {
     pub fn new(world: &mut World) -> SoundEmitter {
         let flute_id = world.sound.load_sound("sounds/flute_48000.wav");
         let sword_id = world.sound.load_sound("sounds/sword.wav");
+        // sound effects can also be generated at runtime
+        let coin_id = world.sound.add_buffer(SfxrParams::pickup(42).to_buffer());
         Self {
             flute_id,
             sword_id,
+            coin_id,
+            laser_seed: 0,
             mouse_pos: 0.0,
             flute_on: true,
         }
@@ -47,6 +54,8 @@ impl Actor for SoundEmitter {
     fn update(&mut self, _go: &mut GameObject, world: &mut World) {
         let mut sword = false;
         let mut flute = false;
+        let mut coin = false;
+        let mut laser = false;
         for evt in world.events().iter() {
             match evt {
                 &AppEvent::MouseUp(ref event) => {
@@ -56,6 +65,11 @@ impl Actor for SoundEmitter {
                         flute = true;
                     }
                 }
+                &AppEvent::KeyUp(ref key) => match key.code.as_str() {
+                    "KeyC" => coin = true,
+                    "KeyL" => laser = true,
+                    _ => (),
+                },
                 &AppEvent::MousePos((x, _)) => {
                     self.mouse_pos = x;
                 }
@@ -73,6 +87,15 @@ impl Actor for SoundEmitter {
                 self.mouse_pos as f32 / 640.0,
             );
         }
+        if coin {
+            world.sound.play_sound(self.coin_id, None, false, 0, 1.0, 0.5);
+        }
+        if laser {
+            // a different laser each time, synthesized while playing
+            self.laser_seed += 1;
+            let voice = SfxrParams::laser(self.laser_seed).voice();
+            world.sound.play_voice(Box::new(voice), None, 0, 1.0, 0.5);
+        }
         if flute {
             // start/stop flute on right click
             if self.flute_on {
@@ -89,7 +112,7 @@ impl Actor for SoundEmitter {
         imgui::pivot((1.0, 1.0));
         imgui::label(
             Native(1.0, 1.0) - Pixel(8.0, 8.0),
-            "right click to start/stop playing flute\nleft click to hit with your sword!",
+            "right click to start/stop playing flute\nleft click to hit with your sword!\npress C for a coin, L for a laser",
         );
     }
 }
@@ -3,8 +3,9 @@ extern crate unrust;
 extern crate unrust_derive;
 
 use unrust::actors::{FirstPersonCamera, ShadowPass, SkyBox};
-use unrust::engine::{AssetError, AssetSystem, DirectionalLight, GameObject, Light, Material, Mesh,
-                     ObjMaterial, PointLight, Prefab, RenderQueue, TextureWrap};
+use unrust::engine::{AssetError, AssetGroup, AssetState, AssetSystem, DirectionalLight,
+                     GameObject, Light, Material, Mesh, ObjMaterial, PointLight, Prefab,
+                     RenderQueue, TextureWrap};
 use unrust::math::*;
 use unrust::world::events::*;
 use unrust::world::{Actor, Handle, Processor, World, WorldBuilder};
@@ -144,12 +145,13 @@ impl Actor for MainScene {
         {
             let mut reset = false;
             let mut toggle_normal_map = false;
+            let ui_wants_input = world.ui_wants_input();
 
             for evt in world.events().iter() {
                 self.last_event = Some(evt.clone());
 
                 match evt {
-                    &AppEvent::KeyUp(ref key) => match key.code.as_str() {
+                    &AppEvent::KeyUp(ref key) if !ui_wants_input => match key.code.as_str() {
                         "KeyU" => toggle_normal_map = true,
                         "Space" => self.animate_light = !self.animate_light,
                         "Escape" => reset = true,
@@ -236,15 +238,17 @@ impl Actor for MainScene {
 }
 
 #[derive(Actor)]
-pub struct WaveObjActor {}
+pub struct WaveObjActor {
+    group: Option<AssetGroup>,
+}
 
 impl WaveObjActor {
     fn new() -> WaveObjActor {
-        WaveObjActor {}
+        WaveObjActor { group: None }
     }
 }
 
-fn build_material(asys: &AssetSystem, obj_mat: ObjMaterial) -> Rc<Material> {
+fn build_material(asys: &AssetSystem, group: &AssetGroup, obj_mat: ObjMaterial) -> Rc<Material> {
     let shader_program = match obj_mat.normal_map {
         Some(_) => asys.new_program("obj_nm"),
         None => asys.new_program("obj"),
@@ -257,7 +261,7 @@ fn build_material(asys: &AssetSystem, obj_mat: ObjMaterial) -> Rc<Material> {
         obj_mat.ambient.unwrap_or(Vector3::new(0.2, 0.2, 0.2)),
     );
 
-    let diffuse_tex = asys.new_texture(&obj_mat.diffuse_map.unwrap_or("default_white".to_owned()));
+    let diffuse_tex = group.add_texture(asys, &obj_mat.diffuse_map.unwrap_or("default_white".to_owned()));
     diffuse_tex.wrap_u.set(TextureWrap::Repeat);
     diffuse_tex.wrap_v.set(TextureWrap::Repeat);
     material.set(
@@ -267,7 +271,7 @@ fn build_material(asys: &AssetSystem, obj_mat: ObjMaterial) -> Rc<Material> {
     material.set("uMaterial.diffuse_tex", diffuse_tex);
 
     let specular_tex =
-        asys.new_texture(&obj_mat.specular_map.unwrap_or("default_black".to_owned()));
+        group.add_texture(asys, &obj_mat.specular_map.unwrap_or("default_black".to_owned()));
     specular_tex.wrap_u.set(TextureWrap::Repeat);
     specular_tex.wrap_v.set(TextureWrap::Repeat);
     material.set(
@@ -280,7 +284,7 @@ fn build_material(asys: &AssetSystem, obj_mat: ObjMaterial) -> Rc<Material> {
     material.set("uMaterial.transparent", obj_mat.alpha.unwrap_or(1.0));
 
     obj_mat.normal_map.as_ref().map(|nm| {
-        let n_tex = asys.new_texture(nm);
+        let n_tex = group.add_texture(asys, nm);
         n_tex.wrap_u.set(TextureWrap::Repeat);
         n_tex.wrap_v.set(TextureWrap::Repeat);
 
@@ -315,11 +319,47 @@ impl Actor for WaveObjActor {
             }
         };
 
-        db.new_prefab(
+        // the textures of the materials are added to the group while the prefab loads
+        let group = AssetGroup::new(*db);
+        let material_group = group.clone();
+
+        group.add_prefab(
+            *db,
             "sponza/sponza.obj",
-            Box::new(build_material),
+            Box::new(move |asys: &AssetSystem, obj_mat: ObjMaterial| {
+                build_material(asys, &material_group, obj_mat)
+            }),
             Box::new(prefab_handler),
         );
+
+        self.group = Some(group);
+    }
+
+    fn update(&mut self, _go: &mut GameObject, _world: &mut World) {
+        use imgui::Metric::*;
+
+        // the failed textures are drawn with a fallback, stop waiting for them
+        let loading = match self.group {
+            Some(ref group) => match group.state() {
+                AssetState::Loading => true,
+                _ => false,
+            },
+            None => false,
+        };
+
+        if !loading {
+            self.group = None;
+            return;
+        }
+
+        if let Some(ref group) = self.group {
+            imgui::pivot((0.5, 0.5));
+            imgui::progress_bar(
+                Native(0.5, 0.5),
+                Pixel(300.0, 24.0),
+                group.progress(),
+            );
+        }
     }
 }
 
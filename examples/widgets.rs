@@ -0,0 +1,107 @@
+extern crate uni_app;
+extern crate unrust;
+
+#[macro_use]
+extern crate unrust_derive;
+
+use unrust::engine::GameObject;
+use unrust::world::{Actor, Camera, World, WorldBuilder};
+
+// GUI
+use unrust::imgui;
+
+#[derive(Actor)]
+struct MainScene {
+    clicks: u32,
+    fullscreen: bool,
+    volume: f32,
+    quality: usize,
+    name: String,
+}
+
+impl Actor for MainScene {
+    fn start(&mut self, _go: &mut GameObject, world: &mut World) {
+        let go = world.new_game_object();
+        go.borrow_mut().add_component(Camera::default());
+    }
+
+    fn update(&mut self, _go: &mut GameObject, world: &mut World) {
+        use imgui::Metric::*;
+
+        imgui::pivot((0.0, 0.0));
+
+        if imgui::button(Pixel(16.0, 16.0), Pixel(120.0, 24.0), "Click me") {
+            self.clicks += 1;
+        }
+        imgui::label(Pixel(148.0, 24.0), &format!("clicked {} times", self.clicks));
+
+        if imgui::checkbox(Pixel(16.0, 56.0), "fullscreen", &mut self.fullscreen) {
+            world.set_fullscreen(self.fullscreen);
+        }
+
+        imgui::label(Pixel(16.0, 92.0), "volume");
+        imgui::slider(
+            Pixel(80.0, 88.0),
+            Pixel(160.0, 16.0),
+            &mut self.volume,
+            0.0,
+            1.0,
+        );
+
+        imgui::label(Pixel(16.0, 124.0), "name");
+        imgui::text_input(Pixel(80.0, 118.0), Pixel(160.0, 20.0), &mut self.name);
+
+        imgui::label(Pixel(16.0, 156.0), "quality");
+        imgui::dropdown(
+            Pixel(80.0, 150.0),
+            Pixel(160.0, 20.0),
+            &["low", "medium", "high"],
+            &mut self.quality,
+        );
+
+        // a panel on the right, its widgets are stacked
+        let mut style = imgui::style();
+        style.rounding = 6.0;
+        style.accent = [0xe0, 0x80, 0x40, 0xff];
+        imgui::push_style(style);
+
+        imgui::pivot((1.0, 0.0));
+        imgui::begin_panel("Log", Native(1.0, 0.0) + Pixel(-16.0, 16.0), Pixel(200.0, 240.0));
+        imgui::pivot((0.0, 0.0));
+
+        imgui::begin_horizontal();
+        if imgui::button(Pixel(0.0, 0.0), Pixel(80.0, 20.0), "Clear") {
+            self.clicks = 0;
+        }
+        imgui::label(Pixel(0.0, 4.0), &format!("{} lines", self.clicks));
+        imgui::end_horizontal();
+
+        imgui::begin_scroll(Pixel(0.0, 0.0), Native(1.0, 1.0) + Pixel(0.0, -24.0));
+        for i in 0..self.clicks {
+            imgui::label(Pixel(0.0, 0.0), &format!("click #{}", i + 1));
+        }
+        imgui::end_scroll();
+
+        imgui::end_panel();
+        imgui::pop_style();
+    }
+}
+
+pub fn main() {
+    let mut world = WorldBuilder::new("Widgets demo")
+        .with_size((640, 480))
+        .with_stats(false)
+        .build();
+
+    let scene = world.new_game_object();
+    scene.borrow_mut().add_component(MainScene {
+        clicks: 0,
+        fullscreen: false,
+        volume: 0.5,
+        quality: 1,
+        name: "player".into(),
+    });
+    drop(scene);
+
+    world.event_loop();
+}
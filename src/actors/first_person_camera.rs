@@ -1,111 +1,58 @@
 use engine::{Camera, Component, ComponentType, GameObject};
 use uni_app::AppEvent;
-use world::{Actor, Processor, World};
+use world::{Actor, Binding, CVar, Processor, World};
 
 use math::*;
 use std::f32::consts::PI;
 use std::sync::Arc;
 
-bitflags! {
-    struct Movement: u32 {
-        const TURN_LEFT = 1;
-        const TURN_RIGHT = 1 << 2;
-        const FORWARD = 1 << 3;
-        const BACKWARD = 1 << 4;
-        const UP = 1 << 5;
-        const DOWN = 1 << 6;
-        const LEFT = 1 << 7;
-        const RIGHT = 1 << 8;
-        const MOUSE = 1 << 16;
-    }
-}
+/// Input actions used by the camera, they can be rebound through `world.input`
+pub const FORWARD: &str = "camera_forward";
+pub const STRAFE: &str = "camera_strafe";
+pub const UP: &str = "camera_up";
+pub const TURN: &str = "camera_turn";
+pub const LOOK: &str = "camera_look";
 
-#[derive(Component)]
+#[derive(Component, Inspect)]
 pub struct FirstPersonCamera {
+    #[inspect]
     pub speed: f32,
+    #[inspect]
     pub angle_speed: f32,
 
+    #[inspect]
     pub position: Vector3<f32>,
     pub direction: Vector3<f32>,
 
     camera: Option<Arc<Component>>,
+    /// "fpc_speed" in the console
+    speed_var: Option<CVar<f32>>,
 
-    state: Movement,
-    handlers: Vec<(Movement, String, Box<Fn(&mut FirstPersonCamera, f64)>)>,
-    mouse_pos:  Vector2<i32>,
     mouse_sensitivity: f32,
 
     camera_pitch: f32,
     pub camera_yaw : f32,
-    current_pitch: f32,
-    current_yaw : f32,
-
-    clicked: bool,
-    click_pos:  Vector2<i32>,
 }
 
 impl Processor for FirstPersonCamera {
     fn new() -> FirstPersonCamera {
-        use math::*;
-
-        let mut m = FirstPersonCamera {
+        FirstPersonCamera {
             speed: 10.0,
             angle_speed: 0.5,
-            state: Movement::empty(),
-            handlers: Vec::new(),
             camera: None,
+            speed_var: None,
             position: Vector3::new(0.0, 0.0, -3.0),
             direction: Vector3::new(0.0, 0.0, 1.0),
-            mouse_pos: Vector2::new(0,0),
             mouse_sensitivity: 0.005,
             camera_pitch: 0.0,
-            camera_yaw : - PI ,
-            current_pitch: 0.0,
-            current_yaw : 0.0,
-            clicked: false,
-            click_pos: Vector2::new(0,0),
-        };
-
-        let up = Vector3::unit_y();
-
-        m.add(Movement::TURN_LEFT, "KeyA", move |s, dt| {
-            s.direction = Quaternion::from_angle_y(Rad(s.angle_speed * dt as f32)) * s.direction;
-        });
-        m.add(Movement::TURN_RIGHT, "KeyD", move |s, dt| {
-            s.direction = Quaternion::from_angle_y(Rad(-s.angle_speed * dt as f32)) * s.direction
-        });
-        m.add(Movement::UP, "Kposition", move |s, dt| {
-            s.position = s.position + up * s.speed * dt as f32;
-        });
-        m.add(Movement::DOWN, "KeyC", move |s, dt| {
-            s.position = s.position + up * -s.speed * dt as f32;
-        });
-        m.add(Movement::FORWARD, "KeyW", move |s, dt| {
-            s.position = s.position + s.direction * s.speed * dt as f32;
-        });
-        m.add(Movement::BACKWARD, "KeyS", move |s, dt| {
-            s.position = s.position + s.direction * -s.speed * dt as f32;
-        });
-        m.add(Movement::LEFT, "KeyZ", move |s, dt| {
-            let right = s.direction.cross(up).normalize();
-            s.position = s.position - right * s.speed * dt as f32;
-        });
-        m.add(Movement::RIGHT, "KeyX", move |s, dt| {
-            let right = s.direction.cross(up).normalize();
-            s.position = s.position + right * s.speed * dt as f32;
-        });
-        m.add(Movement::MOUSE, "", move |s, dt| {
-
-            let delta = s.mouse_pos - s.click_pos;
-            s.current_yaw = s.camera_yaw + delta.x as f32  * s.mouse_sensitivity;
-            s.current_pitch = s.camera_pitch - delta.y as f32 * s.mouse_sensitivity;
-
-        });
-        m
+            camera_yaw : 0.0,
+        }
     }
 }
 
-
+fn key(code: &str, scale: f32) -> (Binding, f32) {
+    (Binding::Key(code.to_string()), scale)
+}
 
 impl Actor for FirstPersonCamera {
     fn start(&mut self, _go: &mut GameObject, world: &mut World) {
@@ -117,28 +64,69 @@ impl Actor for FirstPersonCamera {
 
             self.camera = Some(c);
         }
+
+        self.speed_var = Some(world.console().cvar("fpc_speed", self.speed));
+        world.inspector().register::<FirstPersonCamera>();
+
+        let input = &mut world.input;
+        input.bind_default(
+            FORWARD,
+            vec![
+                key("KeyW", 1.0),
+                key("KeyS", -1.0),
+                (Binding::PadAxis { player: 0, axis: 1 }, -1.0),
+            ],
+        );
+        input.bind_default(
+            STRAFE,
+            vec![
+                key("KeyZ", -1.0),
+                key("KeyX", 1.0),
+                (Binding::PadAxis { player: 0, axis: 0 }, 1.0),
+            ],
+        );
+        input.bind_default(UP, vec![key("KeyE", 1.0), key("KeyC", -1.0)]);
+        input.bind_default(
+            TURN,
+            vec![
+                key("KeyA", 1.0),
+                key("KeyD", -1.0),
+                (Binding::PadAxis { player: 0, axis: 2 }, -1.0),
+            ],
+        );
+        input.bind_default(LOOK, vec![(Binding::MouseButton(0), 1.0)]);
     }
 
     fn update(&mut self, _go: &mut GameObject, world: &mut World) {
+        if let Some(speed) = self.speed_var.as_ref().and_then(|v| v.take_changed()) {
+            self.speed = speed;
+        }
+
+        let mut motion = Vector2::new(0.0, 0.0);
         for evt in world.events().iter() {
-            self.handle_event(evt)
+            if let &AppEvent::MouseMotion(delta) = evt {
+                motion = motion + Vector2::new(delta.0, delta.1);
+            }
         }
 
-        let cam = world.current_camera().unwrap();
+        let dt = world.delta_time() as f32;
+        let input = &world.input;
+        let up = Vector3::unit_y();
+        let right = self.direction.cross(up).normalize();
 
-        let mut handlers = Vec::new();
-        handlers.append(&mut self.handlers);
+        self.position = self.position + self.direction * input.value(FORWARD) * self.speed * dt;
+        self.position = self.position + right * input.value(STRAFE) * self.speed * dt;
+        self.position = self.position + up * input.value(UP) * self.speed * dt;
 
-        for &(ref mv, _, ref h) in handlers.iter() {
-            if self.state.contains(*mv) {
-                h(self, world.delta_time());
-            }
-        }
+        // turning left decreases the yaw
+        self.camera_yaw -= input.value(TURN) * self.angle_speed * dt;
 
-        self.handlers.append(&mut handlers);
+        if input.pressed(LOOK) {
+            self.camera_yaw += motion.x * self.mouse_sensitivity;
+            self.camera_pitch -= motion.y * self.mouse_sensitivity;
+        }
 
         self.update_camera();
-
     }
 }
 
@@ -146,18 +134,18 @@ impl FirstPersonCamera {
 
     pub fn update_camera(&mut self) {
 
-        if self.current_pitch > (PI / 2.0) - 0.1 {
-            self.current_pitch =  (PI / 2.0)  - 0.1;
+        if self.camera_pitch > (PI / 2.0) - 0.1 {
+            self.camera_pitch =  (PI / 2.0)  - 0.1;
         }
 
-        if self.current_pitch < -(PI / 2.0) + 0.1 {
-            self.current_pitch = -(PI / 2.0) + 0.1;
+        if self.camera_pitch < -(PI / 2.0) + 0.1 {
+            self.camera_pitch = -(PI / 2.0) + 0.1;
         }
 
         let new_direction = Vector3::new(
-            Rad(self.current_yaw).cos() * Rad(self.current_pitch).cos(),
-            Rad(self.current_pitch).sin(),
-            Rad(self.current_yaw).sin() * Rad(self.current_pitch).cos(),
+            Rad(self.camera_yaw).cos() * Rad(self.camera_pitch).cos(),
+            Rad(self.camera_pitch).sin(),
+            Rad(self.camera_yaw).sin() * Rad(self.camera_pitch).cos(),
         ).normalize();
         self.direction = new_direction;
 
@@ -174,81 +162,6 @@ impl FirstPersonCamera {
         }
     }
 
-    fn add<F>(&mut self, mv: Movement, key: &str, f: F)
-    where
-        F: Fn(&mut FirstPersonCamera, f64) + 'static,
-    {
-        self.handlers.push((mv, key.to_string(), Box::new(f)));
-    }
-
-    fn key_down(&mut self, input: &str) {
-        for &(ref mv, ref key, _) in self.handlers.iter() {
-            if input == key {
-                self.state.insert(*mv)
-            }
-        }
-    }
-
-    fn key_up(&mut self, input: &str) {
-        for &(ref mv, ref key, _) in self.handlers.iter() {
-            if input == key {
-                self.state.remove(*mv)
-            }
-        }
-    }
-
-    fn mouse_down(&mut self, input: usize) {
-        if input == 0 && !self.clicked  {
-            self.clicked = true;
-            self.click_pos = self.mouse_pos.clone();
-        }
-    }
-
-    fn mouse_up(&mut self, input: usize) {
-        if input == 0 && self.clicked  {
-            self.clicked = false;
-            self.click_pos = self.mouse_pos.clone();
-            self.camera_yaw = self.current_yaw;
-            self.camera_pitch = self.current_pitch;
-        }
-    }
-
-    fn mouse_pos(&mut self, input: &(i32,i32)) {
-        self.mouse_pos = Vector2::new(input.0,input.1);
-
-        if self.clicked {
-            self.state.insert(Movement::MOUSE);
-        }else {
-            self.state.remove(Movement::MOUSE);
-        }
-    }
-
-    fn handle_event(&mut self, evt: &AppEvent) {
-        match evt {
-            &AppEvent::KeyUp(ref key) => {
-                self.key_up(key.code.as_str());
-            }
-
-            &AppEvent::KeyDown(ref key) => {
-                self.key_down(key.code.as_str());
-            }
-
-            &AppEvent::MouseDown(ref button_event) => {
-                self.mouse_down(button_event.button);
-            }
-
-            &AppEvent::MouseUp(ref button_event) => {
-                self.mouse_up(button_event.button);
-            }
-
-            &AppEvent::MousePos(ref pos_tuple) => {
-                self.mouse_pos(pos_tuple);
-            }
-
-            _ => {}
-        }
-    }
-
     pub fn camera(&self) -> &ComponentType<Camera> {
         self.camera.as_ref().unwrap().try_as::<Camera>().unwrap()
     }
@@ -1,8 +1,7 @@
-use world::{Actor, Handle, World};
-use engine::{Asset, Camera, ClearOption, Component, CullMode, GameObject, Light,
-             Material, MaterialParamMap, Mesh, MeshBuffer, MeshData, RenderQueue, RenderTexture,
-             TextureAttachment};
-use engine::mesh_util::*;
+use world::{Actor, Binding, CVar, World};
+use engine::{Camera, ClearOption, Component, GameObject, Light, Material, MaterialParamMap,
+             RenderQueue, RenderTexture, TextureAttachment};
+use engine::debug;
 
 use world::Processor;
 
@@ -11,6 +10,10 @@ use std::sync::Arc;
 
 use math::*;
 
+/// Input actions of the debug view, they can be rebound through `world.input`
+pub const CAPTURE_FRUSTUMS: &str = "shadow_capture_frustums";
+pub const TOGGLE_DEBUG: &str = "shadow_toggle_debug";
+
 struct ShadowMap {
     name: String,
     rt: Rc<RenderTexture>,
@@ -29,102 +32,27 @@ pub struct ShadowPass {
     shadow_material: Option<Rc<Material>>,
     light_camera: Camera,
 
-    debug_gameobjects: Vec<Handle<GameObject>>,
+    debug_frustums: Vec<DebugFrustum>,
     debug_mode: bool,
 
     use_scene_aabb: bool,
-}
 
-#[repr(usize)]
-enum FrustumPoint {
-    NLB = 0usize,
-    NRB = 1usize,
-    NRT = 2usize,
-    NLT = 3usize,
-
-    FLB = 4usize,
-    FRB = 5usize,
-    FRT = 6usize,
-    FLT = 7usize,
+    /// "shadow_partitions" in the console
+    partitions_var: Option<CVar<[f32; 4]>>,
 }
 
-fn add_debug_frustum(
-    view: &Matrix4<f32>,
-    points: &[Point3<f32>],
-    world: &mut World,
-    debug_gameobjects: &mut Vec<Handle<GameObject>>,
-) {
-    let mut wpoints = Vec::new();
-
-    for p in points.iter() {
-        let world_p = view.inverse_transform().unwrap().transform_point(*p);
-        wpoints.push(world_p.to_vec());
+/// Corners of a captured light frustum in world space, in the order of `debug::cuboid`
+type DebugFrustum = [Vector3f; 8];
+
+fn debug_frustum(view: &Matrix4<f32>, points: &[Point3<f32>; 8]) -> DebugFrustum {
+    let inv_view = view.inverse_transform().unwrap();
+    let mut corners = [Vector3::zero(); 8];
+
+    for (c, p) in corners.iter_mut().zip(points.iter()) {
+        *c = inv_view.transform_point(*p).to_vec();
     }
 
-    let go = world.new_game_object();
-    let db = world.asset_system();
-
-    let mut material = Material::new(db.new_program("phong"));
-    material.set("uMaterial.diffuse", db.new_texture("default_white"));
-    material.set("uMaterial.shininess", 32.0);
-    material.states.cull = Some(CullMode::Off);
-
-    let mut mesh = Mesh::new();
-    let mut mesh_data = MeshData::default();
-    use self::FrustumPoint::*;
-
-    // Near
-    mesh_data.add_quad([
-        wpoints[NLB as usize],
-        wpoints[NRB as usize],
-        wpoints[NRT as usize],
-        wpoints[NLT as usize],
-    ]);
-
-    // Far
-    mesh_data.add_quad([
-        wpoints[FLB as usize],
-        wpoints[FLT as usize],
-        wpoints[FRT as usize],
-        wpoints[FRB as usize],
-    ]);
-
-    // Left
-    mesh_data.add_quad([
-        wpoints[NLB as usize],
-        wpoints[NLT as usize],
-        wpoints[FLT as usize],
-        wpoints[FLB as usize],
-    ]);
-
-    // Right
-    mesh_data.add_quad([
-        wpoints[NRB as usize],
-        wpoints[FRB as usize],
-        wpoints[FRT as usize],
-        wpoints[NRT as usize],
-    ]);
-
-    // Top
-    mesh_data.add_quad([
-        wpoints[NLT as usize],
-        wpoints[NRT as usize],
-        wpoints[FRT as usize],
-        wpoints[FLT as usize],
-    ]);
-
-    // Bottom
-    mesh_data.add_quad([
-        wpoints[NLB as usize],
-        wpoints[FLB as usize],
-        wpoints[FRB as usize],
-        wpoints[NRB as usize],
-    ]);
-
-    mesh.add_surface(MeshBuffer::new(mesh_data), material);
-    go.borrow_mut().add_component(mesh);
-
-    debug_gameobjects.push(go);
+    corners
 }
 
 // References:
@@ -207,10 +135,9 @@ fn z_to_ndc(z: f32, n: f32, f: f32) -> f32 {
 
 fn compute_light_matrix(
     ctx: &LightMatrixContext,
-    world: &mut World,
     z_range: &(f32, f32),
     use_scene_aabb: bool,
-    debug: Option<&mut Vec<Handle<GameObject>>>,
+    debug: Option<&mut Vec<DebugFrustum>>,
 ) -> (Matrix4<f32>, (f32, f32)) {
     let bound_m = ctx.view * ctx.inv_pv;
 
@@ -265,22 +192,20 @@ fn compute_light_matrix(
 
         let max_z = ctx.light_space_scene_aabb.max.z;
 
-        if let Some(debug_gameobjects) = debug {
-            add_debug_frustum(
+        if let Some(debug_frustums) = debug {
+            debug_frustums.push(debug_frustum(
                 &ctx.view,
                 &[
                     Point3::new(aabb.min.x, aabb.min.y, aabb.min.z),
                     Point3::new(aabb.max.x, aabb.min.y, aabb.min.z),
-                    Point3::new(aabb.max.x, aabb.max.y, aabb.min.z),
                     Point3::new(aabb.min.x, aabb.max.y, aabb.min.z),
+                    Point3::new(aabb.max.x, aabb.max.y, aabb.min.z),
                     Point3::new(aabb.min.x, aabb.min.y, max_z),
                     Point3::new(aabb.max.x, aabb.min.y, max_z),
-                    Point3::new(aabb.max.x, aabb.max.y, max_z),
                     Point3::new(aabb.min.x, aabb.max.y, max_z),
+                    Point3::new(aabb.max.x, aabb.max.y, max_z),
                 ],
-                world,
-                debug_gameobjects,
-            );
+            ));
         }
 
         aabb.max.z = max_z;
@@ -350,8 +275,10 @@ impl ShadowMap {
         last_partition_z: f32,
         first_render: bool,
         use_scene_aabb: bool,
-        debug: Option<&mut Vec<Handle<GameObject>>>,
+        debug: Option<&mut Vec<DebugFrustum>>,
     ) {
+        profile_scope!("shadow");
+
         light_cam.render_texture = Some(self.rt.clone());
         light_cam.rect = Some(self.viewport);
 
@@ -362,22 +289,13 @@ impl ShadowMap {
         let partition = (last_partition_z, self.partition_z);
 
         let (lm, r) = match debug {
-            Some(debug_gameobjects) => {
-                for go in debug_gameobjects.iter() {
-                    world.remove_game_object(go);
-                }
-                debug_gameobjects.clear();
+            Some(debug_frustums) => {
+                debug_frustums.clear();
 
-                compute_light_matrix(
-                    &ctx,
-                    world,
-                    &partition,
-                    use_scene_aabb,
-                    Some(debug_gameobjects),
-                )
+                compute_light_matrix(&ctx, &partition, use_scene_aabb, Some(debug_frustums))
             }
 
-            None => compute_light_matrix(&ctx, world, &partition, use_scene_aabb, None),
+            None => compute_light_matrix(&ctx, &partition, use_scene_aabb, None),
         };
 
         self.light_matrix = lm;
@@ -430,6 +348,15 @@ impl ShadowPass {
         self.shadow_maps[3].partition_z = partitions[3];
     }
 
+    pub fn partitions(&self) -> [f32; 4] {
+        [
+            self.shadow_maps[0].partition_z,
+            self.shadow_maps[1].partition_z,
+            self.shadow_maps[2].partition_z,
+            self.shadow_maps[3].partition_z,
+        ]
+    }
+
     fn apply(&self, material: &Material) {
         material.set("uShadowEnabled", true);
         material.set("uShadowMapTexture", self.rt.as_texture());
@@ -439,6 +366,9 @@ impl ShadowPass {
 
 impl Actor for ShadowPass {
     fn start(&mut self, _go: &mut GameObject, world: &mut World) {
+        let partitions = self.partitions();
+        self.partitions_var = Some(world.console().cvar("shadow_partitions", partitions));
+
         let db = &mut world.asset_system();
 
         let shadow_mat = Material::new(db.new_program("unrust/shadow"));
@@ -452,25 +382,21 @@ impl Actor for ShadowPass {
             .as_mut()
             .unwrap()
             .insert(RenderQueue::Opaque);
+
+        let key = |code: &str| vec![(Binding::Key(code.to_string()), 1.0)];
+        world.input.bind_default(CAPTURE_FRUSTUMS, key("Space"));
+        world.input.bind_default(TOGGLE_DEBUG, key("KeyO"));
     }
 
     fn update(&mut self, _go: &mut GameObject, world: &mut World) {
-        use uni_app::AppEvent;
-        let mut capture = false;
-
-        for evt in world.events().iter() {
-            match evt {
-                &AppEvent::KeyUp(ref key) => match key.code.as_str() {
-                    "Space" => {
-                        capture = true;
-                    }
-                    "KeyO" => {
-                        self.debug_mode = !self.debug_mode;
-                    }
-                    _ => (),
-                },
-                _ => (),
-            }
+        if let Some(p) = self.partitions_var.as_ref().and_then(|v| v.take_changed()) {
+            self.set_partitions(&p);
+        }
+
+        // the input ignores the keys taken by the ui and the console
+        let capture = world.input.just_pressed(CAPTURE_FRUSTUMS);
+        if world.input.just_pressed(TOGGLE_DEBUG) {
+            self.debug_mode = !self.debug_mode;
         }
 
         // update light
@@ -496,7 +422,7 @@ impl Actor for ShadowPass {
                     true,
                     true,
                     if capture {
-                        Some(&mut self.debug_gameobjects)
+                        Some(&mut self.debug_frustums)
                     } else {
                         None
                     },
@@ -518,7 +444,7 @@ impl Actor for ShadowPass {
                         i == 0,
                         false,
                         if capture {
-                            Some(&mut self.debug_gameobjects)
+                            Some(&mut self.debug_frustums)
                         } else {
                             None
                         },
@@ -529,6 +455,10 @@ impl Actor for ShadowPass {
             }
         }
 
+        for corners in self.debug_frustums.iter() {
+            debug::cuboid(corners, debug::YELLOW);
+        }
+
         // update material params
         {
             for map in self.shadow_maps.iter_mut() {
@@ -602,8 +532,9 @@ impl Processor for ShadowPass {
             ],
             shadow_material: None,
             light_camera: Camera::new(),
-            debug_gameobjects: Vec::new(),
+            debug_frustums: Vec::new(),
             debug_mode: false,
+            partitions_var: None,
         }
     }
 
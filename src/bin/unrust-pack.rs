@@ -0,0 +1,149 @@
+//! Bundle the files of a directory in an asset pack
+//!
+//! ```text
+//! unrust-pack [-c] [-s png,jpg] <dir> <output>   pack the files of <dir>, e.g. static
+//! unrust-pack -l <pack>                          list the files of a pack
+//! ```
+//!
+//! The files are named by their path from `<dir>`, as the assets open them. With `-c` they
+//! are compressed, except the extensions given to `-s` which are already compressed.
+//! The packs found in `<dir>`, e.g. the output of a previous run, are not packed again.
+
+extern crate unrust;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use unrust::engine::{Pack, PackWriter};
+
+const DEFAULT_STORED: &'static str = "png,jpg,jpeg,ogg,mp3,dds";
+
+struct Options {
+    compress: bool,
+    stored: Vec<String>,
+    list: bool,
+    args: Vec<String>,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: unrust-pack [-c] [-s png,jpg] <dir> <output>");
+    eprintln!("       unrust-pack -l <pack>");
+    process::exit(1);
+}
+
+fn parse_options() -> Options {
+    let mut opts = Options {
+        compress: false,
+        stored: DEFAULT_STORED.split(',').map(|s| s.to_string()).collect(),
+        list: false,
+        args: Vec::new(),
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-c" => opts.compress = true,
+            "-l" => opts.list = true,
+            "-s" => {
+                let exts = args.next().unwrap_or_else(|| usage());
+                opts.stored = exts.split(',').map(|s| s.to_lowercase()).collect();
+            }
+            _ if arg.starts_with("-") => usage(),
+            _ => opts.args.push(arg),
+        }
+    }
+
+    opts
+}
+
+fn read_file(path: &Path) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    fs::File::open(path)?.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Add the files under `dir` recursively, named from `root`, except the packs and `output`
+fn add_dir(
+    w: &mut PackWriter,
+    opts: &Options,
+    root: &Path,
+    dir: &Path,
+    output: &Option<PathBuf>,
+) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    // the same directory always makes the same pack
+    entries.sort_by_key(|e| e.path());
+
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            add_dir(w, opts, root, &path, output)?;
+            continue;
+        }
+
+        let ext = path.extension()
+            .map_or(String::new(), |e| e.to_string_lossy().to_lowercase());
+        if ext == "pak" || (output.is_some() && fs::canonicalize(&path).ok() == *output) {
+            continue;
+        }
+
+        let name = path.strip_prefix(root)
+            .unwrap()
+            .to_string_lossy()
+            .replace("\\", "/");
+
+        let compress = opts.compress && !opts.stored.contains(&ext);
+
+        w.add(&name, read_file(&path)?, compress);
+    }
+
+    Ok(())
+}
+
+fn list(file: &str) -> io::Result<()> {
+    let pack = Pack::from_bytes(file, read_file(Path::new(file))?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)))?;
+
+    let mut names: Vec<_> = pack.entries().iter().collect();
+    names.sort_by_key(|&(name, _)| name);
+
+    for (name, e) in names {
+        let method = if e.compressed { "zlib" } else { "stored" };
+        println!("{:>10} {:>10} {:>6} {}", e.orig_size, e.size, method, name);
+    }
+
+    Ok(())
+}
+
+fn pack(opts: &Options, dir: &str, output: &str) -> io::Result<()> {
+    let mut w = PackWriter::new();
+    // the output of a previous run may be in `dir`
+    let skip = fs::canonicalize(output).ok();
+    add_dir(&mut w, opts, Path::new(dir), Path::new(dir), &skip)?;
+
+    let mut buf = Vec::new();
+    w.write(&mut buf)?;
+    fs::write(output, &buf)?;
+
+    println!("packed {} files in {} ({} bytes)", w.len(), output, buf.len());
+    Ok(())
+}
+
+fn main() {
+    let opts = parse_options();
+
+    let r = match (opts.list, opts.args.len()) {
+        (true, 1) => list(&opts.args[0]),
+        (false, 2) => pack(&opts, &opts.args[0], &opts.args[1]),
+        _ => usage(),
+    };
+
+    if let Err(e) = r {
+        eprintln!("unrust-pack: {}", e);
+        process::exit(1);
+    }
+}
@@ -1,24 +1,25 @@
 use std::rc::Rc;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 
-use engine::asset::{CubeMesh, PlaneMesh, QuadMesh, SkyboxMesh};
+use engine::asset::{primitive_key, primitive_mesh, CubeMesh, PlaneMesh, QuadMesh, SkyboxMesh};
 use engine::asset::default_font_bitmap::DEFAULT_FONT_DATA;
 use engine::asset::fs;
 use engine::asset::loader;
-use engine::asset::Resource;
+use engine::asset::{Pack, Resource};
 
-use engine::{Material, MeshBuffer, ShaderFs, ShaderProgram, ShaderVs, Texture, TextureFiltering,
-             TextureImage};
+use engine::{Font, Material, MeshBuffer, ShaderFs, ShaderProgram, ShaderVs, Texture,
+             TextureFiltering, TextureImage, TextureWrap};
 use std::fmt::Debug;
 use std::ops::Deref;
-use futures::{Async, Future};
+use futures::{future, Async, Future};
 use std::boxed::FnBox;
 
 use image;
 use image::ImageBuffer;
+use uni_app;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AssetError {
     NotReady,
     ReadBufferFail(String),
@@ -32,9 +33,11 @@ pub enum AssetError {
 
 pub type AssetResult<T> = Result<T, AssetError>;
 
-type PrefabHandler = Box<FnBox(AssetResult<loader::Prefab>)>;
-type MaterialHandler = Box<Fn(&AssetSystem, loader::ObjMaterial) -> Rc<Material>>;
+pub type PrefabHandler = Box<FnBox(AssetResult<loader::Prefab>)>;
+pub type MaterialHandler = Box<Fn(&AssetSystem, loader::ObjMaterial) -> Rc<Material>>;
 type AssetTask = Box<Future<Item = (), Error = AssetError>>;
+type ReloadTask = Box<FnMut() -> AssetResult<bool>>;
+type ErrorHandler = Box<Fn(&AssetError)>;
 
 pub trait AssetSystem {
     fn new() -> Self
@@ -49,14 +52,31 @@ pub trait AssetSystem {
 
     fn new_mesh_buffer(&self, name: &str) -> Rc<MeshBuffer>;
 
+    fn new_font(&self, name: &str) -> Rc<Font>;
+
     fn new_prefab(&self, name: &str, mh: MaterialHandler, f: PrefabHandler);
 
+    /// Load a pack and serve its files over the loose ones, the files opened
+    /// meanwhile wait for it. The packs mounted last override the previous ones.
+    fn mount_pack(&self, name: &str);
+
     fn reset(&mut self);
 
     fn step(&mut self);
 
     fn loading_files(&self) -> Vec<String>;
 
+    /// The bytes read or decoded of each file, see `AssetGroup` for the progress of a batch
+    fn file_progress(&self) -> fs::FileProgress;
+
+    /// The assets which failed to reload and the shaders which failed to compile
+    fn errors(&self) -> Vec<String>;
+
+    /// Report an asset which failed to load, it is logged and given to the error handler once
+    fn report_error(&self, err: &AssetError);
+
+    fn set_error_handler(&self, f: ErrorHandler);
+
     fn execute(&self, AssetTask);
 }
 
@@ -90,6 +110,18 @@ pub trait LoadableAsset: Asset {
     }
 }
 
+/// An asset which is loaded again when its files change on disk
+pub trait ReloadableAsset: LoadableAsset {
+    /// Whether the asset loaded as `name` uses `file`
+    fn depends_on(&self, name: &str, file: &str) -> bool;
+
+    /// Poll a resource being reloaded, returns whether it is ready
+    fn poll_reload(r: &Self::Resource) -> AssetResult<bool>;
+
+    /// Swap in place the data of a reloaded resource, all the handles see the new data
+    fn reload(&self, r: Self::Resource);
+}
+
 type PrefabFuture = Box<Future<Item = loader::Prefab, Error = AssetError>>;
 
 pub struct AssetDatabaseContext<FS> {
@@ -98,9 +130,21 @@ pub struct AssetDatabaseContext<FS> {
     textures: RefCell<HashMap<String, Rc<Texture>>>,
     mesh_buffers: RefCell<HashMap<String, Rc<MeshBuffer>>>,
     programs: RefCell<HashMap<String, Rc<ShaderProgram>>>,
+    fonts: RefCell<HashMap<String, Rc<Font>>>,
 
     pending_prefabs: RefCell<Vec<(PrefabHandler, PrefabFuture)>>,
     pending_tasks: RefCell<Vec<AssetTask>>,
+
+    pending_reloads: RefCell<Vec<(String, ReloadTask)>>,
+    reload_errors: RefCell<HashMap<String, String>>,
+
+    reported_errors: RefCell<HashSet<String>>,
+    error_handler: RefCell<Option<ErrorHandler>>,
+
+    progress: fs::FileProgress,
+
+    /// the packs being loaded
+    pending_mounts: Rc<Cell<usize>>,
 }
 
 pub struct AssetDatabase<FS, F>
@@ -141,7 +185,20 @@ where
     F: fs::File + 'static,
 {
     fn new_file(&self, name: &str) -> fs::FileFuture {
-        self.fs.open(&self.get_filename(name))
+        let filename = self.get_filename(name);
+        if self.pending_mounts.get() == 0 {
+            return self.progress.track(self.fs.open(&filename));
+        }
+
+        // the file may be in a pack being loaded
+        let pending_mounts = self.pending_mounts.clone();
+        let db = self.clone();
+        let f = future::poll_fn(move || match pending_mounts.get() {
+            0 => Ok(Async::Ready(())),
+            _ => Ok(Async::NotReady),
+        }).and_then(move |_| db.fs.open(&filename));
+
+        self.progress.track(Box::new(f))
     }
 
     fn new_program(&self, name: &str) -> Rc<ShaderProgram> {
@@ -156,6 +213,23 @@ where
 
     fn new_mesh_buffer(&self, name: &str) -> Rc<MeshBuffer> {
         let mut a = self.mesh_buffers.borrow_mut();
+
+        // the primitives with parameters are generated on first use, then cached by their
+        // name with all the parameters in order
+        let key = primitive_key(name);
+        let name = key.as_ref().map_or(name, |k| k.as_str());
+
+        if !a.contains_key(name) {
+            if let Some(mesh_data) = primitive_mesh(name) {
+                a.insert(name.into(), MeshBuffer::new(mesh_data));
+            }
+        }
+
+        self.new_asset(&mut a, name)
+    }
+
+    fn new_font(&self, name: &str) -> Rc<Font> {
+        let mut a = self.fonts.borrow_mut();
         self.new_asset(&mut a, name)
     }
 
@@ -163,6 +237,11 @@ where
         self.textures.borrow_mut().clear();
         self.mesh_buffers.borrow_mut().clear();
         self.programs.borrow_mut().clear();
+        self.fonts.borrow_mut().clear();
+        self.pending_reloads.borrow_mut().clear();
+        self.reload_errors.borrow_mut().clear();
+        self.reported_errors.borrow_mut().clear();
+        self.progress.clear();
 
         self.setup();
     }
@@ -172,25 +251,41 @@ where
         self.pending_prefabs.borrow_mut().push((f, prefab));
     }
 
+    fn mount_pack(&self, name: &str) {
+        let filename = self.get_filename(name);
+        let pack_name = name.to_string();
+        let f = self.progress.track(self.fs.open(&filename));
+
+        self.pending_mounts.set(self.pending_mounts.get() + 1);
+
+        let db = self.clone();
+        let mount = f.then(move |r| {
+            let mounted = r.and_then(|mut file| file.read_binary())
+                .map_err(AssetError::FileIoError)
+                .and_then(|data| Pack::from_bytes(&pack_name, data))
+                .map(|pack| {
+                    if !db.fs.mount(Rc::new(pack)) {
+                        uni_app::App::print(format!(
+                            "{} can not be mounted, the file system does not support packs\n",
+                            pack_name
+                        ));
+                    }
+                });
+
+            // the files waiting for the pack are opened, from the loose files if it failed
+            db.pending_mounts.set(db.pending_mounts.get() - 1);
+            mounted
+        });
+
+        self.execute(Box::new(mount));
+    }
+
     fn execute(&self, task: AssetTask) {
         self.pending_tasks.borrow_mut().push(task);
     }
 
     fn new() -> AssetDatabase<FS, F> {
-        let mut db = AssetDatabase {
-            context: Rc::new(AssetDatabaseContext {
-                fs: FS::default(),
-                path: String::default(),
-                textures: RefCell::new(HashMap::new()),
-                mesh_buffers: RefCell::new(HashMap::new()),
-                programs: RefCell::new(HashMap::new()),
-                pending_prefabs: RefCell::new(Vec::new()),
-                pending_tasks: RefCell::new(Vec::new()),
-            }),
-        };
-
-        db.setup();
-        db
+        AssetDatabase::with_file_system(FS::default())
     }
 
     fn step(&mut self) {
@@ -228,7 +323,8 @@ where
                 .into_iter()
                 .filter_map(|mut t| match t.poll() {
                     Err(e) => {
-                        panic!("Task failed. reason: {:?}", e);
+                        self.report_error(&e);
+                        None
                     }
                     Ok(Async::NotReady) => Some(t),
                     Ok(Async::Ready(_)) => None,
@@ -237,11 +333,82 @@ where
 
             *self.pending_tasks.borrow_mut() = new_pending;
         }
+
+        let changed = self.fs.changed_files();
+        if changed.len() > 0 {
+            self.reload_assets(&self.programs, &changed);
+            self.reload_assets(&self.textures, &changed);
+            self.reload_assets(&self.mesh_buffers, &changed);
+        }
+
+        {
+            let pending_reloads = self.pending_reloads
+                .borrow_mut()
+                .drain(0..)
+                .collect::<Vec<_>>();
+
+            let new_pending = pending_reloads
+                .into_iter()
+                .filter_map(|(name, mut t)| match t() {
+                    Err(e) => {
+                        // the old data is kept
+                        self.reload_errors
+                            .borrow_mut()
+                            .insert(name.clone(), format!("{}: {:?}", name, e));
+                        None
+                    }
+                    Ok(false) => Some((name, t)),
+                    Ok(true) => {
+                        self.reload_errors.borrow_mut().remove(&name);
+                        None
+                    }
+                })
+                .collect();
+
+            *self.pending_reloads.borrow_mut() = new_pending;
+        }
     }
 
     fn loading_files(&self) -> Vec<String> {
         self.fs.loading_files()
     }
+
+    fn file_progress(&self) -> fs::FileProgress {
+        self.progress.clone()
+    }
+
+    fn errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = self.reload_errors.borrow().values().cloned().collect();
+
+        errors.extend(
+            self.programs
+                .borrow()
+                .values()
+                .filter_map(|p| p.compile_error()),
+        );
+
+        errors
+    }
+
+    fn report_error(&self, err: &AssetError) {
+        if let &AssetError::NotReady = err {
+            return;
+        }
+
+        let msg = format!("{:?}", err);
+        if !self.reported_errors.borrow_mut().insert(msg.clone()) {
+            return;
+        }
+
+        uni_app::App::print(format!("Failed to load asset, reason {}\n", msg));
+        if let Some(ref f) = *self.error_handler.borrow() {
+            f(err);
+        }
+    }
+
+    fn set_error_handler(&self, f: ErrorHandler) {
+        *self.error_handler.borrow_mut() = Some(f);
+    }
 }
 
 impl<FS, F> AssetDatabase<FS, F>
@@ -249,6 +416,31 @@ where
     FS: fs::FileSystem<File = F> + 'static,
     F: fs::File + 'static,
 {
+    /// An asset database reading its files from `file_system`
+    pub fn with_file_system(file_system: FS) -> AssetDatabase<FS, F> {
+        let mut db = AssetDatabase {
+            context: Rc::new(AssetDatabaseContext {
+                fs: file_system,
+                path: String::default(),
+                textures: RefCell::new(HashMap::new()),
+                mesh_buffers: RefCell::new(HashMap::new()),
+                programs: RefCell::new(HashMap::new()),
+                fonts: RefCell::new(HashMap::new()),
+                pending_prefabs: RefCell::new(Vec::new()),
+                pending_tasks: RefCell::new(Vec::new()),
+                pending_reloads: RefCell::new(Vec::new()),
+                reload_errors: RefCell::new(HashMap::new()),
+                reported_errors: RefCell::new(HashSet::new()),
+                error_handler: RefCell::new(None),
+                progress: fs::FileProgress::default(),
+                pending_mounts: Rc::new(Cell::new(0)),
+            }),
+        };
+
+        db.setup();
+        db
+    }
+
     fn new_asset<R>(&self, hm: &mut HashMap<String, Rc<R>>, name: &str) -> Rc<R>
     where
         R: LoadableAsset,
@@ -263,6 +455,35 @@ where
         }
     }
 
+    /// Load again the assets using the changed files, they are swapped in place once loaded
+    fn reload_assets<R>(&self, assets: &RefCell<HashMap<String, Rc<R>>>, changed: &[String])
+    where
+        R: ReloadableAsset + 'static,
+        R::Resource: 'static,
+    {
+        let affected: Vec<(String, Rc<R>)> = assets
+            .borrow()
+            .iter()
+            .filter(|&(name, asset)| changed.iter().any(|f| asset.depends_on(name, f)))
+            .map(|(name, asset)| (name.clone(), asset.clone()))
+            .collect();
+
+        for (name, asset) in affected {
+            uni_app::App::print(format!("reloading {}\n", name));
+
+            let mut r = Some(R::load(self, R::gather(self, &name)));
+            let task: ReloadTask = Box::new(move || {
+                let ready = R::poll_reload(r.as_ref().unwrap())?;
+                if ready {
+                    asset.reload(r.take().unwrap());
+                }
+                Ok(ready)
+            });
+
+            self.pending_reloads.borrow_mut().push((name, task));
+        }
+    }
+
     fn setup(&mut self) {
         {
             let mut hm = self.mesh_buffers.borrow_mut();
@@ -309,12 +530,21 @@ where
             );
 
             hm.insert("default".into(), Self::new_default_texture());
+            hm.insert("default_error".into(), Self::new_error_texture());
         }
 
         {
             let mut hm = self.programs.borrow_mut();
             hm.insert("default".into(), Self::new_default_program());
             hm.insert("default_ui".into(), Self::new_default_ui_program());
+            hm.insert("default_ui_sdf".into(), Self::new_default_ui_sdf_program());
+            hm.insert("default_ui_world".into(), Self::new_default_ui_world_program());
+            hm.insert(
+                "default_ui_sdf_world".into(),
+                Self::new_default_ui_sdf_world_program(),
+            );
+            hm.insert("default_debug".into(), Self::new_default_debug_program());
+            hm.insert("default_error".into(), Self::new_error_program());
         }
     }
 
@@ -362,6 +592,23 @@ where
         })))
     }
 
+    /// Magenta checker drawn in place of the textures which failed to load
+    fn new_error_texture() -> Rc<Texture> {
+        let tex = Texture::new(TextureImage::Rgba(ImageBuffer::from_fn(64, 64, |x, y| {
+            if (x / 8 + y / 8) % 2 == 0 {
+                image::Rgba([0xff, 0, 0xff, 0xff])
+            } else {
+                image::Rgba([0, 0, 0, 0xff])
+            }
+        })));
+
+        tex.filtering.set(TextureFiltering::Nearest);
+        tex.wrap_u.set(TextureWrap::Repeat);
+        tex.wrap_v.set(TextureWrap::Repeat);
+
+        tex
+    }
+
     pub fn new_default_program() -> Rc<ShaderProgram> {
         let vs = ShaderVs::new("phong_vs.glsl", DEFAULT_VS);
         let fs = ShaderFs::new("phong_fs.glsl", DEFAULT_FS);
@@ -376,6 +623,42 @@ where
         ShaderProgram::new((Resource::new(vs), Resource::new(fs)))
     }
 
+    pub fn new_default_ui_sdf_program() -> Rc<ShaderProgram> {
+        let vs = ShaderVs::new("ui_vs.glsl", DEFAULT_UI_VS);
+        let fs = ShaderFs::new("ui_sdf_fs.glsl", DEFAULT_UI_SDF_FS);
+
+        ShaderProgram::new((Resource::new(vs), Resource::new(fs)))
+    }
+
+    pub fn new_default_ui_world_program() -> Rc<ShaderProgram> {
+        let vs = ShaderVs::new("ui_world_vs.glsl", DEFAULT_UI_WORLD_VS);
+        let fs = ShaderFs::new("ui_fs.glsl", DEFAULT_UI_FS);
+
+        ShaderProgram::new((Resource::new(vs), Resource::new(fs)))
+    }
+
+    pub fn new_default_ui_sdf_world_program() -> Rc<ShaderProgram> {
+        let vs = ShaderVs::new("ui_world_vs.glsl", DEFAULT_UI_WORLD_VS);
+        let fs = ShaderFs::new("ui_sdf_fs.glsl", DEFAULT_UI_SDF_FS);
+
+        ShaderProgram::new((Resource::new(vs), Resource::new(fs)))
+    }
+
+    pub fn new_default_debug_program() -> Rc<ShaderProgram> {
+        let vs = ShaderVs::new("debug_vs.glsl", DEFAULT_DEBUG_VS);
+        let fs = ShaderFs::new("debug_fs.glsl", DEFAULT_DEBUG_FS);
+
+        ShaderProgram::new((Resource::new(vs), Resource::new(fs)))
+    }
+
+    /// Drawn in place of the shaders which failed to compile
+    pub fn new_error_program() -> Rc<ShaderProgram> {
+        let vs = ShaderVs::new("error_vs.glsl", ERROR_VS);
+        let fs = ShaderFs::new("error_fs.glsl", ERROR_FS);
+
+        ShaderProgram::new((Resource::new(vs), Resource::new(fs)))
+    }
+
     pub fn get_filename(&self, name: &str) -> String {
         format!("{}{}", self.path, name)
     }
@@ -387,3 +670,11 @@ const DEFAULT_FS: &'static str = include_str!("phong_fs.glsl");
 
 const DEFAULT_UI_VS: &'static str = include_str!("ui_vs.glsl");
 const DEFAULT_UI_FS: &'static str = include_str!("ui_fs.glsl");
+const DEFAULT_UI_SDF_FS: &'static str = include_str!("ui_sdf_fs.glsl");
+const DEFAULT_UI_WORLD_VS: &'static str = include_str!("ui_world_vs.glsl");
+
+const DEFAULT_DEBUG_VS: &'static str = include_str!("debug_vs.glsl");
+const DEFAULT_DEBUG_FS: &'static str = include_str!("debug_fs.glsl");
+
+const ERROR_VS: &'static str = include_str!("error_vs.glsl");
+const ERROR_FS: &'static str = include_str!("error_fs.glsl");
@@ -0,0 +1,12 @@
+#ifndef GL_ES
+#define gl_FragColor FragColor
+out vec4 FragColor;
+#endif
+
+// magenta and black checker in screen space, drawn in place of a failed shader
+void main(void) {
+    vec2 cell = floor(gl_FragCoord.xy / 8.0);
+    float c = mod(cell.x + cell.y, 2.0);
+
+    gl_FragColor = vec4(c, 0.0, c, 1.0);
+}
@@ -1,7 +1,12 @@
 use std::default::Default;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 use futures::prelude::*;
 use std;
 
+use engine::asset::Pack;
+
 pub type FileFuture = Box<Future<Item = Box<File>, Error = FileIoError>>;
 
 pub trait FileSystem: Default {
@@ -10,6 +15,46 @@ pub trait FileSystem: Default {
     fn open(&self, filename: &str) -> FileFuture;
 
     fn loading_files(&self) -> Vec<String>;
+
+    /// The files changed on disk since the last call, the assets loaded from them are reloaded
+    fn changed_files(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Serve the files of `pack` over the ones of this file system,
+    /// returns false when it does not support packs
+    fn mount(&self, _pack: Rc<Pack>) -> bool {
+        false
+    }
+}
+
+/// A file system of any type, e.g. a layer of an `OverlayFileSystem`
+pub trait FileSystemLayer {
+    fn open(&self, filename: &str) -> FileFuture;
+
+    fn loading_files(&self) -> Vec<String>;
+
+    fn changed_files(&self) -> Vec<String>;
+
+    fn mount(&self, pack: Rc<Pack>) -> bool;
+}
+
+impl<T: FileSystem> FileSystemLayer for T {
+    fn open(&self, filename: &str) -> FileFuture {
+        FileSystem::open(self, filename)
+    }
+
+    fn loading_files(&self) -> Vec<String> {
+        FileSystem::loading_files(self)
+    }
+
+    fn changed_files(&self) -> Vec<String> {
+        FileSystem::changed_files(self)
+    }
+
+    fn mount(&self, pack: Rc<Pack>) -> bool {
+        FileSystem::mount(self, pack)
+    }
 }
 
 pub trait File {
@@ -18,6 +63,16 @@ pub trait File {
     fn read_binary(&mut self) -> Result<Vec<u8>, FileIoError>;
 }
 
+impl File for Box<File> {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn read_binary(&mut self) -> Result<Vec<u8>, FileIoError> {
+        (**self).read_binary()
+    }
+}
+
 #[derive(Debug)]
 pub enum FileIoError {
     NotReady,
@@ -26,8 +81,88 @@ pub enum FileIoError {
     Unknown(String),
 }
 
+impl Clone for FileIoError {
+    fn clone(&self) -> FileIoError {
+        match self {
+            &FileIoError::NotReady => FileIoError::NotReady,
+            &FileIoError::NoSuchFile(ref s) => FileIoError::NoSuchFile(s.clone()),
+            // io errors can not be cloned, keep their kind and message
+            &FileIoError::IoError(ref e) => {
+                FileIoError::IoError(std::io::Error::new(e.kind(), e.to_string()))
+            }
+            &FileIoError::Unknown(ref s) => FileIoError::Unknown(s.clone()),
+        }
+    }
+}
+
 impl From<std::io::Error> for FileIoError {
     fn from(e: std::io::Error) -> FileIoError {
         FileIoError::IoError(e)
     }
 }
+
+/// The bytes loaded and the total bytes of each file, shared by the asset system and
+/// the loaders to show the progress of a loading screen
+#[derive(Clone, Default)]
+pub struct FileProgress(Rc<RefCell<HashMap<String, (usize, usize)>>>);
+
+impl FileProgress {
+    /// Record the size of the files once they are read
+    pub fn track(&self, f: FileFuture) -> FileFuture {
+        let progress = self.clone();
+
+        Box::new(f.map(move |file| {
+            Box::new(ProgressFile {
+                file: file,
+                progress: progress,
+            }) as Box<File>
+        }))
+    }
+
+    /// Set the bytes of `file` loaded so far out of `total`,
+    /// e.g. by a loader which decodes the file in several frames
+    pub fn set(&self, file: &str, loaded: usize, total: usize) {
+        self.0.borrow_mut().insert(file.into(), (loaded, total));
+    }
+
+    pub fn get(&self, file: &str) -> Option<(usize, usize)> {
+        self.0.borrow().get(file).cloned()
+    }
+
+    /// Sum of the loaded and total bytes of the files matching `pred`,
+    /// `None` when none of them was read yet
+    pub fn sum<P>(&self, pred: P) -> Option<(usize, usize)>
+    where
+        P: Fn(&str) -> bool,
+    {
+        self.0
+            .borrow()
+            .iter()
+            .filter(|&(name, _)| pred(name))
+            .fold(None, |acc, (_, &(loaded, total))| {
+                let (l, t) = acc.unwrap_or((0, 0));
+                Some((l + loaded, t + total))
+            })
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear();
+    }
+}
+
+struct ProgressFile {
+    file: Box<File>,
+    progress: FileProgress,
+}
+
+impl File for ProgressFile {
+    fn name(&self) -> String {
+        self.file.name()
+    }
+
+    fn read_binary(&mut self) -> Result<Vec<u8>, FileIoError> {
+        let buf = self.file.read_binary()?;
+        self.progress.set(&self.file.name(), buf.len(), buf.len());
+        Ok(buf)
+    }
+}
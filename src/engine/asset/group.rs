@@ -0,0 +1,174 @@
+//! Batches of assets loaded together
+//!
+//! A loading screen adds the assets of a level to an `AssetGroup`, draws its `progress()`
+//! and waits for its `future()`. The progress counts the bytes of the files read or decoded
+//! out of their size, the members whose files are not read yet count as the average member.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use engine::asset::asset_database::{MaterialHandler, PrefabHandler};
+use engine::asset::{AssetError, AssetResult, AssetState, AssetSystem, FileProgress, Prefab,
+                    ReloadableAsset};
+use engine::{MeshBuffer, ShaderProgram, Texture};
+use futures::{Async, Future, Poll};
+
+struct Member {
+    /// whether the member is loaded from a file
+    uses_file: Box<Fn(&str) -> bool>,
+    state: Box<Fn() -> AssetState>,
+}
+
+#[derive(Clone)]
+pub struct AssetGroup {
+    members: Rc<RefCell<Vec<Member>>>,
+    progress: FileProgress,
+}
+
+impl AssetGroup {
+    pub fn new(asys: &AssetSystem) -> AssetGroup {
+        AssetGroup {
+            members: Rc::new(RefCell::new(Vec::new())),
+            progress: asys.file_progress(),
+        }
+    }
+
+    pub fn add_texture(&self, asys: &AssetSystem, name: &str) -> Rc<Texture> {
+        let tex = asys.new_texture(name);
+
+        let (t, n) = (tex.clone(), name.to_string());
+        let state = tex.clone();
+        self.push(
+            Box::new(move |f: &str| t.depends_on(&n, f)),
+            Box::new(move || state.state()),
+        );
+
+        tex
+    }
+
+    pub fn add_mesh_buffer(&self, asys: &AssetSystem, name: &str) -> Rc<MeshBuffer> {
+        let mesh = asys.new_mesh_buffer(name);
+
+        let (m, n) = (mesh.clone(), name.to_string());
+        let state = mesh.clone();
+        self.push(
+            Box::new(move |f: &str| m.depends_on(&n, f)),
+            Box::new(move || state.state()),
+        );
+
+        mesh
+    }
+
+    pub fn add_program(&self, asys: &AssetSystem, name: &str) -> Rc<ShaderProgram> {
+        let program = asys.new_program(name);
+
+        let (p, n) = (program.clone(), name.to_string());
+        let state = program.clone();
+        self.push(
+            Box::new(move |f: &str| p.depends_on(&n, f)),
+            Box::new(move || state.state()),
+        );
+
+        program
+    }
+
+    /// Load a prefab, the group is ready once `f` is called.
+    /// Only the bytes of the obj file are counted, not the ones of its materials.
+    pub fn add_prefab(&self, asys: &AssetSystem, name: &str, mh: MaterialHandler, f: PrefabHandler) {
+        let state = Rc::new(RefCell::new(AssetState::Loading));
+
+        let s = state.clone();
+        asys.new_prefab(
+            name,
+            mh,
+            Box::new(move |r: AssetResult<Prefab>| {
+                *s.borrow_mut() = match r {
+                    Ok(_) => AssetState::Ready,
+                    Err(ref e) => AssetState::Failed(e.clone()),
+                };
+                f(r);
+            }),
+        );
+
+        let n = name.to_string();
+        self.push(
+            Box::new(move |file: &str| file == n),
+            Box::new(move || state.borrow().clone()),
+        );
+    }
+
+    fn push(&self, uses_file: Box<Fn(&str) -> bool>, state: Box<Fn() -> AssetState>) {
+        self.members.borrow_mut().push(Member { uses_file, state });
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.borrow().len()
+    }
+
+    /// Ready when all the members are ready, failed as soon as one of them fails
+    pub fn state(&self) -> AssetState {
+        self.members
+            .borrow()
+            .iter()
+            .fold(AssetState::Ready, |state, m| state.merge((m.state)()))
+    }
+
+    /// The bytes loaded and the total bytes of the group
+    pub fn bytes(&self) -> (usize, usize) {
+        let members = self.members.borrow();
+
+        let sizes: Vec<(AssetState, Option<(usize, usize)>)> = members
+            .iter()
+            .map(|m| ((m.state)(), self.progress.sum(|f| (m.uses_file)(f))))
+            .collect();
+
+        let known: Vec<usize> = sizes.iter().filter_map(|s| s.1.map(|b| b.1)).collect();
+        let estimate = if known.len() > 0 {
+            known.iter().sum::<usize>() / known.len()
+        } else {
+            0
+        };
+        // nothing is read yet, count the members instead
+        let estimate = estimate.max(1);
+
+        sizes
+            .iter()
+            .fold((0, 0), |(loaded, total), &(ref state, bytes)| {
+                let (l, t) = bytes.unwrap_or((0, estimate));
+                match state {
+                    &AssetState::Loading => (loaded + l.min(t), total + t),
+                    // the failed assets have nothing left to load
+                    _ => (loaded + t, total + t),
+                }
+            })
+    }
+
+    /// The loaded part of the group, from 0.0 to 1.0
+    pub fn progress(&self) -> f32 {
+        match self.bytes() {
+            (_, 0) => 1.0,
+            (loaded, total) => loaded as f32 / total as f32,
+        }
+    }
+
+    /// Resolves when all the members are ready, fails with the first error.
+    /// It is polled by `AssetSystem::execute` or by the caller each frame.
+    pub fn future(&self) -> GroupFuture {
+        GroupFuture(self.clone())
+    }
+}
+
+pub struct GroupFuture(AssetGroup);
+
+impl Future for GroupFuture {
+    type Item = ();
+    type Error = AssetError;
+
+    fn poll(&mut self) -> Poll<(), AssetError> {
+        match self.0.state() {
+            AssetState::Loading => Ok(Async::NotReady),
+            AssetState::Ready => Ok(Async::Ready(())),
+            AssetState::Failed(e) => Err(e),
+        }
+    }
+}
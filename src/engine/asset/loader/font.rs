@@ -0,0 +1,27 @@
+use engine::asset::loader::{Loadable, Loader};
+use engine::asset::{AssetError, AssetResult, File};
+use engine::render::FontFace;
+
+use rusttype;
+
+pub struct FontLoader {}
+
+impl Loader<FontFace> for FontLoader {
+    fn load<A>(_asys: A, mut file: Box<File>) -> AssetResult<FontFace> {
+        let bytes = file.read_binary()
+            .map_err(|_| AssetError::ReadBufferFail(file.name()))?;
+        let len = bytes.len();
+
+        let font = rusttype::Font::from_bytes(bytes).map_err(|e| AssetError::InvalidFormat {
+            path: file.name(),
+            len: len,
+            reason: format!("{:?}", e),
+        })?;
+
+        Ok(FontFace::new(font))
+    }
+}
+
+impl Loadable for FontFace {
+    type Loader = FontLoader;
+}
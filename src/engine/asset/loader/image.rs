@@ -1,5 +1,5 @@
 use engine::asset::loader::{Loadable, Loader};
-use engine::asset::{AssetError, AssetResult, AssetSystem, File, FileFuture};
+use engine::asset::{AssetError, AssetResult, AssetSystem, File, FileFuture, FileProgress};
 use engine::TextureImage;
 use image::png;
 use image::tga;
@@ -41,6 +41,10 @@ struct ImageBufferLineSteam<T> {
 
     max_read_bytes: usize,
     read_timer: f64,
+
+    /// the decoded rows are reported as a part of the file bytes
+    progress: FileProgress,
+    decoded_rows: usize,
 }
 
 // the number of bytes read for yield
@@ -81,7 +85,18 @@ where
 
         match self.codec.read_scanline(&mut buffer) {
             Err(image::ImageError::ImageEnd) => Ok(Async::Ready(None)),
-            Ok(row_index) => Ok(Async::Ready(Some((buffer, row_index)))),
+            Ok(row_index) => {
+                self.decoded_rows += 1;
+                let info = &self.ctx.info;
+                self.progress.set(
+                    &info.file_name,
+                    info.orig_len * self.decoded_rows.min(self.ctx.h as usize)
+                        / (self.ctx.h as usize).max(1),
+                    info.orig_len,
+                );
+
+                Ok(Async::Ready(Some((buffer, row_index))))
+            }
             Err(e) => Err(make_invalid_format(&self.ctx.info, e)),
         }
     }
@@ -90,6 +105,7 @@ where
 fn image_rows_stream<T>(
     codec: T,
     ctx: ImageContext,
+    progress: FileProgress,
 ) -> Box<Stream<Item = (Vec<u8>, u32), Error = AssetError>>
 where
     T: image::ImageDecoder + 'static,
@@ -100,6 +116,8 @@ where
         ctx: ctx,
         max_read_bytes: MIN_READ_BYTES_YIELD_COUNT,
         read_timer: 0.0,
+        progress: progress,
+        decoded_rows: 0,
     })
 }
 
@@ -191,11 +209,14 @@ fn new_img_context(
     Ok((ctx, codec))
 }
 
-fn load_future_uncompressed<T>(img_buf: T) -> Box<Future<Item = TextureImage, Error = AssetError>>
+fn load_future_uncompressed<T>(
+    img_buf: T,
+    progress: FileProgress,
+) -> Box<Future<Item = TextureImage, Error = AssetError>>
 where
     T: Future<Item = (Vec<u8>, String), Error = AssetError> + 'static,
 {
-    let decoded = img_buf.and_then(|(whole_buf, file_name)| {
+    let decoded = img_buf.and_then(move |(whole_buf, file_name)| {
         let info = ImageFileInfo {
             file_name: file_name.clone(),
             orig_len: whole_buf.len(),
@@ -208,13 +229,16 @@ where
                 reason: format!("{:?}", e),
             })?;
 
+        // the file is read, nothing is decoded yet
+        progress.set(&info.file_name, 0, info.orig_len);
+
         let mut iter = 1..;
         let f0: Box<FnMut(u32) -> u32> = Box::new(|row_index: u32| -> u32 { row_index });
         let f1: Box<FnMut(u32) -> u32> = Box::new(move |_: u32| -> u32 { iter.next().unwrap() });
 
         let (mut indexer, stream) = match codec {
-            ImageCodec::Tga(decoder) => (f0, image_rows_stream(decoder, ctx.clone())),
-            ImageCodec::Png(decoder) => (f1, image_rows_stream(decoder, ctx.clone())),
+            ImageCodec::Tga(decoder) => (f0, image_rows_stream(decoder, ctx.clone(), progress)),
+            ImageCodec::Png(decoder) => (f1, image_rows_stream(decoder, ctx.clone(), progress)),
             _ => unreachable!(),
         };
 
@@ -316,7 +340,7 @@ where
 impl Loadable for TextureImage {
     type Loader = ImageLoader;
 
-    fn load_future<A>(_asys: A, objfile: FileFuture) -> Box<Future<Item = Self, Error = AssetError>>
+    fn load_future<A>(asys: A, objfile: FileFuture) -> Box<Future<Item = Self, Error = AssetError>>
     where
         Self: 'static,
         A: AssetSystem + Clone + 'static,
@@ -330,12 +354,14 @@ impl Loadable for TextureImage {
             Err(e) => Err(AssetError::FileIoError(e)),
         });
 
-        Box::new(img_buf.and_then(|(whole_buf, file_name)| {
+        let progress = asys.file_progress();
+
+        Box::new(img_buf.and_then(move |(whole_buf, file_name)| {
             if whole_buf.starts_with(DDS_MAGIC_BYTES) {
                 return load_future_dds(future::result(Ok((whole_buf, file_name))));
             }
 
-            load_future_uncompressed(future::result(Ok((whole_buf, file_name))))
+            load_future_uncompressed(future::result(Ok((whole_buf, file_name))), progress)
         }))
     }
 }
@@ -1,6 +1,6 @@
 use engine::asset::loader::{Loadable, Loader};
 use engine::asset::{AssetError, AssetResult, File};
-use engine::render::MeshData;
+use engine::render::{mesh_util, MeshData};
 
 use obj;
 use obj::SimplePolygon;
@@ -13,47 +13,64 @@ impl Loader<MeshData> for MeshDataLoader {
             .map_err(|_| AssetError::ReadBufferFail(file.name()))?;
         let mut r = BufReader::new(bytes.as_slice());
 
-        let m = obj::Obj::<SimplePolygon>::load_buf(&mut r);
-        let model = m.unwrap();
-
-        let vertices: Vec<f32> = model
-            .position
-            .into_iter()
-            .flat_map(|s| s.to_vec().into_iter())
-            .collect();
-
-        let uvs = model
-            .texture
-            .into_iter()
-            .flat_map(|s| s.to_vec().into_iter())
-            .collect();
-
-        let normals = model
-            .normal
-            .into_iter()
-            .flat_map(|s| s.to_vec().into_iter())
-            .collect();
-
-        let mut indices: Vec<u16> = Vec::new();
-
-        for o in model.objects {
-            for g in o.groups {
-                for poly in g.polys {
-                    for index_tuple in poly {
-                        indices.push(index_tuple.0 as u16);
+        let invalid = |reason: String| AssetError::InvalidFormat {
+            path: file.name(),
+            len: bytes.len(),
+            reason,
+        };
+
+        let model = obj::Obj::<SimplePolygon>::load_buf(&mut r)
+            .map_err(|e| invalid(format!("{:?}", e)))?;
+        let out_of_range = |i: usize| invalid(format!("index {} out of range", i + 1));
+
+        // a triangle soup, welded afterward
+        let mut vertices = Vec::new();
+        let mut uvs = Vec::new();
+        let mut normals = Vec::new();
+
+        for o in model.objects.iter() {
+            for g in o.groups.iter() {
+                for poly in g.polys.iter() {
+                    // polygons are split in fans
+                    for i in 1..poly.len().saturating_sub(1) {
+                        for index_tuple in [poly[0], poly[i], poly[i + 1]].iter() {
+                            let p = index_tuple.0;
+                            let position = model.position.get(p).ok_or_else(|| out_of_range(p))?;
+                            vertices.extend_from_slice(position);
+
+                            if let Some(uv) = index_tuple.1 {
+                                let t = model.texture.get(uv).ok_or_else(|| out_of_range(uv))?;
+                                uvs.extend_from_slice(t);
+                            }
+                            if let Some(n) = index_tuple.2 {
+                                let normal = model.normal.get(n).ok_or_else(|| out_of_range(n))?;
+                                normals.extend_from_slice(normal);
+                            }
+                        }
                     }
                 }
             }
         }
 
-        Ok(MeshData {
+        let count = vertices.len() / 3;
+        let indices = (0..count as u32).collect();
+
+        // an attribute is dropped when some corners miss it
+        let mut mesh_data = MeshData {
             indices,
             vertices,
-            uvs: Some(uvs),
-            normals: Some(normals),
-            tangents: None,
-            bitangents: None,
-        })
+            uvs: if uvs.len() == count * 2 { Some(uvs) } else { None },
+            normals: if normals.len() == count * 3 {
+                Some(normals)
+            } else {
+                None
+            },
+            ..Default::default()
+        };
+
+        mesh_util::process_loaded(&mut mesh_data, true);
+
+        Ok(mesh_data)
     }
 }
 
@@ -4,9 +4,11 @@ mod shader;
 mod mesh_data;
 mod prefab;
 mod dds;
+mod font;
 
 pub use self::loader::{Loadable, Loader};
 pub use self::image::ImageLoader;
 pub use self::shader::{ShaderFSLoader, ShaderVSLoader};
 pub use self::prefab::{ObjMaterial, Prefab, PrefabLoader};
 pub use self::dds::DDS;
+pub use self::font::FontLoader;
@@ -1,5 +1,5 @@
 use engine::asset::{Asset, AssetError, AssetSystem, FileFuture, Resource};
-use engine::render::{Material, Mesh, MeshBuffer, MeshData};
+use engine::render::{mesh_util, Material, Mesh, MeshBuffer, MeshData};
 use std::borrow::Cow;
 use std::path::Path;
 
@@ -33,135 +33,6 @@ fn parent_path(filename: &str) -> String {
 
 pub struct PrefabLoader {}
 
-struct TangentSpace {
-    tangents: Option<Vec<f32>>,
-    bitangents: Option<Vec<f32>>,
-}
-
-#[inline]
-fn from_slice_v3(i: usize, s: &[f32]) -> Vector3f {
-    return Vector3::new(s[i * 3 + 0], s[i * 3 + 1], s[i * 3 + 2]);
-}
-
-#[inline]
-fn from_slice_v2(i: usize, s: &[f32]) -> Vector2f {
-    return Vector2::new(s[i * 2 + 0], s[i * 2 + 1]);
-}
-
-trait CloseToZero {
-    fn close_to_zero(&self) -> bool;
-}
-
-impl CloseToZero for f32 {
-    fn close_to_zero(&self) -> bool {
-        self.abs() < 0.0001
-    }
-}
-
-fn compute_tangents(
-    v_array: &Vec<f32>,
-    uv_array: &Option<Vec<f32>>,
-    n_array: &Option<Vec<f32>>,
-    indices: &Vec<u16>,
-) -> TangentSpace {
-    if uv_array.is_none() || n_array.is_none() {
-        return TangentSpace {
-            tangents: None,
-            bitangents: None,
-        };
-    }
-
-    let uv_array = uv_array.as_ref().unwrap();
-    let n_array = n_array.as_ref().unwrap();
-
-    assert!(v_array.len() % 9 == 0);
-    assert!(uv_array.len() % 6 == 0);
-    assert!(n_array.len() % 9 == 0);
-    assert!(indices.len() % 3 == 0);
-
-    let mut tangents = Vec::with_capacity(v_array.len());
-    let mut bittangents = Vec::with_capacity(v_array.len());
-
-    let mut i = 0;
-    while i < indices.len() {
-        let pos1 = from_slice_v3(i, &v_array);
-        let pos2 = from_slice_v3(i + 1, &v_array);
-        let pos3 = from_slice_v3(i + 2, &v_array);
-
-        let uv1 = from_slice_v2(i, &uv_array);
-        let uv2 = from_slice_v2(i + 1, &uv_array);
-        let uv3 = from_slice_v2(i + 2, &uv_array);
-
-        let edge1 = pos2 - pos1;
-        let edge2 = pos3 - pos1;
-        let delta_uv1 = uv2 - uv1;
-        let delta_uv2 = uv3 - uv1;
-
-        let d = delta_uv1.x * delta_uv2.y - delta_uv2.x * delta_uv1.y;
-        let tangent: Vector3f;
-        let bitangent: Vector3f;
-
-        if !d.close_to_zero() {
-            let r = 1.0 / d;
-            tangent = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) * r;
-            bitangent = (edge2 * delta_uv1.x - edge1 * delta_uv2.x) * r;
-        } else {
-            // For some ill condition mesh, d would be zero, just fix it
-            if !delta_uv1.x.close_to_zero() {
-                tangent = edge1 / delta_uv1.x;
-            } else if !delta_uv2.x.close_to_zero() {
-                tangent = edge2 / delta_uv2.x;
-            } else {
-                tangent = Vector3::zero();
-            }
-
-            if !delta_uv1.y.close_to_zero() {
-                bitangent = edge1 / delta_uv1.y;
-            } else if !delta_uv2.y.close_to_zero() {
-                bitangent = edge2 / delta_uv2.y;
-            } else {
-                bitangent = Vector3::zero();
-            }
-        }
-
-        // Triangle share same tangent space
-        tangents.extend_from_slice(&tangent[..]);
-        tangents.extend_from_slice(&tangent[..]);
-        tangents.extend_from_slice(&tangent[..]);
-
-        bittangents.extend_from_slice(&bitangent[..]);
-        bittangents.extend_from_slice(&bitangent[..]);
-        bittangents.extend_from_slice(&bitangent[..]);
-
-        i += 3;
-    }
-
-    for i in 0..indices.len() {
-        let n = from_slice_v3(i, &n_array);
-        let mut t = from_slice_v3(i, &tangents);
-        let b = from_slice_v3(i, &bittangents);
-
-        // Gram-Schmidt orthogonalize
-        t = t - n * n.dot(t);
-
-        if n.cross(t).dot(b) < 0.0 {
-            t = -t;
-        }
-
-        tangents[i * 3 + 0] = t.x;
-        tangents[i * 3 + 1] = t.y;
-        tangents[i * 3 + 2] = t.z;
-    }
-
-    assert!(tangents.len() == v_array.len());
-    assert!(bittangents.len() == v_array.len());
-
-    return TangentSpace {
-        tangents: Some(tangents),
-        bitangents: Some(bittangents),
-    };
-}
-
 #[derive(Clone, Copy, Debug)]
 struct WithNormalMap(bool);
 
@@ -312,7 +183,7 @@ impl PrefabLoader {
                 let mut n_array = Vec::new();
 
                 let mut add_v = |index_tuple: obj::IndexTuple| {
-                    indices.push(indices.len() as u16);
+                    indices.push(indices.len() as u32);
                     v_array.extend_from_slice(&vertices[index_tuple.0]);
                     index_tuple.1.map(|uv| {
                         uv_array.push(uvs[uv][0]);
@@ -324,56 +195,37 @@ impl PrefabLoader {
                 };
 
                 for poly in g.polys {
-                    // assert_eq!(poly.len(), 3, "We only handle triangle obj files");
-                    match poly.len() {
-                        3 => {
-                            add_v(poly[0]);
-                            add_v(poly[1]);
-                            add_v(poly[2]);
-                        }
-                        4 => {
-                            add_v(poly[0]);
-                            add_v(poly[1]);
-                            add_v(poly[2]);
-
-                            add_v(poly[2]);
-                            add_v(poly[3]);
-                            add_v(poly[0]);
-                        }
-
-                        _ => panic!("We only handle triangle or quad obj files"),
+                    // polygons are split in fans
+                    for i in 1..poly.len().saturating_sub(1) {
+                        add_v(poly[0]);
+                        add_v(poly[i]);
+                        add_v(poly[i + 1]);
                     }
                 }
 
-                let uv_array = if uv_array.len() > 0 {
+                // an attribute is dropped when some corners miss it
+                let uv_array = if uv_array.len() == indices.len() * 2 {
                     Some(uv_array)
                 } else {
                     None
                 };
-                let n_array = if n_array.len() > 0 {
+                let n_array = if n_array.len() == indices.len() * 3 {
                     Some(n_array)
                 } else {
                     None
                 };
 
-                let tangent_space = if has_normal_map.0 {
-                    compute_tangents(&v_array, &uv_array, &n_array, &indices)
-                } else {
-                    TangentSpace {
-                        tangents: None,
-                        bitangents: None,
-                    }
-                };
-
-                let mesh_data = MeshData {
+                let mut mesh_data = MeshData {
                     indices: indices,
                     vertices: v_array,
                     uvs: uv_array,
-                    tangents: tangent_space.tangents,
-                    bitangents: tangent_space.bitangents,
                     normals: n_array,
+                    ..Default::default()
                 };
 
+                // the tangents are only used by the normal maps
+                mesh_util::process_loaded(&mut mesh_data, has_normal_map.0);
+
                 mesh.add_surface(
                     MeshBuffer::new_from_resource(Resource::new(mesh_data)),
                     material,
@@ -28,7 +28,13 @@ where
             reason: format!("{:?}", e),
         })?;
 
-        let code = PreprocessedShaderCode::new(T::kind(), s, &HashMap::new()).unwrap();
+        let code = PreprocessedShaderCode::new(T::kind(), s, &HashMap::new()).map_err(|e| {
+            AssetError::InvalidFormat {
+                path: file.name(),
+                len: buf.len(),
+                reason: format!("{:?}", e),
+            }
+        })?;
         Ok(Shader::<T>::from_preprocessed(&file.name(), code))
     }
 }
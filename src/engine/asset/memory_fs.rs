@@ -0,0 +1,81 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use futures::future;
+
+use engine::asset::{File, FileFuture, FileIoError, FileSystem};
+
+/// Files kept in memory, e.g. for the tests which should not read the disk.
+/// Its clones share the same files.
+#[derive(Default, Clone)]
+pub struct MemoryFileSystem {
+    files: Rc<RefCell<HashMap<String, Rc<Vec<u8>>>>>,
+    changed: Rc<RefCell<Vec<String>>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> MemoryFileSystem {
+        MemoryFileSystem::default()
+    }
+
+    /// Add or replace a file, the assets already loaded from it are reloaded
+    pub fn insert(&self, name: &str, data: Vec<u8>) {
+        let name = name.replace("\\", "/");
+
+        if self.files
+            .borrow_mut()
+            .insert(name.clone(), Rc::new(data))
+            .is_some()
+        {
+            self.changed.borrow_mut().push(name);
+        }
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.files.borrow_mut().remove(&name.replace("\\", "/"));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.files.borrow().contains_key(&name.replace("\\", "/"))
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    type File = MemoryFile;
+
+    fn open(&self, filename: &str) -> FileFuture {
+        let filename = filename.replace("\\", "/");
+
+        match self.files.borrow().get(&filename) {
+            Some(data) => Box::new(future::ok(Box::new(MemoryFile {
+                name: filename.clone(),
+                data: data.clone(),
+            }) as Box<File>)),
+            None => Box::new(future::err(FileIoError::NoSuchFile(filename.clone()))),
+        }
+    }
+
+    fn loading_files(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn changed_files(&self) -> Vec<String> {
+        self.changed.borrow_mut().drain(..).collect()
+    }
+}
+
+pub struct MemoryFile {
+    name: String,
+    data: Rc<Vec<u8>>,
+}
+
+impl File for MemoryFile {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn read_binary(&mut self) -> Result<Vec<u8>, FileIoError> {
+        Ok(self.data.as_ref().clone())
+    }
+}
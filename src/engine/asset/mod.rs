@@ -2,17 +2,27 @@ mod asset_database;
 mod default_font_bitmap;
 mod quad;
 mod fs;
+mod group;
+mod memory_fs;
+mod overlay_fs;
+mod pack;
 mod primitives;
 mod resource;
 mod skybox;
 
 pub mod loader;
-pub use self::primitives::{CubeMesh, PlaneMesh};
+pub use self::primitives::{primitive_key, primitive_mesh, CapsuleMesh, ConeMesh, CubeMesh,
+                           CylinderMesh, GridMesh, IcosphereMesh, PlaneMesh, SphereMesh,
+                           TorusMesh};
 pub use self::quad::QuadMesh;
 pub use self::skybox::SkyboxMesh;
 pub use self::asset_database::{Asset, AssetDatabase, AssetError, AssetResult, AssetSystem,
-                               LoadableAsset};
+                               LoadableAsset, ReloadableAsset};
 pub use self::loader::{ObjMaterial, Prefab, DDS};
 
-pub use self::resource::Resource;
+pub use self::group::{AssetGroup, GroupFuture};
+pub use self::memory_fs::{MemoryFile, MemoryFileSystem};
+pub use self::overlay_fs::OverlayFileSystem;
+pub use self::pack::{Pack, PackEntry, PackFile, PackFileSystem, PackWriter};
+pub use self::resource::{AssetState, Resource};
 pub use self::fs::*;
@@ -0,0 +1,79 @@
+use std::rc::Rc;
+
+use futures::prelude::*;
+use futures::future;
+
+use engine::asset::{File, FileFuture, FileIoError, FileSystem, FileSystemLayer, Pack};
+
+/// Try several file systems in order, a file missing from a layer is opened from the
+/// next one, e.g. the files of a test in memory over the asset folder.
+///
+/// A layer must fail to open a missing file with `FileIoError::NoSuchFile`,
+/// the web builds only know it once the file is fetched, so they should be the last layer.
+#[derive(Default, Clone)]
+pub struct OverlayFileSystem {
+    layers: Rc<Vec<Box<FileSystemLayer>>>,
+}
+
+impl OverlayFileSystem {
+    pub fn new(layers: Vec<Box<FileSystemLayer>>) -> OverlayFileSystem {
+        OverlayFileSystem {
+            layers: Rc::new(layers),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+}
+
+fn open_from(layers: Rc<Vec<Box<FileSystemLayer>>>, i: usize, filename: String) -> FileFuture {
+    if i >= layers.len() {
+        return Box::new(future::err(FileIoError::NoSuchFile(filename)));
+    }
+
+    let f = layers[i].open(&filename);
+    if i + 1 == layers.len() {
+        return f;
+    }
+
+    Box::new(f.or_else(move |e| -> FileFuture {
+        match e {
+            FileIoError::NoSuchFile(_) => open_from(layers, i + 1, filename),
+            e => Box::new(future::err(e)),
+        }
+    }))
+}
+
+impl FileSystem for OverlayFileSystem {
+    type File = Box<File>;
+
+    fn open(&self, filename: &str) -> FileFuture {
+        open_from(self.layers.clone(), 0, filename.to_string())
+    }
+
+    fn loading_files(&self) -> Vec<String> {
+        self.layers
+            .iter()
+            .flat_map(|l| l.loading_files())
+            .collect()
+    }
+
+    fn changed_files(&self) -> Vec<String> {
+        let mut files: Vec<String> = Vec::new();
+        for l in self.layers.iter() {
+            for f in l.changed_files() {
+                if !files.contains(&f) {
+                    files.push(f);
+                }
+            }
+        }
+
+        files
+    }
+
+    /// The pack is mounted by the first layer supporting packs
+    fn mount(&self, pack: Rc<Pack>) -> bool {
+        self.layers.iter().any(|l| l.mount(pack.clone()))
+    }
+}
@@ -0,0 +1,304 @@
+//! Asset packs
+//!
+//! A pack bundles the files of a directory in one archive, so a web build fetches one
+//! file instead of every texture, shader and material. It is made by the `unrust-pack`
+//! tool. All the numbers are little endian:
+//!
+//! ```text
+//! magic "UPAK", version: u32, count: u32
+//! count entries: name_len: u32, name: utf8, offset: u32, size: u32, orig_size: u32, flags: u32
+//! the data of the files, at their offset from the start of the pack
+//! ```
+//!
+//! The files with the `PACK_COMPRESSED` flag are zlib streams.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+use std::rc::Rc;
+
+use deflate;
+use inflate;
+use futures::future;
+
+use engine::asset::{AssetError, AssetResult, File, FileFuture, FileIoError, FileSystem};
+
+const PACK_MAGIC: &'static [u8] = b"UPAK";
+const PACK_VERSION: u32 = 1;
+const PACK_COMPRESSED: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct PackEntry {
+    pub offset: usize,
+    pub size: usize,
+    pub orig_size: usize,
+    pub compressed: bool,
+}
+
+pub struct Pack {
+    name: String,
+    data: Vec<u8>,
+    entries: HashMap<String, PackEntry>,
+}
+
+struct PackReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PackReader<'a> {
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.data.len() - self.pos < n {
+            return None;
+        }
+
+        let b = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Some(b)
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|b| {
+            (b[0] as u32) | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+        })
+    }
+}
+
+fn write_u32<W: Write>(w: &mut W, v: u32) -> io::Result<()> {
+    w.write_all(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8])
+}
+
+impl Pack {
+    /// Read the index of a pack, `name` is only used in the errors
+    pub fn from_bytes(name: &str, data: Vec<u8>) -> AssetResult<Pack> {
+        let invalid = |reason: &str| AssetError::InvalidFormat {
+            path: name.to_string(),
+            len: data.len(),
+            reason: reason.to_string(),
+        };
+
+        let entries = {
+            let mut r = PackReader {
+                data: &data,
+                pos: 0,
+            };
+
+            if r.bytes(4) != Some(PACK_MAGIC) {
+                return Err(invalid("not a pack"));
+            }
+
+            match r.read_u32() {
+                Some(PACK_VERSION) => (),
+                _ => return Err(invalid("unsupported pack version")),
+            }
+
+            let count = r.read_u32().ok_or_else(|| invalid("truncated header"))?;
+            let mut entries = HashMap::new();
+
+            for _ in 0..count {
+                let entry = (|| {
+                    let len = r.read_u32()? as usize;
+                    let name = String::from_utf8(r.bytes(len)?.to_vec()).ok()?;
+                    let entry = PackEntry {
+                        offset: r.read_u32()? as usize,
+                        size: r.read_u32()? as usize,
+                        orig_size: r.read_u32()? as usize,
+                        compressed: r.read_u32()? & PACK_COMPRESSED != 0,
+                    };
+                    Some((name, entry))
+                })();
+
+                match entry {
+                    Some((name, e)) => {
+                        let end = e.offset.checked_add(e.size);
+                        if end.map_or(true, |end| end > data.len()) {
+                            return Err(invalid("file out of the pack"));
+                        }
+                        entries.insert(name, e);
+                    }
+                    None => return Err(invalid("corrupted index")),
+                }
+            }
+
+            entries
+        };
+
+        Ok(Pack {
+            name: name.to_string(),
+            data,
+            entries,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn contains(&self, file: &str) -> bool {
+        self.entries.contains_key(file)
+    }
+
+    pub fn entries(&self) -> &HashMap<String, PackEntry> {
+        &self.entries
+    }
+
+    /// The uncompressed data of a file, `None` when it is not in the pack
+    pub fn read(&self, file: &str) -> Option<Result<Vec<u8>, FileIoError>> {
+        self.entries.get(file).map(|e| {
+            let bytes = &self.data[e.offset..e.offset + e.size];
+            if !e.compressed {
+                return Ok(bytes.to_vec());
+            }
+
+            inflate::inflate_bytes_zlib(bytes).map_err(|reason| {
+                FileIoError::Unknown(format!("{} in {}: {}", file, self.name, reason))
+            })
+        })
+    }
+}
+
+/// Build a pack from files in memory
+#[derive(Default)]
+pub struct PackWriter {
+    files: Vec<(String, Vec<u8>, bool)>,
+}
+
+impl PackWriter {
+    pub fn new() -> PackWriter {
+        PackWriter::default()
+    }
+
+    /// Add a file named by its path with `/` separators, as the assets open it.
+    /// The compressed files are stored uncompressed when it is smaller.
+    pub fn add(&mut self, name: &str, data: Vec<u8>, compress: bool) {
+        self.files.retain(|f| f.0 != name);
+        self.files.push((name.to_string(), data, compress));
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let stored: Vec<(&str, Vec<u8>, usize, bool)> = self.files
+            .iter()
+            .map(|&(ref name, ref data, compress)| {
+                if compress {
+                    let packed = deflate::deflate_bytes_zlib(data);
+                    if packed.len() < data.len() {
+                        return (name.as_str(), packed, data.len(), true);
+                    }
+                }
+
+                (name.as_str(), data.clone(), data.len(), false)
+            })
+            .collect();
+
+        let index_len: usize = stored.iter().map(|s| 4 + s.0.len() + 16).sum();
+        let mut offset = PACK_MAGIC.len() + 8 + index_len;
+
+        w.write_all(PACK_MAGIC)?;
+        write_u32(w, PACK_VERSION)?;
+        write_u32(w, stored.len() as u32)?;
+
+        for &(name, ref data, orig_size, compressed) in stored.iter() {
+            write_u32(w, name.len() as u32)?;
+            w.write_all(name.as_bytes())?;
+            write_u32(w, offset as u32)?;
+            write_u32(w, data.len() as u32)?;
+            write_u32(w, orig_size as u32)?;
+            write_u32(w, if compressed { PACK_COMPRESSED } else { 0 })?;
+
+            offset += data.len();
+        }
+
+        for s in stored.iter() {
+            w.write_all(&s.1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Serve the files of the mounted packs, the last mounted pack overrides the files
+/// of the previous ones, e.g. a patch or a mod mounted over the base game.
+#[derive(Default, Clone)]
+pub struct PackFileSystem {
+    packs: Rc<RefCell<Vec<Rc<Pack>>>>,
+}
+
+impl PackFileSystem {
+    /// Mount a pack over the others, it replaces a mounted pack of the same name
+    pub fn mount(&self, pack: Pack) {
+        FileSystem::mount(self, Rc::new(pack));
+    }
+
+    pub fn unmount(&self, name: &str) {
+        self.packs.borrow_mut().retain(|p| p.name != name);
+    }
+
+    /// The names of the mounted packs, in mount order
+    pub fn mounted(&self) -> Vec<String> {
+        self.packs.borrow().iter().map(|p| p.name.clone()).collect()
+    }
+
+    /// The pack serving `file`
+    fn find(&self, file: &str) -> Option<Rc<Pack>> {
+        self.packs
+            .borrow()
+            .iter()
+            .rev()
+            .find(|p| p.contains(file))
+            .cloned()
+    }
+
+    pub fn contains(&self, file: &str) -> bool {
+        self.find(file).is_some()
+    }
+}
+
+impl FileSystem for PackFileSystem {
+    type File = PackFile;
+
+    fn open(&self, filename: &str) -> FileFuture {
+        let filename = filename.replace("\\", "/");
+
+        match self.find(&filename) {
+            Some(pack) => Box::new(future::ok(Box::new(PackFile {
+                name: filename,
+                pack,
+            }) as Box<File>)),
+            None => Box::new(future::err(FileIoError::NoSuchFile(filename))),
+        }
+    }
+
+    fn loading_files(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn mount(&self, pack: Rc<Pack>) -> bool {
+        let mut packs = self.packs.borrow_mut();
+        packs.retain(|p| p.name != pack.name);
+        packs.push(pack);
+        true
+    }
+}
+
+pub struct PackFile {
+    name: String,
+    pack: Rc<Pack>,
+}
+
+impl File for PackFile {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn read_binary(&mut self) -> Result<Vec<u8>, FileIoError> {
+        match self.pack.read(&self.name) {
+            Some(r) => r,
+            None => Err(FileIoError::NoSuchFile(self.name.clone())),
+        }
+    }
+}
@@ -37,6 +37,9 @@ struct Material {
 uniform vec3 uViewPos;
 uniform Material uMaterial;
 
+// Cross-fade between two levels of a LodGroup
+uniform float uLodFade;
+
 varying vec3 vFragPos;
 varying vec2 vTexCoords;       
 varying vec3 vNormal;                       
@@ -49,6 +52,12 @@ vec3 CalcDirectionalLight(DirectionalLight light, vec3 normal, vec3 viewDir);
 vec3 CalcPointLight(PointLight light, vec3 normal, vec3 fragPos, vec3 viewDir);
 
 void main(void) {
+    if (uLodFade != 0.0) {
+        float d = fract(sin(dot(floor(gl_FragCoord.xy), vec2(12.9898, 78.233))) * 43758.5453);
+        if (uLodFade > 0.0 ? d < uLodFade : d >= -uLodFade)
+            discard;
+    }
+
     vec3 norm = normalize(vNormal);
     vec3 viewDir = normalize(uViewPos - vFragPos);
 
@@ -1,6 +1,20 @@
-use engine::render::MeshData;
+use engine::render::{mesh_util, MeshData};
+use math::*;
+use std::collections::{BTreeMap, HashMap};
+use std::f32::consts::PI;
+
 pub struct CubeMesh {}
 pub struct PlaneMesh {}
+pub struct SphereMesh {}
+pub struct IcosphereMesh {}
+pub struct CylinderMesh {}
+pub struct ConeMesh {}
+pub struct TorusMesh {}
+pub struct CapsuleMesh {}
+pub struct GridMesh {}
+
+/// The most segments, rings, sides or divisions of a generated primitive
+const MAX_DIVISIONS: u32 = 256;
 
 impl CubeMesh {
     pub fn new() -> MeshData {
@@ -107,7 +121,7 @@ impl CubeMesh {
             -1.0,  0.0,  0.0
         ];
 
-        let indices: Vec<u16> = vec![
+        let indices: Vec<u32> = vec![
             0, 1, 2,      0, 2, 3,    // Front face
             4, 5, 6,      4, 6, 7,    // Back face
             8, 9, 10,     8, 10, 11,  // Top face
@@ -123,6 +137,7 @@ impl CubeMesh {
             indices: indices,
             tangents: None,
             bitangents: None,
+            ..Default::default()
         }
     }
 }
@@ -153,7 +168,7 @@ impl PlaneMesh {
              0.0,  1.0,  0.0,
         ];
 
-        let indices: Vec<u16> = vec![
+        let indices: Vec<u32> = vec![
             0, 1, 2, 0, 2, 3 // Top face
         ];
 
@@ -164,6 +179,580 @@ impl PlaneMesh {
             indices: indices,
             tangents: None,
             bitangents: None,
+            ..Default::default()
+        }
+    }
+}
+
+/// Collects the vertices of a generated mesh
+#[derive(Default)]
+struct MeshBuilder {
+    vertices: Vec<f32>,
+    uvs: Vec<f32>,
+    normals: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    fn vertex(&mut self, p: Vector3f, n: Vector3f, uv: Vector2f) -> u32 {
+        let i = (self.vertices.len() / 3) as u32;
+        self.vertices.extend_from_slice(&[p.x, p.y, p.z]);
+        self.normals.extend_from_slice(&[n.x, n.y, n.z]);
+        self.uvs.extend_from_slice(&[uv.x, uv.y]);
+        i
+    }
+
+    /// Counter-clockwise when seen from the front
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.extend_from_slice(&[a, b, c]);
+    }
+
+    /// The top-left, bottom-left, bottom-right and top-right corners of a quad
+    fn quad(&mut self, a: u32, b: u32, c: u32, d: u32) {
+        self.triangle(a, b, c);
+        self.triangle(a, c, d);
+    }
+
+    /// Turn a profile around the y axis, from its top to its bottom.
+    /// A profile point is `(distance to the axis, height, normal in the (r, y) plane)`.
+    fn lathe(&mut self, profile: &[(f32, f32, Vector2f)], segments: u32) {
+        let rows = profile.len();
+        let first = (self.vertices.len() / 3) as u32;
+        let stride = segments + 1;
+
+        for (k, &(r, y, n)) in profile.iter().enumerate() {
+            let v = 1.0 - k as f32 / (rows - 1) as f32;
+
+            for s in 0..stride {
+                let u = s as f32 / segments as f32;
+                let (sin, cos) = (u * 2.0 * PI).sin_cos();
+
+                self.vertex(
+                    Vector3::new(r * sin, y, r * cos),
+                    Vector3::new(n.x * sin, n.y, n.x * cos),
+                    Vector2::new(u, v),
+                );
+            }
+        }
+
+        for k in 0..rows - 1 {
+            for s in 0..segments {
+                let a = first + k as u32 * stride + s;
+                let (b, c, d) = (a + stride, a + stride + 1, a + 1);
+
+                // no degenerated triangles at the poles
+                if profile[k + 1].0 > 0.0 {
+                    self.triangle(a, b, c);
+                }
+                if profile[k].0 > 0.0 {
+                    self.triangle(a, c, d);
+                }
+            }
+        }
+    }
+
+    /// A disc closing a lathe, facing up or down
+    fn disc(&mut self, y: f32, radius: f32, up: bool, segments: u32) {
+        let n = if up { Vector3::unit_y() } else { -Vector3::unit_y() };
+        let center = self.vertex(Vector3::new(0.0, y, 0.0), n, Vector2::new(0.5, 0.5));
+
+        let first = center + 1;
+        for s in 0..segments + 1 {
+            let (sin, cos) = (s as f32 / segments as f32 * 2.0 * PI).sin_cos();
+            self.vertex(
+                Vector3::new(radius * sin, y, radius * cos),
+                n,
+                Vector2::new(0.5 + 0.5 * sin, 0.5 + 0.5 * cos),
+            );
+        }
+
+        for s in 0..segments {
+            if up {
+                self.triangle(center, first + s, first + s + 1);
+            } else {
+                self.triangle(center, first + s + 1, first + s);
+            }
+        }
+    }
+
+    fn into_mesh_data(self) -> MeshData {
+        MeshData {
+            vertices: self.vertices,
+            uvs: Some(self.uvs),
+            normals: Some(self.normals),
+            indices: self.indices,
+            ..Default::default()
+        }
+    }
+
+    fn build(self) -> MeshData {
+        let mut mesh_data = self.into_mesh_data();
+        mesh_util::compute_tangents(&mut mesh_data);
+        mesh_data
+    }
+}
+
+/// A profile point on a circle of `radius` centered at `y`
+fn arc_point(radius: f32, y: f32, theta: f32) -> (f32, f32, Vector2f) {
+    let (sin, cos) = theta.sin_cos();
+    (radius * sin, y + radius * cos, Vector2::new(sin, cos))
+}
+
+impl SphereMesh {
+    /// An uv sphere, `rings` from pole to pole
+    pub fn new(segments: u32, rings: u32, radius: f32) -> MeshData {
+        let segments = segments.max(3).min(MAX_DIVISIONS);
+        let rings = rings.max(2).min(MAX_DIVISIONS);
+
+        let profile: Vec<_> = (0..rings + 1)
+            .map(|r| arc_point(radius, 0.0, r as f32 / rings as f32 * PI))
+            .collect();
+
+        let mut b = MeshBuilder::default();
+        b.lathe(&profile, segments);
+        b.build()
+    }
+}
+
+impl IcosphereMesh {
+    /// An icosahedron subdivided `subdivisions` times, its triangles have similar sizes
+    pub fn new(subdivisions: u32, radius: f32) -> MeshData {
+        let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+
+        let mut points: Vec<Vector3f> = [
+            (-1.0, t, 0.0),
+            (1.0, t, 0.0),
+            (-1.0, -t, 0.0),
+            (1.0, -t, 0.0),
+            (0.0, -1.0, t),
+            (0.0, 1.0, t),
+            (0.0, -1.0, -t),
+            (0.0, 1.0, -t),
+            (t, 0.0, -1.0),
+            (t, 0.0, 1.0),
+            (-t, 0.0, -1.0),
+            (-t, 0.0, 1.0),
+        ].iter()
+            .map(|&(x, y, z)| Vector3::new(x, y, z).normalize())
+            .collect();
+
+        let mut faces: Vec<[u32; 3]> = vec![
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ];
+
+        // each triangle is split in 4, the new points shared along the edges
+        for _ in 0..subdivisions.min(6) {
+            let mut middles: HashMap<(u32, u32), u32> = HashMap::new();
+            let mut middle = |a: u32, b: u32, points: &mut Vec<Vector3f>| {
+                let key = (a.min(b), a.max(b));
+                *middles.entry(key).or_insert_with(|| {
+                    let p = (points[a as usize] + points[b as usize]).normalize();
+                    points.push(p);
+                    (points.len() - 1) as u32
+                })
+            };
+
+            let mut next = Vec::with_capacity(faces.len() * 4);
+            for f in faces.iter() {
+                let ab = middle(f[0], f[1], &mut points);
+                let bc = middle(f[1], f[2], &mut points);
+                let ca = middle(f[2], f[0], &mut points);
+
+                next.push([f[0], ab, ca]);
+                next.push([f[1], bc, ab]);
+                next.push([f[2], ca, bc]);
+                next.push([ab, bc, ca]);
+            }
+            faces = next;
         }
+
+        // spherical uvs, the triangles crossing the seam get their own vertices
+        let uv_of = |p: &Vector3f| {
+            Vector2::new(
+                0.5 + p.x.atan2(p.z) / (2.0 * PI),
+                0.5 + p.y.max(-1.0).min(1.0).asin() / PI,
+            )
+        };
+
+        let mut b = MeshBuilder::default();
+        for f in faces.iter() {
+            let ps = [
+                points[f[0] as usize],
+                points[f[1] as usize],
+                points[f[2] as usize],
+            ];
+            let mut uvs = [uv_of(&ps[0]), uv_of(&ps[1]), uv_of(&ps[2])];
+
+            let max_u = uvs.iter().fold(0.0f32, |m, uv| m.max(uv.x));
+            let min_u = uvs.iter().fold(1.0f32, |m, uv| m.min(uv.x));
+            if max_u - min_u > 0.5 {
+                for uv in uvs.iter_mut() {
+                    if uv.x < 0.5 {
+                        uv.x += 1.0;
+                    }
+                }
+            }
+
+            let i0 = b.vertex(ps[0] * radius, ps[0], uvs[0]);
+            let i1 = b.vertex(ps[1] * radius, ps[1], uvs[1]);
+            let i2 = b.vertex(ps[2] * radius, ps[2], uvs[2]);
+            b.triangle(i0, i1, i2);
+        }
+
+        let mut mesh_data = b.into_mesh_data();
+        mesh_util::weld(&mut mesh_data, 0.0);
+        mesh_util::compute_tangents(&mut mesh_data);
+        mesh_data
+    }
+}
+
+impl CylinderMesh {
+    /// A closed cylinder along the y axis, centered on the origin
+    pub fn new(segments: u32, radius: f32, height: f32) -> MeshData {
+        let segments = segments.max(3).min(MAX_DIVISIONS);
+        let h = height * 0.5;
+        let n = Vector2::new(1.0, 0.0);
+
+        let mut b = MeshBuilder::default();
+        b.lathe(&[(radius, h, n), (radius, -h, n)], segments);
+        b.disc(h, radius, true, segments);
+        b.disc(-h, radius, false, segments);
+        b.build()
+    }
+}
+
+impl ConeMesh {
+    /// A closed cone along the y axis, its apex up and centered on the origin
+    pub fn new(segments: u32, radius: f32, height: f32) -> MeshData {
+        let segments = segments.max(3).min(MAX_DIVISIONS);
+        let h = height * 0.5;
+        let n = Vector2::new(height, radius).normalize();
+
+        let mut b = MeshBuilder::default();
+        b.lathe(&[(0.0, h, n), (radius, -h, n)], segments);
+        b.disc(-h, radius, false, segments);
+        b.build()
+    }
+}
+
+impl TorusMesh {
+    /// A torus around the y axis, `radius` to the center of its tube
+    pub fn new(radius: f32, tube: f32, segments: u32, sides: u32) -> MeshData {
+        let segments = segments.max(3).min(MAX_DIVISIONS);
+        let sides = sides.max(3).min(MAX_DIVISIONS);
+        let stride = sides + 1;
+
+        let mut b = MeshBuilder::default();
+        for i in 0..segments + 1 {
+            let u = i as f32 / segments as f32;
+            let (sin, cos) = (u * 2.0 * PI).sin_cos();
+            let dir = Vector3::new(sin, 0.0, cos);
+
+            for j in 0..stride {
+                let v = j as f32 / sides as f32;
+                let (tsin, tcos) = (v * 2.0 * PI).sin_cos();
+                let n = dir * tcos + Vector3::unit_y() * tsin;
+
+                b.vertex(dir * radius + n * tube, n, Vector2::new(u, v));
+            }
+        }
+
+        for i in 0..segments {
+            for j in 0..sides {
+                let bl = i * stride + j;
+                b.quad(bl + 1, bl, bl + stride, bl + stride + 1);
+            }
+        }
+
+        b.build()
+    }
+}
+
+impl CapsuleMesh {
+    /// A capsule along the y axis, `height` from end to end, `rings` for each cap
+    pub fn new(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+        let segments = segments.max(3).min(MAX_DIVISIONS);
+        let rings = rings.max(1).min(MAX_DIVISIONS);
+        let h = (height * 0.5 - radius).max(0.0);
+
+        let top = (0..rings + 1).map(|r| arc_point(radius, h, r as f32 / rings as f32 * PI * 0.5));
+        let bottom = (0..rings + 1)
+            .map(|r| arc_point(radius, -h, (1.0 + r as f32 / rings as f32) * PI * 0.5));
+        let profile: Vec<_> = top.chain(bottom).collect();
+
+        let mut b = MeshBuilder::default();
+        b.lathe(&profile, segments);
+        b.build()
+    }
+}
+
+impl GridMesh {
+    /// A plane facing up, `size` wide and split in `divisions` along x and z
+    pub fn new(size: f32, divisions: u32) -> MeshData {
+        let divisions = divisions.max(1).min(MAX_DIVISIONS);
+        let stride = divisions + 1;
+        let step = size / divisions as f32;
+
+        let mut b = MeshBuilder::default();
+        for i in 0..stride {
+            for j in 0..stride {
+                let p = Vector3::new(i as f32 * step, 0.0, j as f32 * step);
+                let uv = Vector2::new(i as f32, j as f32) / divisions as f32;
+                b.vertex(p - Vector3::new(size, 0.0, size) * 0.5, Vector3::unit_y(), uv);
+            }
+        }
+
+        for i in 0..divisions {
+            for j in 0..divisions {
+                let a = i * stride + j;
+                b.quad(a, a + 1, a + stride + 1, a + stride);
+            }
+        }
+
+        b.build()
+    }
+}
+
+/// The parameters of each primitive with their default values
+fn primitive_defaults(kind: &str) -> Option<&'static [(&'static str, f32)]> {
+    let defaults: &'static [(&'static str, f32)] = match kind {
+        "sphere" => &[("segments", 32.0), ("rings", 16.0), ("radius", 1.0)],
+        "icosphere" => &[("subdivisions", 2.0), ("radius", 1.0)],
+        "cylinder" => &[("segments", 32.0), ("radius", 1.0), ("height", 2.0)],
+        "cone" => &[("segments", 32.0), ("radius", 1.0), ("height", 2.0)],
+        "torus" => &[
+            ("radius", 1.0),
+            ("tube", 0.25),
+            ("segments", 32.0),
+            ("sides", 16.0),
+        ],
+        "capsule" => &[
+            ("radius", 0.5),
+            ("height", 2.0),
+            ("segments", 32.0),
+            ("rings", 8.0),
+        ],
+        "grid" => &[("size", 20.0), ("divisions", 10.0)],
+        _ => return None,
+    };
+
+    Some(defaults)
+}
+
+/// The parameters counting something, they are whole numbers
+const COUNTS: [&str; 5] = ["segments", "rings", "sides", "subdivisions", "divisions"];
+
+/// Parameters of a primitive name, e.g. `"sphere?segments=32&radius=2"`, the missing
+/// ones have their default value and the unknown ones are ignored
+struct PrimitiveParams {
+    kind: String,
+    params: BTreeMap<&'static str, f32>,
+}
+
+impl PrimitiveParams {
+    fn parse(name: &str) -> Option<PrimitiveParams> {
+        let mut parts = name.splitn(2, '?');
+        let kind = parts.next()?;
+        let mut params: BTreeMap<_, _> = primitive_defaults(kind)?.iter().cloned().collect();
+
+        for pair in parts.next().unwrap_or("").split('&').filter(|p| !p.is_empty()) {
+            let mut kv = pair.splitn(2, '=');
+            let key = kv.next()?;
+            let value: f32 = kv.next()?.parse().ok()?;
+
+            if let Some(v) = params.get_mut(key) {
+                *v = if COUNTS.iter().any(|c| *c == key) {
+                    value.max(0.0).floor()
+                } else {
+                    value
+                };
+            }
+        }
+
+        Some(PrimitiveParams {
+            kind: kind.to_owned(),
+            params,
+        })
+    }
+
+    /// The name with all the parameters, sorted
+    fn key(&self) -> String {
+        let params: Vec<_> = self.params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+
+        format!("{}?{}", self.kind, params.join("&"))
+    }
+
+    fn get(&self, key: &str) -> f32 {
+        self.params[key]
+    }
+
+    fn count(&self, key: &str) -> u32 {
+        self.params[key] as u32
+    }
+}
+
+/// The canonical name of the primitive named by `name`: the same primitive always has
+/// the same key, whatever the order of its parameters and the defaults given.
+///
+/// Returns None when it is not a primitive or its parameters are not numbers.
+pub fn primitive_key(name: &str) -> Option<String> {
+    PrimitiveParams::parse(name).map(|p| p.key())
+}
+
+/// Generate the primitive named by `name`, with its parameters after a `?`:
+///
+/// * `sphere?segments=32&rings=16&radius=1`
+/// * `icosphere?subdivisions=2&radius=1`
+/// * `cylinder?segments=32&radius=1&height=2`
+/// * `cone?segments=32&radius=1&height=2`
+/// * `torus?radius=1&tube=0.25&segments=32&sides=16`
+/// * `capsule?radius=0.5&height=2&segments=32&rings=8`
+/// * `grid?size=20&divisions=10`
+///
+/// The counts are capped at 256, the subdivisions of the icosphere at 6.
+///
+/// Returns None when it is not a primitive or its parameters are not numbers.
+pub fn primitive_mesh(name: &str) -> Option<MeshData> {
+    let p = PrimitiveParams::parse(name)?;
+
+    Some(match p.kind.as_str() {
+        "sphere" => SphereMesh::new(p.count("segments"), p.count("rings"), p.get("radius")),
+        "icosphere" => IcosphereMesh::new(p.count("subdivisions"), p.get("radius")),
+        "cylinder" => CylinderMesh::new(p.count("segments"), p.get("radius"), p.get("height")),
+        "cone" => ConeMesh::new(p.count("segments"), p.get("radius"), p.get("height")),
+        "torus" => TorusMesh::new(
+            p.get("radius"),
+            p.get("tube"),
+            p.count("segments"),
+            p.count("sides"),
+        ),
+        "capsule" => CapsuleMesh::new(
+            p.get("radius"),
+            p.get("height"),
+            p.count("segments"),
+            p.count("rings"),
+        ),
+        "grid" => GridMesh::new(p.get("size"), p.count("divisions")),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that the attributes of a generated primitive have a value per vertex and
+    /// that its normals have a unit length
+    fn check(name: &str) {
+        let data = primitive_mesh(name).unwrap();
+        let count = data.vertex_count();
+        assert!(count > 0, "{}", name);
+
+        let normals = data.normals.as_ref().unwrap();
+        assert_eq!(normals.len(), count * 3, "{}", name);
+        assert_eq!(data.uvs.as_ref().unwrap().len(), count * 2, "{}", name);
+        assert_eq!(data.tangents.as_ref().unwrap().len(), count * 3, "{}", name);
+        assert_eq!(data.bitangents.as_ref().unwrap().len(), count * 3, "{}", name);
+        assert!(data.indices.iter().all(|&i| (i as usize) < count), "{}", name);
+
+        for n in normals.chunks(3) {
+            let len = Vector3::new(n[0], n[1], n[2]).magnitude();
+            assert!((len - 1.0).abs() < 1.0e-4, "{}: normal {:?}", name, n);
+        }
+    }
+
+    #[test]
+    fn sphere() {
+        check("sphere");
+        check("sphere?segments=3&rings=2&radius=2");
+    }
+
+    #[test]
+    fn icosphere() {
+        check("icosphere");
+        check("icosphere?subdivisions=0");
+    }
+
+    #[test]
+    fn cylinder() {
+        check("cylinder");
+        check("cylinder?segments=3&height=0.5");
+    }
+
+    #[test]
+    fn cone() {
+        check("cone");
+        check("cone?segments=3&radius=2");
+    }
+
+    #[test]
+    fn torus() {
+        check("torus");
+        check("torus?segments=3&sides=3&tube=0.5");
+    }
+
+    #[test]
+    fn capsule() {
+        check("capsule");
+        check("capsule?rings=1&height=0.5");
+    }
+
+    #[test]
+    fn grid() {
+        check("grid");
+        check("grid?divisions=1&size=2");
+    }
+
+    #[test]
+    fn counts_are_capped() {
+        let vertex_count = |name| primitive_mesh(name).unwrap().vertex_count();
+
+        assert_eq!(vertex_count("grid?divisions=100000"), vertex_count("grid?divisions=256"));
+        assert_eq!(
+            vertex_count("icosphere?subdivisions=100"),
+            vertex_count("icosphere?subdivisions=6")
+        );
+    }
+
+    #[test]
+    fn keys_are_canonical() {
+        assert_eq!(
+            primitive_key("sphere?radius=2").unwrap(),
+            "sphere?radius=2&rings=16&segments=32"
+        );
+        assert_eq!(
+            primitive_key("sphere?segments=32&radius=1"),
+            primitive_key("sphere")
+        );
+        assert_eq!(
+            primitive_key("torus?sides=8.5&radius=2"),
+            primitive_key("torus?radius=2.0&sides=8")
+        );
+        assert_ne!(primitive_key("sphere?radius=2"), primitive_key("sphere"));
+
+        assert_eq!(primitive_key("cube"), None);
+        assert_eq!(primitive_key("sphere?radius=big"), None);
     }
 }
@@ -19,7 +19,7 @@ impl QuadMesh {
             1.0, 1.0,
         ];
 
-        let indices: Vec<u16> = vec![
+        let indices: Vec<u32> = vec![
             0, 1, 2, 0, 2, 3 // Top face
         ];
 
@@ -30,6 +30,7 @@ impl QuadMesh {
             indices: indices,
             tangents: None,
             bitangents: None,
+            ..Default::default()
         }
     }
 }
@@ -1,5 +1,5 @@
 use futures::{Async, Future};
-use std::cell::{Ref, RefCell};
+use std::cell::{Ref, RefCell, RefMut};
 use std::fmt;
 use std::fmt::Debug;
 use std::mem;
@@ -16,6 +16,7 @@ where
             &ResourceKind::Consumed => write!(f, "ResourceKind::Consumed"),
             &ResourceKind::Data(ref t) => write!(f, "ResourceKind::Data({:?})", *t),
             &ResourceKind::Future(_) => write!(f, "ResourceKind::Future"),
+            &ResourceKind::Failed(ref e) => write!(f, "ResourceKind::Failed({:?})", e),
         }
     }
 }
@@ -24,6 +25,33 @@ enum ResourceKind<T: Debug> {
     Consumed,
     Data(T),
     Future(Box<Future<Item = T, Error = AssetError>>),
+    Failed(AssetError),
+}
+
+/// Loading state of an asset
+#[derive(Debug, Clone)]
+pub enum AssetState {
+    Loading,
+    Ready,
+    Failed(AssetError),
+}
+
+impl AssetState {
+    /// The state of an asset made of several parts, it fails when one of them fails
+    pub fn merge(self, other: AssetState) -> AssetState {
+        match (self, other) {
+            (AssetState::Failed(e), _) | (_, AssetState::Failed(e)) => AssetState::Failed(e),
+            (AssetState::Loading, _) | (_, AssetState::Loading) => AssetState::Loading,
+            _ => AssetState::Ready,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        match self {
+            &AssetState::Ready => true,
+            _ => false,
+        }
+    }
 }
 
 impl<T: Debug> ResourceKind<T> {
@@ -46,6 +74,13 @@ impl<T: Debug> ResourceKind<T> {
             _ => None,
         }
     }
+
+    fn try_as_data_mut(&mut self) -> Option<&mut T> {
+        match self {
+            &mut ResourceKind::Data(ref mut d) => Some(d),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -63,50 +98,76 @@ impl<T: Debug + loader::Loadable> Resource<T> {
         Resource(RefCell::new(ResourceKind::Data(f)))
     }
 
-    pub fn try_into(&self) -> AssetResult<T> {
-        match &mut *self.0.borrow_mut() {
-            &mut ResourceKind::Future(ref mut f) => {
-                return match f.poll() {
-                    Err(e) => Err(e),
-                    Ok(Async::NotReady) => Err(AssetError::NotReady),
-                    Ok(Async::Ready(i)) => Ok(i),
-                };
-            }
+    /// Poll the future, its data or its error is kept
+    fn resolve(&self) -> AssetResult<()> {
+        let mut kind = self.0.borrow_mut();
 
-            img @ &mut ResourceKind::Data(_) => {
-                let r = img.replace(ResourceKind::Consumed);
-                Ok(r.try_into_data().unwrap())
-            }
+        let r = match &mut *kind {
+            &mut ResourceKind::Future(ref mut f) => f.poll(),
+            &mut ResourceKind::Failed(ref e) => return Err(e.clone()),
+            _ => return Ok(()),
+        };
 
-            _ => unreachable!(),
+        match r {
+            Err(e) => {
+                kind.replace(ResourceKind::Failed(e.clone()));
+                Err(e)
+            }
+            Ok(Async::NotReady) => Err(AssetError::NotReady),
+            Ok(Async::Ready(i)) => {
+                kind.replace(ResourceKind::Data(i));
+                Ok(())
+            }
         }
     }
 
-    pub fn try_borrow(&self) -> AssetResult<Ref<T>> {
-        let mut data = None;
-
-        if let &mut ResourceKind::Future(ref mut f) = &mut *self.0.borrow_mut() {
-            match f.poll() {
-                Err(e) => return Err(e),
-                Ok(Async::NotReady) => return Err(AssetError::NotReady),
-                Ok(Async::Ready(i)) => {
-                    data = Some(i);
-                }
-            }
-        }
+    pub fn try_into(&self) -> AssetResult<T> {
+        self.resolve()?;
 
-        if let Some(i) = data {
-            let kind: &mut ResourceKind<T> = &mut self.0.borrow_mut();
-            kind.replace(ResourceKind::Data(i));
-        }
+        let r = self.0.borrow_mut().replace(ResourceKind::Consumed);
+        Ok(r.try_into_data().unwrap())
+    }
+
+    pub fn try_borrow(&self) -> AssetResult<Ref<T>> {
+        self.resolve()?;
 
         let b0 = self.0.borrow();
         return Ok(Ref::map(b0, |t| t.try_as_data().unwrap()));
     }
 
+    pub fn try_borrow_mut(&self) -> AssetResult<RefMut<T>> {
+        // resolve the future first
+        self.try_borrow()?;
+
+        let b0 = self.0.borrow_mut();
+        return Ok(RefMut::map(b0, |t| t.try_as_data_mut().unwrap()));
+    }
+
     pub fn replace(&self, t: T) {
         self.0.borrow_mut().replace(ResourceKind::Data(t));
     }
+
+    /// Poll the future of the resource, returns whether its data is ready
+    pub fn poll(&self) -> AssetResult<bool> {
+        match self.resolve() {
+            Ok(_) => Ok(true),
+            Err(AssetError::NotReady) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn state(&self) -> AssetState {
+        match self.resolve() {
+            Ok(_) => AssetState::Ready,
+            Err(AssetError::NotReady) => AssetState::Loading,
+            Err(e) => AssetState::Failed(e),
+        }
+    }
+
+    /// Exchange the content of two resources, e.g. to swap in a reloaded one
+    pub fn swap(&self, other: &Resource<T>) {
+        mem::swap(&mut *self.0.borrow_mut(), &mut *other.0.borrow_mut());
+    }
 }
 
 impl<T: Debug + loader::Loadable> From<T> for Resource<T> {
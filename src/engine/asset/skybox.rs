@@ -14,9 +14,9 @@ impl SkyboxMesh {
             -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, -1.0, -1.0, -1.0, -1.0, 1.0, 1.0, -1.0, 1.0,
         ];
 
-        let mut indices: Vec<u16> = vec![];
+        let mut indices: Vec<u32> = vec![];
         for i in 0..vertices.len() / 3 {
-            indices.push(i as u16);
+            indices.push(i as u32);
         }
 
         debug_assert!(indices.len() == 36);
@@ -28,6 +28,7 @@ impl SkyboxMesh {
             indices: indices,
             tangents: None,
             bitangents: None,
+            ..Default::default()
         }
     }
 }
@@ -8,7 +8,8 @@ out vec4 FragColor;
 varying vec3 vColor;
 varying vec2 vTextureCoord;
 uniform sampler2D uDiffuse;
+uniform vec4 uTint;
 
 void main(void) {
-    gl_FragColor = texture2D(uDiffuse, vec2(vTextureCoord.s, vTextureCoord.t));
+    gl_FragColor = texture2D(uDiffuse, vec2(vTextureCoord.s, vTextureCoord.t)) * uTint;
 }
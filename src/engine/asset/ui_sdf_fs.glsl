@@ -0,0 +1,18 @@
+#ifndef GL_ES
+#define varying in
+#define gl_FragColor FragColor
+out vec4 FragColor;
+#define texture2D texture
+#endif
+
+varying vec2 vTextureCoord;
+uniform sampler2D uDiffuse;
+uniform vec4 uTint;
+// half width of the anti-aliased edge, in distance units
+uniform float uSmoothing;
+
+void main(void) {
+    vec4 texel = texture2D(uDiffuse, vTextureCoord);
+    float alpha = smoothstep(0.5 - uSmoothing, 0.5 + uSmoothing, texel.a);
+    gl_FragColor = vec4(texel.rgb, alpha) * uTint;
+}
\ No newline at end of file
@@ -1,8 +1,8 @@
 use engine::asset::AssetResult;
 use engine::core::Component;
 use engine::engine::EngineStats;
-use engine::render::{CullMode, DepthTest, Material, MaterialState, MeshBuffer, ShaderProgram,
-                     Texture};
+use engine::render::{CullMode, DepthTest, Material, MaterialState, MeshBuffer, Scissor,
+                     ShaderProgram, Texture};
 use std::collections::VecDeque;
 use std::rc::{Rc, Weak};
 use std::sync::Arc;
@@ -41,6 +41,7 @@ impl StateCache {
             depth_test: Some(DepthTest::Less),
             alpha_blending: Some(false),
             depth_write: Some(true),
+            scissor: Some(Scissor::Off),
         }
     }
 
@@ -50,6 +51,7 @@ impl StateCache {
         ms.depth_write.map(|s| self.curr.depth_write = Some(s));
         ms.alpha_blending
             .map(|s| self.curr.alpha_blending = Some(s));
+        ms.scissor.map(|s| self.curr.scissor = Some(s));
     }
 
     pub fn commit(&mut self, gl: &WebGLRenderingContext) {
@@ -59,6 +61,25 @@ impl StateCache {
         self.curr
             .alpha_blending
             .map(|s| self.apply_alpha_blending(gl, s));
+        self.curr.scissor.map(|s| self.apply_scissor(gl, s));
+    }
+
+    fn apply_scissor(&mut self, gl: &WebGLRenderingContext, sc: Scissor) {
+        if let Some(s) = self.state.scissor {
+            if s == sc {
+                return;
+            }
+        }
+
+        match sc {
+            Scissor::Off => gl.disable(Flag::ScissorTest as i32),
+            Scissor::Rect(x, y, w, h) => {
+                gl.enable(Flag::ScissorTest as i32);
+                gl.scissor(x, y, w, h);
+            }
+        }
+
+        self.state.scissor = Some(sc);
     }
 
     fn apply_depth_write(&mut self, gl: &WebGLRenderingContext, b: bool) {
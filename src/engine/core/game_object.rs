@@ -24,6 +24,9 @@ pub trait Component: Any {
     fn typeid(&self) -> TypeId;
 
     fn as_any(&self) -> &Any;
+
+    /// Full path of the component type, e.g. `unrust::engine::Camera`
+    fn type_name(&self) -> &'static str;
 }
 
 pub struct ComponentType<T: 'static> {
@@ -62,6 +65,10 @@ where
     fn as_any(&self) -> &Any {
         self
     }
+
+    fn type_name(&self) -> &'static str {
+        unsafe { ::std::intrinsics::type_name::<T>() }
+    }
 }
 
 impl<T> Drop for ComponentType<T>
@@ -156,6 +163,11 @@ impl Transform {
         Transform { node_id, tree }
     }
 
+    /// Id of the node in the scene tree
+    pub fn node_id(&self) -> u64 {
+        self.node_id
+    }
+
     pub fn as_local_matrix(&self) -> Matrix4<f32> {
         let tree = self.tree.upgrade().unwrap();
         tree.get_local_matrix(self.node_id)
@@ -244,6 +256,10 @@ impl GameObject {
         self.transform.tree.upgrade().unwrap()
     }
 
+    pub fn components(&self) -> &[Arc<Component>] {
+        &self.components
+    }
+
     pub fn find_component<T>(&self) -> Option<(Ref<T>, &Arc<Component>)>
     where
         T: 'static,
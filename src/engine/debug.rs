@@ -0,0 +1,347 @@
+//! Immediate-mode debug drawing
+//!
+//! Lines, boxes, spheres, axes, frustums and texts can be added from anywhere during
+//! the frame. The engine draws them at the end of the frame, the lines batched
+//! in a single line mesh for each depth test mode.
+//!
+//! The shapes are kept for the current frame only, unless `set_duration` was called
+//! before them. `set_depth_test(false)` draws the following shapes on top of the scene.
+//! Both options are reset at the end of each frame.
+
+use std::cell::RefCell;
+use std::f32::consts::PI;
+use std::rc::Rc;
+
+use engine::imgui::{self, Color, Metric};
+use engine::{Asset, BufferUsage, Camera, DepthTest, GameObject, IEngine, Material, Mesh,
+             MeshBuffer, MeshData, Primitive, RenderQueue, SceneTree};
+use math::*;
+use uni_app::now;
+
+pub const RED: Color = [0xff, 0x00, 0x00, 0xff];
+pub const GREEN: Color = [0x00, 0xff, 0x00, 0xff];
+pub const BLUE: Color = [0x00, 0x00, 0xff, 0xff];
+pub const YELLOW: Color = [0xff, 0xff, 0x00, 0xff];
+pub const WHITE: Color = [0xff, 0xff, 0xff, 0xff];
+
+/// Number of segments of the circles of a sphere
+const CIRCLE_SEGMENTS: usize = 24;
+
+/// The lines after this count are dropped, which keeps the indices in 16 bits
+const MAX_VERTICES: usize = 65536;
+
+struct DebugLine {
+    a: Vector3f,
+    b: Vector3f,
+    color: Color,
+    depth_test: bool,
+    expires: Option<f64>,
+}
+
+struct DebugText {
+    pos: Vector3f,
+    s: String,
+    color: Color,
+    expires: Option<f64>,
+}
+
+struct DebugRaw {
+    lines: Vec<DebugLine>,
+    texts: Vec<DebugText>,
+    duration: Option<f64>,
+    depth_test: bool,
+}
+
+impl Default for DebugRaw {
+    fn default() -> DebugRaw {
+        DebugRaw {
+            lines: Vec::new(),
+            texts: Vec::new(),
+            duration: None,
+            depth_test: true,
+        }
+    }
+}
+
+impl DebugRaw {
+    fn expires(&self) -> Option<f64> {
+        self.duration.map(|d| now() + d)
+    }
+
+    /// Drop the shapes of this frame and the expired ones, reset the options
+    fn end_frame(&mut self) {
+        let t = now();
+        self.lines.retain(|l| l.expires.map_or(false, |e| e > t));
+        self.texts.retain(|l| l.expires.map_or(false, |e| e > t));
+
+        self.duration = None;
+        self.depth_test = true;
+    }
+}
+
+thread_local!(
+    static DEBUG_RAW: RefCell<DebugRaw> = RefCell::new(Default::default());
+);
+
+fn with_debug<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut DebugRaw) -> R,
+{
+    DEBUG_RAW.with(|raw| f(&mut raw.borrow_mut()))
+}
+
+/// Keep the following shapes of this frame for `secs` seconds
+pub fn set_duration(secs: f64) {
+    with_debug(|raw| raw.duration = Some(secs));
+}
+
+/// Hide the following shapes of this frame behind the scene, or draw them on top of it
+pub fn set_depth_test(enabled: bool) {
+    with_debug(|raw| raw.depth_test = enabled);
+}
+
+pub fn line(a: Vector3f, b: Vector3f, color: Color) {
+    with_debug(|raw| {
+        let line = DebugLine {
+            a,
+            b,
+            color,
+            depth_test: raw.depth_test,
+            expires: raw.expires(),
+        };
+        raw.lines.push(line);
+    });
+}
+
+/// The 12 edges joining 8 corners, the corner `i` is on the max side of x when `i & 1`,
+/// of y when `i & 2` and of z when `i & 4`
+pub fn cuboid(c: &[Vector3f; 8], color: Color) {
+    const EDGES: [(usize, usize); 12] = [
+        (0, 1),
+        (1, 3),
+        (3, 2),
+        (2, 0),
+        (4, 5),
+        (5, 7),
+        (7, 6),
+        (6, 4),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+    ];
+
+    for &(i, j) in EDGES.iter() {
+        line(c[i], c[j], color);
+    }
+}
+
+fn box_corners<F>(f: F) -> [Vector3f; 8]
+where
+    F: Fn(f32, f32, f32) -> Vector3f,
+{
+    let mut corners = [Vector3::zero(); 8];
+    for (i, c) in corners.iter_mut().enumerate() {
+        let x = if i & 1 == 0 { 0.0 } else { 1.0 };
+        let y = if i & 2 == 0 { 0.0 } else { 1.0 };
+        let z = if i & 4 == 0 { 0.0 } else { 1.0 };
+        *c = f(x, y, z);
+    }
+
+    corners
+}
+
+pub fn aabb(aabb: &Aabb, color: Color) {
+    let size = aabb.max - aabb.min;
+    let corners = box_corners(|x, y, z| {
+        aabb.min + Vector3::new(x * size.x, y * size.y, z * size.z)
+    });
+
+    cuboid(&corners, color);
+}
+
+/// Three circles around the axes
+pub fn sphere(center: Vector3f, r: f32, color: Color) {
+    let point = |axis: usize, a: f32| {
+        let (s, c) = (a.sin() * r, a.cos() * r);
+        center + match axis {
+            0 => Vector3::new(0.0, c, s),
+            1 => Vector3::new(c, 0.0, s),
+            _ => Vector3::new(c, s, 0.0),
+        }
+    };
+
+    for axis in 0..3 {
+        for i in 0..CIRCLE_SEGMENTS {
+            let a0 = (i as f32) / (CIRCLE_SEGMENTS as f32) * 2.0 * PI;
+            let a1 = ((i + 1) as f32) / (CIRCLE_SEGMENTS as f32) * 2.0 * PI;
+            line(point(axis, a0), point(axis, a1), color);
+        }
+    }
+}
+
+/// The x, y and z axes of a transform in red, green and blue
+pub fn axes(m: &Matrix4f, size: f32) {
+    let origin = m.w.truncate();
+    let axis = |v: Vector4<f32>| origin + v.truncate().normalize() * size;
+
+    line(origin, axis(m.x), RED);
+    line(origin, axis(m.y), GREEN);
+    line(origin, axis(m.z), BLUE);
+}
+
+/// The view frustum of a camera, from its near to its far plane
+pub fn frustum(camera: &Camera, screen_size: (u32, u32), color: Color) {
+    let inv = match (camera.perspective(screen_size) * camera.v).invert() {
+        Some(inv) => inv,
+        None => return,
+    };
+
+    let corners = box_corners(|x, y, z| {
+        let p = inv * Vector4::new(x * 2.0 - 1.0, y * 2.0 - 1.0, z * 2.0 - 1.0, 1.0);
+        p.truncate() / p.w
+    });
+
+    cuboid(&corners, color);
+}
+
+/// A label at a world position
+pub fn text(pos: Vector3f, s: &str, color: Color) {
+    with_debug(|raw| {
+        let text = DebugText {
+            pos,
+            s: s.into(),
+            color,
+            expires: raw.expires(),
+        };
+        raw.texts.push(text);
+    });
+}
+
+fn make_lines_mesh_data<'a, I>(lines: I) -> MeshData
+where
+    I: Iterator<Item = &'a DebugLine>,
+{
+    let mut vertices = vec![];
+    let mut colors = vec![];
+    let mut indices = vec![];
+
+    for l in lines.take(MAX_VERTICES / 2) {
+        let c = imgui::color_to_vec4(&l.color);
+        for p in [l.a, l.b].iter() {
+            indices.push((vertices.len() / 3) as u32);
+            vertices.extend_from_slice(&[p.x, p.y, p.z]);
+            colors.extend_from_slice(&[c.x, c.y, c.z, c.w]);
+        }
+    }
+
+    MeshData {
+        vertices: vertices,
+        uvs: None,
+        normals: None,
+        indices: indices,
+        tangents: None,
+        bitangents: None,
+        colors: Some(colors),
+        primitive: Primitive::Lines,
+        ..Default::default()
+    }
+}
+
+struct LineBatch {
+    go: Rc<RefCell<GameObject>>,
+    buffer: Rc<MeshBuffer>,
+}
+
+impl LineBatch {
+    fn new(
+        mesh_data: MeshData,
+        depth_test: bool,
+        parent: &GameObject,
+        engine: &mut IEngine,
+    ) -> LineBatch {
+        let mut material = Material::new(engine.asset_system().new_program("default_debug"));
+        if depth_test {
+            material.render_queue = RenderQueue::Transparent;
+        } else {
+            material.render_queue = RenderQueue::UI;
+            material.states.depth_test = Some(DepthTest::Always);
+        }
+        material.states.depth_write = Some(false);
+
+        // the lines are rebuilt every frame
+        let buffer = MeshBuffer::new(mesh_data);
+        buffer.set_usage(BufferUsage::Stream);
+        let mut mesh = Mesh::new();
+        mesh.add_surface(buffer.clone(), Rc::new(material));
+
+        let go = engine.new_game_object(parent);
+        go.borrow_mut().add_component(mesh);
+
+        LineBatch { go, buffer }
+    }
+}
+
+/// Draws the debug shapes, owned by the engine
+pub struct Context {
+    tree: Rc<SceneTree>,
+    batches: [Option<LineBatch>; 2],
+}
+
+impl Context {
+    pub fn new(tree: Rc<SceneTree>) -> Context {
+        Context {
+            tree,
+            batches: [None, None],
+        }
+    }
+
+    /// Upload the lines of this frame and add the texts to imgui,
+    /// must be called before `imgui::pre_render`
+    pub fn update(&mut self, engine: &mut IEngine, camera: Option<&Camera>) {
+        let tree = self.tree.clone();
+
+        with_debug(|raw| {
+            for (i, batch) in self.batches.iter_mut().enumerate() {
+                let depth_test = i == 0;
+                let mut lines = raw.lines.iter().filter(|l| l.depth_test == depth_test).peekable();
+
+                let empty = lines.peek().is_none();
+                match *batch {
+                    Some(ref b) => {
+                        b.go.borrow_mut().active = !empty;
+                        if !empty {
+                            b.buffer.update_mesh_data(make_lines_mesh_data(lines));
+                        }
+                    }
+                    None if !empty => {
+                        let mesh_data = make_lines_mesh_data(lines);
+                        *batch = Some(LineBatch::new(mesh_data, depth_test, &tree.root(), engine));
+                    }
+                    None => (),
+                }
+            }
+
+            if let Some(camera) = camera {
+                let pv = camera.perspective(engine.screen_size()) * camera.v;
+
+                for t in raw.texts.iter() {
+                    let p = pv * t.pos.extend(1.0);
+                    // behind the camera
+                    if p.w <= 0.0 {
+                        continue;
+                    }
+
+                    let (x, y) = (p.x / p.w, p.y / p.w);
+                    let mut style = imgui::style();
+                    style.text = t.color;
+                    imgui::push_style(style);
+                    imgui::label(Metric::Native((x + 1.0) * 0.5, (1.0 - y) * 0.5), &t.s);
+                    imgui::pop_style();
+                }
+            }
+
+            raw.end_frame();
+        });
+    }
+}
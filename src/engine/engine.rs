@@ -8,16 +8,18 @@ use std::sync::Arc;
 
 use engine::asset::{AssetError, AssetResult, AssetSystem};
 use engine::context::EngineContext;
+use engine::profiler::{self, GpuTimer};
 use engine::core::{Component, ComponentArena, ComponentBased, GameObject, SceneTree};
 use engine::render::Camera;
-use engine::render::{DepthTest, DirectionalLight, Light, Material, MaterialState, Mesh,
-                     MeshSurface, ShaderProgram};
+use engine::render::{DepthTest, DirectionalLight, Light, LodGroup, Material, MaterialState,
+                     Mesh, MeshBuffer, MeshSurface, ShaderProgram, Texture};
 use engine::render::{Frustum, RenderQueue};
 use image;
 use math::Aabb;
 
 use std::default::Default;
 
+use super::debug;
 use super::imgui;
 
 pub trait IEngine {
@@ -32,6 +34,8 @@ pub trait IEngine {
     fn screen_size(&self) -> (u32, u32);
 
     fn hidpi_factor(&self) -> f32;
+
+    fn main_camera(&self) -> Option<Arc<Component>>;
 }
 
 #[derive(Default, Copy, Clone)]
@@ -41,6 +45,11 @@ pub struct EngineStats {
     pub transparent_count: u32,
     pub total_opaque_count: u32,
     pub total_transparent_count: u32,
+
+    pub draw_calls: u32,
+    pub switch_prog: u32,
+    pub switch_tex: u32,
+    pub switch_mesh: u32,
 }
 
 pub struct Engine<A>
@@ -55,15 +64,38 @@ where
     pub hidpi: f32,
     pub current_camera: RefCell<Option<Arc<Component>>>,
     pub gui_context: Rc<RefCell<imgui::Context>>,
+    pub debug_context: Rc<RefCell<debug::Context>>,
     pub arena: Rc<ComponentArena>,
 
     pub stats: EngineStats,
+    pub gpu_timer: GpuTimer,
+
+    fallback: FallbackAssets,
+}
+
+/// Drawn in place of the assets which failed to load
+struct FallbackAssets {
+    texture: Rc<Texture>,
+    material: Rc<Material>,
+    mesh: Rc<MeshBuffer>,
+}
+
+impl FallbackAssets {
+    fn new(asys: &AssetSystem) -> FallbackAssets {
+        FallbackAssets {
+            texture: asys.new_texture("default_error"),
+            material: Rc::new(Material::new(asys.new_program("default_error"))),
+            mesh: asys.new_mesh_buffer("cube"),
+        }
+    }
 }
 
 struct RenderCommand {
     pub surface: Rc<MeshSurface>,
     pub model_m: Matrix4<f32>,
     pub cam_distance: f32,
+    /// dithering between two levels of a `LodGroup`
+    pub lod_fade: f32,
 }
 
 #[derive(Default)]
@@ -95,6 +127,18 @@ impl RenderQueueState {
         self
     }
 
+    /// Imgui puts the draw order of widgets in their depth
+    fn sort_by_depth(&mut self) -> &mut Self {
+        self.commands.sort_by(|a, b| {
+            let az: f32 = a.model_m.w.z;
+            let bz: f32 = b.model_m.w.z;
+
+            bz.partial_cmp(&az).unwrap()
+        });
+
+        self
+    }
+
     fn sort_by_material(&mut self) -> &mut Self {
         self.commands.sort_by(|a, b| {
             let prog_a: &Material = &a.surface.material;
@@ -138,9 +182,11 @@ impl RenderQueueList {
         state.states.depth_write = Some(false);
         qlist.queues.insert(RenderQueue::Transparent, state);
 
-        // UI Queue
+        // UI Queue, drawn on top of the scene in the widgets order
         let mut state = RenderQueueState::default();
         state.states.alpha_blending = Some(true);
+        state.states.depth_write = Some(false);
+        state.states.depth_test = Some(DepthTest::Always);
         qlist.queues.insert(RenderQueue::UI, state);
 
         qlist
@@ -231,7 +277,15 @@ where
         material.bind(|tex| {
             ctx.prepare_cache_tex(tex, |ctx, unit| {
                 // Binding texture
-                tex.bind(&self.gl, unit)?;
+                if let Err(err) = tex.bind(&self.gl, unit) {
+                    if let AssetError::NotReady = err {
+                        return Err(err);
+                    }
+
+                    // the texture which failed to load is shown as a magenta checker
+                    self.asset_system.report_error(&err);
+                    self.fallback.texture.bind(&self.gl, unit)?;
+                }
 
                 ctx.switch_tex += 1;
                 Ok(())
@@ -326,33 +380,47 @@ where
                     continue;
                 }
 
-                panic!(format!("Failed to load material, reason {:?}", err));
+                // the shader which failed to compile is replaced by the error shader
+                self.asset_system.report_error(&err);
+                if self.setup_material(ctx, &self.fallback.material).is_err() {
+                    continue;
+                }
             }
 
             let prog = ctx.prog.upgrade().unwrap();
 
-            let r = ctx.prepare_cache(&cmd.surface.buffer, |ctx| {
-                cmd.surface.buffer.bind(&self.gl, &prog)?;
-                ctx.switch_mesh += 1;
-                Ok(())
-            });
+            let bind = |ctx: &mut EngineContext, buffer: &Rc<MeshBuffer>| {
+                ctx.prepare_cache(buffer, |ctx| {
+                    buffer.bind(&self.gl, &prog)?;
+                    ctx.switch_mesh += 1;
+                    Ok(())
+                })
+            };
 
-            match r {
-                Ok(_) => {
-                    self.setup_camera(ctx, cmd.model_m, camera);
-                    prog.commit(gl);
-                    // if let RenderQueue::UI = mat.render_queue
-                    {
-                        cmd.surface.buffer.render(gl);
+            let mut buffer = &cmd.surface.buffer;
+            match bind(ctx, buffer) {
+                Ok(_) => (),
+                Err(AssetError::NotReady) => continue,
+                Err(err) => {
+                    // a cube is drawn in place of the mesh which failed to load
+                    self.asset_system.report_error(&err);
+                    buffer = &self.fallback.mesh;
+                    if bind(ctx, buffer).is_err() {
+                        continue;
                     }
-
-                    cmd.surface.buffer.unbind(gl);
                 }
-                Err(ref err) => match *err {
-                    AssetError::NotReady => (),
-                    _ => panic!(format!("Failed to load mesh, reason {:?}", err)),
-                },
             }
+
+            self.setup_camera(ctx, cmd.model_m, camera);
+            prog.set("uLodFade", cmd.lod_fade);
+            prog.commit(gl);
+            // if let RenderQueue::UI = mat.render_queue
+            {
+                buffer.render(gl);
+                ctx.stats.draw_calls += 1;
+            }
+
+            buffer.unbind(gl);
         }
     }
 
@@ -451,6 +519,7 @@ where
         &self,
         object: &GameObject,
         cam_pos: &Vector3<f32>,
+        proj_scale: f32,
         update_bounds_only: bool,
         frustum_opt: &Option<Frustum>,
         render_q: &mut RenderQueueList,
@@ -461,66 +530,99 @@ where
             return;
         }
 
-        let result = object.find_component::<Mesh>();
-        if let Some((mesh, _)) = result {
-            let m = compute_model_m(&*object);
-            use math::*;
+        let m = compute_model_m(&*object);
+
+        // TODO: local scale only ?? should be using global scale??
+        let scale = get_max_scale(&object.transform.local_scale());
+
+        let mut gather = |mesh: &Mesh, lod_fade: f32| {
+            self.gather_mesh_commands(
+                mesh,
+                object,
+                &m,
+                scale,
+                lod_fade,
+                cam_pos,
+                update_bounds_only,
+                frustum_opt,
+                render_q,
+                included_render_queues,
+                eng_stats,
+            )
+        };
 
-            // TODO: local scale only ?? should be using global scale??
-            let scale = get_max_scale(&object.transform.local_scale());
+        if let Some((mesh, _)) = object.find_component::<Mesh>() {
+            gather(&*mesh, 0.0);
+        }
 
-            for surface in mesh.surfaces.iter() {
-                if let &Some(ref included) = included_render_queues {
-                    if included.get(&surface.material.render_queue).is_none() {
-                        continue;
-                    }
+        // the level is chosen from the bounding sphere of the most detailed one
+        if let Some((lod, _)) = object.find_component::<LodGroup>() {
+            if let Some(bounds) = lod.bounds() {
+                use math::*;
+
+                let (center, r) = bounds.local_aabb().sphere();
+                let p = m.transform_point(Point3::from_vec(center));
+                let distance = (cam_pos - p.to_vec()).magnitude();
+                let coverage = LodGroup::screen_coverage(r * scale, distance, proj_scale);
+
+                for (mesh, fade) in lod.select(coverage) {
+                    gather(mesh, fade);
                 }
+            }
+        }
+    }
 
-                if let &mut Some(ref mut stats) = eng_stats {
-                    match surface.material.render_queue {
-                        RenderQueue::Transparent => stats.total_transparent_count += 1,
-                        RenderQueue::Opaque => stats.total_opaque_count += 1,
-                        _ => (),
-                    }
+    fn gather_mesh_commands(
+        &self,
+        mesh: &Mesh,
+        object: &GameObject,
+        m: &Matrix4<f32>,
+        scale: f32,
+        lod_fade: f32,
+        cam_pos: &Vector3<f32>,
+        update_bounds_only: bool,
+        frustum_opt: &Option<Frustum>,
+        render_q: &mut RenderQueueList,
+        included_render_queues: &Option<BTreeSet<RenderQueue>>,
+        eng_stats: &mut Option<&mut EngineStats>,
+    ) {
+        use math::*;
+
+        for surface in mesh.surfaces.iter() {
+            if let &Some(ref included) = included_render_queues {
+                if included.get(&surface.material.render_queue).is_none() {
+                    continue;
+                }
+            }
+
+            if let &mut Some(ref mut stats) = eng_stats {
+                match surface.material.render_queue {
+                    RenderQueue::Transparent => stats.total_transparent_count += 1,
+                    RenderQueue::Opaque => stats.total_opaque_count += 1,
+                    _ => (),
                 }
+            }
 
-                // TODO: should use a material flag to skip
-                if let &Some(ref frustum) = frustum_opt {
-                    match surface.material.render_queue {
-                        RenderQueue::Skybox | RenderQueue::UI => (),
-                        _ => {
-                            let bounds = surface.buffer.bounds();
-                            if bounds.is_none() {
-                                continue;
-                            }
-
-                            let bounds = bounds.unwrap();
-                            let (center, r) = bounds.local_aabb().sphere();
-
-                            let scaled_r = r * scale;
-                            let p = m.transform_point(Point3::from_vec(center));
-
-                            if !frustum.collide_sphere(&p.to_vec(), scaled_r) {
-                                continue;
-                            }
-
-                            if render_q.aabb.is_none() {
-                                render_q.aabb = Some(Aabb::empty());
-                            }
-
-                            render_q
-                                .aabb
-                                .as_mut()
-                                .unwrap()
-                                .merge_sphere(&p.to_vec(), scaled_r);
+            // TODO: should use a material flag to skip
+            if let &Some(ref frustum) = frustum_opt {
+                match surface.material.render_queue {
+                    RenderQueue::Skybox | RenderQueue::UI => (),
+                    _ => {
+                        let bounds = surface.buffer.bounds();
+                        if bounds.is_none() {
+                            continue;
                         }
-                    }
-                } else {
-                    let bounds = surface.buffer.bounds();
-                    if let Some(bounds) = bounds {
+
+                        let bounds = bounds.unwrap();
                         let (center, r) = bounds.local_aabb().sphere();
+
+                        let scaled_r = r * scale;
                         let p = m.transform_point(Point3::from_vec(center));
 
+                        if !frustum.collide_sphere(&p.to_vec(), scaled_r) {
+                            continue;
+                        }
+
                         if render_q.aabb.is_none() {
                             render_q.aabb = Some(Aabb::empty());
                         }
@@ -529,24 +631,41 @@ where
                             .aabb
                             .as_mut()
                             .unwrap()
-                            .merge_sphere(&p.to_vec(), r * scale);
+                            .merge_sphere(&p.to_vec(), scaled_r);
                     }
                 }
+            } else {
+                let bounds = surface.buffer.bounds();
+                if let Some(bounds) = bounds {
+                    let (center, r) = bounds.local_aabb().sphere();
+                    let p = m.transform_point(Point3::from_vec(center));
+
+                    if render_q.aabb.is_none() {
+                        render_q.aabb = Some(Aabb::empty());
+                    }
 
-                if !update_bounds_only {
-                    let q = render_q
-                        .queues
-                        .get_mut(&surface.material.render_queue)
-                        .unwrap();
+                    render_q
+                        .aabb
+                        .as_mut()
+                        .unwrap()
+                        .merge_sphere(&p.to_vec(), r * scale);
+                }
+            }
 
-                    let cam_dist = (cam_pos - object.transform.global().disp).magnitude();
+            if !update_bounds_only {
+                let q = render_q
+                    .queues
+                    .get_mut(&surface.material.render_queue)
+                    .unwrap();
 
-                    q.commands.push(RenderCommand {
-                        surface: surface.clone(),
-                        model_m: m,
-                        cam_distance: cam_dist,
-                    })
-                }
+                let cam_dist = (cam_pos - object.transform.global().disp).magnitude();
+
+                q.commands.push(RenderCommand {
+                    surface: surface.clone(),
+                    model_m: *m,
+                    cam_distance: cam_dist,
+                    lod_fade,
+                })
             }
         }
     }
@@ -572,12 +691,16 @@ where
             None
         };
 
+        // picks the levels of detail
+        let proj_scale = camera.perspective(self.screen_size).y.y;
+
         for obj in objects.iter() {
             obj.upgrade().map(|obj| {
                 if let Ok(object) = obj.try_borrow() {
                     self.gather_render_commands(
                         &object,
                         &camera.eye(),
+                        proj_scale,
                         update_bounds_only,
                         &frustum,
                         &mut render_q,
@@ -598,7 +721,10 @@ where
         material: Option<&Rc<Material>>,
         clear_option: ClearOption,
     ) -> EngineStats {
+        profile_scope!("render_pass");
+
         let mut ctx: EngineContext = EngineContext::new();
+        self.gpu_timer.begin(&self.gl);
 
         if let Some(ref rt) = camera.render_texture {
             rt.bind_frame_buffer(&self.gl);
@@ -619,7 +745,10 @@ where
         self.prepare_ctx(&mut ctx);
 
         // gather commands
-        let mut render_q = self.gather_all_render_commands(&camera, false, Some(&mut ctx.stats));
+        let mut render_q = {
+            profile_scope!("gather");
+            self.gather_all_render_commands(&camera, false, Some(&mut ctx.stats))
+        };
 
         // Sort the opaque queue
         render_q
@@ -636,6 +765,13 @@ where
             .unwrap()
             .sort_by_cam_distance();
 
+        // Sort the ui queue
+        render_q
+            .queues
+            .get_mut(&RenderQueue::UI)
+            .unwrap()
+            .sort_by_depth();
+
         ctx.stats.surfaces_count = render_q.surface_count() as u32;
         ctx.stats.transparent_count = render_q
             .queues
@@ -654,10 +790,21 @@ where
             self.render_commands(&mut ctx, &q, camera, material);
         }
 
+        // the ui could leave the scissor test on, which would clip the next clear
+        ctx.states.apply_defaults();
+        ctx.states.commit(&self.gl);
+
         if let Some(ref rt) = camera.render_texture {
             rt.unbind_frame_buffer(&self.gl);
         }
 
+        self.gpu_timer.end(&self.gl);
+
+        ctx.stats.switch_prog = ctx.switch_prog;
+        ctx.stats.switch_tex = ctx.switch_tex;
+        ctx.stats.switch_mesh = ctx.switch_mesh;
+        profiler::add_pass_stats(&ctx.stats);
+
         ctx.stats
     }
 
@@ -682,7 +829,20 @@ where
 
     #[cfg_attr(feature = "flame_it", flame)]
     pub fn render(&mut self, clear_option: ClearOption) {
-        imgui::pre_render(self);
+        profile_scope!("render");
+
+        {
+            profile_scope!("debug");
+            let camera = self.main_camera();
+            let camera = camera.as_ref().map(|c| c.try_as::<Camera>().unwrap().borrow());
+            let debug_ctx = self.debug_context.clone();
+            debug_ctx.borrow_mut().update(self, camera.as_ref().map(|c| &**c));
+        }
+
+        {
+            profile_scope!("imgui");
+            imgui::pre_render(self);
+        }
 
         if let Some(ref camera) = self.main_camera() {
             self.stats =
@@ -694,6 +854,16 @@ where
     }
 
     pub fn new(webgl_ctx: WebGLContext, size: (u32, u32), hidpi: f32) -> Engine<A> {
+        Engine::with_asset_system(webgl_ctx, size, hidpi, A::new())
+    }
+
+    /// An engine loading its assets with `asys`, e.g. from other file systems
+    pub fn with_asset_system(
+        webgl_ctx: WebGLContext,
+        size: (u32, u32),
+        hidpi: f32,
+        asys: A,
+    ) -> Engine<A> {
         let gl = WebGLRenderingContext::new(webgl_ctx);
 
         /*=========Drawing the triangle===========*/
@@ -717,23 +887,32 @@ where
 
         let gui_tree = SceneTree::new();
 
+        let asset_system = Box::new(asys);
+        let fallback = FallbackAssets::new(&*asset_system);
+
         Engine {
             gl: gl,
             objects: vec![],
             program_cache: RefCell::new(HashMap::new()),
-            asset_system: Box::new(A::new()),
+            asset_system: asset_system,
             gui_context: Rc::new(RefCell::new(imgui::Context::new(gui_tree))),
+            debug_context: Rc::new(RefCell::new(debug::Context::new(SceneTree::new()))),
             screen_size: size,
             hidpi: hidpi,
             current_camera: RefCell::new(None),
             stats: Default::default(),
+            gpu_timer: Default::default(),
             arena: Rc::new(ComponentArena::new()),
+            fallback: fallback,
         }
     }
 
     pub fn begin(&mut self) {
         imgui::begin();
 
+        self.gpu_timer.poll(&self.gl);
+
+        profile_scope!("assets");
         self.asset_system_mut().step();
     }
 
@@ -801,4 +980,8 @@ impl<A: AssetSystem> IEngine for Engine<A> {
     fn hidpi_factor(&self) -> f32 {
         self.hidpi
     }
+
+    fn main_camera(&self) -> Option<Arc<Component>> {
+        Engine::main_camera(self)
+    }
 }
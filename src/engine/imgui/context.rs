@@ -1,45 +1,109 @@
-use engine::{Asset, Component, GameObject, Material, Mesh, MeshBuffer, RenderQueue, SceneTree};
+use engine::{Asset, AssetResult, BufferUsage, Camera, Font, GameObject, Material, Mesh,
+             MeshBuffer, RenderQueue, SceneTree, Scissor};
 
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
-use std::sync::Arc;
 
 use super::instance;
+use super::instance::TextFont;
 use super::label::Label;
+use super::style::{self, Color};
 use super::widgets;
+use super::widgets::WidgetBinder;
+use super::world;
 
 use engine::IEngine;
 
+/// Key of the label materials: font, sdf mode, clipping, color
+/// and the depth test of the world-space labels
+type LabelMaterialKey = (Option<TextFont>, bool, Scissor, Color, Option<bool>);
+
 struct LabelRenderer {
-    go: Option<Rc<RefCell<GameObject>>>,
-    mesh: Option<Arc<Component>>,
-    material: Option<Rc<Material>>,
+    /// dropped by `prune` once no label uses them
+    materials: HashMap<LabelMaterialKey, Rc<Material>>,
 }
 
 struct LabelHandle {
-    mesh: Arc<Component>,
-    mesh_buffer: Option<Rc<MeshBuffer>>,
+    go: Rc<RefCell<GameObject>>,
+    mesh_buffer: Rc<MeshBuffer>,
+    material: Rc<Material>,
+    font_generation: Option<u32>,
 }
 
-impl Drop for LabelHandle {
-    fn drop(&mut self) {
-        if let Some(ref mb) = self.mesh_buffer {
-            let mesh = self.mesh.try_as::<Mesh>().unwrap();
-            mesh.borrow_mut().remove_buffer(&mb);
-        }
-    }
+/// Widgets are drawn in the order of their ids
+fn set_draw_order(go: &Rc<RefCell<GameObject>>, id: u32) {
+    let mut gomut = go.borrow_mut();
+    let mut gtrans = gomut.transform.global();
+    gtrans.disp.z = widgets::draw_order_depth(id);
+    gomut.transform.set_global(gtrans);
 }
 
 impl LabelRenderer {
     fn new() -> LabelRenderer {
         LabelRenderer {
-            go: None,
-            material: None,
-            mesh: None,
+            materials: HashMap::new(),
         }
     }
 
+    /// Drop the materials of the labels removed during this frame
+    fn prune(&mut self) {
+        self.materials.retain(|_, m| Rc::strong_count(m) > 1);
+    }
+
+    fn material_for(&mut self, label: &Label, ssize: (u32, u32), engine: &mut IEngine) -> Rc<Material> {
+        let font = label.font().cloned();
+        let sdf = font.as_ref().map_or(false, |f| f.font.is_sdf());
+        let key = (
+            font,
+            sdf,
+            widgets::to_scissor(&label.state.clip, &ssize),
+            label.state.color.unwrap_or(style::WHITE),
+            label.state.world.map(|sheet| sheet.depth_test),
+        );
+
+        if let Some(m) = self.materials.get(&key) {
+            return m.clone();
+        }
+
+        let db = engine.asset_system();
+        let program = |name: &str| match key.4 {
+            Some(_) => db.new_program(&format!("{}_world", name)),
+            None => db.new_program(name),
+        };
+        let mut material = match key.0 {
+            None => {
+                let mut m = Material::new(program("default_ui"));
+                m.set("uDiffuse", db.new_texture("default_font_bitmap"));
+                m
+            }
+            Some(ref font) if sdf => {
+                let m = Material::new(program("default_ui_sdf"));
+                let size = font.size * engine.hidpi_factor();
+                m.set("uSmoothing", Font::sdf_smoothing(size));
+                m.set("uDiffuse", font.font.texture());
+                m
+            }
+            Some(ref font) => {
+                let m = Material::new(program("default_ui"));
+                m.set("uDiffuse", font.font.texture());
+                m
+            }
+        };
+        match key.4 {
+            Some(depth_test) => world::set_sheet_states(&mut material, depth_test),
+            None => {
+                material.render_queue = RenderQueue::UI;
+                material.states.scissor = Some(key.2);
+            }
+        }
+        material.set("uTint", style::color_to_vec4(&key.3));
+
+        let material = Rc::new(material);
+        self.materials.insert(key, material.clone());
+        material
+    }
+
     fn bind(
         &mut self,
         ssize: (u32, u32),
@@ -47,83 +111,95 @@ impl LabelRenderer {
         old_handle: Option<&mut LabelHandle>,
         parent: &GameObject,
         engine: &mut IEngine,
-    ) -> LabelHandle {
-        let material = self.material.get_or_insert_with(|| {
-            let db = engine.asset_system();
-            let mut material = Material::new(db.new_program("default_ui"));
-            material.set("uDiffuse", db.new_texture("default_font_bitmap"));
-            material.render_queue = RenderQueue::UI;
-            Rc::new(material)
-        });
-
-        let go = self.go
-            .get_or_insert_with(|| engine.new_game_object(parent));
-
-        let mesh = self.mesh.get_or_insert_with(|| {
-            let mesh = Mesh::new();
-            let mut gomut = go.borrow_mut();
-            gomut.add_component(mesh)
-        });
-
+    ) -> AssetResult<LabelHandle> {
         let hidpi = engine.hidpi_factor();
         let mesh_data = {
-            let mut mesh_data = label.bind(ssize, hidpi);
-            let disp = widgets::compute_translate(
-                &label.pos,
-                &label.state.pivot,
-                &ssize,
-                hidpi,
-                &mesh_data.compute_bound().local_aabb(),
-            );
+            let (mut mesh_data, bounds) = label.bind(ssize, hidpi)?;
+            let disp =
+                widgets::compute_translate(&label.pos, &label.state.pivot, &ssize, hidpi, &bounds);
 
             mesh_data.translate(disp);
+            if label.state.world.is_some() {
+                world::to_sheet_pixels(&mut mesh_data, ssize);
+            }
             mesh_data
         };
 
-        match old_handle {
-            Some(h) => {
-                if let Some(ref mb) = h.mesh_buffer {
-                    let mesh_buffer = mb.clone();
-                    h.mesh_buffer = None;
-                    mesh_buffer.update_mesh_data(mesh_data);
-
-                    return LabelHandle {
-                        mesh: h.mesh.clone(),
-                        mesh_buffer: Some(mesh_buffer),
-                    };
-                }
+        let material = self.material_for(label, ssize, engine);
+        let font_generation = label.font_generation();
+
+        // a new game object is made when the font is changed
+        Ok(match old_handle {
+            Some(ref h) if Rc::ptr_eq(&h.material, &material) => {
+                h.mesh_buffer.update_mesh_data(mesh_data);
 
                 LabelHandle {
-                    mesh: h.mesh.clone(),
+                    go: h.go.clone(),
                     mesh_buffer: h.mesh_buffer.clone(),
+                    material,
+                    font_generation,
                 }
             }
-            None => {
+            _ => {
                 // MeshBuffer
                 let mesh_buffer = MeshBuffer::new(mesh_data);
+                // the text may change, the buffers are updated in place
+                mesh_buffer.set_usage(BufferUsage::Dynamic);
 
                 // Mesh
-                mesh.try_as::<Mesh>()
-                    .unwrap()
-                    .borrow_mut()
-                    .add_surface(mesh_buffer.clone(), material.clone());
+                let mut mesh = Mesh::new();
+                mesh.add_surface(mesh_buffer.clone(), material.clone());
+
+                // Game Object
+                let go = engine.new_game_object(parent);
+                go.borrow_mut().add_component(mesh);
+                set_draw_order(&go, label.id());
 
                 LabelHandle {
-                    mesh: mesh.clone(),
-                    mesh_buffer: Some(mesh_buffer),
+                    go,
+                    mesh_buffer,
+                    material,
+                    font_generation,
                 }
             }
-        }
+        })
     }
 }
 
 enum WidgetHandle {
-    GameObject(Rc<RefCell<GameObject>>),
+    /// an image, which is stretched while its nine-slice is not ready
+    Image {
+        go: Rc<RefCell<GameObject>>,
+        slice_ready: bool,
+    },
     Label(LabelHandle),
 }
 
+impl WidgetHandle {
+    fn go(&self) -> &Rc<RefCell<GameObject>> {
+        match self {
+            &WidgetHandle::Image { ref go, .. } => go,
+            &WidgetHandle::Label(ref h) => &h.go,
+        }
+    }
+}
+
 type WidgetMap = HashMap<u32, (Rc<widgets::Widget>, WidgetHandle)>;
 
+/// A label must be laid out again when its font atlas is cleared,
+/// and an image when the size of its nine-slice texture becomes known
+fn needs_rebind(w: &widgets::Widget, handle: &WidgetHandle) -> bool {
+    match (w, handle) {
+        (&widgets::Widget::Label(ref label), &WidgetHandle::Label(ref h)) => {
+            label.font_generation() != h.font_generation
+        }
+        (&widgets::Widget::Image(ref image), &WidgetHandle::Image { slice_ready, .. }) => {
+            !slice_ready && image.slice_ready()
+        }
+        _ => false,
+    }
+}
+
 pub struct Context {
     go: WidgetMap,
     tree: Rc<SceneTree>,
@@ -153,7 +229,7 @@ impl Context {
                 let hm = &self.go;
                 match hm.get(&w.id()) {
                     None => true,
-                    Some(&(ref oldw, _)) => **oldw != **w,
+                    Some(&(ref oldw, ref handle)) => **oldw != **w || needs_rebind(w, handle),
                 }
             };
 
@@ -165,16 +241,23 @@ impl Context {
                             _ => None,
                         });
 
-                        WidgetHandle::Label(self.label_renderer.bind(
+                        match self.label_renderer.bind(
                             (sw, sh),
                             label,
                             h,
                             &self.tree.root(),
                             engine,
-                        ))
+                        ) {
+                            Ok(h) => WidgetHandle::Label(h),
+                            // the font is not loaded yet, try again next frame
+                            Err(_) => continue,
+                        }
                     }
                     &widgets::Widget::Image(ref image) => {
-                        WidgetHandle::GameObject(image.bind((sw, sh), &self.tree.root(), engine))
+                        let slice_ready = image.slice_ready();
+                        let go = image.bind((sw, sh), &self.tree.root(), engine);
+                        set_draw_order(&go, w.id());
+                        WidgetHandle::Image { go, slice_ready }
                     }
                 };
 
@@ -182,7 +265,44 @@ impl Context {
             }
         }
 
+        self.place_world_widgets(inner, engine);
+
         // remove all go in hm which id >= last id
         self.go.retain(|k, _| *k <= inner.id);
+        self.label_renderer.prune();
+    }
+
+    /// The world-space widgets follow their anchor through the main camera,
+    /// they are hidden when there is no camera
+    fn place_world_widgets(&self, inner: &instance::ImguiRaw, engine: &IEngine) {
+        if inner.world_anchors.is_empty() {
+            return;
+        }
+
+        let ssize = engine.screen_size();
+        let hidpi = engine.hidpi_factor();
+        let camera = engine.main_camera();
+        let camera = camera.as_ref().map(|c| c.try_as::<Camera>().unwrap().borrow());
+
+        for w in inner.render_list.iter() {
+            let (sheet, go) = match (w.world(), self.go.get(&w.id())) {
+                (Some(sheet), Some(&(_, ref handle))) => (sheet, handle.go()),
+                _ => continue,
+            };
+
+            let mut gomut = go.borrow_mut();
+            gomut.active = camera.is_some();
+            if let Some(ref camera) = camera {
+                let (ref anchor, first_id) = inner.world_anchors[sheet.index];
+                gomut.transform.set_global(world::sheet_transform(
+                    anchor,
+                    first_id,
+                    w.id(),
+                    camera,
+                    ssize,
+                    hidpi,
+                ));
+            }
+        }
     }
 }
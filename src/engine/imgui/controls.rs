@@ -0,0 +1,561 @@
+//! Interactive widgets
+//!
+//! Each widget is drawn with a colored frame and labels, and checks the mouse
+//! against the rect computed by `layout::place`.
+
+use std::rc::Rc;
+
+use engine::{Texture, TextureImage};
+use image;
+use image::ImageBuffer;
+
+use super::image::Image;
+use super::instance::{imgui_inst, ImguiRaw, ImguiState, TextFont};
+use super::label::{text_width, Label};
+use super::layout;
+use super::navigation::Nav;
+use super::style::{Borders, Color, Sprite};
+use super::widgets::{self, Rect};
+use super::{push_widget, Metric, TextAlign};
+
+const CHECKBOX_SIZE: f32 = 16.0;
+/// steps between the min and the max of a slider, for the navigation keys
+const SLIDER_STEPS: f32 = 20.0;
+/// mouse move in pixels of a navigation key press on a drag value
+const DRAG_NAV_PIXELS: f32 = 10.0;
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Interaction {
+    pub hovered: bool,
+    pub active: bool,
+    pub clicked: bool,
+    /// focused by the keyboard or gamepad navigation
+    pub focused: bool,
+}
+
+pub fn with_imgui<R, F>(f: F) -> R
+where
+    F: FnOnce(&mut ImguiRaw) -> R,
+{
+    let imgui = imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    f(&mut inner)
+}
+
+/// White texture with rounded corners of `radius` pixels,
+/// drawn with nine-slice scaling so the corners keep their size
+pub fn shape_texture(inner: &mut ImguiRaw, radius: u32) -> Rc<Texture> {
+    inner
+        .shapes
+        .entry(radius)
+        .or_insert_with(|| {
+            let n = radius * 2 + 2;
+            let r = radius as f32;
+            let far = (n - radius) as f32;
+
+            Texture::new(TextureImage::Rgba(ImageBuffer::from_fn(n, n, |x, y| {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let dx = (r - px).max(px - far).max(0.0);
+                let dy = (r - py).max(py - far).max(0.0);
+                let d = (dx * dx + dy * dy).sqrt();
+
+                let alpha = if radius == 0 {
+                    1.0
+                } else {
+                    (r - d + 0.5).max(0.0).min(1.0)
+                };
+                image::Rgba([0xff, 0xff, 0xff, (alpha * 255.0) as u8])
+            })))
+        })
+        .clone()
+}
+
+pub fn pixels(inner: &ImguiRaw, px: f32, py: f32) -> (f32, f32) {
+    widgets::to_native(&Metric::Pixel(px, py), &inner.screen_size, inner.hidpi)
+}
+
+fn top_left(inner: &ImguiRaw, color: Color) -> ImguiState {
+    ImguiState {
+        pivot: Metric::Native(0.0, 0.0),
+        clip: inner.state.clip,
+        color: Some(color),
+        world: inner.state.world,
+        ..Default::default()
+    }
+}
+
+/// Draw a texture, with nine-slice scaling when `borders` is set, returns the widget id
+pub fn draw_sliced(
+    inner: &mut ImguiRaw,
+    rect: &Rect,
+    tex: Rc<Texture>,
+    borders: Option<Borders>,
+    color: Color,
+) -> u32 {
+    let pos = Metric::Native(rect.min.0, rect.min.1);
+    let size = Metric::Native(rect.size().0, rect.size().1);
+    let state = top_left(inner, color);
+
+    push_widget(inner, state, |id, state| match borders {
+        Some(borders) => Image::new_sliced(id, pos, size, state, tex, borders),
+        None => Image::new(id, pos, size, state, tex),
+    })
+}
+
+/// Draw a rect with rounded corners of `radius` pixels, returns the widget id
+pub fn draw_rounded_rect(inner: &mut ImguiRaw, rect: &Rect, radius: f32, color: Color) -> u32 {
+    let radius = radius.max(0.0).round() as u32;
+    let tex = shape_texture(inner, radius);
+
+    let borders = if radius > 0 {
+        Some(Borders::all(radius as f32))
+    } else {
+        None
+    };
+
+    draw_sliced(inner, rect, tex, borders, color)
+}
+
+/// Draw a colored rect, returns the widget id
+pub fn draw_rect(inner: &mut ImguiRaw, rect: &Rect, color: Color) -> u32 {
+    draw_rounded_rect(inner, rect, 0.0, color)
+}
+
+pub fn draw_sprite(inner: &mut ImguiRaw, rect: &Rect, sprite: &Sprite) -> u32 {
+    draw_sliced(
+        inner,
+        rect,
+        sprite.texture.clone(),
+        Some(sprite.borders),
+        sprite.tint,
+    )
+}
+
+/// Draw the frame of a widget with the current style, returns the widget id
+pub fn draw_frame(inner: &mut ImguiRaw, rect: &Rect, it: &Interaction) -> u32 {
+    let sprite = inner
+        .style
+        .frame_sprites
+        .get(it.hovered || it.focused, it.active)
+        .cloned();
+
+    match sprite {
+        Some(sprite) => draw_sprite(inner, rect, &sprite),
+        None => {
+            let color = inner.style.frame_color(it.hovered || it.focused, it.active);
+            let rounding = inner.style.rounding;
+            draw_rounded_rect(inner, rect, rounding, color)
+        }
+    }
+}
+
+/// Font of the widget texts: the one of the style, or the current font
+pub fn text_font(inner: &ImguiRaw) -> Option<TextFont> {
+    inner
+        .style
+        .text_font()
+        .or_else(|| inner.state.font.clone())
+}
+
+/// Draw a single line of text, `pivot` is relative to the text bounds
+pub fn draw_text(inner: &mut ImguiRaw, pos: (f32, f32), pivot: (f32, f32), s: &str) {
+    let state = ImguiState {
+        pivot: Metric::Native(pivot.0, pivot.1),
+        text_align: TextAlign::Left,
+        font: text_font(inner),
+        clip: inner.state.clip,
+        color: Some(inner.style.text),
+        world: inner.state.world,
+    };
+
+    push_widget(inner, state, |id, state| {
+        Label::new(id, Metric::Native(pos.0, pos.1), state, s.into())
+    });
+}
+
+/// Update the hot/active state of the widget `id`.
+/// Widgets inside a popup are above the others.
+pub fn interact(inner: &mut ImguiRaw, id: u32, rect: &Rect, in_popup: bool) -> Interaction {
+    // the widgets of a world sheet are not hit tested, their rect is not on the screen
+    if inner.state.world.is_some() {
+        return Interaction::default();
+    }
+
+    let mouse_pos = inner.input.mouse_pos;
+
+    // the hidden part of a scrolled widget can't be clicked
+    let visible = match inner.state.clip {
+        Some(clip) => rect.intersect(&clip),
+        None => *rect,
+    };
+    inner.hit_rects.push(visible);
+
+    let mut inside = visible.contains(mouse_pos);
+
+    if !in_popup {
+        if let Some(popup) = inner.popup_rect {
+            inside = inside && !popup.contains(mouse_pos);
+        }
+    }
+
+    if inside {
+        inner.hover_candidate = Some(id);
+    }
+
+    let hovered = inside && inner.hot == Some(id);
+    if hovered && inner.input.mouse_pressed {
+        inner.active = Some(id);
+    }
+
+    let active = inner.active == Some(id);
+
+    Interaction {
+        hovered,
+        active,
+        clicked: active && hovered && inner.input.mouse_released,
+        focused: false,
+    }
+}
+
+/// Same as `interact`, for the widgets reachable by the keyboard and gamepad navigation
+pub fn interact_focusable(
+    inner: &mut ImguiRaw,
+    id: u32,
+    rect: &Rect,
+    in_popup: bool,
+) -> Interaction {
+    let mut it = interact(inner, id, rect, in_popup);
+    if inner.state.world.is_some() {
+        return it;
+    }
+
+    inner.focusables.push((id, *rect));
+
+    it.focused = inner.focus == Some(id);
+    if it.focused && inner.input.activate {
+        inner.input.activate = false;
+        it.clicked = true;
+    }
+
+    it
+}
+
+/// Horizontal move of the navigation, consumed by the focused widget
+fn take_horizontal_nav(inner: &mut ImguiRaw) -> f32 {
+    match inner.input.nav {
+        Some(Nav::Left) => {
+            inner.input.nav = None;
+            -1.0
+        }
+        Some(Nav::Right) => {
+            inner.input.nav = None;
+            1.0
+        }
+        _ => 0.0,
+    }
+}
+
+fn widget_rect(inner: &mut ImguiRaw, pos: &Metric, size: &Metric) -> Rect {
+    layout::place(inner, pos, size)
+}
+
+/// Rect filled with `color`
+pub fn rect(pos: Metric, size: Metric, color: Color) {
+    rounded_rect(pos, size, 0.0, color);
+}
+
+/// Rect with rounded corners of `radius` pixels
+pub fn rounded_rect(pos: Metric, size: Metric, radius: f32, color: Color) {
+    with_imgui(|inner| {
+        let rect = widget_rect(inner, &pos, &size);
+        draw_rounded_rect(inner, &rect, radius, color);
+    })
+}
+
+/// Sprite stretched with nine-slice scaling, e.g. the background of a resizable window
+pub fn nine_slice(pos: Metric, size: Metric, sprite: &Sprite) {
+    with_imgui(|inner| {
+        let rect = widget_rect(inner, &pos, &size);
+        draw_sprite(inner, &rect, sprite);
+    })
+}
+
+/// Button, returns true when it is clicked
+pub fn button(pos: Metric, size: Metric, label: &str) -> bool {
+    with_imgui(|inner| {
+        let rect = widget_rect(inner, &pos, &size);
+        let id = inner.id + 1;
+
+        let it = interact_focusable(inner, id, &rect, false);
+        draw_frame(inner, &rect, &it);
+        draw_text(inner, rect.center(), (0.5, 0.5), label);
+
+        it.clicked
+    })
+}
+
+/// Checkbox followed by a label, returns true when `value` is changed
+pub fn checkbox(pos: Metric, label: &str, value: &mut bool) -> bool {
+    with_imgui(|inner| {
+        let label_width = text_width(&text_font(inner), label);
+        let padding = inner.style.padding;
+        let size = Metric::Pixel(CHECKBOX_SIZE + padding + label_width, CHECKBOX_SIZE);
+        let rect = widget_rect(inner, &pos, &size);
+        let id = inner.id + 1;
+
+        let it = interact_focusable(inner, id, &rect, false);
+        if it.clicked {
+            *value = !*value;
+        }
+
+        let box_size = pixels(inner, CHECKBOX_SIZE, CHECKBOX_SIZE);
+        let box_rect = Rect::new(rect.min, box_size);
+        draw_frame(inner, &box_rect, &it);
+
+        if *value {
+            let inset = pixels(inner, padding, padding);
+            let mark = Rect::new(
+                (box_rect.min.0 + inset.0, box_rect.min.1 + inset.1),
+                (box_size.0 - inset.0 * 2.0, box_size.1 - inset.1 * 2.0),
+            );
+            let (accent, rounding) = (inner.style.accent, inner.style.rounding * 0.5);
+            draw_rounded_rect(inner, &mark, rounding, accent);
+        }
+
+        let gap = pixels(inner, padding, 0.0);
+        draw_text(
+            inner,
+            (box_rect.max.0 + gap.0, box_rect.center().1),
+            (0.0, 0.5),
+            label,
+        );
+
+        it.clicked
+    })
+}
+
+/// Horizontal slider, returns true when `value` is changed
+pub fn slider(pos: Metric, size: Metric, value: &mut f32, min: f32, max: f32) -> bool {
+    with_imgui(|inner| {
+        let rect = widget_rect(inner, &pos, &size);
+        let id = inner.id + 1;
+
+        let it = interact_focusable(inner, id, &rect, false);
+        let old = *value;
+
+        let (w, h) = rect.size();
+        if it.active && w > 0.0 {
+            let t = ((inner.input.mouse_pos.0 - rect.min.0) / w).max(0.0).min(1.0);
+            *value = min + t * (max - min);
+        }
+
+        // left and right change the value of the focused slider
+        if it.focused {
+            let step = take_horizontal_nav(inner) * (max - min) / SLIDER_STEPS;
+            *value = (*value + step).max(min.min(max)).min(max.max(min));
+        }
+
+        let t = if max != min {
+            ((*value - min) / (max - min)).max(0.0).min(1.0)
+        } else {
+            0.0
+        };
+
+        draw_frame(inner, &rect, &it);
+        if t > 0.0 {
+            let (accent, rounding) = (inner.style.accent, inner.style.rounding);
+            draw_rounded_rect(inner, &Rect::new(rect.min, (w * t, h)), rounding, accent);
+        }
+        draw_text(inner, rect.center(), (0.5, 0.5), &format!("{:.2}", *value));
+
+        *value != old
+    })
+}
+
+/// Bar filled up to `value` from 0.0 to 1.0, e.g. the progress of a loading screen
+pub fn progress_bar(pos: Metric, size: Metric, value: f32) {
+    with_imgui(|inner| {
+        let rect = widget_rect(inner, &pos, &size);
+        draw_frame(inner, &rect, &Interaction::default());
+
+        let t = value.max(0.0).min(1.0);
+        let (w, h) = rect.size();
+        if t > 0.0 {
+            let (accent, rounding) = (inner.style.accent, inner.style.rounding);
+            draw_rounded_rect(inner, &Rect::new(rect.min, (w * t, h)), rounding, accent);
+        }
+        draw_text(inner, rect.center(), (0.5, 0.5), &format!("{:.0}%", t * 100.0));
+    })
+}
+
+/// Number changed by dragging the mouse horizontally, `speed` per pixel.
+/// Returns true when `value` is changed.
+pub fn drag_value(pos: Metric, size: Metric, value: &mut f32, speed: f32) -> bool {
+    with_imgui(|inner| {
+        let rect = widget_rect(inner, &pos, &size);
+        let id = inner.id + 1;
+
+        let it = interact_focusable(inner, id, &rect, false);
+        let old = *value;
+
+        if it.active {
+            let dx = inner.input.mouse_delta.0 * inner.screen_size.0 as f32 / inner.hidpi;
+            *value += dx * speed;
+        }
+
+        if it.focused {
+            *value += take_horizontal_nav(inner) * DRAG_NAV_PIXELS * speed;
+        }
+
+        draw_frame(inner, &rect, &it);
+        draw_text(inner, rect.center(), (0.5, 0.5), &format!("{:.3}", *value));
+
+        *value != old
+    })
+}
+
+/// Drop-down list, returns true when `selected` is changed
+pub fn dropdown(pos: Metric, size: Metric, items: &[&str], selected: &mut usize) -> bool {
+    with_imgui(|inner| {
+        let rect = widget_rect(inner, &pos, &size);
+        let id = inner.id + 1;
+
+        let it = interact_focusable(inner, id, &rect, false);
+        let mut open = inner.open_popup == Some(id);
+        if it.clicked {
+            open = !open;
+        }
+
+        draw_frame(inner, &rect, &it);
+        let padding = pixels(inner, inner.style.padding, 0.0);
+        draw_text(
+            inner,
+            (rect.min.0 + padding.0, rect.center().1),
+            (0.0, 0.5),
+            items.get(*selected).unwrap_or(&""),
+        );
+        draw_text(inner, (rect.max.0 - padding.0, rect.center().1), (1.0, 0.5), "v");
+
+        let mut changed = false;
+        if open {
+            let (w, h) = rect.size();
+            let mut popup = rect;
+
+            // the list is not clipped by a scroll region
+            let clip = inner.state.clip.take();
+
+            for (i, item) in items.iter().enumerate() {
+                let item_rect = Rect::new((rect.min.0, rect.max.1 + h * i as f32), (w, h));
+                let item_id = inner.id + 1;
+
+                let item_it = interact_focusable(inner, item_id, &item_rect, true);
+                let hot = item_it.hovered || item_it.focused;
+                let color = if i == *selected && !hot {
+                    inner.style.accent
+                } else {
+                    inner.style.frame_color(hot, item_it.active)
+                };
+                draw_rect(inner, &item_rect, color);
+                draw_text(
+                    inner,
+                    (item_rect.min.0 + padding.0, item_rect.center().1),
+                    (0.0, 0.5),
+                    item,
+                );
+
+                if item_it.clicked {
+                    changed = *selected != i;
+                    *selected = i;
+                    open = false;
+                }
+
+                popup = popup.union(&item_rect);
+            }
+
+            // clicking outside closes the list
+            if inner.input.mouse_pressed && !popup.contains(inner.input.mouse_pos) {
+                open = false;
+            }
+
+            if open {
+                inner.next_popup_rect = Some(popup);
+            }
+
+            inner.state.clip = clip;
+        }
+
+        if open {
+            inner.open_popup = Some(id);
+        } else if inner.open_popup == Some(id) {
+            inner.open_popup = None;
+        }
+
+        changed
+    })
+}
+
+/// Single line text input, returns true when `text` is changed.
+/// It receives the keyboard after being clicked, until Enter or Escape is pressed.
+pub fn text_input(pos: Metric, size: Metric, text: &mut String) -> bool {
+    with_imgui(|inner| {
+        let rect = widget_rect(inner, &pos, &size);
+        let id = inner.id + 1;
+
+        let it = interact_focusable(inner, id, &rect, false);
+        let was_focused = inner.keyboard_focus == Some(id);
+        if it.clicked {
+            inner.keyboard_focus = Some(id);
+        } else if inner.input.mouse_pressed && !it.hovered && was_focused {
+            inner.keyboard_focus = None;
+        }
+
+        let focused = inner.keyboard_focus == Some(id);
+        let mut changed = false;
+
+        // the Enter which starts the edition must not end it
+        if focused && was_focused {
+            if inner.input.text.len() > 0 {
+                text.push_str(&inner.input.text);
+                changed = true;
+            }
+
+            for key in inner.input.keys.iter() {
+                match key.as_str() {
+                    "Backspace" => changed |= text.pop().is_some(),
+                    "Enter" | "NumpadEnter" | "Escape" => inner.keyboard_focus = None,
+                    _ => (),
+                }
+            }
+        }
+
+        let frame = Interaction {
+            active: focused || it.active,
+            ..it
+        };
+        draw_frame(inner, &rect, &frame);
+
+        // only show the end of the text which fits in the rect
+        let padding = pixels(inner, inner.style.padding, 0.0);
+        let font = text_font(inner);
+        let max_width = rect.size().0 - padding.0 * 2.0;
+
+        let mut shown: String = text.clone();
+        if focused {
+            shown.push('_');
+        }
+        while shown.chars().count() > 1
+            && pixels(inner, text_width(&font, &shown), 0.0).0 > max_width
+        {
+            shown.remove(0);
+        }
+
+        draw_text(
+            inner,
+            (rect.min.0 + padding.0, rect.center().1),
+            (0.0, 0.5),
+            &shown,
+        );
+
+        changed
+    })
+}
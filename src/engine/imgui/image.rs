@@ -1,7 +1,9 @@
 use super::Metric;
 use super::instance::ImguiState;
+use super::style::{self, Borders, Color};
 use super::widgets;
-use super::widgets::Widget;
+use super::widgets::{Rect, Widget};
+use super::world::{self, WorldSheet};
 
 use engine::{Asset, GameObject, IEngine, Material, Mesh, MeshBuffer, MeshData, RenderQueue,
              Texture};
@@ -28,7 +30,7 @@ fn make_quad_mesh_data(ndc_size: (f32, f32)) -> MeshData {
             1.0, 1.0,
         ];
 
-    let indices: Vec<u16> = vec![
+    let indices: Vec<u32> = vec![
         0, 1, 2, 0, 2, 3 // Top face
     ];
 
@@ -39,6 +41,61 @@ fn make_quad_mesh_data(ndc_size: (f32, f32)) -> MeshData {
         indices: indices,
         tangents: None,
         bitangents: None,
+        ..Default::default()
+    }
+}
+
+/// Shrink two opposite borders when they don't fit in `size`
+fn fit_borders(a: f32, b: f32, size: f32) -> (f32, f32) {
+    if a + b > size && a + b > 0.0 {
+        let k = size / (a + b);
+        (a * k, b * k)
+    } else {
+        (a, b)
+    }
+}
+
+/// 4x4 vertices grid, the borders are (left, top, right, bottom) in ndc and in uv
+fn make_nine_slice_mesh_data(
+    ndc_size: (f32, f32),
+    borders: (f32, f32, f32, f32),
+    uv_borders: (f32, f32, f32, f32),
+) -> MeshData {
+    let (w, h) = ndc_size;
+    let (l, r) = fit_borders(borders.0, borders.2, w);
+    let (t, b) = fit_borders(borders.1, borders.3, h);
+
+    let xs = [0.0, l, w - r, w];
+    let ys = [0.0, -t, -(h - b), -h];
+    let us = [0.0, uv_borders.0, 1.0 - uv_borders.2, 1.0];
+    let vs = [1.0, 1.0 - uv_borders.1, uv_borders.3, 0.0];
+
+    let mut vertices = Vec::with_capacity(16 * 3);
+    let mut uvs = Vec::with_capacity(16 * 2);
+    for j in 0..4 {
+        for i in 0..4 {
+            vertices.extend_from_slice(&[xs[i], ys[j], 0.0]);
+            uvs.extend_from_slice(&[us[i], vs[j]]);
+        }
+    }
+
+    let mut indices: Vec<u32> = Vec::with_capacity(9 * 6);
+    for j in 0..3 {
+        for i in 0..3 {
+            let tl = (j * 4 + i) as u16;
+            let (bl, br, tr) = (tl + 4, tl + 5, tl + 1);
+            indices.extend_from_slice(&[tl, bl, br, tl, br, tr]);
+        }
+    }
+
+    MeshData {
+        vertices: vertices,
+        uvs: Some(uvs),
+        normals: None,
+        indices: indices,
+        tangents: None,
+        bitangents: None,
+        ..Default::default()
     }
 }
 
@@ -94,7 +151,12 @@ pub struct Image {
     pos: Metric,
     size: Metric,
     pivot: Metric,
+    clip: Option<Rect>,
+    tint: Color,
+    /// nine-slice insets, only used by the images made from a texture
+    borders: Option<Borders>,
     kind: ImageKind,
+    pub world: Option<WorldSheet>,
 }
 
 impl Image {
@@ -107,19 +169,87 @@ impl Image {
             pos,
             size,
             pivot: state.pivot,
+            clip: state.clip,
+            tint: state.color.unwrap_or(style::WHITE),
+            borders: None,
             kind: t.into(),
+            world: state.world,
         })
     }
 
-    fn create_material(&self, engine: &mut IEngine) -> Rc<Material> {
+    /// Image drawn with nine-slice scaling
+    pub fn new_sliced(
+        id: u32,
+        pos: Metric,
+        size: Metric,
+        state: ImguiState,
+        tex: Rc<Texture>,
+        borders: Borders,
+    ) -> Widget {
+        match Image::new(id, pos, size, state, tex) {
+            Widget::Image(img) => Widget::Image(Image {
+                borders: Some(borders),
+                ..img
+            }),
+            _ => unreachable!(),
+        }
+    }
+
+    /// The slices are computed from the texture size, which is only known
+    /// once the texture is uploaded. Until then the image is stretched.
+    pub fn slice_ready(&self) -> bool {
+        match (&self.kind, &self.borders) {
+            (&ImageKind::Texture(ref t), &Some(_)) => t.0.size().is_some(),
+            _ => true,
+        }
+    }
+
+    fn make_mesh_data(&self, ssize: &(u32, u32), hidpi: f32) -> MeshData {
+        let ndc_size = compute_size_to_ndc(&self.size, ssize, hidpi);
+
+        let tex_size = match self.kind {
+            ImageKind::Texture(ref t) => t.0.size(),
+            ImageKind::Material(_) => None,
+        };
+
+        match (self.borders, tex_size) {
+            (Some(b), Some((tw, th))) => {
+                let (l, t) = widgets::to_pixel_pos(b.left, b.top, ssize, hidpi);
+                let (r, bottom) = widgets::to_pixel_pos(b.right, b.bottom, ssize, hidpi);
+                let (tw, th) = (tw as f32, th as f32);
+
+                make_nine_slice_mesh_data(
+                    ndc_size,
+                    (l, t, r, bottom),
+                    (b.left / tw, b.top / th, b.right / tw, b.bottom / th),
+                )
+            }
+            _ => make_quad_mesh_data(ndc_size),
+        }
+    }
+
+    /// Only the images made from a texture are clipped
+    fn create_material(&self, ssize: &(u32, u32), engine: &mut IEngine) -> Rc<Material> {
         match self.kind {
             ImageKind::Material(ref m) => m.0.clone(),
             ImageKind::Texture(ref t) => {
                 let db = engine.asset_system();
 
-                let mut m = Material::new(db.new_program("default_ui"));
-                m.render_queue = RenderQueue::UI;
+                let m = match self.world {
+                    Some(sheet) => {
+                        let mut m = Material::new(db.new_program("default_ui_world"));
+                        world::set_sheet_states(&mut m, sheet.depth_test);
+                        m
+                    }
+                    None => {
+                        let mut m = Material::new(db.new_program("default_ui"));
+                        m.render_queue = RenderQueue::UI;
+                        m.states.scissor = Some(widgets::to_scissor(&self.clip, ssize));
+                        m
+                    }
+                };
                 m.set("uDiffuse", t.0.clone());
+                m.set("uTint", style::color_to_vec4(&self.tint));
                 Rc::new(m)
             }
         }
@@ -134,10 +264,23 @@ impl Image {
         let hidpi = engine.hidpi_factor();
 
         // Mesh Data
-        let meshdata = make_quad_mesh_data(compute_size_to_ndc(&self.size, &ssize, hidpi));
+        let mut meshdata = self.make_mesh_data(&ssize, hidpi);
+        let disp = widgets::compute_translate(
+            &self.pos,
+            &self.pivot,
+            &ssize,
+            hidpi,
+            &meshdata.compute_bound().local_aabb(),
+        );
+
+        // a world-space image is moved by its sheet, its placement is kept in the mesh
+        if self.world.is_some() {
+            meshdata.translate(disp);
+            world::to_sheet_pixels(&mut meshdata, ssize);
+        }
 
         // Material
-        let material = self.create_material(engine);
+        let material = self.create_material(&ssize, engine);
 
         //Mesh
         let mut mesh = Mesh::new();
@@ -147,15 +290,11 @@ impl Image {
         let go = engine.new_game_object(parent);
         let mut gomut = go.borrow_mut();
 
-        let mut gtrans = gomut.transform.global();
-        gtrans.disp += widgets::compute_translate(
-            &self.pos,
-            &self.pivot,
-            &ssize,
-            hidpi,
-            &mesh.bounds().unwrap().local_aabb(),
-        );
-        gomut.transform.set_global(gtrans);
+        if self.world.is_none() {
+            let mut gtrans = gomut.transform.global();
+            gtrans.disp += disp;
+            gomut.transform.set_global(gtrans);
+        }
         gomut.add_component(mesh);
         drop(gomut);
 
@@ -1,11 +1,76 @@
+use super::layout;
+use super::navigation::Nav;
+use super::style::{Color, Style};
 use super::widgets;
+use super::world;
+use engine::{Font, Texture};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
-#[derive(Default, Debug, PartialEq, Copy, Clone)]
+/// Font and size in pixels of the labels
+#[derive(Debug, Clone)]
+pub struct TextFont {
+    pub font: Rc<Font>,
+    pub size: f32,
+}
+
+impl PartialEq for TextFont {
+    fn eq(&self, other: &TextFont) -> bool {
+        Rc::ptr_eq(&self.font, &other.font) && self.size == other.size
+    }
+}
+
+impl Eq for TextFont {}
+
+impl Hash for TextFont {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.font.as_ref() as *const Font).hash(state);
+        self.size.to_bits().hash(state);
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Clone)]
 pub struct ImguiState {
     pub pivot: super::Metric,
     pub text_align: super::TextAlign,
+    /// None for the default bitmap font
+    pub font: Option<TextFont>,
+    /// Widgets are only visible inside this rect, set by scroll regions
+    pub clip: Option<widgets::Rect>,
+    /// tint of the labels and images, None for white
+    pub color: Option<Color>,
+    /// set between `begin_world` and `end_world`
+    pub world: Option<world::WorldSheet>,
+}
+
+/// Input of the current frame, positions are in native coordinates
+#[derive(Default, Debug)]
+pub struct ImguiInput {
+    pub mouse_pos: (f32, f32),
+    /// mouse move during this frame
+    pub mouse_delta: (f32, f32),
+    pub mouse_down: bool,
+    pub mouse_pressed: bool,
+    pub mouse_released: bool,
+    /// mouse wheel lines scrolled during this frame, positive is up
+    pub wheel: f32,
+    /// the touch which acts as the mouse
+    pub touch_id: Option<u64>,
+    /// text typed during this frame
+    pub text: String,
+    /// codes of the keys pressed during this frame
+    pub keys: Vec<String>,
+
+    /// focus move requested during this frame, widgets can consume it
+    pub nav: Option<Nav>,
+    /// click the focused widget
+    pub activate: bool,
+    /// give the focus back to the game
+    pub cancel: bool,
+    /// gamepad buttons held, to find the pressed ones
+    pub pad_buttons: u32,
 }
 
 #[derive(Default, Debug)]
@@ -13,6 +78,49 @@ pub struct ImguiRaw {
     pub id: u32,
     pub state: ImguiState,
     pub render_list: Vec<Rc<widgets::Widget>>,
+
+    pub screen_size: (u32, u32),
+    pub hidpi: f32,
+    pub input: ImguiInput,
+
+    /// opened panels and stacks, the last one places the next widget
+    pub layouts: Vec<layout::Layout>,
+    /// scroll regions by id
+    pub scrolls: HashMap<u32, layout::ScrollState>,
+
+    /// widget under the mouse, found during the last frame
+    pub hot: Option<u32>,
+    /// last widget under the mouse during this frame
+    pub hover_candidate: Option<u32>,
+    /// widget being clicked or dragged
+    pub active: Option<u32>,
+    /// widget receiving the text input
+    pub keyboard_focus: Option<u32>,
+    /// widget focused by the keyboard or gamepad navigation
+    pub focus: Option<u32>,
+    /// focus the first widget of the next frame, set by `focus_first`
+    pub focus_first: bool,
+    /// widgets which can be focused during this frame, in call order
+    pub focusables: Vec<(u32, widgets::Rect)>,
+
+    /// visible rects of the interactive widgets, during this frame and the last one
+    pub hit_rects: Vec<widgets::Rect>,
+    pub last_hit_rects: Vec<widgets::Rect>,
+
+    /// opened drop-down, its list is drawn above the other widgets
+    pub open_popup: Option<u32>,
+    pub popup_rect: Option<widgets::Rect>,
+    pub next_popup_rect: Option<widgets::Rect>,
+
+    /// current style, and the ones replaced by `push_style`
+    pub style: Style,
+    pub style_stack: Vec<Style>,
+
+    /// white textures used to draw the frames, by corner radius
+    pub shapes: HashMap<u32, Rc<Texture>>,
+
+    /// anchors of the world sheets of this frame, with the id of their first widget
+    pub world_anchors: Vec<(world::WorldAnchor, u32)>,
 }
 
 pub struct Imgui {
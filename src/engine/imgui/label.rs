@@ -1,9 +1,14 @@
-use super::instance::ImguiState;
+use super::instance::{ImguiState, TextFont};
 use super::widgets;
 use super::widgets::Widget;
 use super::{Metric, TextAlign};
 
-use engine::MeshData;
+use engine::core::Aabb;
+use engine::{AssetResult, MeshData};
+use math::*;
+
+/// Size of a glyph of the default font, in pixels
+const BITMAP_GLYPH_SIZE: f32 = 8.0;
 
 struct BitmapFontData {
     hidpi: f32,
@@ -122,7 +127,127 @@ fn make_text_mesh_data(text_data: TextData) -> MeshData {
         indices: indices,
         tangents: None,
         bitangents: None,
+        ..Default::default()
+    }
+}
+
+fn make_font_mesh_data(
+    s: &str,
+    align: TextAlign,
+    font: &TextFont,
+    ssize: (u32, u32),
+    hidpi: f32,
+) -> AssetResult<(MeshData, Aabb)> {
+    let mut vertices = vec![];
+    let mut uvs = vec![];
+    let mut indices = vec![];
+
+    // glyphs are rasterized at the physical size
+    let size = font.size * hidpi;
+    let metrics = font.font.metrics(size)?;
+    let lines = s.split('\n')
+        .map(|line| font.font.layout_line(line, size))
+        .collect::<AssetResult<Vec<_>>>()?;
+
+    let max_width = lines.iter().fold(0.0, |acc: f32, line| acc.max(line.width));
+
+    // pixels to ndc size
+    let sx = 2.0 / ssize.0 as f32;
+    let sy = 2.0 / ssize.1 as f32;
+
+    let mut baseline = metrics.ascent;
+    let mut i = 0;
+    for line in lines.iter() {
+        let x_offset = match align {
+            TextAlign::Left => 0.0,
+            TextAlign::Right => max_width - line.width,
+            TextAlign::Center => (max_width - line.width) * 0.5,
+        };
+
+        for q in line.quads.iter() {
+            let (x0, x1) = ((q.min.0 + x_offset) * sx, (q.max.0 + x_offset) * sx);
+            let (y0, y1) = (-(q.min.1 + baseline) * sy, -(q.max.1 + baseline) * sy);
+
+            vertices.append(&mut vec![
+                x0, y0, 0.0, // 0
+                x0, y1, 0.0, // 1
+                x1, y1, 0.0, // 2
+                x1, y0, 0.0, // 3
+            ]);
+
+            uvs.append(&mut vec![
+                q.uv_min.0, q.uv_min.1, // 0
+                q.uv_min.0, q.uv_max.1, // 1
+                q.uv_max.0, q.uv_max.1, // 2
+                q.uv_max.0, q.uv_min.1, // 3
+            ]);
+
+            indices.append(&mut vec![
+                i * 4,
+                i * 4 + 1,
+                i * 4 + 2,
+                i * 4 + 0,
+                i * 4 + 2,
+                i * 4 + 3,
+            ]);
+
+            i += 1;
+        }
+
+        baseline += metrics.line_height();
     }
+
+    // the bounds cover the lines, not only the drawn glyphs
+    let height = metrics.line_height() * (lines.len() - 1) as f32 + metrics.ascent
+        - metrics.descent;
+    let bounds = Aabb {
+        min: Vector3::new(0.0, -height * sy, 0.0),
+        max: Vector3::new(max_width * sx, 0.0, 0.0),
+    };
+
+    let mesh_data = MeshData {
+        vertices: vertices,
+        uvs: Some(uvs),
+        normals: None,
+        indices: indices,
+        tangents: None,
+        bitangents: None,
+        ..Default::default()
+    };
+
+    Ok((mesh_data, bounds))
+}
+
+/// Width in pixels of the longest line of `s`.
+/// The default font size is used while `font` is loading.
+pub fn text_width(font: &Option<TextFont>, s: &str) -> f32 {
+    let bitmap_width = |line: &str| line.chars().count() as f32 * BITMAP_GLYPH_SIZE;
+
+    s.split('\n')
+        .map(|line| match font {
+            &Some(ref f) => f.font
+                .measure(line, f.size)
+                .unwrap_or_else(|_| bitmap_width(line)),
+            &None => bitmap_width(line),
+        })
+        .fold(0.0, f32::max)
+}
+
+/// Size in pixels of the text
+pub fn text_size(font: &Option<TextFont>, s: &str) -> (f32, f32) {
+    let lines = s.split('\n').count() as f32;
+    let height = match font {
+        &Some(ref f) => f.font
+            .metrics(f.size)
+            .ok()
+            .map(|m| m.line_height() * (lines - 1.0) + m.ascent - m.descent),
+        &None => None,
+    };
+
+    // the bitmap font leaves an empty line between lines
+    let height = height.unwrap_or(BITMAP_GLYPH_SIZE * (lines * 2.0 - 1.0));
+
+    (text_width(font, s), height)
 }
 
 #[derive(Debug, PartialEq)]
@@ -143,7 +268,21 @@ impl Label {
         })
     }
 
-    pub fn bind(&self, ssize: (u32, u32), hidpi: f32) -> MeshData {
+    pub fn font(&self) -> Option<&TextFont> {
+        self.state.font.as_ref()
+    }
+
+    /// Generation of the font atlas used by the mesh
+    pub fn font_generation(&self) -> Option<u32> {
+        self.font().map(|f| f.font.generation())
+    }
+
+    /// Returns the mesh data and its bounds, in ndc
+    pub fn bind(&self, ssize: (u32, u32), hidpi: f32) -> AssetResult<(MeshData, Aabb)> {
+        if let Some(font) = self.font() {
+            return make_font_mesh_data(&self.s, self.state.text_align, font, ssize, hidpi);
+        }
+
         // Mesh Data
         let meshdata = {
             make_text_mesh_data(TextData {
@@ -158,7 +297,8 @@ impl Label {
             })
         };
 
-        return meshdata;
+        let bounds = meshdata.compute_bound().local_aabb();
+        Ok((meshdata, bounds))
     }
 }
 
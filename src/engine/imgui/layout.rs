@@ -0,0 +1,360 @@
+//! Layout containers
+//!
+//! Inside a panel or a stack, each widget is placed after the previous one:
+//! its `pos` is an offset from the end of the previous widget and the pivot is ignored.
+//! `Metric::Native` offsets and sizes are fractions of the layout area
+//! instead of the screen, e.g. `Native(1.0, 0.0) + Pixel(0.0, 20.0)` is a full width row.
+//!
+//! Panels are anchored in the content of their parent panel, with the pivot,
+//! and don't move the widgets which follow them.
+
+use super::controls::{draw_frame, draw_rounded_rect, draw_sprite, draw_text, interact, pixels,
+                      with_imgui};
+use super::instance::ImguiRaw;
+use super::widgets::{self, Rect};
+use super::Metric;
+
+use uni_app::PIXELS_PER_LINE;
+
+const TITLE_HEIGHT: f32 = 20.0;
+const SCROLLBAR_WIDTH: f32 = 8.0;
+const MIN_THUMB_HEIGHT: f32 = 16.0;
+
+const SCREEN: Rect = Rect {
+    min: (0.0, 0.0),
+    max: (1.0, 1.0),
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Direction {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum LayoutKind {
+    Panel,
+    Stack,
+    Scroll { id: u32, view: Rect },
+}
+
+#[derive(Debug, Clone)]
+pub struct Layout {
+    kind: LayoutKind,
+    dir: Direction,
+    /// area of the content, in native coordinates
+    rect: Rect,
+    /// top-left of the next widget
+    cursor: (f32, f32),
+    /// bounds of the widgets placed so far
+    used: Option<Rect>,
+    /// clip rect to restore at the end
+    parent_clip: Option<Rect>,
+}
+
+impl Layout {
+    fn advance(&mut self, rect: &Rect, spacing: (f32, f32)) {
+        match self.dir {
+            Direction::Vertical => self.cursor.1 = rect.max.1 + spacing.1,
+            Direction::Horizontal => self.cursor.0 = rect.max.0 + spacing.0,
+        }
+
+        self.used = Some(self.used.map_or(*rect, |used| used.union(rect)));
+    }
+}
+
+/// Scroll position of a scroll region, in native coordinates
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ScrollState {
+    offset: f32,
+    /// the region was drawn since the last `prune_scrolls`
+    used: bool,
+}
+
+/// Forget the scroll regions which were not drawn during the last frame
+pub fn prune_scrolls(inner: &mut ImguiRaw) {
+    inner.scrolls.retain(|_, s| s.used);
+    for s in inner.scrolls.values_mut() {
+        s.used = false;
+    }
+}
+
+/// Convert a metric in the `parent` area, native values are fractions of its size
+fn resolve(inner: &ImguiRaw, m: &Metric, parent: &Rect) -> (f32, f32) {
+    let (native, pixel) = match m {
+        &Metric::Native(x, y) => ((x, y), (0.0, 0.0)),
+        &Metric::Pixel(x, y) => ((0.0, 0.0), (x, y)),
+        &Metric::Mixed(a, b) => (a, b),
+    };
+
+    let (pw, ph) = parent.size();
+    let p = pixels(inner, pixel.0, pixel.1);
+
+    (native.0 * pw + p.0, native.1 * ph + p.1)
+}
+
+/// Rect of a panel, anchored in the parent panel or the screen
+fn anchor(inner: &ImguiRaw, pos: &Metric, size: &Metric) -> Rect {
+    let parent = inner.layouts.last().map(|l| l.rect).unwrap_or(SCREEN);
+    let size = resolve(inner, size, &parent);
+    let p = resolve(inner, pos, &parent);
+
+    let pivot = match inner.state.pivot {
+        Metric::Native(x, y) => (x, y),
+        _ => unreachable!(),
+    };
+
+    Rect::new(
+        (
+            parent.min.0 + p.0 - pivot.0 * size.0,
+            parent.min.1 + p.1 - pivot.1 * size.1,
+        ),
+        size,
+    )
+}
+
+/// Rect of the next widget, placed in the current layout.
+/// Without layout, `pos` is on the screen and the pivot is used.
+pub fn place(inner: &mut ImguiRaw, pos: &Metric, size: &Metric) -> Rect {
+    let (parent, cursor) = match inner.layouts.last() {
+        None => {
+            return widgets::compute_rect(
+                pos,
+                size,
+                &inner.state.pivot,
+                &inner.screen_size,
+                inner.hidpi,
+            )
+        }
+        Some(l) => (l.rect, l.cursor),
+    };
+
+    let size = resolve(inner, size, &parent);
+    let offset = resolve(inner, pos, &parent);
+    let rect = Rect::new((cursor.0 + offset.0, cursor.1 + offset.1), size);
+
+    let spacing = layout_spacing(inner);
+    inner.layouts.last_mut().unwrap().advance(&rect, spacing);
+
+    rect
+}
+
+/// Background of the panels and scroll regions, returns the widget id
+fn draw_background(inner: &mut ImguiRaw, rect: &Rect) -> u32 {
+    match inner.style.panel_sprite.clone() {
+        Some(sprite) => draw_sprite(inner, rect, &sprite),
+        None => {
+            let (color, rounding) = (inner.style.panel, inner.style.rounding);
+            draw_rounded_rect(inner, rect, rounding, color)
+        }
+    }
+}
+
+/// Space between the widgets of a layout
+fn layout_spacing(inner: &ImguiRaw) -> (f32, f32) {
+    pixels(inner, inner.style.spacing, inner.style.spacing)
+}
+
+fn push(inner: &mut ImguiRaw, kind: LayoutKind, dir: Direction, rect: Rect) {
+    let parent_clip = inner.state.clip;
+
+    inner.layouts.push(Layout {
+        kind,
+        dir,
+        rect,
+        cursor: rect.min,
+        used: None,
+        parent_clip,
+    });
+}
+
+fn pop(inner: &mut ImguiRaw, name: &str) -> Layout {
+    let layout = inner
+        .layouts
+        .pop()
+        .expect(&format!("imgui::end_{} without begin", name));
+
+    inner.state.clip = layout.parent_clip;
+    layout
+}
+
+/// Panel with a background and an optional title bar,
+/// the widgets until `end_panel` are stacked vertically inside it.
+pub fn begin_panel(title: &str, pos: Metric, size: Metric) {
+    with_imgui(|inner| {
+        let rect = anchor(inner, &pos, &size);
+
+        // the panel catches the mouse, so the game ignores clicks on it
+        let id = draw_background(inner, &rect);
+        interact(inner, id, &rect, false);
+
+        let mut top = rect.min.1;
+        if title.len() > 0 {
+            let bar = Rect::new(rect.min, (rect.size().0, pixels(inner, 0.0, TITLE_HEIGHT).1));
+            let (color, rounding) = (inner.style.title, inner.style.rounding);
+            draw_rounded_rect(inner, &bar, rounding, color);
+
+            let padding = pixels(inner, inner.style.padding, 0.0);
+            draw_text(inner, (bar.min.0 + padding.0, bar.center().1), (0.0, 0.5), title);
+            top = bar.max.1;
+        }
+
+        let padding = pixels(inner, inner.style.padding, inner.style.padding);
+        let content = Rect {
+            min: (rect.min.0 + padding.0, top + padding.1),
+            max: (rect.max.0 - padding.0, rect.max.1 - padding.1),
+        };
+
+        push(inner, LayoutKind::Panel, Direction::Vertical, content);
+    })
+}
+
+pub fn end_panel() {
+    with_imgui(|inner| {
+        let layout = pop(inner, "panel");
+        assert!(
+            layout.kind == LayoutKind::Panel,
+            "imgui::end_panel does not match the last begin"
+        );
+    })
+}
+
+/// Stack in the remaining area of the current layout
+fn begin_stack(dir: Direction) {
+    with_imgui(|inner| {
+        let rect = match inner.layouts.last() {
+            Some(l) => Rect {
+                min: l.cursor,
+                max: l.rect.max,
+            },
+            None => SCREEN,
+        };
+
+        push(inner, LayoutKind::Stack, dir, rect);
+    })
+}
+
+/// The stack takes the size of its widgets in the parent layout
+fn end_stack(dir: Direction, name: &str) {
+    with_imgui(|inner| {
+        let layout = pop(inner, name);
+        assert!(
+            layout.kind == LayoutKind::Stack && layout.dir == dir,
+            "imgui::end_{} does not match the last begin",
+            name
+        );
+
+        let spacing = layout_spacing(inner);
+        if let (Some(parent), Some(used)) = (inner.layouts.last_mut(), layout.used) {
+            parent.advance(&used, spacing);
+        }
+    })
+}
+
+/// Widgets until `end_vertical` are placed below each other
+pub fn begin_vertical() {
+    begin_stack(Direction::Vertical);
+}
+
+pub fn end_vertical() {
+    end_stack(Direction::Vertical, "vertical");
+}
+
+/// Widgets until `end_horizontal` are placed next to each other
+pub fn begin_horizontal() {
+    begin_stack(Direction::Horizontal);
+}
+
+pub fn end_horizontal() {
+    end_stack(Direction::Horizontal, "horizontal");
+}
+
+/// Scrollable region, placed like a widget.
+/// The widgets until `end_scroll` are stacked vertically and clipped by the region.
+pub fn begin_scroll(pos: Metric, size: Metric) {
+    with_imgui(|inner| {
+        let view = place(inner, &pos, &size);
+
+        let id = draw_background(inner, &view);
+        interact(inner, id, &view, false);
+
+        let offset = inner.scrolls.get(&id).map_or(0.0, |s| s.offset);
+        let padding = pixels(inner, inner.style.padding, inner.style.padding);
+        let scrollbar = pixels(inner, SCROLLBAR_WIDTH, 0.0);
+
+        let content = Rect {
+            min: (view.min.0 + padding.0, view.min.1 + padding.1 - offset),
+            max: (
+                view.max.0 - padding.0 - scrollbar.0,
+                view.max.1 - padding.1 - offset,
+            ),
+        };
+
+        let clip = match inner.state.clip {
+            Some(parent) => parent.intersect(&view),
+            None => view,
+        };
+
+        push(inner, LayoutKind::Scroll { id, view }, Direction::Vertical, content);
+        inner.state.clip = Some(clip);
+    })
+}
+
+pub fn end_scroll() {
+    with_imgui(|inner| {
+        let layout = pop(inner, "scroll");
+        let (id, view) = match layout.kind {
+            LayoutKind::Scroll { id, view } => (id, view),
+            _ => panic!("imgui::end_scroll does not match the last begin"),
+        };
+
+        let padding = pixels(inner, inner.style.padding, inner.style.padding);
+        let content_height = layout.used.map_or(0.0, |used| used.max.1 - layout.rect.min.1);
+        let view_height = view.size().1;
+        let max_offset = (content_height + padding.1 * 2.0 - view_height).max(0.0);
+
+        let mut state = inner.scrolls.get(&id).cloned().unwrap_or_default();
+
+        // the innermost region under the mouse takes the wheel
+        let mouse_pos = inner.input.mouse_pos;
+        if inner.input.wheel != 0.0 && view.contains(mouse_pos) {
+            let lines = pixels(inner, 0.0, inner.input.wheel * PIXELS_PER_LINE);
+            state.offset -= lines.1;
+            inner.input.wheel = 0.0;
+        }
+
+        if max_offset > 0.0 {
+            let scrollbar = pixels(inner, SCROLLBAR_WIDTH, MIN_THUMB_HEIGHT);
+            let track = Rect {
+                min: (view.max.0 - scrollbar.0, view.min.1),
+                max: view.max,
+            };
+
+            let thumb_height = (view_height * view_height / (view_height + max_offset))
+                .max(scrollbar.1)
+                .min(view_height);
+            let free = view_height - thumb_height;
+
+            // dragging anywhere on the track moves the thumb there
+            let thumb_id = inner.id + 1;
+            let it = interact(inner, thumb_id, &track, false);
+            if it.active && free > 0.0 {
+                let t = (mouse_pos.1 - track.min.1 - thumb_height * 0.5) / free;
+                state.offset = t.max(0.0).min(1.0) * max_offset;
+            }
+
+            state.offset = state.offset.max(0.0).min(max_offset);
+
+            let thumb = Rect::new(
+                (track.min.0, track.min.1 + free * state.offset / max_offset),
+                (scrollbar.0, thumb_height),
+            );
+            draw_frame(inner, &thumb, &it);
+        } else {
+            state.offset = 0.0;
+        }
+
+        state.used = true;
+        inner.scrolls.insert(id, state);
+    })
+}
@@ -5,7 +5,39 @@
 //!
 //! Supported elements
 //!
-//! Label
+//! Label, Image, Rect, Rounded rect, Nine-slice sprite
+//!
+//! World-space widgets
+//!     Widgets between `begin_world(anchor)` and `end_world()` follow a point of the scene,
+//!     e.g. `imgui::begin_world(WorldAnchor::new(&go))` for a name tag above a character.
+//!     The sheet can face the camera, keep its size in pixels and be hidden behind the scene.
+//!
+//! Fonts
+//!     Labels use a 8x8 bitmap font by default, `font(handle, size)` switches
+//!     to a TrueType / OpenType font loaded with `AssetSystem::new_font`.
+//!     Any UTF-8 text can be drawn as long as the font has the glyphs.
+//!
+//! Interactive widgets : Button, Checkbox, Slider, DragValue, Dropdown, TextInput
+//!     They return their state immediately, e.g. `if imgui::button(..) { .. }`.
+//!     A widget is identified by its call order, so the same widgets
+//!     should be called in the same order every frame.
+//!
+//! Navigation
+//!     Tab / Shift+Tab, the arrow keys and the gamepad d-pad move the focus between
+//!     the interactive widgets, Enter, Space or the South button click the focused one.
+//!     Only Tab or `focus_first()` start the navigation, e.g. when a pause menu opens.
+//!     Escape or the East button give the focus back to the game.
+//!     `wants_mouse` and `wants_keyboard` tell when the game should ignore the input.
+//!
+//! Layouts : Panel, Vertical / Horizontal stacks, Scroll region
+//!     Widgets between `begin_*` and `end_*` are placed after each other,
+//!     their `pos` becomes an offset from the previous widget.
+//!     A scroll region clips its content and scrolls with the mouse wheel.
+//!
+//! Styles
+//!     Widgets take their colors, padding, font and sprites from the current `Style`.
+//!     `push_style(style)` replaces it until `pop_style()`, e.g.
+//!     `let mut s = imgui::style(); s.rounding = 4.0; imgui::push_style(s);`
 //!
 //! Positioning
 //!     Pivot to control how the element is positiion related to itself.
@@ -16,18 +48,30 @@
 //!
 
 mod context;
+mod controls;
 mod image;
 mod instance;
 mod label;
+mod layout;
 mod metric;
+mod navigation;
+mod style;
 mod widgets;
+mod world;
 
 use engine::IEngine;
-use engine::render::{Material, Texture};
+use engine::render::{Font, Material, Texture};
 use std::rc::Rc;
+use uni_app::AppEvent;
 
 pub use self::context::Context;
+pub use self::controls::{button, checkbox, drag_value, dropdown, nine_slice, progress_bar, rect,
+                         rounded_rect, slider, text_input};
+pub use self::layout::{begin_horizontal, begin_panel, begin_scroll, begin_vertical,
+                       end_horizontal, end_panel, end_scroll, end_vertical};
 pub use self::metric::*;
+pub use self::style::{color_to_vec4, Borders, Color, Sprite, StateSprites, Style};
+pub use self::world::{begin_world, end_world, WorldAnchor, WorldScale};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum TextAlign {
@@ -48,22 +92,180 @@ pub fn begin() {
     inner.id = 0;
 
     inner.render_list.clear();
+    inner.world_anchors.clear();
+    inner.state.world = None;
+
+    navigation::navigate(&mut inner);
+    inner.focusables.clear();
+    inner.last_hit_rects = ::std::mem::replace(&mut inner.hit_rects, Vec::new());
+
+    inner.layouts.clear();
+    inner.state.clip = None;
+    layout::prune_scrolls(&mut inner);
+
+    // styles which were not popped in the last frame
+    if inner.style_stack.len() > 0 {
+        inner.style = inner.style_stack.swap_remove(0);
+        inner.style_stack.clear();
+    }
+
+    inner.hot = inner.hover_candidate.take();
+    inner.popup_rect = inner.next_popup_rect.take();
+}
+
+/// Feed the input of this frame, mouse positions are in physical pixels
+pub fn handle_events(events: &[AppEvent], screen_size: (u32, u32), hidpi: f32) {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.screen_size = screen_size;
+    inner.hidpi = hidpi;
+
+    {
+        let editing = inner.keyboard_focus.is_some();
+        let input = &mut inner.input;
+        let last_mouse_pos = input.mouse_pos;
+        input.mouse_pressed = false;
+        input.mouse_released = false;
+        input.wheel = 0.0;
+        input.text.clear();
+        input.keys.clear();
+        input.nav = None;
+        input.activate = false;
+        input.cancel = false;
+
+        let to_native = |p: (i32, i32)| {
+            (
+                p.0 as f32 / screen_size.0 as f32,
+                p.1 as f32 / screen_size.1 as f32,
+            )
+        };
+
+        for evt in events.iter() {
+            match evt {
+                &AppEvent::MousePos(pos) => input.mouse_pos = to_native(pos),
+                &AppEvent::MouseDown(ref e) if e.button == 0 => {
+                    input.mouse_down = true;
+                    input.mouse_pressed = true;
+                }
+                &AppEvent::MouseUp(ref e) if e.button == 0 => {
+                    input.mouse_down = false;
+                    input.mouse_released = true;
+                }
+                // the first finger acts as the mouse
+                &AppEvent::Touch(ref t) if input.touch_id.unwrap_or(t.id) == t.id => {
+                    use uni_app::TouchPhase;
+
+                    input.mouse_pos = to_native(t.pos);
+                    match t.phase {
+                        TouchPhase::Started => {
+                            input.touch_id = Some(t.id);
+                            input.mouse_down = true;
+                            input.mouse_pressed = true;
+                        }
+                        TouchPhase::Ended | TouchPhase::Cancelled => {
+                            input.touch_id = None;
+                            input.mouse_down = false;
+                            input.mouse_released = true;
+                        }
+                        TouchPhase::Moved => (),
+                    }
+                }
+                &AppEvent::MouseWheel(ref e) => input.wheel += e.delta_y,
+                &AppEvent::TextInput(ref s) => input.text.push_str(s),
+                &AppEvent::KeyDown(ref k) => {
+                    input.keys.push(k.code.clone());
+
+                    // the keys go to the text input being edited
+                    if !editing {
+                        match k.code.as_str() {
+                            "Enter" | "NumpadEnter" | "Space" => input.activate = true,
+                            "Escape" => input.cancel = true,
+                            code => {
+                                if let Some(nav) = navigation::nav_from_key(code, k.shift) {
+                                    input.nav = Some(nav);
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        input.mouse_delta = (
+            input.mouse_pos.0 - last_mouse_pos.0,
+            input.mouse_pos.1 - last_mouse_pos.1,
+        );
+
+        if !editing {
+            navigation::poll_gamepad(input);
+        }
+    }
+
+    // the mouse takes the focus back from the navigation
+    if inner.input.mouse_pressed || inner.input.cancel {
+        inner.focus = None;
+    }
+
+    if !inner.input.mouse_down && !inner.input.mouse_released {
+        inner.active = None;
+    }
+}
+
+/// True when an interactive widget or a panel is at `pos`, in physical pixels
+pub fn hit_test(pos: (i32, i32)) -> bool {
+    let imgui = instance::imgui_inst();
+    let inner = imgui.inner.lock().unwrap();
+    let (sw, sh) = inner.screen_size;
+    let pos = (pos.0 as f32 / sw as f32, pos.1 as f32 / sh as f32);
+
+    navigation::hit_test(&inner, pos)
+        || inner.popup_rect.map(|r| r.contains(pos)).unwrap_or(false)
+}
+
+/// True when the mouse is over an interactive widget or drags one,
+/// the game should ignore mouse clicks in that case.
+pub fn wants_mouse() -> bool {
+    let imgui = instance::imgui_inst();
+    let inner = imgui.inner.lock().unwrap();
+    let mouse_pos = inner.input.mouse_pos;
+
+    inner.hot.is_some() || inner.active.is_some() || navigation::hit_test(&inner, mouse_pos)
+        || inner
+            .popup_rect
+            .map(|r| r.contains(mouse_pos))
+            .unwrap_or(false)
+}
+
+/// True when a text input is edited or a widget is focused by the navigation,
+/// the game should ignore key presses in that case.
+pub fn wants_keyboard() -> bool {
+    let imgui = instance::imgui_inst();
+    let inner = imgui.inner.lock().unwrap();
+    inner.keyboard_focus.is_some() || inner.focus.is_some()
 }
 
-fn add_widget<F>(f: F)
+/// Focus the first interactive widget of the next frame, e.g. when a menu opens,
+/// so the arrow keys and the gamepad d-pad navigate in it.
+pub fn focus_first() {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.focus_first = true;
+}
+
+fn push_widget<F>(inner: &mut instance::ImguiRaw, state: instance::ImguiState, f: F) -> u32
 where
     F: FnOnce(u32, instance::ImguiState) -> widgets::Widget,
 {
-    let imgui = instance::imgui_inst();
-    let mut inner = imgui.inner.lock().unwrap();
     inner.id += 1;
 
     let id: u32 = inner.id;
-    let state = inner.state;
 
     if id as usize >= inner.render_list.len() {
         inner.render_list.push(Rc::new(f(id, state)));
     }
+
+    id
 }
 
 /// Pivot controls how to place the ui element
@@ -80,22 +282,120 @@ pub fn text_align(align: TextAlign) {
     inner.state.text_align = align;
 }
 
+/// Font used by the next labels, `size` is in pixels
+pub fn font(font: Rc<Font>, size: f32) {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.state.font = Some(instance::TextFont { font, size });
+}
+
+/// Go back to the default bitmap font
+pub fn default_font() {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.state.font = None;
+}
+
+/// Copy of the current style
+pub fn style() -> Style {
+    let imgui = instance::imgui_inst();
+    let inner = imgui.inner.lock().unwrap();
+    inner.style.clone()
+}
+
+/// Replace the current style for all the next frames
+pub fn set_style(style: Style) {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.style = style;
+}
+
+/// Use `style` until the matching `pop_style`
+pub fn push_style(style: Style) {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    let old = ::std::mem::replace(&mut inner.style, style);
+    inner.style_stack.push(old);
+}
+
+pub fn pop_style() {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.style = inner
+        .style_stack
+        .pop()
+        .expect("imgui::pop_style without push_style");
+}
+
+/// Inside a layout, replace `pos` and `size` by the rect given by the layout
+fn place_in_layout(
+    inner: &mut instance::ImguiRaw,
+    state: &mut instance::ImguiState,
+    pos: &mut Metric,
+    size: &mut Metric,
+) {
+    if inner.layouts.is_empty() {
+        return;
+    }
+
+    let rect = layout::place(inner, pos, size);
+    let (w, h) = rect.size();
+
+    *pos = Metric::Native(rect.min.0, rect.min.1);
+    *size = Metric::Native(w, h);
+    state.pivot = Metric::Native(0.0, 0.0);
+}
+
 /// Label
 pub fn label(pos: Metric, s: &str) {
-    add_widget(|id, state| label::Label::new(id, pos, state, s.into()));
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    let mut state = inner.state.clone();
+    state.color = Some(inner.style.text);
+
+    let mut pos = pos;
+    let (w, h) = label::text_size(&state.font, s);
+    place_in_layout(&mut inner, &mut state, &mut pos, &mut Metric::Pixel(w, h));
+
+    push_widget(&mut inner, state, |id, state| {
+        label::Label::new(id, pos, state, s.into())
+    });
 
     // reset text settings
-    text_align(TextAlign::default());
+    inner.state.text_align = TextAlign::default();
+}
+
+/// Size in pixels of `s` drawn by `label` with the current font
+pub fn text_size(s: &str) -> (f32, f32) {
+    let imgui = instance::imgui_inst();
+    let inner = imgui.inner.lock().unwrap();
+    label::text_size(&inner.state.font, s)
+}
+
+fn add_image<T>(pos: Metric, size: Metric, t: T)
+where
+    T: Into<image::ImageKind>,
+{
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    let mut state = inner.state.clone();
+
+    let (mut pos, mut size) = (pos, size);
+    place_in_layout(&mut inner, &mut state, &mut pos, &mut size);
+
+    push_widget(&mut inner, state, |id, state| {
+        image::Image::new(id, pos, size, state, t)
+    });
 }
 
 /// Image
 pub fn image(pos: Metric, size: Metric, tex: Rc<Texture>) {
-    add_widget(|id, state| image::Image::new(id, pos, size, state, tex));
+    add_image(pos, size, tex);
 }
 
 /// Image with material
 pub fn image_with_material(pos: Metric, size: Metric, material: Rc<Material>) {
-    add_widget(|id, state| image::Image::new(id, pos, size, state, material));
+    add_image(pos, size, material);
 }
 
 pub fn pre_render(engine: &mut IEngine) {
@@ -0,0 +1,190 @@
+//! Keyboard and gamepad navigation
+//!
+//! Tab / Shift+Tab move the focus in the order the widgets are called,
+//! the arrow keys and the d-pad move it to the nearest widget in that direction.
+//! Only Tab or `imgui::focus_first` start the navigation, so the arrow keys and
+//! the d-pad still drive the game while no widget is focused.
+//! Enter, Space or the South button click the focused widget,
+//! Escape or the East button give the focus back to the game.
+
+use super::instance::{ImguiInput, ImguiRaw};
+use super::widgets::Rect;
+
+use uni_pad as pad;
+use uni_pad::mapping;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Nav {
+    Next,
+    Prev,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Gamepad of the player navigating in the ui
+const NAV_PLAYER: i32 = 0;
+
+const PAD_BUTTONS: [i32; 6] = [
+    mapping::BUTTON_DPAD_UP,
+    mapping::BUTTON_DPAD_DOWN,
+    mapping::BUTTON_DPAD_LEFT,
+    mapping::BUTTON_DPAD_RIGHT,
+    mapping::BUTTON_SOUTH,
+    mapping::BUTTON_EAST,
+];
+
+pub fn nav_from_key(code: &str, shift: bool) -> Option<Nav> {
+    match code {
+        "Tab" if shift => Some(Nav::Prev),
+        "Tab" => Some(Nav::Next),
+        "ArrowLeft" => Some(Nav::Left),
+        "ArrowRight" => Some(Nav::Right),
+        "ArrowUp" => Some(Nav::Up),
+        "ArrowDown" => Some(Nav::Down),
+        _ => None,
+    }
+}
+
+/// Gamepad buttons pressed since the last frame
+pub fn poll_gamepad(input: &mut ImguiInput) {
+    let mut down = 0;
+    for (i, button) in PAD_BUTTONS.iter().enumerate() {
+        if pad::gamepad_button(NAV_PLAYER, *button) {
+            down |= 1 << i;
+        }
+    }
+
+    let pressed = down & !input.pad_buttons;
+    input.pad_buttons = down;
+
+    let nav = [Nav::Up, Nav::Down, Nav::Left, Nav::Right];
+    for (i, n) in nav.iter().enumerate() {
+        if pressed & (1 << i) != 0 {
+            input.nav = Some(*n);
+        }
+    }
+
+    input.activate |= pressed & (1 << 4) != 0;
+    input.cancel |= pressed & (1 << 5) != 0;
+}
+
+/// Distance to `to` when it is in the direction `nav` of `from`,
+/// the offset across the direction counts more than the one along it
+fn directional_distance(nav: Nav, from: &Rect, to: &Rect) -> Option<f32> {
+    let (fx, fy) = from.center();
+    let (tx, ty) = to.center();
+    let (dx, dy) = (tx - fx, ty - fy);
+
+    let (along, across) = match nav {
+        Nav::Left => (-dx, dy),
+        Nav::Right => (dx, dy),
+        Nav::Up => (-dy, dx),
+        Nav::Down => (dy, dx),
+        Nav::Next | Nav::Prev => return None,
+    };
+
+    if along <= 0.0 {
+        return None;
+    }
+
+    Some(along + across.abs() * 2.0)
+}
+
+/// Move the focus with the navigation input of the last frame,
+/// between the focusable widgets of the last frame
+pub fn navigate(inner: &mut ImguiRaw) {
+    let list = &inner.focusables;
+
+    let current = inner
+        .focus
+        .and_then(|f| list.iter().position(|&(id, _)| id == f));
+
+    // the focused widget is gone
+    if current.is_none() {
+        inner.focus = None;
+    }
+
+    if list.len() == 0 {
+        inner.input.nav = None;
+        return;
+    }
+
+    if inner.focus_first && current.is_none() {
+        inner.focus = Some(list[0].0);
+    }
+    inner.focus_first = false;
+
+    let nav = match inner.input.nav.take() {
+        Some(nav) => nav,
+        None => return,
+    };
+
+    let next = match (nav, current) {
+        (Nav::Prev, None) => Some(list.len() - 1),
+        (Nav::Next, None) => Some(0),
+        // the directions only move an existing focus
+        (_, None) => None,
+        (Nav::Next, Some(i)) => Some((i + 1) % list.len()),
+        (Nav::Prev, Some(i)) => Some((i + list.len() - 1) % list.len()),
+        (dir, Some(i)) => {
+            let from = list[i].1;
+            list.iter()
+                .enumerate()
+                .filter_map(|(j, &(_, ref r))| directional_distance(dir, &from, r).map(|d| (j, d)))
+                .fold(None, |best: Option<(usize, f32)>, (j, d)| match best {
+                    Some((_, bd)) if bd <= d => best,
+                    _ => Some((j, d)),
+                })
+                .map(|(j, _)| j)
+        }
+    };
+
+    if let Some(j) = next {
+        inner.focus = Some(list[j].0);
+    }
+}
+
+/// True when an interactive widget of the last frame is at `pos`, in native coordinates
+pub fn hit_test(inner: &ImguiRaw, pos: (f32, f32)) -> bool {
+    inner.last_hit_rects.iter().any(|r| r.contains(pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::imgui;
+    use engine::imgui::instance::imgui_inst;
+
+    fn navigate_with(nav: Option<Nav>) {
+        let imgui = imgui_inst();
+        let mut inner = imgui.inner.lock().unwrap();
+        inner.focusables = vec![
+            (1, Rect::new((0.0, 0.0), (0.1, 0.1))),
+            (2, Rect::new((0.0, 0.2), (0.1, 0.1))),
+        ];
+        inner.input.nav = nav;
+        navigate(&mut inner);
+    }
+
+    #[test]
+    fn arrow_without_focus_keeps_keyboard() {
+        navigate_with(nav_from_key("ArrowDown", false));
+        assert!(!imgui::wants_keyboard());
+
+        navigate_with(nav_from_key("Tab", false));
+        assert!(imgui::wants_keyboard());
+
+        // the arrows move the focus once it is started
+        navigate_with(nav_from_key("ArrowDown", false));
+        assert_eq!(imgui_inst().inner.lock().unwrap().focus, Some(2));
+    }
+
+    #[test]
+    fn focus_first_starts_navigation() {
+        imgui::focus_first();
+        navigate_with(None);
+        assert_eq!(imgui_inst().inner.lock().unwrap().focus, Some(1));
+    }
+}
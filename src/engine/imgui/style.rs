@@ -0,0 +1,157 @@
+//! Widget styles
+//!
+//! The widgets take their colors, margins, font and sprites from the current `Style`.
+//! `push_style` replaces it until the matching `pop_style`.
+
+use engine::{Font, Texture};
+use math::*;
+use std::rc::Rc;
+
+use super::instance::TextFont;
+
+/// RGBA color, 8 bits per channel
+pub type Color = [u8; 4];
+
+pub const WHITE: Color = [0xff, 0xff, 0xff, 0xff];
+
+pub fn color_to_vec4(c: &Color) -> Vector4<f32> {
+    Vector4::new(
+        c[0] as f32 / 255.0,
+        c[1] as f32 / 255.0,
+        c[2] as f32 / 255.0,
+        c[3] as f32 / 255.0,
+    )
+}
+
+/// Insets of a nine-slice image, in pixels of the texture.
+/// The border of the drawn image has the same size in pixels.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Borders {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Borders {
+    pub fn all(px: f32) -> Borders {
+        Borders {
+            left: px,
+            top: px,
+            right: px,
+            bottom: px,
+        }
+    }
+}
+
+/// Texture drawn with nine-slice scaling: the corners keep their size,
+/// the edges are stretched along one axis and the center along both.
+#[derive(Debug, Clone)]
+pub struct Sprite {
+    pub texture: Rc<Texture>,
+    pub borders: Borders,
+    pub tint: Color,
+}
+
+impl Sprite {
+    pub fn new(texture: Rc<Texture>, borders: Borders) -> Sprite {
+        Sprite {
+            texture,
+            borders,
+            tint: WHITE,
+        }
+    }
+}
+
+/// Sprites of a widget for each state, `hot` and `active` fall back to `normal`
+#[derive(Debug, Default, Clone)]
+pub struct StateSprites {
+    pub normal: Option<Sprite>,
+    pub hot: Option<Sprite>,
+    pub active: Option<Sprite>,
+}
+
+impl StateSprites {
+    pub fn get(&self, hovered: bool, active: bool) -> Option<&Sprite> {
+        let sprite = if active {
+            self.active.as_ref()
+        } else if hovered {
+            self.hot.as_ref()
+        } else {
+            None
+        };
+
+        sprite.or(self.normal.as_ref())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Style {
+    /// frame of the widgets, when they are not hovered
+    pub frame: Color,
+    pub hot: Color,
+    pub active: Color,
+    /// checkbox mark, slider bar and selected item
+    pub accent: Color,
+    pub panel: Color,
+    pub title: Color,
+    pub text: Color,
+
+    /// inner margin of the widgets and panels, in pixels
+    pub padding: f32,
+    /// space between the widgets of a layout, in pixels
+    pub spacing: f32,
+    /// corner radius of the frames in pixels, 0 for square corners
+    pub rounding: f32,
+
+    /// font and size in pixels of the widget texts, None for the current font
+    pub font: Option<(Rc<Font>, f32)>,
+
+    /// frames of the buttons, checkboxes, sliders, dropdowns and text inputs,
+    /// drawn instead of the colors when they are set
+    pub frame_sprites: StateSprites,
+    pub panel_sprite: Option<Sprite>,
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style {
+            frame: [0x30, 0x30, 0x30, 0xd0],
+            hot: [0x48, 0x48, 0x48, 0xe0],
+            active: [0x60, 0x60, 0x60, 0xf0],
+            accent: [0x40, 0x80, 0xe0, 0xff],
+            panel: [0x20, 0x20, 0x20, 0xc0],
+            title: [0x30, 0x50, 0x80, 0xe0],
+            text: WHITE,
+
+            padding: 4.0,
+            spacing: 4.0,
+            rounding: 0.0,
+
+            font: None,
+
+            frame_sprites: Default::default(),
+            panel_sprite: None,
+        }
+    }
+}
+
+impl Style {
+    /// Color of a frame for the state of its widget
+    pub fn frame_color(&self, hovered: bool, active: bool) -> Color {
+        if active {
+            self.active
+        } else if hovered {
+            self.hot
+        } else {
+            self.frame
+        }
+    }
+
+    pub fn text_font(&self) -> Option<TextFont> {
+        self.font.as_ref().map(|&(ref font, size)| TextFont {
+            font: font.clone(),
+            size,
+        })
+    }
+}
@@ -1,4 +1,5 @@
 use engine::core::Aabb;
+use engine::Scissor;
 use math::*;
 
 use super::Metric;
@@ -6,6 +7,7 @@ use std::fmt::Debug;
 
 use super::image;
 use super::label;
+use super::world::WorldSheet;
 
 pub trait WidgetBinder: Debug {
     fn id(&self) -> u32;
@@ -25,6 +27,14 @@ impl Widget {
             &Widget::Label(ref lbl) => lbl.id(),
         }
     }
+
+    /// The sheet of a world-space widget
+    pub fn world(&self) -> Option<WorldSheet> {
+        match self {
+            &Widget::Image(ref img) => img.world,
+            &Widget::Label(ref lbl) => lbl.state.world,
+        }
+    }
 }
 
 impl PartialEq for Widget {
@@ -36,6 +46,118 @@ impl PartialEq for Widget {
     }
 }
 
+/// Rectangle in native coordinates, top-left of screen is (0.0,0.0)
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Rect {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+impl Rect {
+    pub fn new(min: (f32, f32), size: (f32, f32)) -> Rect {
+        Rect {
+            min,
+            max: (min.0 + size.0, min.1 + size.1),
+        }
+    }
+
+    pub fn size(&self) -> (f32, f32) {
+        (self.max.0 - self.min.0, self.max.1 - self.min.1)
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (
+            (self.min.0 + self.max.0) * 0.5,
+            (self.min.1 + self.max.1) * 0.5,
+        )
+    }
+
+    pub fn contains(&self, p: (f32, f32)) -> bool {
+        p.0 >= self.min.0 && p.0 < self.max.0 && p.1 >= self.min.1 && p.1 < self.max.1
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+
+    /// Common part of two rects, empty if they don't overlap
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let min = (self.min.0.max(other.min.0), self.min.1.max(other.min.1));
+        let max = (self.max.0.min(other.max.0), self.max.1.min(other.max.1));
+
+        Rect {
+            min,
+            max: (max.0.max(min.0), max.1.max(min.1)),
+        }
+    }
+}
+
+/// Scissor test of a clip rect, in pixels from the bottom-left of the screen
+pub fn to_scissor(clip: &Option<Rect>, ssize: &(u32, u32)) -> Scissor {
+    match clip {
+        &None => Scissor::Off,
+        &Some(ref r) => {
+            let (sw, sh) = (ssize.0 as f32, ssize.1 as f32);
+            let x = (r.min.0 * sw).round();
+            let y = ((1.0 - r.max.1) * sh).round();
+            let w = (r.max.0 * sw).round() - x;
+            let h = ((1.0 - r.min.1) * sh).round() - y;
+
+            Scissor::Rect(x as i32, y as i32, w.max(0.0) as u32, h.max(0.0) as u32)
+        }
+    }
+}
+
+/// Depth step between two widgets, used to keep the draw order of the ui
+const DRAW_ORDER_STEP: f32 = 1.0 / 100000.0;
+
+/// The ui queue is sorted by depth, widgets with a bigger id are drawn on top
+pub fn draw_order_depth(id: u32) -> f32 {
+    -(id as f32) * DRAW_ORDER_STEP
+}
+
+/// Convert a metric to native coordinates
+pub fn to_native(m: &Metric, ssize: &(u32, u32), hidpi: f32) -> (f32, f32) {
+    let from_pixel = |px: f32, py: f32| {
+        (
+            px * hidpi / (ssize.0 as f32),
+            py * hidpi / (ssize.1 as f32),
+        )
+    };
+
+    match m {
+        &Metric::Native(px, py) => (px, py),
+        &Metric::Pixel(px, py) => from_pixel(px, py),
+        &Metric::Mixed((ax, ay), (bx, by)) => {
+            let vp = from_pixel(bx, by);
+            (ax + vp.0, ay + vp.1)
+        }
+    }
+}
+
+/// Screen rectangle of a widget, the same placement as `compute_translate`
+pub fn compute_rect(
+    pos: &Metric,
+    size: &Metric,
+    pivot: &Metric,
+    ssize: &(u32, u32),
+    hidpi: f32,
+) -> Rect {
+    let (w, h) = to_native(size, ssize, hidpi);
+
+    // compute_translate works in ndc, where a native unit is 2.0 and y is up
+    let bounds = Aabb {
+        min: Vector3::new(0.0, -h * 2.0, 0.0),
+        max: Vector3::new(w * 2.0, 0.0, 0.0),
+    };
+    let t = compute_translate(pos, pivot, ssize, hidpi, &bounds);
+
+    Rect::new(((t.x + 1.0) * 0.5, (1.0 - t.y) * 0.5), (w, h))
+}
+
 pub fn to_pixel_pos(px: f32, py: f32, ssize: &(u32, u32), hidpi: f32) -> (f32, f32) {
     ((
         (px * 2.0 * hidpi) / (ssize.0 as f32),
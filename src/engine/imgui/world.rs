@@ -0,0 +1,149 @@
+//! World-space widgets
+//!
+//! The widgets between `begin_world(anchor)` and `end_world()` are drawn on a sheet
+//! attached to a point of the scene, e.g. the name tag or the health bar of a character.
+//! They are placed as usual, with the anchor at the top-left of the screen,
+//! and follow it through the main camera each frame.
+//!
+//! Labels, images and rects can be drawn on a sheet, the interactive widgets
+//! are not hit tested there.
+
+use engine::{Camera, DepthTest, GameObject, Material, MeshData, RenderQueue, Scissor};
+use math::*;
+
+use super::instance;
+
+/// Distance in pixels between two widgets of a sheet, which keeps their draw order
+const LAYER_STEP: f32 = 0.1;
+
+/// Size of the widgets of a sheet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldScale {
+    /// the widgets keep their size in pixels, whatever their distance to the camera
+    Constant,
+    /// `n` pixels make one world unit, the widgets get smaller with the distance
+    PixelsPerUnit(f32),
+}
+
+/// Placement of the widgets drawn between `begin_world` and `end_world`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldAnchor {
+    /// world position of the top-left of the sheet
+    pub position: Vector3f,
+    /// orientation of the sheet when it is not billboarded
+    pub rotation: Quaternion<f32>,
+    /// the sheet always faces the camera
+    pub billboard: bool,
+    pub scale: WorldScale,
+    /// the widgets are hidden by the objects in front of them
+    pub depth_test: bool,
+}
+
+impl WorldAnchor {
+    /// Billboarded anchor at the position of a game object
+    pub fn new(go: &GameObject) -> WorldAnchor {
+        let t = go.transform.global();
+
+        WorldAnchor {
+            rotation: t.rot,
+            ..WorldAnchor::at(t.disp)
+        }
+    }
+
+    /// Billboarded anchor with a constant size, hidden behind the scene
+    pub fn at(position: Vector3f) -> WorldAnchor {
+        WorldAnchor {
+            position,
+            rotation: Quaternion::one(),
+            billboard: true,
+            scale: WorldScale::Constant,
+            depth_test: true,
+        }
+    }
+}
+
+/// Sheet of a widget, the index of its anchor in this frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldSheet {
+    pub index: usize,
+    pub depth_test: bool,
+}
+
+/// Draw the next widgets on a sheet attached to `anchor`
+pub fn begin_world(anchor: WorldAnchor) {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+
+    let index = inner.world_anchors.len();
+    let first_id = inner.id + 1;
+    inner.world_anchors.push((anchor, first_id));
+    inner.state.world = Some(WorldSheet {
+        index,
+        depth_test: anchor.depth_test,
+    });
+}
+
+pub fn end_world() {
+    let imgui = instance::imgui_inst();
+    let mut inner = imgui.inner.lock().unwrap();
+    inner.state.world = None;
+}
+
+/// Move a mesh built in ndc for a screen of `ssize` to the pixels of a sheet,
+/// the top-left of the screen becomes the origin
+pub fn to_sheet_pixels(mesh_data: &mut MeshData, ssize: (u32, u32)) {
+    let (hw, hh) = (ssize.0 as f32 * 0.5, ssize.1 as f32 * 0.5);
+
+    for v in mesh_data.vertices.chunks_mut(3) {
+        v[0] = (v[0] + 1.0) * hw;
+        v[1] = (v[1] - 1.0) * hh;
+    }
+}
+
+/// The widgets of a sheet are sorted with the transparent objects,
+/// they are drawn over the whole scene without the depth test
+pub fn set_sheet_states(material: &mut Material, depth_test: bool) {
+    material.render_queue = RenderQueue::Transparent;
+    material.states.scissor = Some(Scissor::Off);
+    if !depth_test {
+        material.states.depth_test = Some(DepthTest::Always);
+    }
+}
+
+/// Transform of the widget `id` of a sheet, seen by `camera`.
+/// `first_id` is the first widget of the sheet, the next ones are moved toward the camera.
+pub fn sheet_transform(
+    anchor: &WorldAnchor,
+    first_id: u32,
+    id: u32,
+    camera: &Camera,
+    ssize: (u32, u32),
+    hidpi: f32,
+) -> Isometry3<f32> {
+    let rot = if anchor.billboard {
+        // the camera rotation is the inverse of the view one
+        let v = &camera.v;
+        let view_rot = Matrix3::from_cols(v.x.truncate(), v.y.truncate(), v.z.truncate());
+        Quaternion::from(view_rot.transpose())
+    } else {
+        anchor.rotation
+    };
+
+    // the meshes of the widgets are in physical pixels
+    let scale = match anchor.scale {
+        WorldScale::Constant => {
+            let depth = -(camera.v * anchor.position.extend(1.0)).z;
+            let p = camera.perspective(ssize);
+            2.0 * depth.max(camera.znear) / (p.y.y * ssize.1 as f32)
+        }
+        WorldScale::PixelsPerUnit(n) => 1.0 / (n * hidpi),
+    };
+
+    let layer = id.saturating_sub(first_id) as f32 * LAYER_STEP * scale;
+
+    Decomposed {
+        scale,
+        rot,
+        disp: anchor.position + rot.rotate_vector(Vector3::new(0.0, 0.0, layer)),
+    }
+}
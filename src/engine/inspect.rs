@@ -0,0 +1,214 @@
+//! Editable fields of the components, drawn by the scene inspector
+//!
+//! `InspectValue` draws a single named field with imgui widgets,
+//! `Inspect` draws all the fields of a component.
+//! `#[derive(Inspect)]` implements it for the fields marked with `#[inspect]`.
+
+use engine::imgui::{self, Metric};
+use engine::{Camera, DirectionalLight, Light, Material, MaterialParam, PointLight};
+use math::*;
+
+/// width of the field names, in pixels
+const NAME_WIDTH: f32 = 100.0;
+const ROW_HEIGHT: f32 = 18.0;
+const FIELD_WIDTH: f32 = 60.0;
+/// change of a float per pixel dragged
+const DRAG_SPEED: f32 = 0.01;
+
+pub trait InspectValue {
+    /// Draw the value after its name, returns true when it is changed
+    fn inspect_value(&mut self, name: &str) -> bool;
+}
+
+pub trait Inspect {
+    /// Draw the fields, returns true when one of them is changed
+    fn inspect(&mut self) -> bool;
+}
+
+/// Row with the name of a field, `f` draws its widgets from the given position
+/// so the values of all the rows are aligned
+pub fn field_row<F, R>(name: &str, f: F) -> R
+where
+    F: FnOnce(Metric) -> R,
+{
+    imgui::begin_horizontal();
+    imgui::label(Metric::Pixel(0.0, 0.0), name);
+
+    let (w, _) = imgui::text_size(name);
+    let gap = (NAME_WIDTH - w - imgui::style().spacing).max(0.0);
+    let r = f(Metric::Pixel(gap, 0.0));
+
+    imgui::end_horizontal();
+    r
+}
+
+fn drag(pos: Metric, v: &mut f32, speed: f32) -> bool {
+    imgui::drag_value(pos, Metric::Pixel(FIELD_WIDTH, ROW_HEIGHT), v, speed)
+}
+
+/// Drag on a float copy of an integer, one unit per pixel
+fn drag_int(name: &str, v: i64) -> Option<i64> {
+    let mut f = v as f32;
+    field_row(name, |pos| drag(pos, &mut f, 1.0));
+
+    let n = f.round() as i64;
+    if n != v {
+        Some(n)
+    } else {
+        None
+    }
+}
+
+impl InspectValue for f32 {
+    fn inspect_value(&mut self, name: &str) -> bool {
+        field_row(name, |pos| drag(pos, self, DRAG_SPEED))
+    }
+}
+
+impl InspectValue for i32 {
+    fn inspect_value(&mut self, name: &str) -> bool {
+        drag_int(name, *self as i64)
+            .map(|n| *self = n as i32)
+            .is_some()
+    }
+}
+
+impl InspectValue for u32 {
+    fn inspect_value(&mut self, name: &str) -> bool {
+        drag_int(name, *self as i64)
+            .map(|n| *self = n.max(0) as u32)
+            .is_some()
+    }
+}
+
+impl InspectValue for bool {
+    fn inspect_value(&mut self, name: &str) -> bool {
+        field_row(name, |pos| imgui::checkbox(pos, "", self))
+    }
+}
+
+impl InspectValue for String {
+    fn inspect_value(&mut self, name: &str) -> bool {
+        field_row(name, |pos| {
+            imgui::text_input(pos, Metric::Pixel(FIELD_WIDTH * 3.0, ROW_HEIGHT), self)
+        })
+    }
+}
+
+impl InspectValue for Vector2<f32> {
+    fn inspect_value(&mut self, name: &str) -> bool {
+        field_row(name, |pos| {
+            let zero = Metric::Pixel(0.0, 0.0);
+            // all the components are drawn, even when the first one changes
+            drag(pos, &mut self.x, DRAG_SPEED) | drag(zero, &mut self.y, DRAG_SPEED)
+        })
+    }
+}
+
+impl InspectValue for Vector3<f32> {
+    fn inspect_value(&mut self, name: &str) -> bool {
+        field_row(name, |pos| {
+            let zero = Metric::Pixel(0.0, 0.0);
+            drag(pos, &mut self.x, DRAG_SPEED) | drag(zero, &mut self.y, DRAG_SPEED)
+                | drag(zero, &mut self.z, DRAG_SPEED)
+        })
+    }
+}
+
+impl InspectValue for Vector4<f32> {
+    fn inspect_value(&mut self, name: &str) -> bool {
+        field_row(name, |pos| {
+            let zero = Metric::Pixel(0.0, 0.0);
+            drag(pos, &mut self.x, DRAG_SPEED) | drag(zero, &mut self.y, DRAG_SPEED)
+                | drag(zero, &mut self.z, DRAG_SPEED)
+                | drag(zero, &mut self.w, DRAG_SPEED)
+        })
+    }
+}
+
+impl Inspect for Camera {
+    fn inspect(&mut self) -> bool {
+        self.znear.inspect_value("znear") | self.zfar.inspect_value("zfar")
+            | self.enable_frustum_culling.inspect_value("frustum culling")
+    }
+}
+
+impl Inspect for DirectionalLight {
+    fn inspect(&mut self) -> bool {
+        self.direction.inspect_value("direction") | self.ambient.inspect_value("ambient")
+            | self.diffuse.inspect_value("diffuse")
+            | self.specular.inspect_value("specular")
+    }
+}
+
+impl Inspect for PointLight {
+    fn inspect(&mut self) -> bool {
+        self.position.inspect_value("position") | self.ambient.inspect_value("ambient")
+            | self.diffuse.inspect_value("diffuse")
+            | self.specular.inspect_value("specular")
+            | self.constant.inspect_value("constant")
+            | self.linear.inspect_value("linear")
+            | self.quadratic.inspect_value("quadratic")
+    }
+}
+
+impl Inspect for Light {
+    fn inspect(&mut self) -> bool {
+        match *self {
+            Light::Directional(ref mut l) => l.inspect(),
+            Light::Point(ref mut l) => l.inspect(),
+        }
+    }
+}
+
+/// Draw the params of a material, the numbers and booleans can be edited.
+/// Returns true when one of them is changed.
+pub fn inspect_material(material: &Material) -> bool {
+    // the params are copied, so they can be set while they are drawn
+    let mut params: Vec<_> = material
+        .params()
+        .iter()
+        .map(|(name, p)| (name.clone(), p.clone()))
+        .collect();
+    params.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut changed = false;
+
+    for (name, param) in params.into_iter() {
+        let edited = match param {
+            MaterialParam::Float(v) => edit(&name, v),
+            MaterialParam::Int(v) => edit(&name, v),
+            MaterialParam::Bool(v) => edit(&name, v),
+            MaterialParam::Vec2(v) => edit(&name, v),
+            MaterialParam::Vec3(v) => edit(&name, v),
+            MaterialParam::Vec4(v) => edit(&name, v),
+            MaterialParam::Texture(_) => read_only(&name, "texture"),
+            MaterialParam::Matrix4(_) => read_only(&name, "matrix"),
+            MaterialParam::Params(ref p) => read_only(&name, &format!("{} params", p.len())),
+        };
+
+        if let Some(v) = edited {
+            material.set(name, v);
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+fn read_only(name: &str, value: &str) -> Option<MaterialParam> {
+    field_row(name, |pos| imgui::label(pos, value));
+    None
+}
+
+/// Draw a copy of a param, returns the new value when it is changed
+fn edit<T>(name: &str, mut v: T) -> Option<MaterialParam>
+where
+    T: InspectValue + Into<MaterialParam>,
+{
+    if v.inspect_value(name) {
+        Some(v.into())
+    } else {
+        None
+    }
+}
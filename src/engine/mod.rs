@@ -1,8 +1,13 @@
+#[macro_use]
+pub mod profiler;
+
 mod asset;
 mod core;
+mod inspect;
 mod render;
 
 pub mod context;
+pub mod debug;
 pub mod engine;
 pub mod imgui;
 pub mod sound;
@@ -15,6 +20,8 @@ pub use self::core::{Component, ComponentArena, ComponentBased, ComponentEvent,
                      GameObject, IntoComponentPtr, SceneTree};
 pub use self::render::*;
 
+pub use self::inspect::{field_row, inspect_material, Inspect, InspectValue};
+
 pub use self::engine::{ClearOption, IEngine};
 
 pub use self::sound::{SoundHandle, SoundSystem};
@@ -0,0 +1,379 @@
+//! Frame profiler
+//!
+//! `profile_scope!("name")` measures the time until the end of the enclosing block.
+//! Scopes can be nested, each frame keeps the list of its scopes with their depth,
+//! the gpu time of its render passes and the counters of the render state changes.
+//!
+//! The gpu time is measured with `EXT_disjoint_timer_query` on WebGL and
+//! `ARB_timer_query` on desktop, when they are available. The results arrive
+//! a few frames later and are added to the frame which issued the queries.
+//!
+//! The last frames can be exported with `chrome_trace` and opened in `chrome://tracing`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use engine::engine::EngineStats;
+use uni_app::now;
+use webgl::{WebGLQuery, WebGLRenderingContext};
+
+/// Number of frames kept in the history
+const MAX_FRAMES: usize = 120;
+
+/// Measure the time until the end of the enclosing block
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::engine::profiler::ProfileScope::new($name);
+    };
+}
+
+#[derive(Debug, Clone)]
+pub struct ScopeTiming {
+    pub name: &'static str,
+    /// number of enclosing scopes
+    pub depth: usize,
+    /// start time from the beginning of the frame, in milliseconds
+    pub start: f64,
+    /// in milliseconds
+    pub duration: f64,
+}
+
+/// Render state changes during a frame, summed over all the render passes
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FrameCounters {
+    pub passes: u32,
+    pub draw_calls: u32,
+    pub switch_prog: u32,
+    pub switch_tex: u32,
+    pub switch_mesh: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameProfile {
+    pub index: u64,
+    /// start time, in seconds
+    pub start: f64,
+    /// cpu time of the frame, in milliseconds
+    pub duration: f64,
+    /// scopes in the order they were opened
+    pub scopes: Vec<ScopeTiming>,
+    /// gpu time of the render passes in milliseconds, named after the enclosing scope
+    pub gpu: Vec<(&'static str, f64)>,
+    pub counters: FrameCounters,
+}
+
+struct ProfilerRaw {
+    enabled: bool,
+    frame: Option<FrameProfile>,
+    /// indices of the opened scopes in `frame.scopes`
+    stack: Vec<usize>,
+    history: VecDeque<FrameProfile>,
+    next_index: u64,
+}
+
+thread_local!(
+    static PROFILER: RefCell<ProfilerRaw> = RefCell::new(ProfilerRaw {
+        enabled: true,
+        frame: None,
+        stack: Vec::new(),
+        history: VecDeque::new(),
+        next_index: 0,
+    })
+);
+
+fn with_profiler<R, F>(f: F) -> R
+where
+    F: FnOnce(&mut ProfilerRaw) -> R,
+{
+    PROFILER.with(|p| f(&mut p.borrow_mut()))
+}
+
+/// Milliseconds since the start of the current frame
+fn frame_time(frame: &FrameProfile) -> f64 {
+    (now() - frame.start) * 1000.0
+}
+
+/// Guard created by `profile_scope!`, the scope ends when it is dropped
+pub struct ProfileScope {
+    index: Option<usize>,
+}
+
+impl ProfileScope {
+    pub fn new(name: &'static str) -> ProfileScope {
+        let index = with_profiler(|p| {
+            let depth = p.stack.len();
+            let frame = p.frame.as_mut()?;
+            let start = frame_time(frame);
+
+            frame.scopes.push(ScopeTiming {
+                name,
+                depth,
+                start,
+                duration: 0.0,
+            });
+
+            let index = frame.scopes.len() - 1;
+            p.stack.push(index);
+            Some(index)
+        });
+
+        ProfileScope { index }
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        let index = match self.index {
+            Some(index) => index,
+            None => return,
+        };
+
+        with_profiler(|p| {
+            // the frame could have ended before this scope
+            if let Some(pos) = p.stack.iter().rposition(|&i| i == index) {
+                p.stack.truncate(pos);
+            }
+
+            if let Some(ref mut frame) = p.frame {
+                let end = frame_time(frame);
+                if let Some(scope) = frame.scopes.get_mut(index) {
+                    scope.duration = end - scope.start;
+                }
+            }
+        })
+    }
+}
+
+pub fn set_enabled(enabled: bool) {
+    with_profiler(|p| p.enabled = enabled);
+}
+
+pub fn is_enabled() -> bool {
+    with_profiler(|p| p.enabled)
+}
+
+/// Start recording a frame, called by the world
+pub fn begin_frame() {
+    with_profiler(|p| {
+        p.stack.clear();
+        p.frame = None;
+
+        if !p.enabled {
+            return;
+        }
+
+        p.frame = Some(FrameProfile {
+            index: p.next_index,
+            start: now(),
+            duration: 0.0,
+            scopes: Vec::new(),
+            gpu: Vec::new(),
+            counters: FrameCounters::default(),
+        });
+        p.next_index += 1;
+    })
+}
+
+/// Stop recording the current frame and add it to the history
+pub fn end_frame() {
+    with_profiler(|p| {
+        p.stack.clear();
+
+        if let Some(mut frame) = p.frame.take() {
+            frame.duration = frame_time(&frame);
+
+            p.history.push_back(frame);
+            while p.history.len() > MAX_FRAMES {
+                p.history.pop_front();
+            }
+        }
+    })
+}
+
+/// Index of the frame being recorded
+pub fn current_frame_index() -> Option<u64> {
+    with_profiler(|p| p.frame.as_ref().map(|f| f.index))
+}
+
+/// Name of the innermost opened scope
+pub fn current_scope() -> Option<&'static str> {
+    with_profiler(|p| {
+        let frame = p.frame.as_ref()?;
+        p.stack.last().map(|&i| frame.scopes[i].name)
+    })
+}
+
+/// Add the counters of a render pass to the current frame
+pub fn add_pass_stats(stats: &EngineStats) {
+    with_profiler(|p| {
+        if let Some(ref mut frame) = p.frame {
+            let c = &mut frame.counters;
+            c.passes += 1;
+            c.draw_calls += stats.draw_calls;
+            c.switch_prog += stats.switch_prog;
+            c.switch_tex += stats.switch_tex;
+            c.switch_mesh += stats.switch_mesh;
+        }
+    })
+}
+
+fn add_gpu_time(index: u64, name: &'static str, ms: f64) {
+    with_profiler(|p| {
+        let frame = p.frame
+            .as_mut()
+            .into_iter()
+            .chain(p.history.iter_mut())
+            .find(|f| f.index == index);
+
+        if let Some(frame) = frame {
+            frame.gpu.push((name, ms));
+        }
+    })
+}
+
+/// Frames of the history, the oldest first
+pub fn frames() -> Vec<FrameProfile> {
+    with_profiler(|p| p.history.iter().cloned().collect())
+}
+
+/// Last complete frame
+pub fn last_frame() -> Option<FrameProfile> {
+    with_profiler(|p| p.history.back().cloned())
+}
+
+fn escape_json(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Frames in the Chrome trace event format.
+/// The cpu scopes are on the thread 1, the gpu passes on the thread 2,
+/// one after the other since only their duration is known.
+pub fn chrome_trace(frames: &[FrameProfile]) -> String {
+    let mut events: Vec<String> = Vec::new();
+
+    for frame in frames.iter() {
+        let frame_us = frame.start * 1_000_000.0;
+
+        events.push(format!(
+            r#"{{"name":"frame {}","cat":"frame","ph":"X","ts":{:.3},"dur":{:.3},"pid":1,"tid":1}}"#,
+            frame.index,
+            frame_us,
+            frame.duration * 1000.0
+        ));
+
+        for scope in frame.scopes.iter() {
+            events.push(format!(
+                r#"{{"name":"{}","cat":"cpu","ph":"X","ts":{:.3},"dur":{:.3},"pid":1,"tid":1}}"#,
+                escape_json(scope.name),
+                frame_us + scope.start * 1000.0,
+                scope.duration * 1000.0
+            ));
+        }
+
+        let mut gpu_us = frame_us;
+        for &(name, ms) in frame.gpu.iter() {
+            events.push(format!(
+                r#"{{"name":"{}","cat":"gpu","ph":"X","ts":{:.3},"dur":{:.3},"pid":1,"tid":2}}"#,
+                escape_json(name),
+                gpu_us,
+                ms * 1000.0
+            ));
+            gpu_us += ms * 1000.0;
+        }
+
+        let c = &frame.counters;
+        events.push(format!(
+            r#"{{"name":"render","ph":"C","ts":{:.3},"pid":1,"args":{{"passes":{},"draw_calls":{},"switch_prog":{},"switch_tex":{},"switch_mesh":{}}}}}"#,
+            frame_us, c.passes, c.draw_calls, c.switch_prog, c.switch_tex, c.switch_mesh
+        ));
+    }
+
+    let mut json = String::from("{\"traceEvents\":[\n");
+    for (i, e) in events.iter().enumerate() {
+        let sep = if i + 1 < events.len() { ",\n" } else { "\n" };
+        write!(json, "{}{}", e, sep).unwrap();
+    }
+    json.push_str("]}\n");
+
+    json
+}
+
+/// Time queries of the render passes, read back when their results are available
+#[derive(Default)]
+pub struct GpuTimer {
+    supported: Option<bool>,
+    /// (frame index, scope name, query) in the order they were issued
+    pending: VecDeque<(u64, &'static str, WebGLQuery)>,
+    free: Vec<WebGLQuery>,
+    /// a query is running, they can't be nested
+    running: bool,
+}
+
+impl GpuTimer {
+    fn is_supported(&mut self, gl: &WebGLRenderingContext) -> bool {
+        if self.supported.is_none() {
+            self.supported = Some(gl.timer_query_supported());
+        }
+
+        self.supported.unwrap()
+    }
+
+    /// Start measuring a render pass, named after the current scope
+    pub fn begin(&mut self, gl: &WebGLRenderingContext) {
+        if self.running || !self.is_supported(gl) {
+            return;
+        }
+
+        let index = match current_frame_index() {
+            Some(index) => index,
+            None => return,
+        };
+
+        let query = self.free.pop().unwrap_or_else(|| gl.create_query());
+        gl.begin_time_query(&query);
+
+        let name = current_scope().unwrap_or("render");
+        self.pending.push_back((index, name, query));
+        self.running = true;
+    }
+
+    pub fn end(&mut self, gl: &WebGLRenderingContext) {
+        if self.running {
+            gl.end_time_query();
+            self.running = false;
+        }
+    }
+
+    /// Add the available results to their frames
+    pub fn poll(&mut self, gl: &WebGLRenderingContext) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        // the results of all the queries in flight are invalid
+        let disjoint = gl.gpu_disjoint();
+
+        loop {
+            let ready = match self.pending.front() {
+                Some(&(_, _, ref query)) if !self.running || self.pending.len() > 1 => {
+                    disjoint || gl.query_result_available(query)
+                }
+                _ => false,
+            };
+
+            if !ready {
+                break;
+            }
+
+            let (index, name, query) = self.pending.pop_front().unwrap();
+            if !disjoint {
+                let ns = gl.query_result(&query);
+                add_gpu_time(index, name, ns as f64 / 1_000_000.0);
+            }
+
+            self.free.push(query);
+        }
+    }
+}
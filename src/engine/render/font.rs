@@ -0,0 +1,414 @@
+//! TrueType / OpenType fonts
+//!
+//! Glyphs are rasterized on demand and packed into a dynamic atlas texture
+//! shared by all the sizes of a font.
+//! In SDF mode, each glyph is rendered once as a signed distance field and
+//! the same atlas is used for every size.
+
+use rusttype;
+use rusttype::{point, GlyphId, Scale};
+
+use engine::asset::{Asset, AssetResult, AssetState, AssetSystem, FileFuture, LoadableAsset,
+                    Resource};
+use engine::render::Texture;
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+const ATLAS_SIZE: u32 = 1024;
+/// Empty pixels around each glyph in the atlas to avoid bleeding
+const GLYPH_PADDING: u32 = 1;
+
+/// Size in pixels of the glyphs rendered in SDF mode
+const SDF_BASE_SIZE: f32 = 48.0;
+/// Distance in pixels covered by the field on each side of the outline
+const SDF_SPREAD: f32 = 6.0;
+
+pub struct FontFace {
+    font: rusttype::Font<'static>,
+}
+
+impl FontFace {
+    pub fn new(font: rusttype::Font<'static>) -> FontFace {
+        FontFace { font }
+    }
+}
+
+impl fmt::Debug for FontFace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FontFace")
+    }
+}
+
+/// Vertical metrics in pixels, descent is negative
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FontMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+}
+
+impl FontMetrics {
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+}
+
+/// Quad of a glyph, positions are in pixels relative to the start of the
+/// baseline with y going down, uvs are in the atlas texture.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlyphQuad {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+    pub uv_min: (f32, f32),
+    pub uv_max: (f32, f32),
+}
+
+/// A single line of text laid out
+#[derive(Debug, Default)]
+pub struct TextLine {
+    pub quads: Vec<GlyphQuad>,
+    pub width: f32,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct AtlasGlyph {
+    /// position of the top-left of the bitmap from the pen position
+    offset: (f32, f32),
+    size: (u32, u32),
+    pos: (u32, u32),
+}
+
+/// Packs the glyphs in rows, a new row starts when the current one is full
+#[derive(Debug, Default)]
+struct AtlasPacker {
+    x: u32,
+    y: u32,
+    row_height: u32,
+}
+
+impl AtlasPacker {
+    fn alloc(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        if self.x + w > ATLAS_SIZE {
+            self.x = 0;
+            self.y += self.row_height;
+            self.row_height = 0;
+        }
+
+        if self.x + w > ATLAS_SIZE || self.y + h > ATLAS_SIZE {
+            return None;
+        }
+
+        let pos = (self.x, self.y);
+        self.x += w;
+        self.row_height = self.row_height.max(h);
+
+        Some(pos)
+    }
+}
+
+#[derive(Debug)]
+struct GlyphAtlas {
+    texture: Rc<Texture>,
+    /// glyphs by id and rasterized size, None for glyphs without outline
+    glyphs: HashMap<(GlyphId, u32), Option<AtlasGlyph>>,
+    packer: AtlasPacker,
+    generation: u32,
+}
+
+impl GlyphAtlas {
+    fn new() -> GlyphAtlas {
+        GlyphAtlas {
+            texture: Texture::new_dynamic(ATLAS_SIZE, ATLAS_SIZE),
+            glyphs: HashMap::new(),
+            packer: AtlasPacker::default(),
+            generation: 0,
+        }
+    }
+
+    /// Remove all glyphs, the text using them must be laid out again
+    fn clear(&mut self) {
+        self.glyphs.clear();
+        self.packer = AtlasPacker::default();
+        self.generation += 1;
+        self.texture
+            .update_region((0, 0), ImageBuffer::new(ATLAS_SIZE, ATLAS_SIZE));
+    }
+
+    fn glyph(
+        &mut self,
+        font: &rusttype::Font<'static>,
+        id: GlyphId,
+        raster_size: f32,
+        sdf: bool,
+    ) -> Option<AtlasGlyph> {
+        let key = (id, raster_size as u32);
+        if let Some(g) = self.glyphs.get(&key) {
+            return *g;
+        }
+
+        let g = rasterize(font, id, raster_size, sdf).and_then(|(img, offset)| {
+            let (w, h) = (img.width(), img.height());
+
+            // start over when the atlas is full
+            let pos = self.packer.alloc(w, h).or_else(|| {
+                self.clear();
+                self.packer.alloc(w, h)
+            })?;
+
+            self.texture.update_region(pos, img);
+
+            Some(AtlasGlyph {
+                offset,
+                size: (w, h),
+                pos,
+            })
+        });
+
+        self.glyphs.insert(key, g);
+        g
+    }
+}
+
+/// Returns the glyph image and the offset of its top-left from the pen position
+fn rasterize(
+    font: &rusttype::Font<'static>,
+    id: GlyphId,
+    size: f32,
+    sdf: bool,
+) -> Option<(RgbaImage, (f32, f32))> {
+    let glyph = font.glyph(id)
+        .scaled(Scale::uniform(size))
+        .positioned(point(0.0, 0.0));
+
+    let bb = glyph.pixel_bounding_box()?;
+    let (w, h) = (bb.width() as u32, bb.height() as u32);
+
+    let mut coverage = vec![0.0; (w * h) as usize];
+    glyph.draw(|x, y, v| coverage[(x + y * w) as usize] = v);
+
+    let pad = if sdf {
+        GLYPH_PADDING + SDF_SPREAD.ceil() as u32
+    } else {
+        GLYPH_PADDING
+    };
+
+    let img = if sdf {
+        make_distance_field(&coverage, (w, h), pad)
+    } else {
+        ImageBuffer::from_fn(w + pad * 2, h + pad * 2, |x, y| {
+            let (x, y) = (x as i32 - pad as i32, y as i32 - pad as i32);
+            let v = if x >= 0 && y >= 0 && x < w as i32 && y < h as i32 {
+                coverage[(x as u32 + y as u32 * w) as usize]
+            } else {
+                0.0
+            };
+
+            Rgba([0xff, 0xff, 0xff, (v.max(0.0).min(1.0) * 255.0) as u8])
+        })
+    };
+
+    let offset = (
+        (bb.min.x - pad as i32) as f32,
+        (bb.min.y - pad as i32) as f32,
+    );
+
+    Some((img, offset))
+}
+
+/// Store the signed distance to the outline in the alpha channel,
+/// 0.5 is on the outline and bigger values are inside the glyph.
+fn make_distance_field(coverage: &[f32], size: (u32, u32), pad: u32) -> RgbaImage {
+    let (w, h) = (size.0 as i32, size.1 as i32);
+    let inside = |x: i32, y: i32| {
+        x >= 0 && y >= 0 && x < w && y < h && coverage[(x + y * w) as usize] >= 0.5
+    };
+
+    let r = SDF_SPREAD.ceil() as i32;
+
+    ImageBuffer::from_fn(size.0 + pad * 2, size.1 + pad * 2, |ox, oy| {
+        let (x, y) = (ox as i32 - pad as i32, oy as i32 - pad as i32);
+        let is_inside = inside(x, y);
+
+        // brute force search of the nearest pixel on the other side
+        let mut nearest = (SDF_SPREAD + 0.5) * (SDF_SPREAD + 0.5);
+        for dy in -r..r + 1 {
+            for dx in -r..r + 1 {
+                if inside(x + dx, y + dy) != is_inside {
+                    nearest = nearest.min((dx * dx + dy * dy) as f32);
+                }
+            }
+        }
+
+        // the outline is between the two pixels
+        let d = nearest.sqrt() - 0.5;
+        let d = if is_inside { d } else { -d };
+        let v = 0.5 + d / (2.0 * SDF_SPREAD);
+
+        Rgba([0xff, 0xff, 0xff, (v.max(0.0).min(1.0) * 255.0) as u8])
+    })
+}
+
+#[derive(Debug)]
+pub struct Font {
+    face: Resource<FontFace>,
+    atlas: RefCell<GlyphAtlas>,
+    sdf: Cell<bool>,
+}
+
+impl Asset for Font {
+    type Resource = Resource<FontFace>;
+
+    fn new_from_resource(r: Self::Resource) -> Rc<Self> {
+        Rc::new(Font {
+            face: r,
+            atlas: RefCell::new(GlyphAtlas::new()),
+            sdf: Cell::new(false),
+        })
+    }
+}
+
+impl LoadableAsset for Font {
+    fn load<T: AssetSystem + Clone + 'static>(
+        asys: &T,
+        mut files: Vec<FileFuture>,
+    ) -> Self::Resource {
+        Self::load_resource::<FontFace, T>(asys.clone(), files.remove(0))
+    }
+
+    fn gather<T: AssetSystem>(asys: &T, fname: &str) -> Vec<FileFuture> {
+        vec![asys.new_file(fname)]
+    }
+}
+
+impl Font {
+    pub fn state(&self) -> AssetState {
+        self.face.state()
+    }
+
+    pub fn is_sdf(&self) -> bool {
+        self.sdf.get()
+    }
+
+    /// Render the glyphs as signed distance fields,
+    /// they must be drawn with the `default_ui_sdf` program.
+    pub fn set_sdf(&self, sdf: bool) {
+        if self.sdf.get() != sdf {
+            self.sdf.set(sdf);
+            self.atlas.borrow_mut().clear();
+        }
+    }
+
+    /// Edge smoothing used by the `default_ui_sdf` program for text of `size` pixels
+    pub fn sdf_smoothing(size: f32) -> f32 {
+        (SDF_BASE_SIZE / size.max(1.0)) / (4.0 * SDF_SPREAD)
+    }
+
+    pub fn texture(&self) -> Rc<Texture> {
+        self.atlas.borrow().texture.clone()
+    }
+
+    /// Changed each time the atlas is cleared,
+    /// text laid out with another generation must be laid out again.
+    pub fn generation(&self) -> u32 {
+        self.atlas.borrow().generation
+    }
+
+    pub fn metrics(&self, size: f32) -> AssetResult<FontMetrics> {
+        let face = self.face.try_borrow()?;
+        let m = face.font.v_metrics(Scale::uniform(size));
+
+        Ok(FontMetrics {
+            ascent: m.ascent,
+            descent: m.descent,
+            line_gap: m.line_gap,
+        })
+    }
+
+    /// Width in pixels of a single line of text
+    pub fn measure(&self, text: &str, size: f32) -> AssetResult<f32> {
+        let face = self.face.try_borrow()?;
+        let mut width = 0.0;
+
+        for_each_glyph(&face.font, text, size, |_, x, advance| width = x + advance);
+
+        Ok(width)
+    }
+
+    /// Layout a single line of text of `size` pixels, the glyphs are added to the atlas
+    pub fn layout_line(&self, text: &str, size: f32) -> AssetResult<TextLine> {
+        let face = self.face.try_borrow()?;
+        let sdf = self.sdf.get();
+        let raster_size = if sdf {
+            SDF_BASE_SIZE
+        } else {
+            size.round().max(1.0)
+        };
+        let scale = size / raster_size;
+        let inv_atlas = 1.0 / ATLAS_SIZE as f32;
+
+        // The atlas could be cleared in the middle of the line,
+        // layout it again once in that case.
+        let mut retried = false;
+        loop {
+            let mut atlas = self.atlas.borrow_mut();
+            let generation = atlas.generation;
+            let mut line = TextLine::default();
+
+            for_each_glyph(&face.font, text, size, |id, x, advance| {
+                if let Some(g) = atlas.glyph(&face.font, id, raster_size, sdf) {
+                    let min = (x + g.offset.0 * scale, g.offset.1 * scale);
+
+                    line.quads.push(GlyphQuad {
+                        min,
+                        max: (
+                            min.0 + g.size.0 as f32 * scale,
+                            min.1 + g.size.1 as f32 * scale,
+                        ),
+                        uv_min: (g.pos.0 as f32 * inv_atlas, g.pos.1 as f32 * inv_atlas),
+                        uv_max: (
+                            (g.pos.0 + g.size.0) as f32 * inv_atlas,
+                            (g.pos.1 + g.size.1) as f32 * inv_atlas,
+                        ),
+                    });
+                }
+
+                line.width = x + advance;
+            });
+
+            if atlas.generation == generation || retried {
+                return Ok(line);
+            }
+            retried = true;
+        }
+    }
+}
+
+/// Call `f(glyph, pen_x, advance)` for each char of the line, with kerning
+fn for_each_glyph<F>(font: &rusttype::Font<'static>, text: &str, size: f32, mut f: F)
+where
+    F: FnMut(GlyphId, f32, f32),
+{
+    let scale = Scale::uniform(size);
+    let mut x = 0.0;
+    let mut last = None;
+
+    for c in text.chars() {
+        let glyph = font.glyph(c).scaled(scale);
+        let id = glyph.id();
+
+        if let Some(last) = last {
+            x += font.pair_kerning(scale, last, id);
+        }
+
+        let advance = glyph.h_metrics().advance_width;
+        f(id, x, advance);
+
+        x += advance;
+        last = Some(id);
+    }
+}
@@ -0,0 +1,154 @@
+//! Levels of detail
+//!
+//! A `LodGroup` holds the meshes of an object, from the most to the least detailed.
+//! Each camera picks a level from the size on its screen of the bounding sphere of
+//! the first level, so the shadow pass and the main pass choose them independently.
+//!
+//! Near a switch, both levels can be drawn with complementary dithering patterns,
+//! the fragment shaders discard their pixels with `uLodFade` (see `unrust/lod_fade.glsl`).
+
+use engine::render::mesh::MeshBound;
+use engine::render::Mesh;
+
+/// A mesh drawn while the object covers at least `screen_size` of the screen height
+#[derive(Clone)]
+pub struct LodLevel {
+    pub mesh: Mesh,
+    pub screen_size: f32,
+}
+
+#[derive(Component, Clone)]
+pub struct LodGroup {
+    /// by decreasing `screen_size`, the object is not drawn below the last one
+    pub levels: Vec<LodLevel>,
+
+    /// Width of the cross-fade above each switch, relative to its `screen_size`,
+    /// `0.0` switches at once
+    pub fade_width: f32,
+}
+
+impl LodGroup {
+    pub fn new() -> LodGroup {
+        LodGroup {
+            levels: Vec::new(),
+            fade_width: 0.0,
+        }
+    }
+
+    /// Add a level, the levels are kept sorted by their screen size
+    pub fn add_level(&mut self, mesh: Mesh, screen_size: f32) {
+        self.levels.push(LodLevel { mesh, screen_size });
+        self.levels
+            .sort_by(|a, b| b.screen_size.partial_cmp(&a.screen_size).unwrap());
+    }
+
+    /// Fraction of the screen height covered by a sphere of radius `r` at `distance`,
+    /// `proj_scale` is the vertical scale of the projection matrix
+    pub fn screen_coverage(r: f32, distance: f32, proj_scale: f32) -> f32 {
+        r * proj_scale / distance.max(0.0001)
+    }
+
+    /// Bounds of the most detailed level
+    pub fn bounds(&self) -> Option<MeshBound> {
+        self.levels.first().and_then(|l| l.mesh.bounds())
+    }
+
+    /// The levels to draw when the object covers `coverage` of the screen height,
+    /// with their `uLodFade`. A positive fade discards this part of the pixels,
+    /// a negative one keeps only them.
+    pub fn select(&self, coverage: f32) -> Vec<(&Mesh, f32)> {
+        let i = match self.levels
+            .iter()
+            .position(|l| coverage >= l.screen_size)
+        {
+            Some(i) => i,
+            None => return Vec::new(),
+        };
+
+        let level = &self.levels[i];
+        let fade_end = level.screen_size * (1.0 + self.fade_width);
+
+        // the fade starts at the switch from the next level to this one
+        let next = match self.levels.get(i + 1) {
+            Some(next) if coverage < fade_end => next,
+            _ => return vec![(&level.mesh, 0.0)],
+        };
+
+        // from 0.0 at the switch to 1.0 at the end of the fade
+        let t = (coverage - level.screen_size) / (fade_end - level.screen_size);
+        let fade = 1.0 - t;
+
+        vec![(&level.mesh, fade), (&next.mesh, -fade)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group() -> LodGroup {
+        let mut lod = LodGroup::new();
+        lod.add_level(Mesh::new(), 0.5);
+        lod.add_level(Mesh::new(), 0.2);
+        lod.add_level(Mesh::new(), 0.05);
+        lod.fade_width = 0.2;
+        lod
+    }
+
+    /// The levels drawn, by index, with their fade
+    fn select(lod: &LodGroup, coverage: f32) -> Vec<(usize, f32)> {
+        lod.select(coverage)
+            .into_iter()
+            .map(|(mesh, fade)| {
+                let i = lod.levels
+                    .iter()
+                    .position(|l| ::std::ptr::eq(&l.mesh, mesh))
+                    .unwrap();
+                (i, fade)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn select_without_fade() {
+        let mut lod = group();
+        lod.fade_width = 0.0;
+
+        assert_eq!(select(&lod, 1.0), vec![(0, 0.0)]);
+        assert_eq!(select(&lod, 0.5), vec![(0, 0.0)]);
+        assert_eq!(select(&lod, 0.49), vec![(1, 0.0)]);
+        assert_eq!(select(&lod, 0.2), vec![(1, 0.0)]);
+        assert_eq!(select(&lod, 0.1), vec![(2, 0.0)]);
+        assert_eq!(select(&lod, 0.01), vec![]);
+    }
+
+    #[test]
+    fn select_fades_above_each_switch() {
+        let lod = group();
+
+        // above the fade of the switch at 0.5
+        assert_eq!(select(&lod, 0.7), vec![(0, 0.0)]);
+
+        // just below the switch, only the next level is drawn
+        assert_eq!(select(&lod, 0.45), vec![(1, 0.0)]);
+
+        // the fade goes from the next level at the switch to this one at its end
+        let fades = select(&lod, 0.5);
+        assert_eq!(fades, vec![(0, 1.0), (1, -1.0)]);
+
+        let fades = select(&lod, 0.55);
+        assert_eq!((fades[0].0, fades[1].0), (0, 1));
+        assert!((fades[0].1 - 0.5).abs() < 1e-4);
+        assert_eq!(fades[1].1, -fades[0].1);
+
+        // the switch at 0.2 fades between the levels 1 and 2
+        let fades = select(&lod, 0.21);
+        assert_eq!((fades[0].0, fades[1].0), (1, 2));
+        assert!((fades[0].1 - 0.75).abs() < 1e-4);
+        assert_eq!(select(&lod, 0.19), vec![(2, 0.0)]);
+
+        // the last level is culled at once
+        assert_eq!(select(&lod, 0.055), vec![(2, 0.0)]);
+        assert_eq!(select(&lod, 0.045), vec![]);
+    }
+}
@@ -4,7 +4,7 @@ use engine::render::{RenderQueue, ShaderProgram, Texture};
 use fnv::FnvHashMap;
 use math::*;
 use std::borrow::Cow;
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
 use std::rc::Rc;
 
 #[derive(Debug, Clone)]
@@ -83,12 +83,20 @@ impl Default for DepthTest {
     }
 }
 
+/// Scissor test, the rect is in pixels from the bottom-left of the screen
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Scissor {
+    Off,
+    Rect(i32, i32, u32, u32),
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
 pub struct MaterialState {
     pub cull: Option<CullMode>,
     pub alpha_blending: Option<bool>,
     pub depth_write: Option<bool>,
     pub depth_test: Option<DepthTest>,
+    pub scissor: Option<Scissor>,
 }
 
 #[derive(Debug)]
@@ -126,6 +134,10 @@ impl Material {
         self.params.borrow_mut().insert(name.into(), t.into());
     }
 
+    pub fn params(&self) -> Ref<MaterialParamMap> {
+        self.params.borrow()
+    }
+
     fn bind_params<F>(
         &self,
         params: &MaterialParamMap,
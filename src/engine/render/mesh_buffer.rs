@@ -2,7 +2,8 @@ use std::mem::size_of;
 use webgl::*;
 
 use super::ShaderProgram;
-use engine::asset::{Asset, AssetResult, AssetSystem, FileFuture, LoadableAsset, Resource};
+use engine::asset::{Asset, AssetError, AssetResult, AssetState, AssetSystem, FileFuture,
+                    LoadableAsset, ReloadableAsset, Resource};
 use engine::core::Aabb;
 use engine::render::mesh::MeshBound;
 use engine::render::shader_program::ShaderAttrib;
@@ -11,6 +12,7 @@ use math::*;
 use std::cell::Cell;
 use std::cell::RefCell;
 use std::f32::{MAX, MIN};
+use std::ops::Range;
 use std::rc::Rc;
 use std::rc::Weak;
 
@@ -28,95 +30,352 @@ impl<T> IntoBytes for Vec<T> {
     }
 }
 
-enum RebindAction {
-    Vertices,
-    UV,
-    Normal,
-    Tangent,
-    Bitangent,
-    Indices,
+/// Where a vertex attribute is bound in the shader
+#[derive(Debug, Clone, PartialEq)]
+enum AttribLocation {
+    Fixed(u32),
+    Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct LayoutAttrib {
+    location: AttribLocation,
+    /// number of floats per vertex
+    size: usize,
+    /// offset in bytes in a vertex
+    offset: usize,
+}
+
+/// Interleaved layout of the vertices of a mesh, all attributes are floats
+#[derive(Debug, Clone, PartialEq)]
+struct VertexLayout {
+    attribs: Vec<LayoutAttrib>,
+    /// size in bytes of a vertex
+    stride: usize,
+}
+
+impl VertexLayout {
+    fn new(data: &MeshData) -> VertexLayout {
+        let mut attribs = Vec::new();
+        let mut offset = 0;
+
+        for (location, size, _) in data.attributes() {
+            attribs.push(LayoutAttrib {
+                location,
+                size,
+                offset,
+            });
+            offset += size * size_of::<f32>();
+        }
+
+        VertexLayout {
+            attribs,
+            stride: offset,
+        }
+    }
+
+    fn has_named(&self) -> bool {
+        self.attribs.iter().any(|a| match a.location {
+            AttribLocation::Named(_) => true,
+            _ => false,
+        })
+    }
+}
+
+/// Interleave all the attributes of the vertices in `range`
+fn interleave(data: &MeshData, range: Range<usize>) -> Vec<f32> {
+    let attrs = data.attributes();
+    let floats: usize = attrs.iter().map(|&(_, size, _)| size).sum();
+
+    let mut buf = Vec::with_capacity(range.len() * floats);
+    for i in range {
+        for &(_, size, values) in attrs.iter() {
+            let v = &values[i * size..(i + 1) * size];
+            buf.extend_from_slice(v);
+        }
+    }
+
+    buf
+}
+
+fn index_bytes(data: &MeshData, gl: &WebGLRenderingContext) -> AssetResult<(DataType, Vec<u8>)> {
+    let max_index = data.indices.iter().cloned().max().unwrap_or(0);
+
+    if max_index <= u16::max_value() as u32 {
+        let indices: Vec<u16> = data.indices.iter().map(|i| *i as u16).collect();
+        Ok((DataType::U16, indices.into_bytes()))
+    } else if gl.element_index_uint_supported() {
+        Ok((DataType::U32, data.indices.clone().into_bytes()))
+    } else {
+        Err(AssetError::InvalidFormat {
+            path: "<mesh>".into(),
+            len: data.indices.len(),
+            reason: "32 bits indices are not supported (OES_element_index_uint)".into(),
+        })
+    }
+}
+
+fn attribute_size(size: usize) -> AttributeSize {
+    match size {
+        1 => AttributeSize::One,
+        2 => AttributeSize::Two,
+        3 => AttributeSize::Three,
+        _ => AttributeSize::Four,
+    }
+}
+
+/// Number of segments of the ring buffer of a streamed mesh
+const STREAM_SEGMENTS: usize = 3;
+
+/// Smallest segment of a streamed mesh, in bytes
+const MIN_STREAM_SEGMENT: usize = 1024;
+
+/// How often the data of a mesh buffer changes
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BufferUsage {
+    /// uploaded once, e.g. the loaded meshes
+    Static,
+    /// updated from time to time, in place and possibly partially
+    Dynamic,
+    /// rebuilt every frame, e.g. particles or debug lines. Each update is written to
+    /// the next segment of a ring buffer, so the gpu may still draw the previous ones
+    Stream,
+}
+
+impl Default for BufferUsage {
+    fn default() -> BufferUsage {
+        BufferUsage::Static
+    }
+}
+
+impl BufferUsage {
+    fn as_draw_mode(&self) -> DrawMode {
+        match *self {
+            BufferUsage::Static => DrawMode::Static,
+            BufferUsage::Dynamic => DrawMode::Dynamic,
+            BufferUsage::Stream => DrawMode::Stream,
+        }
+    }
+}
+
+/// Part of the mesh data to upload before the next draw
+#[derive(Debug, Clone, PartialEq)]
+enum Dirty {
+    Clean,
+    All,
+    /// a range of vertices, the indices are unchanged
+    Vertices(Range<usize>),
+}
+
+impl Dirty {
+    fn merge(&mut self, range: Range<usize>) {
+        *self = match *self {
+            Dirty::Clean => Dirty::Vertices(range),
+            Dirty::All => Dirty::All,
+            Dirty::Vertices(ref r) => Dirty::Vertices(r.start.min(range.start)..r.end.max(range.end)),
+        };
+    }
 }
 
 struct MeshGLState {
     pub vao: WebGLVertexArray,
     pub vb: WebGLBuffer,
-    pub uvb: Option<WebGLBuffer>,
+    pub ib: WebGLBuffer,
 
-    pub nb: Option<WebGLBuffer>,
-    pub tb: Option<WebGLBuffer>,
-    pub btb: Option<WebGLBuffer>,
+    layout: VertexLayout,
+    usage: BufferUsage,
+    index_type: DataType,
+    dirty: Dirty,
 
-    pub ib: WebGLBuffer,
-    pub gl: WebGLRenderingContext,
+    /// allocated bytes of the vertex and index buffers, for a segment when streamed
+    vb_size: usize,
+    ib_size: usize,
 
-    pub rebind_actions: Vec<RebindAction>,
+    /// current segment of a streamed mesh
+    segment: usize,
+
+    pub gl: WebGLRenderingContext,
 }
 
 impl MeshGLState {
-    fn rebind_buffer(
-        &mut self,
+    fn new(
         data: &MeshData,
-        tt: &RebindAction,
-    ) -> (BufferKind, Vec<u8>, &mut WebGLBuffer) {
-        match *tt {
-            RebindAction::Vertices => (
-                BufferKind::Array,
-                data.vertices.clone().into_bytes(),
-                &mut self.vb,
-            ),
-            RebindAction::UV => (
-                BufferKind::Array,
-                data.uvs.clone().unwrap().into_bytes(),
-                self.uvb.as_mut().unwrap(),
-            ),
-            RebindAction::Normal => (
-                BufferKind::Array,
-                data.normals.clone().unwrap().into_bytes(),
-                self.nb.as_mut().unwrap(),
-            ),
-            RebindAction::Tangent => (
-                BufferKind::Array,
-                data.tangents.clone().unwrap().into_bytes(),
-                self.tb.as_mut().unwrap(),
-            ),
-            RebindAction::Bitangent => (
-                BufferKind::Array,
-                data.bitangents.clone().unwrap().into_bytes(),
-                self.btb.as_mut().unwrap(),
-            ),
-            RebindAction::Indices => (
-                BufferKind::ElementArray,
-                data.indices.clone().into_bytes(),
-                &mut self.ib,
-            ),
+        usage: BufferUsage,
+        gl: &WebGLRenderingContext,
+    ) -> AssetResult<MeshGLState> {
+        // some opengl 3.x core profile require a VAO. See issue #11
+        let vao = gl.create_vertex_array();
+        gl.bind_vertex_array(&vao);
+
+        let mut state = MeshGLState {
+            vao,
+            vb: gl.create_buffer(),
+            ib: gl.create_buffer(),
+            layout: VertexLayout::new(data),
+            usage,
+            index_type: DataType::U16,
+            dirty: Dirty::All,
+            vb_size: 0,
+            ib_size: 0,
+            segment: STREAM_SEGMENTS - 1,
+            gl: gl.clone(),
+        };
+
+        state.upload(data, gl)?;
+        Ok(state)
+    }
+
+    /// Offset in bytes of the vertices of the current segment
+    fn vertex_offset(&self) -> usize {
+        match self.usage {
+            BufferUsage::Stream => self.segment * self.vb_size,
+            _ => 0,
         }
     }
 
-    fn rebind(&mut self, actions: &Vec<RebindAction>, data: &MeshData, gl: &WebGLRenderingContext) {
-        for action in actions.iter() {
-            let (k, p, buf) = self.rebind_buffer(data, action);
+    /// Offset in bytes of the indices of the current segment
+    fn index_offset(&self) -> usize {
+        match self.usage {
+            BufferUsage::Stream => self.segment * self.ib_size,
+            _ => 0,
+        }
+    }
+
+    /// Upload the dirty part of the data
+    fn upload(&mut self, data: &MeshData, gl: &WebGLRenderingContext) -> AssetResult<()> {
+        let dirty = ::std::mem::replace(&mut self.dirty, Dirty::Clean);
+
+        match dirty {
+            Dirty::Clean => Ok(()),
+            Dirty::All => self.upload_all(data, gl),
+            Dirty::Vertices(range) => {
+                let offset = self.vertex_offset() + range.start * self.layout.stride;
+                let vertices = interleave(data, range).into_bytes();
 
-            gl.bind_buffer(k, &buf);
-            gl.buffer_data(k, &p, DrawMode::Static);
-            gl.unbind_buffer(k);
+                gl.bind_buffer(BufferKind::Array, &self.vb);
+                gl.buffer_sub_data(BufferKind::Array, offset as u32, &vertices);
+                gl.unbind_buffer(BufferKind::Array);
+
+                Ok(())
+            }
         }
     }
+
+    /// Upload the interleaved vertices and the indices, in 16 bits when they fit
+    fn upload_all(&mut self, data: &MeshData, gl: &WebGLRenderingContext) -> AssetResult<()> {
+        let (index_type, indices) = index_bytes(data, gl)?;
+        let vertices = interleave(data, 0..data.vertex_count()).into_bytes();
+        let mode = self.usage.as_draw_mode();
+
+        match self.usage {
+            BufferUsage::Static => {
+                upload_buffer(gl, BufferKind::Array, &self.vb, &vertices, mode);
+                upload_buffer(gl, BufferKind::ElementArray, &self.ib, &indices, mode);
+
+                self.vb_size = vertices.len();
+                self.ib_size = indices.len();
+            }
+            BufferUsage::Dynamic => {
+                // reuse the buffers while the data fits in them
+                if vertices.len() <= self.vb_size {
+                    upload_sub_buffer(gl, BufferKind::Array, &self.vb, 0, &vertices);
+                } else {
+                    upload_buffer(gl, BufferKind::Array, &self.vb, &vertices, mode);
+                    self.vb_size = vertices.len();
+                }
+
+                if indices.len() <= self.ib_size {
+                    upload_sub_buffer(gl, BufferKind::ElementArray, &self.ib, 0, &indices);
+                } else {
+                    upload_buffer(gl, BufferKind::ElementArray, &self.ib, &indices, mode);
+                    self.ib_size = indices.len();
+                }
+            }
+            BufferUsage::Stream => {
+                self.segment = (self.segment + 1) % STREAM_SEGMENTS;
+
+                // grow the ring buffers, the segments are kept aligned for the indices
+                if vertices.len() > self.vb_size {
+                    self.vb_size = vertices.len().next_power_of_two().max(MIN_STREAM_SEGMENT);
+                    let empty = vec![0u8; self.vb_size * STREAM_SEGMENTS];
+                    upload_buffer(gl, BufferKind::Array, &self.vb, &empty, mode);
+                }
+                if indices.len() > self.ib_size {
+                    self.ib_size = indices.len().next_power_of_two().max(MIN_STREAM_SEGMENT);
+                    let empty = vec![0u8; self.ib_size * STREAM_SEGMENTS];
+                    upload_buffer(gl, BufferKind::ElementArray, &self.ib, &empty, mode);
+                }
+
+                let (vo, io) = (self.vertex_offset(), self.index_offset());
+                upload_sub_buffer(gl, BufferKind::Array, &self.vb, vo, &vertices);
+                upload_sub_buffer(gl, BufferKind::ElementArray, &self.ib, io, &indices);
+            }
+        }
+
+        self.index_type = index_type;
+
+        Ok(())
+    }
+}
+
+fn upload_buffer(
+    gl: &WebGLRenderingContext,
+    kind: BufferKind,
+    buffer: &WebGLBuffer,
+    data: &[u8],
+    mode: DrawMode,
+) {
+    gl.bind_buffer(kind, buffer);
+    gl.buffer_data(kind, data, mode);
+    gl.unbind_buffer(kind);
+}
+
+fn upload_sub_buffer(
+    gl: &WebGLRenderingContext,
+    kind: BufferKind,
+    buffer: &WebGLBuffer,
+    offset: usize,
+    data: &[u8],
+) {
+    gl.bind_buffer(kind, buffer);
+    gl.buffer_sub_data(kind, offset as u32, data);
+    gl.unbind_buffer(kind);
 }
 
 impl Drop for MeshGLState {
     fn drop(&mut self) {
         self.gl.delete_buffer(&self.vb);
-        self.uvb.as_ref().map(|b| self.gl.delete_buffer(&b));
-        self.nb.as_ref().map(|b| self.gl.delete_buffer(&b));
-        self.tb.as_ref().map(|b| self.gl.delete_buffer(&b));
-        self.btb.as_ref().map(|b| self.gl.delete_buffer(&b));
         self.gl.delete_buffer(&self.ib);
 
         self.gl.delete_vertex_array(&self.vao);
     }
 }
 
-#[derive(Default, Debug)]
+/// How the indices of a mesh are assembled
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Primitive {
+    /// every 3 indices make a triangle
+    Triangles,
+    /// every 2 indices make a line segment
+    Lines,
+}
+
+impl Default for Primitive {
+    fn default() -> Primitive {
+        Primitive::Triangles
+    }
+}
+
+impl Primitive {
+    fn as_gl_primitives(&self) -> Primitives {
+        match *self {
+            Primitive::Triangles => Primitives::Triangles,
+            Primitive::Lines => Primitives::Lines,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
 pub struct MeshData {
     pub vertices: Vec<f32>,
     pub uvs: Option<Vec<f32>>,
@@ -125,10 +384,66 @@ pub struct MeshData {
     pub tangents: Option<Vec<f32>>,
     pub bitangents: Option<Vec<f32>>,
 
-    pub indices: Vec<u16>,
+    /// RGBA vertex colors
+    pub colors: Option<Vec<f32>>,
+    /// second uv set, e.g. for lightmaps
+    pub uvs1: Option<Vec<f32>>,
+
+    /// 4 joint indices per vertex for skinning, stored as floats
+    pub joints: Option<Vec<f32>>,
+    /// 4 joint weights per vertex
+    pub weights: Option<Vec<f32>>,
+
+    /// Extra attributes, bound by name in the shader
+    pub custom: Vec<VertexAttribute>,
+
+    pub indices: Vec<u32>,
+    pub primitive: Primitive,
+}
+
+/// A custom vertex attribute, `size` floats per vertex
+#[derive(Default, Debug, Clone)]
+pub struct VertexAttribute {
+    /// name of the attribute in the shader, e.g. "aWind"
+    pub name: String,
+    /// 1 to 4
+    pub size: usize,
+    pub data: Vec<f32>,
 }
 
 impl MeshData {
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / 3
+    }
+
+    /// All the attributes of the vertices, with their location and size
+    fn attributes(&self) -> Vec<(AttribLocation, usize, &[f32])> {
+        let fixed = [
+            (ShaderAttrib::Position as u32, 3, Some(&self.vertices)),
+            (ShaderAttrib::UV0 as u32, 2, self.uvs.as_ref()),
+            (ShaderAttrib::Normal as u32, 3, self.normals.as_ref()),
+            (ShaderAttrib::Tangent as u32, 3, self.tangents.as_ref()),
+            (ShaderAttrib::Bitangent as u32, 3, self.bitangents.as_ref()),
+            (ShaderAttrib::Color as u32, 4, self.colors.as_ref()),
+            (ShaderAttrib::UV1 as u32, 2, self.uvs1.as_ref()),
+            (ShaderAttrib::Joints as u32, 4, self.joints.as_ref()),
+            (ShaderAttrib::Weights as u32, 4, self.weights.as_ref()),
+        ];
+
+        let mut attrs: Vec<_> = fixed
+            .iter()
+            .filter_map(|&(attr, size, data)| {
+                data.map(|d| (AttribLocation::Fixed(attr), size, d.as_slice()))
+            })
+            .collect();
+
+        for c in self.custom.iter() {
+            attrs.push((AttribLocation::Named(c.name.clone()), c.size, c.data.as_slice()));
+        }
+
+        attrs
+    }
+
     pub fn compute_bound(&self) -> MeshBound {
         let mut min = Vector3::new(MAX, MAX, MAX);
         let mut max = Vector3::new(MIN, MIN, MIN);
@@ -162,6 +477,7 @@ pub struct MeshBuffer {
     data: Resource<MeshData>,
     gl_state: RefCell<Option<MeshGLState>>,
     bounds: Cell<Option<MeshBound>>,
+    usage: Cell<BufferUsage>,
 
     bound_prog: RefCell<Weak<ShaderProgram>>,
 }
@@ -174,6 +490,7 @@ impl Asset for MeshBuffer {
             data: r,
             gl_state: Default::default(),
             bounds: Default::default(),
+            usage: Default::default(),
             bound_prog: RefCell::new(Weak::new()),
         })
     }
@@ -192,85 +509,117 @@ impl LoadableAsset for MeshBuffer {
     }
 }
 
-pub fn bind_buffer(
-    gl: &WebGLRenderingContext,
-    buffer: &WebGLBuffer,
-    coord: u32,
-    asize: AttributeSize,
-) {
-    gl.bind_buffer(BufferKind::Array, buffer);
+impl ReloadableAsset for MeshBuffer {
+    fn depends_on(&self, name: &str, file: &str) -> bool {
+        name == file
+    }
+
+    fn poll_reload(r: &Self::Resource) -> AssetResult<bool> {
+        r.poll()
+    }
 
-    gl.enable_vertex_attrib_array(coord);
-    gl.vertex_attrib_pointer(coord, asize, DataType::Float, false, 0, 0);
+    fn reload(&self, r: Self::Resource) {
+        if let Ok(mesh_data) = r.try_into() {
+            self.update_mesh_data(mesh_data);
+        }
+    }
 }
 
 impl MeshBuffer {
+    pub fn state(&self) -> AssetState {
+        self.data.state()
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        self.usage.get()
+    }
+
+    /// Set how often the data changes, the gpu buffers are rebuilt on the next draw
+    pub fn set_usage(&self, usage: BufferUsage) {
+        if self.usage.replace(usage) != usage {
+            self.gl_state.replace(None);
+            *self.bound_prog.borrow_mut() = Weak::new();
+        }
+    }
+
+    /// Replace the whole mesh data
     pub fn update_mesh_data(&self, mesh_data: MeshData) {
-        let mut actions = Vec::new();
+        let layout = VertexLayout::new(&mesh_data);
 
-        match self.data.try_borrow() {
-            Err(_) => {}
-            Ok(_da) => {
-                actions.push(RebindAction::Vertices);
+        self.data.replace(mesh_data);
+        self.mark_dirty(Dirty::All, &layout);
+    }
 
-                mesh_data.uvs.as_ref().map(|_| {
-                    actions.push(RebindAction::UV);
-                });
+    /// Modify the vertices in `range` in place, only this range is uploaded again.
+    ///
+    /// The whole mesh is uploaded when the vertex count, the attributes or
+    /// the indices are changed, or when the mesh is streamed.
+    pub fn update_vertices<F>(&self, range: Range<usize>, f: F) -> AssetResult<()>
+    where
+        F: FnOnce(&mut MeshData),
+    {
+        let (layout, dirty) = {
+            let mut data = self.data.try_borrow_mut()?;
+            let vertex_count = data.vertex_count();
+            let index_count = data.indices.len();
 
-                mesh_data.normals.as_ref().map(|_| {
-                    actions.push(RebindAction::Normal);
-                });
+            f(&mut data);
 
-                mesh_data.tangents.as_ref().map(|_| {
-                    actions.push(RebindAction::Tangent);
-                });
+            let unchanged = data.vertex_count() == vertex_count
+                && data.indices.len() == index_count
+                && range.end <= vertex_count;
 
-                mesh_data.bitangents.as_ref().map(|_| {
-                    actions.push(RebindAction::Bitangent);
-                });
+            let dirty = if unchanged && self.usage.get() != BufferUsage::Stream {
+                Dirty::Vertices(range)
+            } else {
+                Dirty::All
+            };
 
-                actions.push(RebindAction::Indices);
-            }
+            (VertexLayout::new(&data), dirty)
         };
 
-        self.data.replace(mesh_data);
+        self.mark_dirty(dirty, &layout);
+        Ok(())
+    }
+
+    fn mark_dirty(&self, dirty: Dirty, layout: &VertexLayout) {
+        self.bounds.set(None);
 
         // check whether the state is ready
-        match *self.gl_state.borrow_mut() {
-            None => {}
+        let mut state_opt = self.gl_state.borrow_mut();
+        let same_layout = match *state_opt {
+            None => return,
             Some(ref mut state) => {
-                state.rebind_actions.append(&mut actions);
-                *self.bound_prog.borrow_mut() = Weak::new();
+                match dirty {
+                    Dirty::Vertices(range) => state.dirty.merge(range),
+                    d => state.dirty = d,
+                }
+                state.layout == *layout
             }
+        };
+
+        // the vertex array keeps the old attributes, build a new one
+        if !same_layout {
+            *state_opt = None;
         }
+        *self.bound_prog.borrow_mut() = Weak::new();
     }
 
     pub fn prepare(&self, gl: &WebGLRenderingContext) -> AssetResult<()> {
         if let Some(ref mut state) = *self.gl_state.borrow_mut() {
-            if state.rebind_actions.len() > 0 {
+            if state.dirty != Dirty::Clean {
                 gl.bind_vertex_array(&state.vao);
 
                 let data = self.data.try_borrow()?;
-                let rebind_actions = state.rebind_actions.drain(..).collect();
-
-                // Rebind the mesh
-                state.rebind(&rebind_actions, &data, gl);
+                state.upload(&data, gl)?;
             }
 
             return Ok(());
         }
 
         let data = self.data.try_borrow()?;
-
-        self.gl_state.replace(Some(mesh_bind_buffer(
-            &data.vertices,
-            &data.uvs,
-            &data.normals,
-            &data.tangents,
-            &data.bitangents,
-            &data.indices,
-            gl,
-        )));
+        let state = MeshGLState::new(&data, self.usage.get(), gl)?;
+        self.gl_state.replace(Some(state));
 
         Ok(())
     }
@@ -303,42 +652,36 @@ impl MeshBuffer {
         gl.bind_vertex_array(&state.vao);
 
         if gl.is_webgl2 {
-            if let Some(_) = self.bound_prog.borrow().upgrade() {
-                return Ok(());
+            if let Some(prog) = self.bound_prog.borrow().upgrade() {
+                // the named attributes may be at another location in another program
+                if !state.layout.has_named() || Rc::ptr_eq(&prog, program) {
+                    return Ok(());
+                }
             }
         }
 
-        // Bind vertex buffer object
-        // "aVertexPosition"
-        bind_buffer(
-            gl,
-            &state.vb,
-            ShaderAttrib::Position as u32,
-            AttributeSize::Three,
-        );
-
-        // "aTextureCoord"
-        if let Some(ref uvb) = state.uvb {
-            bind_buffer(gl, uvb, ShaderAttrib::UV0 as u32, AttributeSize::Two);
-        }
-
-        // "aVertexNormal"
-        if let Some(ref nb) = state.nb {
-            bind_buffer(gl, nb, ShaderAttrib::Normal as u32, AttributeSize::Three);
-        }
-
-        // "aVertexTangent"
-        if let Some(ref tb) = state.tb {
-            bind_buffer(gl, tb, ShaderAttrib::Tangent as u32, AttributeSize::Three);
-        }
-
-        // "aVertexBitangent"
-        if let Some(ref btb) = state.btb {
-            bind_buffer(
-                gl,
-                btb,
-                ShaderAttrib::Bitangent as u32,
-                AttributeSize::Three,
+        // Bind the interleaved vertex buffer object
+        gl.bind_buffer(BufferKind::Array, &state.vb);
+
+        let stride = state.layout.stride as u32;
+        let base = state.vertex_offset();
+        for attr in state.layout.attribs.iter() {
+            let loc = match attr.location {
+                AttribLocation::Fixed(loc) => loc,
+                AttribLocation::Named(ref name) => match program.attrib_loc(gl, name) {
+                    Some(loc) => loc,
+                    None => continue,
+                },
+            };
+
+            gl.enable_vertex_attrib_array(loc);
+            gl.vertex_attrib_pointer(
+                loc,
+                attribute_size(attr.size),
+                DataType::Float,
+                false,
+                stride,
+                (base + attr.offset) as u32,
             );
         }
 
@@ -353,8 +696,15 @@ impl MeshBuffer {
     #[cfg_attr(feature = "flame_it", flame)]
     pub fn render(&self, gl: &WebGLRenderingContext) {
         let data = self.data.try_borrow().unwrap();
+        let state_option = self.gl_state.borrow();
+        let state = state_option.as_ref().unwrap();
 
-        gl.draw_elements(Primitives::Triangles, data.indices.len(), DataType::U16, 0);
+        gl.draw_elements(
+            data.primitive.as_gl_primitives(),
+            data.indices.len(),
+            state.index_type,
+            state.index_offset() as u32,
+        );
     }
 
     pub fn unbind(&self, _gl: &WebGLRenderingContext) {
@@ -365,70 +715,3 @@ impl MeshBuffer {
         //gl.unbind_vertex_array(&state.vao);
     }
 }
-
-fn bind_f32_array(gl: &WebGLRenderingContext, data: &Vec<f32>) -> WebGLBuffer {
-    // Create an empty buffer object to store vertex buffer
-    let vb = gl.create_buffer();
-    {
-        // Bind appropriate array buffer to it
-        gl.bind_buffer(BufferKind::Array, &vb);
-
-        // Pass the vertex data to the buffer
-        let cv = data.clone();
-        gl.buffer_data(BufferKind::Array, &cv.into_bytes(), DrawMode::Static);
-
-        // Unbind the buffer
-        gl.unbind_buffer(BufferKind::Array);
-    }
-
-    vb
-}
-
-fn mesh_bind_buffer(
-    vertices: &Vec<f32>,
-    uvs: &Option<Vec<f32>>,
-    normals: &Option<Vec<f32>>,
-    tangents: &Option<Vec<f32>>,
-    bitangents: &Option<Vec<f32>>,
-    indices: &Vec<u16>,
-    gl: &WebGLRenderingContext,
-) -> MeshGLState {
-    // some opengl 3.x core profile require a VAO. See issue #11
-    let vao = gl.create_vertex_array();
-    gl.bind_vertex_array(&vao);
-
-    let vertex_buffer = bind_f32_array(&gl, vertices);
-    let uv_buffer = uvs.as_ref().map(|data| bind_f32_array(gl, data));
-    let normal_buffer = normals.as_ref().map(|data| bind_f32_array(gl, data));
-    let tangent_buffer = tangents.as_ref().map(|data| bind_f32_array(gl, data));
-    let bitangent_buffer = bitangents.as_ref().map(|data| bind_f32_array(gl, data));
-
-    // Create an empty buffer object to store Index buffer
-    let index_buffer = gl.create_buffer();
-    {
-        // Bind appropriate array buffer to it
-        gl.bind_buffer(BufferKind::ElementArray, &index_buffer);
-
-        // Pass the vertex data to the buffer
-        let ci = indices.clone();
-        gl.buffer_data(BufferKind::ElementArray, &ci.into_bytes(), DrawMode::Static);
-
-        // Unbind the buffer
-        gl.unbind_buffer(BufferKind::ElementArray);
-    }
-
-    MeshGLState {
-        vao,
-        vb: vertex_buffer,
-        uvb: uv_buffer,
-
-        nb: normal_buffer,
-        tb: tangent_buffer,
-        btb: bitangent_buffer,
-
-        ib: index_buffer,
-        gl: gl.clone(),
-
-        rebind_actions: Vec::new(),
-    }
-}
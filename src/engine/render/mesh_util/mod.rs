@@ -0,0 +1,330 @@
+//! Processing of the mesh data
+//!
+//! The loaders build triangle soups, `process_loaded` welds them into indexed meshes,
+//! generates the missing normals and tangents and reorders the triangles for the
+//! vertex cache. `simplify` makes the lower levels of detail of a mesh.
+//!
+//! All the functions expect triangle meshes.
+
+use math::*;
+use engine::MeshData;
+
+mod normals;
+mod optimize;
+mod simplify;
+mod tangents;
+mod weld;
+
+pub use self::normals::{compute_flat_normals, compute_smooth_normals};
+pub use self::optimize::optimize_vertex_cache;
+pub use self::simplify::simplify;
+pub use self::tangents::compute_tangents;
+pub use self::weld::{compact, unweld, weld};
+
+/// The processing shared by the loaders, from a triangle soup to an indexed mesh
+/// with normals, tangents when `with_tangents` and an optimized triangle order
+pub fn process_loaded(data: &mut MeshData, with_tangents: bool) {
+    weld(data, 0.0);
+
+    if data.normals.is_none() {
+        compute_smooth_normals(data);
+    }
+
+    if with_tangents && data.tangents.is_none() {
+        compute_tangents(data);
+    }
+
+    optimize_vertex_cache(data);
+}
+
+pub trait QuadBuilder {
+    fn add_quad(&mut self, ps: [Vector3f; 4]);
+}
+
+fn add_v(v: &mut Vec<f32>, p: &Vector3f) {
+    v.push(p.x);
+    v.push(p.y);
+    v.push(p.z);
+}
+
+impl QuadBuilder for MeshData {
+    fn add_quad(&mut self, ps: [Vector3f; 4]) {
+        add_v(&mut self.vertices, &ps[0]);
+        add_v(&mut self.vertices, &ps[1]);
+        add_v(&mut self.vertices, &ps[2]);
+
+        add_v(&mut self.vertices, &ps[2]);
+        add_v(&mut self.vertices, &ps[3]);
+        add_v(&mut self.vertices, &ps[0]);
+
+        self.indices.push(self.indices.len() as u32);
+        self.indices.push(self.indices.len() as u32);
+        self.indices.push(self.indices.len() as u32);
+
+        self.indices.push(self.indices.len() as u32);
+        self.indices.push(self.indices.len() as u32);
+        self.indices.push(self.indices.len() as u32);
+    }
+}
+
+/// The attributes of the vertices with their number of floats per vertex
+fn attributes(data: &MeshData) -> Vec<(usize, &Vec<f32>)> {
+    let mut attrs = vec![(3, &data.vertices)];
+    {
+        let optionals = [
+            (2, &data.uvs),
+            (3, &data.normals),
+            (3, &data.tangents),
+            (3, &data.bitangents),
+            (4, &data.colors),
+            (2, &data.uvs1),
+            (4, &data.joints),
+            (4, &data.weights),
+        ];
+
+        for &(size, values) in optionals.iter() {
+            if let Some(ref v) = *values {
+                attrs.push((size, v));
+            }
+        }
+    }
+
+    for c in data.custom.iter() {
+        attrs.push((c.size, &c.data));
+    }
+
+    attrs
+}
+
+fn attributes_mut(data: &mut MeshData) -> Vec<(usize, &mut Vec<f32>)> {
+    let mut attrs = vec![(3, &mut data.vertices)];
+
+    let optionals = vec![
+        (2, &mut data.uvs),
+        (3, &mut data.normals),
+        (3, &mut data.tangents),
+        (3, &mut data.bitangents),
+        (4, &mut data.colors),
+        (2, &mut data.uvs1),
+        (4, &mut data.joints),
+        (4, &mut data.weights),
+    ];
+
+    for (size, values) in optionals.into_iter() {
+        if let Some(v) = values.as_mut() {
+            attrs.push((size, v));
+        }
+    }
+
+    for c in data.custom.iter_mut() {
+        attrs.push((c.size, &mut c.data));
+    }
+
+    attrs
+}
+
+/// Rebuild the vertices, the new vertex `i` is the old vertex `order[i]`
+fn gather_vertices(data: &mut MeshData, order: &[u32]) {
+    for (size, values) in attributes_mut(data) {
+        let mut gathered = Vec::with_capacity(order.len() * size);
+        for &v in order.iter() {
+            let v = v as usize;
+            gathered.extend_from_slice(&values[v * size..(v + 1) * size]);
+        }
+        *values = gathered;
+    }
+}
+
+#[inline]
+fn vec3_at(values: &[f32], i: u32) -> Vector3f {
+    let i = i as usize * 3;
+    Vector3::new(values[i], values[i + 1], values[i + 2])
+}
+
+#[inline]
+fn vec2_at(values: &[f32], i: u32) -> Vector2f {
+    let i = i as usize * 2;
+    Vector2::new(values[i], values[i + 1])
+}
+
+fn push_vec3(values: &mut Vec<f32>, v: &Vector3f) {
+    values.extend_from_slice(&[v.x, v.y, v.z]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A flat grid of `n` by `n` unit quads in the xz plane, as a triangle soup
+    fn grid(n: usize) -> MeshData {
+        let mut data = MeshData::default();
+        for x in 0..n {
+            for z in 0..n {
+                let (x, z) = (x as f32, z as f32);
+                data.add_quad([
+                    Vector3::new(x, 0.0, z),
+                    Vector3::new(x, 0.0, z + 1.0),
+                    Vector3::new(x + 1.0, 0.0, z + 1.0),
+                    Vector3::new(x + 1.0, 0.0, z),
+                ]);
+            }
+        }
+        data
+    }
+
+    fn triangles(data: &MeshData) -> Vec<[Vector3f; 3]> {
+        data.indices
+            .chunks(3)
+            .map(|t| {
+                [
+                    vec3_at(&data.vertices, t[0]),
+                    vec3_at(&data.vertices, t[1]),
+                    vec3_at(&data.vertices, t[2]),
+                ]
+            })
+            .collect()
+    }
+
+    fn area(data: &MeshData) -> f32 {
+        triangles(data)
+            .iter()
+            .map(|t| (t[1] - t[0]).cross(t[2] - t[0]).magnitude() / 2.0)
+            .sum()
+    }
+
+    fn sorted(mut tris: Vec<[Vector3f; 3]>) -> Vec<[Vector3f; 3]> {
+        let key = |t: &[Vector3f; 3]| -> Vec<f32> {
+            t.iter().flat_map(|p| vec![p.x, p.y, p.z]).collect()
+        };
+        tris.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        tris
+    }
+
+    /// The welded grid with up normals and the uvs of `uv`
+    fn uv_grid<F: Fn(&Vector3f) -> Vector2f>(n: usize, uv: F) -> MeshData {
+        let mut data = grid(n);
+        let mut uvs = Vec::new();
+        let mut normals = Vec::new();
+        for i in 0..data.vertex_count() as u32 {
+            let t = uv(&vec3_at(&data.vertices, i));
+            uvs.extend_from_slice(&[t.x, t.y]);
+            normals.extend_from_slice(&[0.0, 1.0, 0.0]);
+        }
+
+        data.uvs = Some(uvs);
+        data.normals = Some(normals);
+        weld(&mut data, 0.0);
+        data
+    }
+
+    fn assert_near(a: Vector3f, b: Vector3f) {
+        assert!((a - b).magnitude() < 1.0e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn weld_merges_coincident_vertices() {
+        let mut data = grid(2);
+        let soup = triangles(&data);
+        assert_eq!(data.vertex_count(), 24);
+
+        weld(&mut data, 0.0);
+        assert_eq!(data.vertex_count(), 9);
+        assert_eq!(triangles(&data), soup);
+
+        // the vertices with another uv are kept apart
+        let mut data = grid(1);
+        data.uvs = Some(vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.5, 0.0]);
+        weld(&mut data, 0.0);
+        assert_eq!(data.vertex_count(), 5);
+    }
+
+    #[test]
+    fn weld_with_epsilon() {
+        let mut data = grid(1);
+        data.vertices[3 * 3] += 1.0e-4;
+
+        let mut exact = data.clone();
+        weld(&mut exact, 0.0);
+        assert_eq!(exact.vertex_count(), 5);
+
+        weld(&mut data, 1.0e-2);
+        assert_eq!(data.vertex_count(), 4);
+    }
+
+    #[test]
+    fn compact_drops_unused_vertices() {
+        let mut data = grid(2);
+        weld(&mut data, 0.0);
+        data.indices.truncate(6);
+        let tris = triangles(&data);
+
+        compact(&mut data);
+        assert_eq!(data.vertex_count(), 4);
+        assert_eq!(triangles(&data), tris);
+    }
+
+    #[test]
+    fn optimize_keeps_the_triangles() {
+        let mut data = grid(8);
+        weld(&mut data, 0.0);
+        let tris = sorted(triangles(&data));
+
+        optimize_vertex_cache(&mut data);
+        assert_eq!(data.vertex_count(), 81);
+        assert_eq!(sorted(triangles(&data)), tris);
+    }
+
+    #[test]
+    fn simplify_keeps_the_border() {
+        let mut data = grid(6);
+        weld(&mut data, 0.0);
+
+        let lod = simplify(&data, 0.25, 0.01);
+        assert!(lod.indices.len() < data.indices.len());
+        assert!(lod.indices.len() > 0);
+
+        // the flat grid keeps its area and all its border vertices
+        assert!((area(&lod) - 36.0).abs() < 1.0e-3);
+        for i in 0..data.vertex_count() as u32 {
+            let p = vec3_at(&data.vertices, i);
+            let on_border = p.x == 0.0 || p.x == 6.0 || p.z == 0.0 || p.z == 6.0;
+            let kept = (0..lod.vertex_count() as u32).any(|j| vec3_at(&lod.vertices, j) == p);
+            assert!(!on_border || kept, "border vertex {:?} removed", p);
+        }
+    }
+
+    #[test]
+    fn tangents_follow_the_uvs() {
+        let mut data = uv_grid(2, |p| Vector2::new(p.x, p.z));
+        assert!(compute_tangents(&mut data));
+        assert_eq!(data.vertex_count(), 9);
+
+        let tangents = data.tangents.unwrap();
+        let bitangents = data.bitangents.unwrap();
+        for i in 0..9 {
+            assert_near(vec3_at(&tangents, i), Vector3::unit_x());
+            assert_near(vec3_at(&bitangents, i), Vector3::unit_z());
+        }
+    }
+
+    #[test]
+    fn tangents_split_mirrored_uvs() {
+        // the uvs are mirrored at x = 1
+        let mut data = uv_grid(2, |p| Vector2::new((p.x - 1.0).abs(), p.z));
+        assert_eq!(data.vertex_count(), 9);
+        assert!(compute_tangents(&mut data));
+
+        // the vertices on the mirror have a tangent space on each side
+        assert_eq!(data.vertex_count(), 12);
+
+        let tangents = data.tangents.as_ref().unwrap();
+        let bitangents = data.bitangents.as_ref().unwrap();
+        for (t, p) in data.indices.chunks(3).zip(triangles(&data)) {
+            let du = if p[0].x + p[1].x + p[2].x < 3.0 { -1.0 } else { 1.0 };
+            for &i in t {
+                assert_near(vec3_at(tangents, i), Vector3::new(du, 0.0, 0.0));
+                assert_near(vec3_at(bitangents, i), Vector3::unit_z());
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use engine::MeshData;
+use math::*;
+use super::{push_vec3, unweld, vec3_at};
+
+fn face_normal(data: &MeshData, tri: &[u32]) -> Vector3f {
+    let a = vec3_at(&data.vertices, tri[0]);
+    let b = vec3_at(&data.vertices, tri[1]);
+    let c = vec3_at(&data.vertices, tri[2]);
+
+    // its length is twice the area of the triangle
+    (b - a).cross(c - a)
+}
+
+fn normalize_or_up(n: Vector3f) -> Vector3f {
+    if n.magnitude2() > 0.0 {
+        n.normalize()
+    } else {
+        Vector3::unit_y()
+    }
+}
+
+/// Area weighted normals, shared by all the triangles around a position.
+///
+/// The vertices at the same position share their normal even when they are split
+/// by another attribute, so the uv seams do not show in the lighting.
+pub fn compute_smooth_normals(data: &mut MeshData) {
+    let count = data.vertex_count();
+
+    // group the vertices by position
+    let mut groups: HashMap<[u32; 3], usize> = HashMap::with_capacity(count);
+    let group_of: Vec<usize> = (0..count)
+        .map(|i| {
+            let p = &data.vertices[i * 3..i * 3 + 3];
+            let key = [
+                (p[0] + 0.0).to_bits(),
+                (p[1] + 0.0).to_bits(),
+                (p[2] + 0.0).to_bits(),
+            ];
+            let next = groups.len();
+            *groups.entry(key).or_insert(next)
+        })
+        .collect();
+
+    let mut sums = vec![Vector3::zero(); groups.len()];
+    for tri in data.indices.chunks(3).filter(|t| t.len() == 3) {
+        let n = face_normal(data, tri);
+        for &i in tri.iter() {
+            sums[group_of[i as usize]] += n;
+        }
+    }
+
+    let mut normals = Vec::with_capacity(count * 3);
+    for g in group_of.iter() {
+        push_vec3(&mut normals, &normalize_or_up(sums[*g]));
+    }
+
+    data.normals = Some(normals);
+}
+
+/// One normal per triangle, the vertices are split so that the triangles do not share them
+pub fn compute_flat_normals(data: &mut MeshData) {
+    unweld(data);
+
+    let mut normals = Vec::with_capacity(data.vertices.len());
+    for tri in data.indices.chunks(3) {
+        let n = if tri.len() == 3 {
+            normalize_or_up(face_normal(data, tri))
+        } else {
+            Vector3::unit_y()
+        };
+
+        for _ in tri.iter() {
+            push_vec3(&mut normals, &n);
+        }
+    }
+
+    data.normals = Some(normals);
+}
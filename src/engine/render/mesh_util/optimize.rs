@@ -0,0 +1,123 @@
+use engine::{MeshData, Primitive};
+use super::compact;
+
+/// Size of the simulated post-transform cache
+const CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+/// Score of a vertex from its position in the cache and its triangles left to emit
+fn vertex_score(cache_pos: Option<usize>, remaining: usize) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_pos {
+        None => 0.0,
+        // the vertices of the last triangle get a fixed score,
+        // so the next triangle does not reuse too much of it
+        Some(p) if p < 3 => LAST_TRIANGLE_SCORE,
+        Some(p) => {
+            let s = 1.0 - (p - 3) as f32 / (CACHE_SIZE - 3) as f32;
+            s.powf(CACHE_DECAY_POWER)
+        }
+    };
+
+    // favor the vertices with few triangles left, to finish them off
+    cache_score + VALENCE_BOOST_SCALE * (remaining as f32).powf(-VALENCE_BOOST_POWER)
+}
+
+/// Reorder the triangles for the post-transform vertex cache with Tom Forsyth's
+/// linear-speed algorithm, then the vertices in the order of their first use.
+pub fn optimize_vertex_cache(data: &mut MeshData) {
+    if data.primitive != Primitive::Triangles || data.indices.len() % 3 != 0 {
+        return;
+    }
+
+    let tri_count = data.indices.len() / 3;
+    let vertex_count = data.vertex_count();
+    let indices = &data.indices;
+    let corner = |t: usize, k: usize| indices[t * 3 + k] as usize;
+
+    // triangles left to emit for each vertex
+    let mut vertex_tris: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+    for t in 0..tri_count {
+        for k in 0..3 {
+            vertex_tris[corner(t, k)].push(t);
+        }
+    }
+
+    let mut vscore: Vec<f32> = vertex_tris
+        .iter()
+        .map(|tris| vertex_score(None, tris.len()))
+        .collect();
+    let tscore: Vec<f32> = (0..tri_count)
+        .map(|t| (0..3).map(|k| vscore[corner(t, k)]).sum())
+        .collect();
+
+    let mut emitted = vec![false; tri_count];
+    let mut cache: Vec<usize> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut result = Vec::with_capacity(indices.len());
+
+    let first = (0..tri_count).fold(None, |best: Option<usize>, t| match best {
+        Some(b) if tscore[b] >= tscore[t] => Some(b),
+        _ => Some(t),
+    });
+
+    // triangles before this one are all emitted
+    let mut next_scan = 0;
+    let mut best = first;
+
+    while let Some(t) = best {
+        emitted[t] = true;
+
+        // move the vertices of the triangle to the front of the cache
+        let tri = [corner(t, 0), corner(t, 1), corner(t, 2)];
+        cache.retain(|v| !tri.contains(v));
+        for (k, &v) in tri.iter().enumerate() {
+            result.push(v as u32);
+            cache.insert(k, v);
+            vertex_tris[v].retain(|&o| o != t);
+        }
+
+        let evicted = if cache.len() > CACHE_SIZE {
+            cache.split_off(CACHE_SIZE)
+        } else {
+            Vec::new()
+        };
+
+        for &v in evicted.iter() {
+            vscore[v] = vertex_score(None, vertex_tris[v].len());
+        }
+        for (p, &v) in cache.iter().enumerate() {
+            vscore[v] = vertex_score(Some(p), vertex_tris[v].len());
+        }
+
+        // only the triangles around the cache have changed
+        best = None;
+        let mut best_score = -1.0;
+        for &v in cache.iter().chain(evicted.iter()) {
+            for &o in vertex_tris[v].iter() {
+                let score: f32 = (0..3).map(|k| vscore[corner(o, k)]).sum();
+                if score > best_score {
+                    best_score = score;
+                    best = Some(o);
+                }
+            }
+        }
+
+        if best.is_none() {
+            while next_scan < tri_count && emitted[next_scan] {
+                next_scan += 1;
+            }
+            if next_scan < tri_count {
+                best = Some(next_scan);
+            }
+        }
+    }
+
+    data.indices = result;
+    compact(data);
+}
@@ -0,0 +1,325 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use engine::{MeshData, Primitive};
+use math::*;
+use super::{compact, vec3_at};
+
+/// Symmetric 4x4 matrix summing the squared distances to a set of planes,
+/// only the 10 upper coefficients are stored
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(n: Vector3f, d: f32) -> Quadric {
+        let (a, b, c, d) = (n.x as f64, n.y as f64, n.z as f64, d as f64);
+
+        Quadric([
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ])
+    }
+
+    fn add(&self, o: &Quadric) -> Quadric {
+        let mut q = *self;
+        for (a, b) in q.0.iter_mut().zip(o.0.iter()) {
+            *a += *b;
+        }
+        q
+    }
+
+    /// Sum of the squared distances of `p` to the planes
+    fn error(&self, p: Vector3f) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x as f64, p.y as f64, p.z as f64);
+
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y + q[7] * z * z
+            + 2.0 * q[8] * z + q[9]
+    }
+}
+
+/// Collapse of the vertex `from` onto the vertex `to`
+#[derive(Debug, Clone, Copy)]
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, o: &Collapse) -> bool {
+        self.cost == o.cost
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, o: &Collapse) -> Option<Ordering> {
+        Some(self.cmp(o))
+    }
+}
+
+impl Ord for Collapse {
+    // reversed, the heap pops the cheapest collapse first
+    fn cmp(&self, o: &Collapse) -> Ordering {
+        o.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+struct Simplifier<'a> {
+    positions: Vec<Vector3f>,
+    indices: &'a [u32],
+    quadrics: Vec<Quadric>,
+    /// the vertices on a border or a seam are never removed
+    locked: Vec<bool>,
+    /// the vertex a removed vertex was collapsed onto
+    parent: Vec<u32>,
+    /// the triangles around each vertex
+    vertex_tris: Vec<Vec<usize>>,
+    dead: Vec<bool>,
+}
+
+impl<'a> Simplifier<'a> {
+    fn find(&mut self, v: u32) -> u32 {
+        let mut root = v;
+        while self.parent[root as usize] != root {
+            root = self.parent[root as usize];
+        }
+
+        // path compression
+        let mut v = v;
+        while self.parent[v as usize] != root {
+            let next = self.parent[v as usize];
+            self.parent[v as usize] = root;
+            v = next;
+        }
+
+        root
+    }
+
+    fn triangle(&mut self, t: usize) -> [u32; 3] {
+        let indices = self.indices;
+        [
+            self.find(indices[t * 3]),
+            self.find(indices[t * 3 + 1]),
+            self.find(indices[t * 3 + 2]),
+        ]
+    }
+
+    fn best_collapse(&self, a: u32, b: u32) -> Option<Collapse> {
+        let q = self.quadrics[a as usize].add(&self.quadrics[b as usize]);
+
+        let candidate = |from: u32, to: u32| {
+            if self.locked[from as usize] {
+                None
+            } else {
+                Some(Collapse {
+                    cost: q.error(self.positions[to as usize]),
+                    from,
+                    to,
+                })
+            }
+        };
+
+        match (candidate(a, b), candidate(b, a)) {
+            (Some(x), Some(y)) => Some(if x.cost <= y.cost { x } else { y }),
+            (x, None) => x,
+            (None, y) => y,
+        }
+    }
+
+    /// Whether moving `from` to `to` turns over one of its triangles
+    fn flips(&mut self, from: u32, to: u32) -> bool {
+        let p = self.positions[to as usize];
+
+        for t in self.vertex_tris[from as usize].clone() {
+            if self.dead[t] {
+                continue;
+            }
+
+            let tri = self.triangle(t);
+            // this one disappears
+            if tri.contains(&to) {
+                continue;
+            }
+
+            let pos = |v: u32| self.positions[v as usize];
+            let before = (pos(tri[1]) - pos(tri[0])).cross(pos(tri[2]) - pos(tri[0]));
+
+            let moved = |v: u32| if v == from { p } else { pos(v) };
+            let after = (moved(tri[1]) - moved(tri[0])).cross(moved(tri[2]) - moved(tri[0]));
+
+            if before.dot(after) <= 0.0 {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Collapse `from` onto `to`, returns the number of removed triangles
+    fn collapse(&mut self, from: u32, to: u32) -> usize {
+        self.parent[from as usize] = to;
+        self.quadrics[to as usize] = self.quadrics[to as usize].add(&self.quadrics[from as usize]);
+
+        let mut removed = 0;
+        let tris = ::std::mem::replace(&mut self.vertex_tris[from as usize], Vec::new());
+        for t in tris {
+            if self.dead[t] {
+                continue;
+            }
+
+            let tri = self.triangle(t);
+            if tri[0] == tri[1] || tri[1] == tri[2] || tri[2] == tri[0] {
+                self.dead[t] = true;
+                removed += 1;
+            } else {
+                self.vertex_tris[to as usize].push(t);
+            }
+        }
+
+        removed
+    }
+}
+
+/// Simplify a mesh with quadric error metrics by collapsing its edges, e.g. to make
+/// the levels of detail of a mesh.
+///
+/// Stops when `ratio` of the triangles are left, or before moving the surface by more
+/// than about `max_error`. The borders of the mesh and the seams between the vertices
+/// split by an attribute are kept, so the mesh should be welded first.
+pub fn simplify(data: &MeshData, ratio: f32, max_error: f32) -> MeshData {
+    let mut result = data.clone();
+    if data.primitive != Primitive::Triangles || data.indices.len() % 3 != 0 {
+        return result;
+    }
+
+    let vertex_count = data.vertex_count();
+    let tri_count = data.indices.len() / 3;
+
+    let mut s = Simplifier {
+        positions: (0..vertex_count as u32)
+            .map(|i| vec3_at(&data.vertices, i))
+            .collect(),
+        indices: &data.indices,
+        quadrics: vec![Quadric::default(); vertex_count],
+        locked: vec![false; vertex_count],
+        parent: (0..vertex_count as u32).collect(),
+        vertex_tris: vec![Vec::new(); vertex_count],
+        dead: vec![false; tri_count],
+    };
+
+    // the edges used by a single triangle are on a border
+    let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+
+    for (t, tri) in data.indices.chunks(3).enumerate() {
+        let (a, b, c) = (
+            s.positions[tri[0] as usize],
+            s.positions[tri[1] as usize],
+            s.positions[tri[2] as usize],
+        );
+
+        let n = (b - a).cross(c - a);
+        if n.magnitude2() > 0.0 {
+            let n = n.normalize();
+            let q = Quadric::from_plane(n, -n.dot(a));
+            for &v in tri.iter() {
+                s.quadrics[v as usize] = s.quadrics[v as usize].add(&q);
+            }
+        }
+
+        for k in 0..3 {
+            let (v0, v1) = (tri[k], tri[(k + 1) % 3]);
+            *edges.entry((v0.min(v1), v0.max(v1))).or_insert(0) += 1;
+            s.vertex_tris[v0 as usize].push(t);
+        }
+    }
+
+    for (&(a, b), &count) in edges.iter() {
+        if count == 1 {
+            s.locked[a as usize] = true;
+            s.locked[b as usize] = true;
+        }
+    }
+
+    let mut heap: BinaryHeap<Collapse> = edges
+        .keys()
+        .filter_map(|&(a, b)| s.best_collapse(a, b))
+        .collect();
+
+    let target = (tri_count as f32 * ratio.max(0.0).min(1.0)) as usize;
+    let max_error = (max_error * max_error) as f64;
+    let mut live = tri_count;
+
+    while live > target {
+        let c = match heap.pop() {
+            Some(c) => c,
+            None => break,
+        };
+
+        if c.cost > max_error {
+            break;
+        }
+
+        // one side was collapsed since, try again with the new vertices
+        let (from, to) = (s.find(c.from), s.find(c.to));
+        if from != c.from || to != c.to {
+            if from != to {
+                heap.extend(s.best_collapse(from, to));
+            }
+            continue;
+        }
+
+        // the quadrics grew since, the collapse is no more the cheapest
+        let current = match s.best_collapse(from, to) {
+            Some(current) => current,
+            None => continue,
+        };
+        if current.cost > c.cost || current.from != from {
+            heap.push(current);
+            continue;
+        }
+
+        if s.flips(from, to) {
+            continue;
+        }
+
+        live -= s.collapse(from, to);
+
+        // the edges around the new vertex
+        let mut neighbors = Vec::new();
+        for t in s.vertex_tris[to as usize].clone() {
+            if s.dead[t] {
+                continue;
+            }
+            neighbors.extend(s.triangle(t).iter().cloned().filter(|&v| v != to));
+        }
+        neighbors.sort();
+        neighbors.dedup();
+
+        for v in neighbors {
+            heap.extend(s.best_collapse(to, v));
+        }
+    }
+
+    let mut indices = Vec::with_capacity(live * 3);
+    for t in 0..tri_count {
+        if !s.dead[t] {
+            indices.extend_from_slice(&s.triangle(t));
+        }
+    }
+
+    result.indices = indices;
+    compact(&mut result);
+    result
+}
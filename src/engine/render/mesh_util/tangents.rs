@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::f32;
+
+use engine::MeshData;
+use math::*;
+use super::{gather_vertices, push_vec3, vec2_at, vec3_at};
+
+/// A triangle of the MikkTSpace algorithm
+struct Face {
+    /// Direction of the derivative of the position along u
+    os: Vector3f,
+    /// The uv mapping keeps the winding of the triangle
+    preserving: bool,
+    /// The derivatives are degenerated, the triangle takes the orientation of its first group
+    group_with_any: bool,
+    /// Two corners of the triangle are the same vertex
+    degenerate: bool,
+    /// The triangle across the edge from the corner `i` to the corner `i + 1`
+    neighbors: [Option<usize>; 3],
+    /// The group of each corner
+    groups: [Option<usize>; 3],
+}
+
+/// The triangles around a vertex connected by their edges, with the same orientation
+struct Group {
+    vertex: u32,
+    preserving: bool,
+    faces: Vec<usize>,
+}
+
+#[inline]
+fn not_zero(v: f32) -> bool {
+    v.abs() > f32::MIN_POSITIVE
+}
+
+/// `v` projected on the plane of the normal `n` and normalized
+fn project(v: Vector3f, n: Vector3f) -> Vector3f {
+    let v = v - n * n.dot(v);
+    let len = v.magnitude();
+    if not_zero(len) {
+        v / len
+    } else {
+        v
+    }
+}
+
+/// Any unit vector orthogonal to `n`
+fn orthogonal(n: Vector3f) -> Vector3f {
+    let axis = if n.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+
+    (axis - n * n.dot(axis)).normalize()
+}
+
+/// The first vertex with the same position, normal and uv of each vertex
+fn shared_vertices(data: &MeshData, normals: &[f32], uvs: &[f32]) -> Vec<u32> {
+    let mut first = HashMap::new();
+
+    (0..data.vertex_count() as u32)
+        .map(|i| {
+            let p = vec3_at(&data.vertices, i);
+            let n = vec3_at(normals, i);
+            let uv = vec2_at(uvs, i);
+            // 0.0 and -0.0 are the same value
+            let key: Vec<u32> = [p.x, p.y, p.z, n.x, n.y, n.z, uv.x, uv.y]
+                .iter()
+                .map(|v| (v + 0.0).to_bits())
+                .collect();
+
+            *first.entry(key).or_insert(i)
+        })
+        .collect()
+}
+
+fn init_faces(corners: &[u32], positions: &[f32], uvs: &[f32]) -> Vec<Face> {
+    corners
+        .chunks(3)
+        .map(|c| {
+            let p = [
+                vec3_at(positions, c[0]),
+                vec3_at(positions, c[1]),
+                vec3_at(positions, c[2]),
+            ];
+            let uv = [vec2_at(uvs, c[0]), vec2_at(uvs, c[1]), vec2_at(uvs, c[2])];
+
+            let (d1, d2) = (p[1] - p[0], p[2] - p[0]);
+            let (t21, t31) = (uv[1] - uv[0], uv[2] - uv[0]);
+
+            let area = t21.x * t31.y - t21.y * t31.x;
+            let preserving = area > 0.0;
+            let mut os = d1 * t31.y - d2 * t21.y;
+            let ot = d2 * t21.x - d1 * t31.x;
+            let mut group_with_any = true;
+
+            if not_zero(area) {
+                let sign = if preserving { 1.0 } else { -1.0 };
+                let (len_s, len_t) = (os.magnitude(), ot.magnitude());
+                if not_zero(len_s) {
+                    os = os * (sign / len_s);
+                }
+
+                group_with_any = !(not_zero(len_s / area.abs()) && not_zero(len_t / area.abs()));
+            }
+
+            Face {
+                os,
+                preserving,
+                group_with_any,
+                degenerate: c[0] == c[1] || c[0] == c[2] || c[1] == c[2],
+                neighbors: [None; 3],
+                groups: [None; 3],
+            }
+        })
+        .collect()
+}
+
+/// Pair the edges of the triangles with the opposite edges of their neighbors
+fn build_neighbors(faces: &mut [Face], corners: &[u32]) {
+    // the edges waiting for their opposite edge
+    let mut open = HashMap::new();
+
+    for f in 0..faces.len() {
+        if faces[f].degenerate {
+            continue;
+        }
+
+        for i in 0..3 {
+            let (a, b) = (corners[f * 3 + i], corners[f * 3 + (i + 1) % 3]);
+            match open.remove(&(b, a)) {
+                Some((g, j)) => {
+                    faces[f].neighbors[i] = Some(g);
+                    faces[g].neighbors[j] = Some(f);
+                }
+                None => {
+                    open.entry((a, b)).or_insert((f, i));
+                }
+            }
+        }
+    }
+}
+
+/// Add the triangle `start` and its neighbors around the vertex of the group
+fn assign(faces: &mut [Face], corners: &[u32], start: usize, g: usize, group: &mut Group) {
+    let vertex = group.vertex;
+    let mut stack = vec![start];
+
+    while let Some(f) = stack.pop() {
+        let i = match (0..3).find(|&i| corners[f * 3 + i] == vertex) {
+            Some(i) => i,
+            None => continue,
+        };
+
+        let face = &mut faces[f];
+        if face.groups[i].is_some() {
+            continue;
+        }
+
+        if face.group_with_any && face.groups.iter().all(Option::is_none) {
+            face.preserving = group.preserving;
+        }
+        if face.preserving != group.preserving {
+            continue;
+        }
+
+        face.groups[i] = Some(g);
+        group.faces.push(f);
+
+        // the two edges of the triangle around the vertex
+        stack.extend(face.neighbors[i]);
+        stack.extend(face.neighbors[(i + 2) % 3]);
+    }
+}
+
+fn build_groups(faces: &mut [Face], corners: &[u32]) -> Vec<Group> {
+    let mut groups = Vec::new();
+
+    for f in 0..faces.len() {
+        if faces[f].degenerate || faces[f].group_with_any {
+            continue;
+        }
+
+        for i in 0..3 {
+            if faces[f].groups[i].is_none() {
+                let mut group = Group {
+                    vertex: corners[f * 3 + i],
+                    preserving: faces[f].preserving,
+                    faces: Vec::new(),
+                };
+
+                let g = groups.len();
+                assign(faces, corners, f, g, &mut group);
+                groups.push(group);
+            }
+        }
+    }
+
+    groups
+}
+
+/// The tangent of a group: the tangents of its triangles projected on the plane of the
+/// normal and weighted by the angle of the triangles at the vertex
+fn eval_tangent(group: &Group, faces: &[Face], corners: &[u32], positions: &[f32], normals: &[f32]) -> Vector3f {
+    let mut sum = Vector3::zero();
+
+    for &f in group.faces.iter() {
+        if faces[f].group_with_any {
+            continue;
+        }
+
+        let i = match (0..3).find(|&i| corners[f * 3 + i] == group.vertex) {
+            Some(i) => i,
+            None => continue,
+        };
+
+        let n = vec3_at(normals, group.vertex);
+        let p = vec3_at(positions, group.vertex);
+        let prev = vec3_at(positions, corners[f * 3 + (i + 2) % 3]);
+        let next = vec3_at(positions, corners[f * 3 + (i + 1) % 3]);
+
+        let (e1, e2) = (project(prev - p, n), project(next - p, n));
+        let angle = e1.dot(e2).max(-1.0).min(1.0).acos();
+
+        sum += project(faces[f].os, n) * angle;
+    }
+
+    sum
+}
+
+/// MikkTSpace tangents and bitangents of a mesh with normals and uvs, so the normal
+/// maps baked by other tools line up.
+///
+/// The corners with the same position, normal and uv are the same vertex. Around each
+/// vertex the triangles connected by their edges with the same uv orientation make a
+/// group, the tangents of a group are projected on the plane of the normal and weighted
+/// by the angle of the triangles at the vertex. With the default angular threshold of
+/// 180 degrees a group has a single tangent space. The bitangent is `sign * cross(n, t)`.
+///
+/// A vertex in several groups, on a mirrored uv seam, is split.
+///
+/// Returns false when the mesh has no normals or no uvs.
+pub fn compute_tangents(data: &mut MeshData) -> bool {
+    let (order, indices, tangents, bitangents) = {
+        let (uvs, normals) = match (data.uvs.as_ref(), data.normals.as_ref()) {
+            (Some(uvs), Some(normals)) => (uvs, normals),
+            _ => return false,
+        };
+
+        let shared = shared_vertices(data, normals, uvs);
+        let triangles = data.indices.len() / 3;
+        let corners: Vec<u32> = data.indices[..triangles * 3]
+            .iter()
+            .map(|&i| shared[i as usize])
+            .collect();
+
+        let mut faces = init_faces(&corners, &data.vertices, uvs);
+        build_neighbors(&mut faces, &corners);
+        let groups = build_groups(&mut faces, &corners);
+
+        let group_tangents: Vec<Vector3f> = groups
+            .iter()
+            .map(|g| eval_tangent(g, &faces, &corners, &data.vertices, normals))
+            .collect();
+
+        // the tangent and the sign of the bitangent of each corner
+        let mut spaces = Vec::with_capacity(corners.len());
+        for face in faces.iter() {
+            for i in 0..3 {
+                spaces.push(face.groups[i].map(|g| {
+                    let sign = if groups[g].preserving { 1.0 } else { -1.0 };
+                    (group_tangents[g], sign)
+                }));
+            }
+        }
+
+        // the corners out of any group copy the tangent space of the same vertex
+        let mut by_vertex = HashMap::new();
+        for (c, space) in spaces.iter().enumerate() {
+            if let Some(space) = *space {
+                by_vertex.entry(corners[c]).or_insert(space);
+            }
+        }
+
+        let count = data.vertex_count();
+        let mut order: Vec<u32> = (0..count as u32).collect();
+        let mut vertex_spaces: Vec<Option<(Vector3f, f32)>> = vec![None; count];
+        let mut indices = data.indices.clone();
+        let mut split = HashMap::new();
+
+        for (c, space) in spaces.into_iter().enumerate() {
+            let v = indices[c];
+            let n = vec3_at(normals, v);
+            let (t, sign) = space
+                .or_else(|| by_vertex.get(&corners[c]).cloned())
+                .unwrap_or((Vector3::zero(), 1.0));
+            let t = if not_zero(t.magnitude()) {
+                t.normalize()
+            } else {
+                orthogonal(n)
+            };
+
+            let current = vertex_spaces[v as usize];
+            match current {
+                None => vertex_spaces[v as usize] = Some((t, sign)),
+                Some(s) if s == (t, sign) => (),
+                Some(_) => {
+                    // the vertex has several tangent spaces, it is split
+                    let key = (v, [t.x.to_bits(), t.y.to_bits(), t.z.to_bits()], sign > 0.0);
+                    indices[c] = *split.entry(key).or_insert_with(|| {
+                        order.push(v);
+                        vertex_spaces.push(Some((t, sign)));
+                        order.len() as u32 - 1
+                    });
+                }
+            }
+        }
+
+        let mut tangents = Vec::with_capacity(order.len() * 3);
+        let mut bitangents = Vec::with_capacity(order.len() * 3);
+
+        for (&space, &v) in vertex_spaces.iter().zip(order.iter()) {
+            let n = vec3_at(normals, v);
+            let (t, sign) = space.unwrap_or_else(|| (orthogonal(n), 1.0));
+
+            push_vec3(&mut tangents, &t);
+            push_vec3(&mut bitangents, &(n.cross(t) * sign));
+        }
+
+        (order, indices, tangents, bitangents)
+    };
+
+    data.tangents = None;
+    data.bitangents = None;
+    if order.len() > data.vertex_count() {
+        gather_vertices(data, &order);
+    }
+
+    data.indices = indices;
+    data.tangents = Some(tangents);
+    data.bitangents = Some(bitangents);
+    true
+}
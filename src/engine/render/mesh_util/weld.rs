@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use engine::MeshData;
+use super::{attributes, gather_vertices};
+
+/// An attribute value compared exactly, or quantized to `epsilon`
+fn quantize(v: f32, epsilon: f32) -> i64 {
+    if epsilon > 0.0 {
+        (v / epsilon).round() as i64
+    } else {
+        // 0.0 and -0.0 are the same value
+        (v + 0.0).to_bits() as i64
+    }
+}
+
+/// Merge the vertices with the same attributes into an indexed mesh.
+///
+/// With `epsilon > 0.0` the attributes are quantized to `epsilon` before
+/// being compared, `0.0` merges the exactly equal vertices only.
+pub fn weld(data: &mut MeshData, epsilon: f32) {
+    let count = data.vertex_count();
+
+    let mut first: HashMap<Vec<i64>, u32> = HashMap::with_capacity(count);
+    // old vertex to new vertex
+    let mut remap = Vec::with_capacity(count);
+    // new vertex to old vertex
+    let mut order = Vec::new();
+
+    {
+        let attrs = attributes(data);
+        let mut key = Vec::new();
+
+        for i in 0..count {
+            key.clear();
+            for &(size, values) in attrs.iter() {
+                for v in values[i * size..(i + 1) * size].iter() {
+                    key.push(quantize(*v, epsilon));
+                }
+            }
+
+            let next = order.len() as u32;
+            let id = *first.entry(key.clone()).or_insert(next);
+            if id == next {
+                order.push(i as u32);
+            }
+            remap.push(id);
+        }
+    }
+
+    gather_vertices(data, &order);
+    for i in data.indices.iter_mut() {
+        *i = remap[*i as usize];
+    }
+}
+
+/// Give each index its own vertex, the triangles do not share any vertex anymore
+pub fn unweld(data: &mut MeshData) {
+    let order = data.indices.clone();
+    gather_vertices(data, &order);
+
+    for (n, i) in data.indices.iter_mut().enumerate() {
+        *i = n as u32;
+    }
+}
+
+/// Drop the vertices which are not used by any index.
+/// The vertices are sorted in the order of their first use, which helps the vertex fetch.
+pub fn compact(data: &mut MeshData) {
+    const UNUSED: u32 = ::std::u32::MAX;
+
+    let mut remap = vec![UNUSED; data.vertex_count()];
+    let mut order = Vec::new();
+
+    for i in data.indices.iter_mut() {
+        let r = &mut remap[*i as usize];
+        if *r == UNUSED {
+            *r = order.len() as u32;
+            order.push(*i);
+        }
+        *i = *r;
+    }
+
+    gather_vertices(data, &order);
+}
@@ -9,6 +9,8 @@ mod uniforms;
 mod frame_buffer;
 mod render_texture;
 mod mesh_buffer;
+mod font;
+mod lod;
 
 #[derive(Hash, Eq, Ord, PartialOrd, PartialEq, Copy, Clone, Debug)]
 pub enum RenderQueue {
@@ -27,8 +29,10 @@ pub use self::shader_program::ShaderProgram;
 pub use self::texture::{Texture, TextureAsset, TextureAttachment, TextureFiltering, TextureImage,
                         TextureWrap};
 pub use self::mesh::{Mesh, MeshSurface};
-pub use self::mesh_buffer::{MeshBuffer, MeshData};
+pub use self::lod::{LodGroup, LodLevel};
+pub use self::mesh_buffer::{BufferUsage, MeshBuffer, MeshData, Primitive, VertexAttribute};
 pub use self::material::{CullMode, DepthTest, Material, MaterialParam, MaterialParamMap,
-                         MaterialState};
+                         MaterialState, Scissor};
 pub use self::light::{DirectionalLight, Light, PointLight};
 pub use self::render_texture::RenderTexture;
+pub use self::font::{Font, FontFace, FontMetrics, GlyphQuad, TextLine};
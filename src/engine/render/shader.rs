@@ -36,13 +36,18 @@ impl ShaderKindProvider for ShaderKindFs {
 }
 
 #[derive(Debug)]
-pub struct PreprocessedShaderCode(String);
+pub struct PreprocessedShaderCode(String, Vec<String>);
 
 impl PreprocessedShaderCode {
     pub fn as_string(&self) -> &String {
         &self.0
     }
 
+    /// The files included by the code
+    pub fn includes(&self) -> &[String] {
+        &self.1
+    }
+
     pub fn new(
         kind: ShaderKind,
         s: &str,
@@ -76,8 +81,9 @@ impl PreprocessedShaderCode {
         }
 
         let processed = preprocessor::preprocess(&s, &predefs, external_files);
+        let includes = external_files.keys().cloned().collect();
 
-        processed.map(|s| PreprocessedShaderCode(prefix + &s))
+        processed.map(|s| PreprocessedShaderCode(prefix + &s, includes))
     }
 }
 
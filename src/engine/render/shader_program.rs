@@ -1,10 +1,12 @@
-use engine::asset::{Asset, AssetResult, AssetSystem, FileFuture, LoadableAsset, Resource};
+use engine::asset::{Asset, AssetError, AssetResult, AssetState, AssetSystem, FileFuture,
+                    LoadableAsset, ReloadableAsset, Resource};
 use engine::render::shader::{ShaderFs, ShaderVs};
 use engine::render::uniforms::*;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
-use webgl::{ShaderKind as WebGLShaderKind, WebGLProgram, WebGLRenderingContext};
+use webgl::{ShaderKind as WebGLShaderKind, ShaderParameter, WebGLProgram, WebGLRenderingContext,
+            WebGLShader};
 
 use std::borrow::Cow;
 
@@ -16,6 +18,10 @@ pub enum ShaderAttrib {
     Normal = 2,
     Tangent = 3,
     Bitangent = 4,
+    Color = 5,
+    UV1 = 6,
+    Joints = 7,
+    Weights = 8,
 }
 
 impl Asset for ShaderProgram {
@@ -24,6 +30,8 @@ impl Asset for ShaderProgram {
     fn new_from_resource((vs, fs): Self::Resource) -> Rc<ShaderProgram> {
         Rc::new(ShaderProgram {
             gl_state: RefCell::new(None),
+            stale: Cell::new(false),
+            error: RefCell::new(None),
 
             coord_map: Default::default(),
             uniform_cache: Default::default(),
@@ -53,6 +61,31 @@ impl LoadableAsset for ShaderProgram {
     }
 }
 
+impl ReloadableAsset for ShaderProgram {
+    fn depends_on(&self, name: &str, file: &str) -> bool {
+        if file == format!("{}_vs.glsl", name) || file == format!("{}_fs.glsl", name) {
+            return true;
+        }
+
+        self.includes().iter().any(|f| f == file)
+    }
+
+    fn poll_reload(r: &Self::Resource) -> AssetResult<bool> {
+        let vs = r.0.poll()?;
+        let fs = r.1.poll()?;
+
+        Ok(vs && fs)
+    }
+
+    fn reload(&self, (vs, fs): Self::Resource) {
+        self.vs_shader.swap(&vs);
+        self.fs_shader.swap(&fs);
+
+        // the old program is used until the new one compiles
+        self.stale.set(true);
+    }
+}
+
 #[derive(Debug)]
 pub struct ShaderProgramGLState {
     prog: WebGLProgram,
@@ -61,6 +94,9 @@ pub struct ShaderProgramGLState {
 #[derive(Debug)]
 pub struct ShaderProgram {
     gl_state: RefCell<Option<ShaderProgramGLState>>,
+    /// the shaders were reloaded, the program is built again
+    stale: Cell<bool>,
+    error: RefCell<Option<AssetError>>,
 
     coord_map: RefCell<HashMap<String, Option<u32>>>,
 
@@ -85,17 +121,86 @@ impl ShaderProgram {
     }
 
     fn prepare(&self, gl: &WebGLRenderingContext) -> AssetResult<()> {
-        if self.gl_state.borrow().is_some() {
-            return Ok(());
+        if !self.stale.get() {
+            if self.gl_state.borrow().is_some() {
+                return Ok(());
+            }
+
+            // it is compiled again only when reloaded
+            if let Some(ref e) = *self.error.borrow() {
+                return Err(e.clone());
+            }
         }
 
         let vs = self.vs_shader.try_borrow()?;
         let fs = self.fs_shader.try_borrow()?;
+        self.stale.set(false);
 
-        let state = Some(ShaderProgramGLState::new(gl, &vs, &fs));
-        *self.gl_state.borrow_mut() = state;
+        match ShaderProgramGLState::new(gl, &vs, &fs) {
+            Ok(state) => {
+                *self.error.borrow_mut() = None;
+                self.coord_map.borrow_mut().clear();
+                self.uniform_cache.reset();
 
-        Ok(())
+                // the program replaced by a reload
+                let old = ::std::mem::replace(&mut *self.gl_state.borrow_mut(), Some(state));
+                if let Some(old) = old {
+                    gl.delete_program(&old.prog);
+                }
+
+                Ok(())
+            }
+            Err(log) => {
+                let err = AssetError::InvalidFormat {
+                    path: fs.filename.clone(),
+                    len: fs.code.as_string().len(),
+                    reason: log,
+                };
+                *self.error.borrow_mut() = Some(err.clone());
+
+                // keep the old program after a reload
+                if self.gl_state.borrow().is_some() {
+                    return Ok(());
+                }
+
+                Err(err)
+            }
+        }
+    }
+
+    /// The errors of the last compilation, if it failed
+    pub fn compile_error(&self) -> Option<String> {
+        match *self.error.borrow() {
+            Some(AssetError::InvalidFormat { ref reason, .. }) => Some(reason.clone()),
+            _ => None,
+        }
+    }
+
+    /// Failed when a shader failed to load, or to compile without an older program to use
+    pub fn state(&self) -> AssetState {
+        let state = self.vs_shader.state().merge(self.fs_shader.state());
+        if !state.is_ready() || self.gl_state.borrow().is_some() {
+            return state;
+        }
+
+        match *self.error.borrow() {
+            Some(ref e) => AssetState::Failed(e.clone()),
+            None => state,
+        }
+    }
+
+    /// The files included by the shaders
+    pub fn includes(&self) -> Vec<String> {
+        let mut files = Vec::new();
+
+        if let Ok(vs) = self.vs_shader.try_borrow() {
+            files.extend(vs.code.includes().iter().cloned());
+        }
+        if let Ok(fs) = self.fs_shader.try_borrow() {
+            files.extend(fs.code.includes().iter().cloned());
+        }
+
+        files
     }
 
     pub fn attrib_loc(&self, gl: &WebGLRenderingContext, s: &str) -> Option<u32> {
@@ -134,7 +239,7 @@ impl ShaderProgramGLState {
         gl: &WebGLRenderingContext,
         vs_unit: &ShaderVs,
         fs_unit: &ShaderFs,
-    ) -> ShaderProgramGLState {
+    ) -> Result<ShaderProgramGLState, String> {
         /*================ Shaders ====================*/
 
         // Create a vertex shader object
@@ -146,6 +251,10 @@ impl ShaderProgramGLState {
         // Compile the vertex shader
         uni_app::App::print(format!("Compiling shader file : {}\n", vs_unit.filename));
         gl.compile_shader(&vert_shader);
+        if let Err(err) = check_compile(gl, &vert_shader, &vs_unit.filename) {
+            gl.delete_shader(&vert_shader);
+            return Err(err);
+        }
 
         // Create fragment shader object
         let frag_shader = gl.create_shader(WebGLShaderKind::Fragment);
@@ -156,6 +265,11 @@ impl ShaderProgramGLState {
         // Compile the fragmentt shader
         uni_app::App::print(format!("Compiling shader file : {}\n", fs_unit.filename));
         gl.compile_shader(&frag_shader);
+        if let Err(err) = check_compile(gl, &frag_shader, &fs_unit.filename) {
+            gl.delete_shader(&vert_shader);
+            gl.delete_shader(&frag_shader);
+            return Err(err);
+        }
 
         // Create a shader program object to store
         // the combined shader program
@@ -193,14 +307,49 @@ impl ShaderProgramGLState {
             "aVertexBitangent",
             ShaderAttrib::Bitangent as _,
         );
+        gl.bind_attrib_location(&shader_program, "aVertexColor", ShaderAttrib::Color as _);
+        gl.bind_attrib_location(&shader_program, "aTextureCoord1", ShaderAttrib::UV1 as _);
+        gl.bind_attrib_location(&shader_program, "aJoints", ShaderAttrib::Joints as _);
+        gl.bind_attrib_location(&shader_program, "aWeights", ShaderAttrib::Weights as _);
 
         // Link both the programs
         gl.link_program(&shader_program);
 
+        // The shaders are freed with the program they are attached to
+        gl.delete_shader(&vert_shader);
+        gl.delete_shader(&frag_shader);
+
+        if gl.get_program_parameter(&shader_program, ShaderParameter::LinkStatus) == 0 {
+            let err = format!(
+                "Fail to link {} and {}:\n{}",
+                vs_unit.filename,
+                fs_unit.filename,
+                gl.get_program_info_log(&shader_program)
+            );
+            gl.delete_program(&shader_program);
+            return Err(err);
+        }
+
         let prog = ShaderProgramGLState {
             prog: shader_program,
         };
 
-        prog
+        Ok(prog)
     }
 }
+
+fn check_compile(
+    gl: &WebGLRenderingContext,
+    shader: &WebGLShader,
+    filename: &str,
+) -> Result<(), String> {
+    if gl.get_shader_parameter(shader, ShaderParameter::CompileStatus) == 0 {
+        return Err(format!(
+            "Fail to compile {}:\n{}",
+            filename,
+            gl.get_shader_info_log(shader)
+        ));
+    }
+
+    Ok(())
+}
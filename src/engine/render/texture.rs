@@ -3,7 +3,8 @@ use webgl::*;
 
 use image::{RgbImage, RgbaImage};
 
-use engine::asset::{Asset, AssetResult, AssetSystem, FileFuture, LoadableAsset, Resource, DDS};
+use engine::asset::{Asset, AssetResult, AssetState, AssetSystem, FileFuture, LoadableAsset,
+                    ReloadableAsset, Resource, DDS};
 use std::cell::{Cell, RefCell};
 use std::path::Path;
 use std::rc::Rc;
@@ -43,6 +44,17 @@ enum TextureKind {
         size: (u32, u32),
         attach: TextureAttachment,
     },
+    Dynamic {
+        size: (u32, u32),
+        updates: RefCell<Vec<TextureRegion>>,
+    },
+}
+
+/// Part of a dynamic texture waiting to be uploaded
+#[derive(Debug)]
+struct TextureRegion {
+    pos: (u32, u32),
+    img: RgbaImage,
 }
 
 #[derive(Debug)]
@@ -53,6 +65,8 @@ pub struct Texture {
     pub wrap_w: Cell<Option<TextureWrap>>,
 
     gl_state: RefCell<Option<TextureGLState>>,
+    /// the images were reloaded, the texture is uploaded again
+    stale: Cell<bool>,
     kind: TextureKind,
 }
 
@@ -78,12 +92,14 @@ impl Asset for Texture {
                 wrap_v: Cell::new(TextureWrap::ClampToEdge),
                 wrap_w: Cell::new(None),
                 gl_state: RefCell::new(None),
+                stale: Cell::new(false),
                 kind: TextureKind::Image(res),
             }),
 
             TextureAsset::Cube(res) => Rc::new(Texture {
                 filtering: Cell::new(TextureFiltering::Linear),
                 gl_state: RefCell::new(None),
+                stale: Cell::new(false),
                 kind: TextureKind::CubeMap(res),
                 wrap_u: Cell::new(TextureWrap::ClampToEdge),
                 wrap_v: Cell::new(TextureWrap::ClampToEdge),
@@ -116,34 +132,78 @@ impl LoadableAsset for Texture {
     }
 
     fn gather<T: AssetSystem>(asys: &T, fname: &str) -> Vec<FileFuture> {
-        let path = Path::new(fname);
-        let ext = path.extension();
-        let stem = path.file_stem();
-        let parent = path.parent();
-        let parent = parent.map_or("".to_string(), |p| p.to_str().unwrap().to_string() + "/");
-
-        if ext.is_none() || stem.is_none() {
-            return vec![asys.new_file(fname)];
+        texture_files(fname)
+            .iter()
+            .map(|f| asys.new_file(f))
+            .collect()
+    }
+}
+
+impl ReloadableAsset for Texture {
+    fn depends_on(&self, name: &str, file: &str) -> bool {
+        texture_files(name).iter().any(|f| f == file)
+    }
+
+    fn poll_reload(r: &Self::Resource) -> AssetResult<bool> {
+        match r {
+            &TextureAsset::Single(ref img) => img.poll(),
+            &TextureAsset::Cube(ref faces) => {
+                let mut ready = true;
+                for face in faces.iter() {
+                    ready = face.poll()? && ready;
+                }
+                Ok(ready)
+            }
         }
+    }
 
-        let ext = ext.unwrap().to_str().unwrap();
-        let stem = stem.unwrap().to_str().unwrap();
-        let tag = "_cubemap";
-
-        if stem.to_lowercase().ends_with(tag) {
-            let f = (&stem[..stem.len() - tag.len()]).to_string();
-            return vec![
-                asys.new_file(&format!("{}{}_right.{}", &parent, &f, ext)),
-                asys.new_file(&format!("{}{}_left.{}", &parent, &f, ext)),
-                asys.new_file(&format!("{}{}_top.{}", &parent, &f, ext)),
-                asys.new_file(&format!("{}{}_bottom.{}", &parent, &f, ext)),
-                asys.new_file(&format!("{}{}_front.{}", &parent, &f, ext)),
-                asys.new_file(&format!("{}{}_back.{}", &parent, &f, ext)),
-            ];
+    fn reload(&self, r: Self::Resource) {
+        match (&self.kind, &r) {
+            (&TextureKind::Image(ref img), &TextureAsset::Single(ref new_img)) => {
+                img.swap(new_img);
+            }
+            (&TextureKind::CubeMap(ref faces), &TextureAsset::Cube(ref new_faces)) => {
+                for (face, new_face) in faces.iter().zip(new_faces.iter()) {
+                    face.swap(new_face);
+                }
+            }
+            _ => return,
         }
 
-        vec![asys.new_file(fname)]
+        // the old texture is used until the new one is uploaded
+        self.stale.set(true);
+    }
+}
+
+/// The files of a texture, the 6 faces of a cube map are named after a "_cubemap" file
+fn texture_files(fname: &str) -> Vec<String> {
+    let path = Path::new(fname);
+    let ext = path.extension();
+    let stem = path.file_stem();
+    let parent = path.parent();
+    let parent = parent.map_or("".to_string(), |p| p.to_str().unwrap().to_string() + "/");
+
+    if ext.is_none() || stem.is_none() {
+        return vec![fname.to_string()];
+    }
+
+    let ext = ext.unwrap().to_str().unwrap();
+    let stem = stem.unwrap().to_str().unwrap();
+    let tag = "_cubemap";
+
+    if stem.to_lowercase().ends_with(tag) {
+        let f = (&stem[..stem.len() - tag.len()]).to_string();
+        return vec![
+            format!("{}{}_right.{}", &parent, &f, ext),
+            format!("{}{}_left.{}", &parent, &f, ext),
+            format!("{}{}_top.{}", &parent, &f, ext),
+            format!("{}{}_bottom.{}", &parent, &f, ext),
+            format!("{}{}_front.{}", &parent, &f, ext),
+            format!("{}{}_back.{}", &parent, &f, ext),
+        ];
     }
+
+    vec![fname.to_string()]
 }
 
 #[derive(Debug)]
@@ -157,6 +217,7 @@ impl Texture {
         Rc::new(Texture {
             filtering: Cell::new(TextureFiltering::Linear),
             gl_state: RefCell::new(None),
+            stale: Cell::new(false),
             wrap_u: Cell::new(TextureWrap::ClampToEdge),
             wrap_v: Cell::new(TextureWrap::ClampToEdge),
             wrap_w: Cell::new(None),
@@ -167,6 +228,44 @@ impl Texture {
         })
     }
 
+    /// Texture which content is changed with `update_region`, e.g. a glyph atlas.
+    /// It starts fully transparent and has no mipmap.
+    pub fn new_dynamic(width: u32, height: u32) -> Rc<Self> {
+        Rc::new(Texture {
+            filtering: Cell::new(TextureFiltering::Linear),
+            gl_state: RefCell::new(None),
+            stale: Cell::new(false),
+            wrap_u: Cell::new(TextureWrap::ClampToEdge),
+            wrap_v: Cell::new(TextureWrap::ClampToEdge),
+            wrap_w: Cell::new(None),
+            kind: TextureKind::Dynamic {
+                size: (width, height),
+                updates: RefCell::new(Vec::new()),
+            },
+        })
+    }
+
+    /// Replace the pixels of a dynamic texture at `pos`,
+    /// the upload happens the next time the texture is bound.
+    pub fn update_region(&self, pos: (u32, u32), img: RgbaImage) {
+        match self.kind {
+            TextureKind::Dynamic { ref updates, .. } => {
+                updates.borrow_mut().push(TextureRegion { pos, img });
+            }
+            _ => panic!("Only dynamic texture can be updated"),
+        }
+    }
+
+    pub fn state(&self) -> AssetState {
+        match self.kind {
+            TextureKind::Image(ref img) => img.state(),
+            TextureKind::CubeMap(ref faces) => faces
+                .iter()
+                .fold(AssetState::Ready, |state, face| state.merge(face.state())),
+            _ => AssetState::Ready,
+        }
+    }
+
     pub fn size(&self) -> Option<(u32, u32)> {
         self.gl_state.borrow().as_ref().map(|s| s.size)
     }
@@ -183,6 +282,22 @@ impl Texture {
             _ => gl.bind_texture(&state.tex),
         }
 
+        if let TextureKind::Dynamic { ref updates, .. } = self.kind {
+            for region in updates.borrow_mut().drain(..) {
+                gl.tex_sub_image2d(
+                    TextureBindPoint::Texture2d,
+                    0,
+                    region.pos.0 as u16,
+                    region.pos.1 as u16,
+                    region.img.width() as u16,
+                    region.img.height() as u16,
+                    PixelFormat::Rgba,
+                    PixelType::UnsignedByte,
+                    &*region.img,
+                );
+            }
+        }
+
         Ok(())
     }
 
@@ -214,7 +329,7 @@ impl Texture {
     }
 
     pub fn prepare(&self, gl: &WebGLRenderingContext, unit: u32) -> AssetResult<()> {
-        if self.gl_state.borrow().is_some() {
+        if self.gl_state.borrow().is_some() && !self.stale.get() {
             return Ok(());
         }
 
@@ -228,7 +343,10 @@ impl Texture {
             unit,
         )?);
 
-        self.gl_state.replace(new_state);
+        self.stale.set(false);
+        if let Some(old) = self.gl_state.replace(new_state) {
+            gl.delete_texture(&old.tex);
+        }
 
         Ok(())
     }
@@ -484,6 +602,23 @@ fn texture_bind_buffer(
 
             (tex, size, false)
         }
+
+        &TextureKind::Dynamic { size, .. } => {
+            let tex = gl.create_texture();
+            gl.active_texture(unit);
+            gl.bind_texture(&tex);
+            gl.tex_image2d(
+                TextureBindPoint::Texture2d,              // target
+                0,                                        // level
+                size.0 as u16,                            // width
+                size.1 as u16,                            // height
+                PixelFormat::Rgba,                        // format
+                PixelType::UnsignedByte,                  // type
+                &vec![0; (size.0 * size.1 * 4) as usize], // data
+            );
+
+            (tex, size, false)
+        }
     };
 
     let mut filtering: (i32, i32) = match texfilter {
@@ -139,6 +139,17 @@ impl UniformCache {
         }
     }
 
+    /// Commit all the values again on the next `commit`,
+    /// and forget the locations, e.g. when the program is rebuilt
+    pub fn reset(&self) {
+        let mut pending = self.pending_entries.borrow_mut();
+        for (key, adapter) in self.uniform_entries.borrow_mut().drain() {
+            pending.entry(key).or_insert(adapter);
+        }
+
+        self.uniform_map.borrow_mut().clear();
+    }
+
     pub fn commit(&self, gl: &WebGLRenderingContext, prog: &WebGLProgram) {
         {
             let mut pending = self.pending_entries.borrow_mut();
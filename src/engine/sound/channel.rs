@@ -2,14 +2,24 @@ use std::sync::Arc;
 
 use super::SoundPlayEvent;
 use super::generator::SoundBuffer;
+use super::resample;
+use super::synth::Voice;
 
 pub struct Channel {
     event: Option<SoundPlayEvent>,
     buffer: Option<Arc<SoundBuffer>>,
+    voice: Option<Box<Voice>>,
     sample_rate: f32,
-    t: f32,
-    delta_t: f32,
+    /// number of output channels of the sound device
+    output_count: usize,
+    /// play position, in source frames
+    t: f64,
+    delta_t: f64,
     cur_output: usize,
+    /// current output frame, one value per output channel
+    frame: Vec<f32>,
+    /// the sound ends once the current frame is output
+    finished: bool,
 }
 
 impl Channel {
@@ -17,76 +27,109 @@ impl Channel {
         Self {
             event: None,
             buffer: None,
+            voice: None,
             t: 0.0,
             delta_t: 0.0,
             sample_rate: 1.0,
+            output_count: 2,
             cur_output: 0,
+            frame: vec![0.0; 2],
+            finished: false,
         }
     }
-    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+    pub fn set_output(&mut self, sample_rate: f32, output_count: usize) {
         self.sample_rate = sample_rate;
+        self.output_count = output_count.max(1);
+        self.frame = vec![0.0; self.output_count];
+        self.cur_output = 0;
     }
     pub fn set_event(&mut self, evt: SoundPlayEvent, buffer: Arc<SoundBuffer>) {
         self.event = Some(evt);
-        self.delta_t = buffer.sample_rate as f32 / self.sample_rate;
+        self.delta_t = buffer.sample_rate as f64 / self.sample_rate as f64;
         self.buffer = Some(buffer);
+        self.voice = None;
         self.cur_output = 0;
+        self.finished = false;
         self.t = 0.0;
     }
+    pub fn set_voice(&mut self, evt: SoundPlayEvent, voice: Box<Voice>) {
+        self.event = Some(evt);
+        self.buffer = None;
+        self.voice = Some(voice);
+        self.cur_output = 0;
+        self.finished = false;
+    }
     pub fn is_free(&self) -> bool {
         self.event.is_none()
     }
     pub fn next_value(&mut self) -> f32 {
-        let mut ret = 0.0;
-        if let Some(ref buffer) = self.buffer {
-            let sample_idx = self.t as usize;
-            if buffer.output_count == 1 {
-                ret = buffer.samples[sample_idx];
-            } else {
-                ret = buffer.samples[sample_idx * buffer.output_count + self.cur_output];
-            }
-            if self.delta_t != 1.0 {
-                // interpolate samples when buffer sample rate is not equal to driver sample rate
-                let interpol_coef = self.t - sample_idx as f32;
-                if interpol_coef > 0.0 {
-                    let next_sample =
-                        if buffer.output_count == 1 && sample_idx + 1 < buffer.samples.len() {
-                            buffer.samples[sample_idx + 1]
-                        } else if (sample_idx + 1) * buffer.output_count + self.cur_output
-                            < buffer.samples.len()
-                        {
-                            buffer.samples[(sample_idx + 1) * buffer.output_count + self.cur_output]
-                        } else {
-                            0.0
-                        };
-                    ret = (1.0 - interpol_coef) * ret + interpol_coef * next_sample;
+        if self.is_free() {
+            return 0.0;
+        }
+        if self.cur_output == 0 {
+            self.compute_frame();
+        }
+        let ret = self.frame[self.cur_output];
+        // alternate between the device output channels
+        self.cur_output = (self.cur_output + 1) % self.output_count;
+        if self.cur_output == 0 && self.finished {
+            self.clear();
+        }
+        ret
+    }
+    fn compute_frame(&mut self) {
+        let output_count = self.output_count;
+
+        if let Some(ref mut voice) = self.voice {
+            // voices are mono
+            let v = match voice.next_value(self.sample_rate) {
+                Some(v) => v,
+                None => {
+                    self.finished = true;
+                    0.0
                 }
+            };
+            for (dst, out) in self.frame.iter_mut().enumerate() {
+                *out = v * resample::channel_gain(0, 1, dst, output_count);
             }
-            // balance and volume
-            let event = self.event.unwrap();
-            if event.balance != 0.5 {
-                ret *= event.balance * self.cur_output as f32
-                    + (1.0 - event.balance) * (1.0 - self.cur_output as f32);
-            }
-            ret *= event.volume;
-            // alternate between left/right output channels
-            self.cur_output = 1 - self.cur_output;
-            if self.cur_output == 0 {
-                self.t += self.delta_t;
-                if self.t as usize * buffer.output_count >= buffer.samples.len() {
-                    if self.event.unwrap().do_loop {
-                        self.t = 0.0;
-                    } else {
-                        self.clear();
+        } else if let Some(ref buffer) = self.buffer {
+            let do_loop = self.event.unwrap().do_loop;
+            for (dst, out) in self.frame.iter_mut().enumerate() {
+                *out = 0.0;
+                for src in 0..buffer.output_count {
+                    let gain = resample::channel_gain(src, buffer.output_count, dst, output_count);
+                    if gain != 0.0 {
+                        *out += gain * resample::sample_at(buffer, self.t, src, do_loop);
                     }
                 }
             }
+
+            self.t += self.delta_t;
+            let len = resample::frame_count(buffer) as f64;
+            if self.t >= len {
+                if do_loop && len > 0.0 {
+                    self.t %= len;
+                } else {
+                    self.finished = true;
+                }
+            }
+        }
+
+        // balance and volume
+        let event = self.event.unwrap();
+        for (dst, out) in self.frame.iter_mut().enumerate() {
+            // even outputs are on the left, odd outputs on the right
+            let right = if output_count > 1 { (dst % 2) as f32 } else { 0.5 };
+            if event.balance != 0.5 && output_count > 1 {
+                *out *= event.balance * right + (1.0 - event.balance) * (1.0 - right);
+            }
+            *out *= event.volume;
         }
-        ret
     }
     pub fn clear(&mut self) {
         self.event = None;
         self.buffer = None;
+        self.voice = None;
     }
     pub fn get_priority(&self) -> usize {
         if let Some(ref event) = self.event {
@@ -95,3 +138,130 @@ impl Channel {
         0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::sound::resample::anti_alias;
+    use std::f32::consts::PI;
+
+    fn play_event() -> SoundPlayEvent {
+        SoundPlayEvent {
+            id: 0,
+            channel: None,
+            do_loop: false,
+            priority: 0,
+            volume: 1.0,
+            balance: 0.5,
+        }
+    }
+
+    fn sine(freq: f32, rate: usize, output_count: usize, seconds: f32) -> SoundBuffer {
+        let frames = (rate as f32 * seconds) as usize;
+        let mut samples = Vec::new();
+        for i in 0..frames {
+            let v = (2.0 * PI * freq * i as f32 / rate as f32).sin();
+            for c in 0..output_count {
+                // second channel is the inverted signal
+                samples.push(if c % 2 == 0 { v } else { -v });
+            }
+        }
+        SoundBuffer {
+            output_count,
+            sample_rate: rate,
+            samples,
+        }
+    }
+
+    /// Play the buffer and return the interleaved output
+    fn play(buffer: SoundBuffer, rate: f32, output_count: usize) -> Vec<f32> {
+        let mut chan = Channel::new();
+        chan.set_output(rate, output_count);
+        chan.set_event(play_event(), Arc::new(buffer));
+
+        let mut out = Vec::new();
+        while !chan.is_free() {
+            out.push(chan.next_value());
+        }
+        out
+    }
+
+    fn rms(v: &[f32]) -> f32 {
+        (v.iter().map(|x| x * x).sum::<f32>() / v.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn upsample_sine_matches_reference() {
+        let out = play(sine(440.0, 22050, 1, 0.1), 48000.0, 1);
+
+        assert!((out.len() as i32 - 4800).abs() <= 3);
+        // skip the edges, where the signal is interpolated with silence
+        for (i, v) in out.iter().enumerate().take(4700).skip(4) {
+            let expected = (2.0 * PI * 440.0 * i as f32 / 48000.0).sin();
+            assert!((v - expected).abs() < 0.005, "sample {} : {} != {}", i, v, expected);
+        }
+    }
+
+    #[test]
+    fn mono_to_stereo() {
+        let out = play(sine(440.0, 48000, 1, 0.01), 48000.0, 2);
+
+        assert_eq!(out.len(), 480 * 2);
+        for frame in out.chunks(2) {
+            assert_eq!(frame[0], frame[1]);
+        }
+    }
+
+    #[test]
+    fn stereo_to_mono() {
+        // the two channels are opposite, so the downmix cancels out
+        let out = play(sine(440.0, 48000, 2, 0.01), 48000.0, 1);
+
+        assert_eq!(out.len(), 480);
+        assert!(out.iter().all(|v| v.abs() < 1e-6));
+    }
+
+    #[test]
+    fn stereo_to_surround() {
+        let out = play(sine(440.0, 48000, 2, 0.01), 48000.0, 6);
+
+        assert_eq!(out.len(), 480 * 6);
+        for (i, frame) in out.chunks(6).enumerate() {
+            let expected = (2.0 * PI * 440.0 * i as f32 / 48000.0).sin();
+            assert!((frame[0] - expected).abs() < 1e-5);
+            assert!((frame[1] + expected).abs() < 1e-5);
+            assert!(frame[2..].iter().all(|v| *v == 0.0));
+        }
+    }
+
+    #[test]
+    fn balance_full_left() {
+        let mut chan = Channel::new();
+        chan.set_output(48000.0, 2);
+        let mut evt = play_event();
+        evt.balance = 0.0;
+        chan.set_event(evt, Arc::new(sine(440.0, 48000, 1, 0.01)));
+
+        let mut out = Vec::new();
+        while !chan.is_free() {
+            out.push(chan.next_value());
+        }
+        assert!(rms(&out.iter().step_by(2).cloned().collect::<Vec<_>>()) > 0.5);
+        assert!(out.iter().skip(1).step_by(2).all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn downsample_removes_aliasing() {
+        // 30kHz cannot be represented at 44.1kHz, it would alias to 14.1kHz
+        let buffer = anti_alias(sine(30000.0, 96000, 1, 0.1), 44100.0);
+        let out = play(buffer, 44100.0, 1);
+
+        assert!(rms(&out[100..out.len() - 100]) < 0.05);
+
+        // while a 1kHz tone is kept
+        let buffer = anti_alias(sine(1000.0, 96000, 1, 0.1), 44100.0);
+        let out = play(buffer, 44100.0, 1);
+
+        assert!((rms(&out[100..out.len() - 100]) - 0.707).abs() < 0.01);
+    }
+}
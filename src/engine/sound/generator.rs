@@ -2,10 +2,12 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use uni_snd::SoundGenerator;
+use hound;
 use hound::WavReader;
 
 use super::{SoundEvent, SoundPlayEvent};
 use super::channel::Channel;
+use super::synth::Voice;
 
 pub struct SoundBuffer {
     /// number of channels. 1:mono, 2: stereo
@@ -16,6 +18,30 @@ pub struct SoundBuffer {
     pub samples: Vec<f32>,
 }
 
+/// Decode a wav file. Integer samples of any size (8, 16, 24 and 32 bits)
+/// and float samples are converted to f32 between -1.0 and 1.0.
+pub fn decode_wav(data: &[u8]) -> Result<SoundBuffer, hound::Error> {
+    let mut wav = WavReader::new(data)?;
+    let spec = wav.spec();
+
+    // hound sample is interleaved
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => wav.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+        hound::SampleFormat::Int => {
+            let coef = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            wav.samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * coef))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+
+    Ok(SoundBuffer {
+        output_count: spec.channels as usize,
+        sample_rate: spec.sample_rate as usize,
+        samples,
+    })
+}
+
 pub struct Generator {
     cache: HashMap<usize, Arc<SoundBuffer>>,
     channels: Vec<Channel>,
@@ -34,7 +60,7 @@ impl Generator {
             next_channel: 0,
         }
     }
-    fn handle_play_event(&mut self, evt: &SoundPlayEvent) {
+    fn find_channel(&mut self, evt: &SoundPlayEvent) -> Option<usize> {
         let mut free_channel_id = evt.channel;
         if free_channel_id.is_none() {
             // find a free channel
@@ -60,65 +86,44 @@ impl Generator {
                 free_channel_id = Some(self.next_channel);
             }
         }
-        match free_channel_id {
+        free_channel_id
+    }
+    fn handle_play_event(&mut self, evt: &SoundPlayEvent) {
+        match self.find_channel(evt) {
             None => return, // no channel available. skip this sound
             Some(id) => {
                 self.channels[id].set_event(*evt, self.cache.get(&evt.id).unwrap().clone());
             }
         }
     }
-    fn handle_load_buffer_event(&mut self, id: usize, buffer: Vec<u8>, filepath: String) {
-        let new_buf = self.new_buffer(buffer, &filepath);
-        self.cache.insert(id, Arc::new(new_buf));
+    fn handle_play_voice_event(&mut self, evt: &SoundPlayEvent, voice: Box<Voice>) {
+        match self.find_channel(evt) {
+            None => return, // no channel available. skip this voice
+            Some(id) => self.channels[id].set_voice(*evt, voice),
+        }
+    }
+    fn handle_add_buffer_event(&mut self, id: usize, buffer: SoundBuffer) {
+        self.cache.insert(id, Arc::new(buffer));
     }
     fn handle_stop_channel_event(&mut self, channel: usize) {
         if channel < self.channels.len() {
             self.channels[channel].clear();
         }
     }
-    fn new_buffer(&mut self, buffer: Vec<u8>, filepath: &str) -> SoundBuffer {
-        let mut wav =
-            WavReader::new(&buffer[..]).expect(&format!("error cannot read from {}", filepath));
-        let spec = wav.spec();
-
-        println!(
-            "loading sound {} channels {} sample rate {} bits per sample {}",
-            filepath, spec.channels, spec.sample_rate, spec.bits_per_sample
-        );
-
-        let mut buffer = SoundBuffer {
-            output_count: spec.channels as usize,
-            sample_rate: spec.sample_rate as usize,
-            samples: Vec::new(),
-        };
-        let coef = 2.0 / (1 << spec.bits_per_sample) as f32;
-        if spec.channels == 1 {
-            // mono sample
-            for frame in wav.samples::<i32>() {
-                buffer.samples.push(frame.unwrap() as f32 * coef);
-            }
-        } else {
-            // stereo sample
-            // hound sample is interleaved
-            for frame in wav.samples::<i32>() {
-                buffer.samples.push(frame.unwrap() as f32 * coef);
-            }
-        }
-        buffer
-    }
 }
 
 impl SoundGenerator<SoundEvent> for Generator {
-    fn init(&mut self, sample_rate: f32) {
+    fn init(&mut self, sample_rate: f32, output_count: usize) {
         for chan in self.channels.iter_mut() {
-            chan.set_sample_rate(sample_rate);
+            chan.set_output(sample_rate, output_count);
         }
     }
     fn handle_event(&mut self, evt: SoundEvent) {
         match evt {
             SoundEvent::Play(ref play_evt) => self.handle_play_event(play_evt),
-            SoundEvent::LoadBuffer(id, buffer, filepath) => {
-                self.handle_load_buffer_event(id, buffer, filepath)
+            SoundEvent::AddBuffer(id, buffer) => self.handle_add_buffer_event(id, buffer),
+            SoundEvent::PlayVoice(play_evt, voice) => {
+                self.handle_play_voice_event(&play_evt, voice)
             }
             SoundEvent::StopChannel(channel) => self.handle_stop_channel_event(channel),
         }
@@ -131,3 +136,72 @@ impl SoundGenerator<SoundEvent> for Generator {
         sample / self.channels.len() as f32
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn encode_wav<F>(spec: hound::WavSpec, write: F) -> Vec<u8>
+    where
+        F: FnOnce(&mut hound::WavWriter<&mut Cursor<Vec<u8>>>),
+    {
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+            write(&mut writer);
+            writer.finalize().unwrap();
+        }
+        cursor.into_inner()
+    }
+
+    fn int_spec(bits_per_sample: u16, channels: u16) -> hound::WavSpec {
+        hound::WavSpec {
+            channels,
+            sample_rate: 22050,
+            bits_per_sample,
+            sample_format: hound::SampleFormat::Int,
+        }
+    }
+
+    #[test]
+    fn decode_int_formats() {
+        for &bits in [8u16, 16, 24, 32].iter() {
+            let max = ((1u64 << (bits - 1)) - 1) as i32;
+            let min = -max - 1;
+            let data = encode_wav(int_spec(bits, 1), |w| {
+                for &s in [0, max, min, max / 2].iter() {
+                    w.write_sample(s).unwrap();
+                }
+            });
+
+            let buffer = decode_wav(&data).unwrap();
+            assert_eq!(buffer.output_count, 1);
+            assert_eq!(buffer.sample_rate, 22050);
+            assert_eq!(buffer.samples.len(), 4);
+            assert_eq!(buffer.samples[0], 0.0);
+            assert!((buffer.samples[1] - 1.0).abs() < 0.01, "{} bits", bits);
+            assert_eq!(buffer.samples[2], -1.0);
+            assert!((buffer.samples[3] - 0.5).abs() < 0.01, "{} bits", bits);
+        }
+    }
+
+    #[test]
+    fn decode_float_stereo() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let data = encode_wav(spec, |w| {
+            for &s in [0.25f32, -0.25, 1.0, -1.0].iter() {
+                w.write_sample(s).unwrap();
+            }
+        });
+
+        let buffer = decode_wav(&data).unwrap();
+        assert_eq!(buffer.output_count, 2);
+        assert_eq!(buffer.samples, vec![0.25, -0.25, 1.0, -1.0]);
+    }
+}
@@ -1,5 +1,7 @@
 mod channel;
 mod generator;
+mod resample;
+pub mod synth;
 
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -8,12 +10,26 @@ use engine::{AssetError, AssetSystem};
 use futures::Future;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
+use uni_app;
 use uni_snd::SoundDriver;
 
-use self::generator::Generator;
+use self::generator::{decode_wav, Generator};
+use self::synth::Voice;
+
+pub use self::generator::SoundBuffer;
 
 const CHANNEL_COUNT: usize = 4;
 
+/// Filter the buffer for the device sample rate before it is sent to the mixer,
+/// so that it can be downsampled without aliasing. The filter is too slow
+/// to run in the audio callback.
+fn prepare_buffer(driver: &SoundDriver<SoundEvent>, buffer: SoundBuffer) -> SoundBuffer {
+    match driver.sample_rate() {
+        Some(rate) => resample::anti_alias(buffer, rate),
+        None => buffer,
+    }
+}
+
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
 pub struct SoundHandle(usize);
 
@@ -51,24 +67,34 @@ impl SoundSystem {
                 let f = self.asys.new_file(&filepath);
                 let driver = self.driver.clone();
 
-                let load_f = f.and_then({
+                let load_f = f.map_err(|e| AssetError::FileIoError(e)).and_then({
                     let filepath = filepath.clone();
                     let loading = self.loading.clone();
                     move |mut fdata| {
-                        driver.borrow_mut().send_event(SoundEvent::LoadBuffer(
-                            id,
-                            fdata.read_binary()?,
-                            filepath,
+                        let data = fdata.read_binary().map_err(AssetError::FileIoError)?;
+                        let buffer = decode_wav(&data).map_err(|e| AssetError::InvalidFormat {
+                            path: filepath.clone(),
+                            len: data.len(),
+                            reason: format!("{}", e),
+                        })?;
+
+                        uni_app::App::print(format!(
+                            "loading sound {} channels {} sample rate {}\n",
+                            filepath, buffer.output_count, buffer.sample_rate
                         ));
 
+                        let buffer = prepare_buffer(&driver.borrow(), buffer);
+                        driver
+                            .borrow_mut()
+                            .send_event(SoundEvent::AddBuffer(id, buffer));
+
                         loading.borrow_mut().remove(&SoundHandle(id));
 
                         Ok(())
                     }
                 });
 
-                self.asys
-                    .execute(Box::new(load_f.map_err(|e| AssetError::FileIoError(e))));
+                self.asys.execute(Box::new(load_f));
 
                 self.cache.insert(filepath.clone(), SoundHandle(id));
                 self.loading.borrow_mut().insert(SoundHandle(id));
@@ -78,6 +104,19 @@ impl SoundSystem {
             Some(buf) => *buf,
         }
     }
+    /// Register a buffer generated at runtime (e.g. with `synth::render`)
+    pub fn add_buffer(&mut self, buffer: SoundBuffer) -> SoundHandle {
+        let id = self.next_handle;
+        self.next_handle += 1;
+
+        let buffer = prepare_buffer(&self.driver.borrow(), buffer);
+        self.driver
+            .borrow_mut()
+            .send_event(SoundEvent::AddBuffer(id, buffer));
+
+        SoundHandle(id)
+    }
+
     pub fn play_sound(
         &mut self,
         id: SoundHandle,
@@ -104,6 +143,29 @@ impl SoundSystem {
         self.driver.borrow_mut().send_event(SoundEvent::Play(evt))
     }
 
+    /// Play a voice generated on the fly
+    pub fn play_voice(
+        &mut self,
+        voice: Box<Voice>,
+        channel: Option<usize>,
+        priority: usize,
+        volume: f32,
+        balance: f32,
+    ) {
+        let evt = SoundPlayEvent {
+            id: 0,
+            channel,
+            do_loop: false,
+            priority,
+            volume,
+            balance,
+        };
+
+        self.driver
+            .borrow_mut()
+            .send_event(SoundEvent::PlayVoice(evt, voice))
+    }
+
     pub fn stop_channel(&mut self, channel: usize) {
         self.driver
             .borrow_mut()
@@ -132,8 +194,9 @@ impl SoundSystem {
 }
 
 enum SoundEvent {
-    LoadBuffer(usize, Vec<u8>, String),
+    AddBuffer(usize, SoundBuffer),
     Play(SoundPlayEvent),
+    PlayVoice(SoundPlayEvent, Box<Voice>),
     StopChannel(usize),
 }
 
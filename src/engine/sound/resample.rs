@@ -0,0 +1,157 @@
+//! Sample rate conversion and channel layout mapping used by the mixer.
+
+use std::f32::consts::PI;
+
+use super::generator::SoundBuffer;
+
+/// Number of taps on each side of the anti-aliasing filter center
+const FILTER_HALF_TAPS: isize = 24;
+
+/// Catmull-Rom interpolation between `y1` (t = 0.0) and `y2` (t = 1.0)
+pub fn cubic(y0: f32, y1: f32, y2: f32, y3: f32, t: f32) -> f32 {
+    let a = -0.5 * y0 + 1.5 * y1 - 1.5 * y2 + 0.5 * y3;
+    let b = y0 - 2.5 * y1 + 2.0 * y2 - 0.5 * y3;
+    let c = -0.5 * y0 + 0.5 * y2;
+
+    ((a * t + b) * t + c) * t + y1
+}
+
+fn frame_sample(buffer: &SoundBuffer, frame: isize, channel: usize, looping: bool) -> f32 {
+    let count = frame_count(buffer) as isize;
+    if count == 0 {
+        return 0.0;
+    }
+
+    let frame = if looping {
+        ((frame % count) + count) % count
+    } else if frame < 0 || frame >= count {
+        return 0.0;
+    } else {
+        frame
+    };
+
+    buffer.samples[frame as usize * buffer.output_count + channel]
+}
+
+pub fn frame_count(buffer: &SoundBuffer) -> usize {
+    buffer.samples.len() / buffer.output_count.max(1)
+}
+
+/// Value of `channel` at the fractional frame position `t`.
+/// Outside of the buffer, the signal is silent unless `looping` is set.
+pub fn sample_at(buffer: &SoundBuffer, t: f64, channel: usize, looping: bool) -> f32 {
+    let idx = t.floor() as isize;
+    let frac = (t - idx as f64) as f32;
+
+    let y1 = frame_sample(buffer, idx, channel, looping);
+    if frac == 0.0 {
+        return y1;
+    }
+
+    cubic(
+        frame_sample(buffer, idx - 1, channel, looping),
+        y1,
+        frame_sample(buffer, idx + 1, channel, looping),
+        frame_sample(buffer, idx + 2, channel, looping),
+        frac,
+    )
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman windowed-sinc low pass kernel, `cutoff` is in cycles per sample
+fn lowpass_kernel(cutoff: f32) -> Vec<f32> {
+    let m = (FILTER_HALF_TAPS * 2) as f32;
+
+    let mut kernel: Vec<f32> = (-FILTER_HALF_TAPS..FILTER_HALF_TAPS + 1)
+        .map(|i| {
+            let n = (i + FILTER_HALF_TAPS) as f32;
+            let window = 0.42 - 0.5 * (2.0 * PI * n / m).cos() + 0.08 * (4.0 * PI * n / m).cos();
+            2.0 * cutoff * sinc(2.0 * cutoff * i as f32) * window
+        })
+        .collect();
+
+    // normalize to unity gain at DC
+    let sum: f32 = kernel.iter().sum();
+    for k in kernel.iter_mut() {
+        *k /= sum;
+    }
+
+    kernel
+}
+
+/// Remove the frequencies that cannot be represented at `target_rate`, so that
+/// downsampling the buffer does not alias.
+/// Buffers which do not need to be downsampled are returned untouched.
+pub fn anti_alias(buffer: SoundBuffer, target_rate: f32) -> SoundBuffer {
+    if buffer.sample_rate as f32 <= target_rate {
+        return buffer;
+    }
+
+    let kernel = lowpass_kernel(0.5 * target_rate / buffer.sample_rate as f32);
+    let count = frame_count(&buffer) as isize;
+    let mut samples = Vec::with_capacity(buffer.samples.len());
+
+    for frame in 0..count {
+        for channel in 0..buffer.output_count {
+            let mut acc = 0.0;
+            for (k, coef) in kernel.iter().enumerate() {
+                let src = frame + k as isize - FILTER_HALF_TAPS;
+                acc += coef * frame_sample(&buffer, src, channel, false);
+            }
+            samples.push(acc);
+        }
+    }
+
+    SoundBuffer { samples, ..buffer }
+}
+
+/// Gain of source channel `src` when mixed to stereo.
+/// Layouts follow the wav/smpte order : FL FR FC LFE SL SR
+fn stereo_gain(src: usize, src_count: usize, dst: usize) -> f32 {
+    const HALF_POWER: f32 = 0.707;
+
+    match (src_count, src) {
+        (_, 0) | (_, 1) => if src == dst {
+            1.0
+        } else {
+            0.0
+        },
+        (_, 2) if src_count == 3 || src_count >= 5 => HALF_POWER,
+        (6, 3) => 0.0,
+        (_, n) => if n % 2 == dst {
+            HALF_POWER
+        } else {
+            0.0
+        },
+    }
+}
+
+/// Gain applied to source channel `src` when it is mixed into output channel `dst`
+pub fn channel_gain(src: usize, src_count: usize, dst: usize, dst_count: usize) -> f32 {
+    if src_count == dst_count {
+        return if src == dst { 1.0 } else { 0.0 };
+    }
+
+    if src_count == 1 {
+        // mono sound goes to the front left/right speakers
+        return if dst < 2 { 1.0 } else { 0.0 };
+    }
+
+    match dst_count {
+        1 => 0.5 * (stereo_gain(src, src_count, 0) + stereo_gain(src, src_count, 1)),
+        2 => stereo_gain(src, src_count, dst),
+        // surround output : keep the channels both layouts have in common
+        _ => if src == dst {
+            1.0
+        } else {
+            0.0
+        },
+    }
+}
@@ -0,0 +1,831 @@
+//! Procedural sound synthesis
+//!
+//! Small building blocks to make retro sound effects at runtime without
+//! shipping any wav file: oscillators, ADSR envelopes, two operators FM
+//! and sfxr-style parameterized effects.
+//!
+//! Everything implements `Voice`, so it can either be rendered into a
+//! `SoundBuffer` with `render` (and registered with `SoundSystem::add_buffer`)
+//! or played live with `SoundSystem::play_voice`.
+
+use std::f32::consts::PI;
+
+use super::generator::SoundBuffer;
+
+/// A source of mono samples generated on the fly.
+pub trait Voice: Send {
+    /// Returns the next sample (between -1.0 and 1.0) at the given sample rate,
+    /// or `None` when the voice is finished.
+    fn next_value(&mut self, sample_rate: f32) -> Option<f32>;
+}
+
+/// Render a voice into a mono `SoundBuffer`.
+/// Rendering stops when the voice is finished or after `max_duration` seconds.
+pub fn render<V: Voice + ?Sized>(voice: &mut V, sample_rate: usize, max_duration: f32) -> SoundBuffer {
+    let max_samples = (max_duration * sample_rate as f32) as usize;
+    let mut samples = Vec::new();
+
+    while samples.len() < max_samples {
+        match voice.next_value(sample_rate as f32) {
+            Some(v) => samples.push(v),
+            None => break,
+        }
+    }
+
+    SoundBuffer {
+        output_count: 1,
+        sample_rate,
+        samples,
+    }
+}
+
+/// Xorshift random generator, good enough for noise and effect variations
+#[derive(Debug, Clone)]
+pub struct Rng(u32);
+
+impl Rng {
+    pub fn new(seed: u32) -> Rng {
+        // xorshift must not be seeded with 0
+        Rng(seed ^ 0x9e37_79b9 | 1)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    /// Random value between 0.0 and `range`
+    pub fn frnd(&mut self, range: f32) -> f32 {
+        (self.next_u32() % 10001) as f32 / 10000.0 * range
+    }
+
+    /// Random integer between 0 and `n` (inclusive)
+    pub fn rnd(&mut self, n: u32) -> u32 {
+        self.next_u32() % (n + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Saw,
+    Triangle,
+    Noise,
+}
+
+#[derive(Debug, Clone)]
+pub struct Oscillator {
+    pub waveform: Waveform,
+    /// Frequency in Hz
+    pub frequency: f32,
+    /// Duty cycle of the square wave, between 0.0 and 1.0
+    pub duty: f32,
+
+    phase: f32,
+    rng: Rng,
+    noise_step: u32,
+    noise_value: f32,
+}
+
+impl Oscillator {
+    pub fn new(waveform: Waveform, frequency: f32) -> Oscillator {
+        Oscillator {
+            waveform,
+            frequency,
+            duty: 0.5,
+            phase: 0.0,
+            rng: Rng::new(frequency as u32),
+            noise_step: 0,
+            noise_value: 0.0,
+        }
+    }
+
+    pub fn next_value(&mut self, sample_rate: f32) -> f32 {
+        self.next_value_with_offset(sample_rate, 0.0)
+    }
+
+    /// Same as `next_value`, but the phase is shifted by `offset` periods.
+    /// This is what phase modulation (FM) is built on.
+    pub fn next_value_with_offset(&mut self, sample_rate: f32, offset: f32) -> f32 {
+        let phase = (self.phase + offset).fract();
+        let phase = if phase < 0.0 { phase + 1.0 } else { phase };
+
+        let v = match self.waveform {
+            Waveform::Sine => (phase * 2.0 * PI).sin(),
+            Waveform::Square => if phase < self.duty {
+                1.0
+            } else {
+                -1.0
+            },
+            Waveform::Saw => 1.0 - phase * 2.0,
+            Waveform::Triangle => if phase < 0.5 {
+                phase * 4.0 - 1.0
+            } else {
+                3.0 - phase * 4.0
+            },
+            Waveform::Noise => {
+                // sample and hold 32 random values per period
+                let step = (phase * 32.0) as u32;
+                if step != self.noise_step {
+                    self.noise_step = step;
+                    self.noise_value = self.rng.frnd(2.0) - 1.0;
+                }
+                self.noise_value
+            }
+        };
+
+        self.phase += self.frequency / sample_rate;
+        self.phase -= self.phase.floor();
+
+        v
+    }
+}
+
+/// Attack, decay, sustain, release envelope.
+/// Times are in seconds, sustain is a level between 0.0 and 1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl Default for Envelope {
+    fn default() -> Envelope {
+        Envelope::new(0.01, 0.05, 0.8, 0.1)
+    }
+}
+
+impl Envelope {
+    pub fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Envelope {
+        Envelope {
+            attack,
+            decay,
+            sustain,
+            release,
+        }
+    }
+
+    /// Amplitude at time `t` of a note held during `hold` seconds
+    pub fn amplitude(&self, t: f32, hold: f32) -> f32 {
+        if t < hold {
+            return self.held_amplitude(t);
+        }
+
+        let rt = t - hold;
+        if rt >= self.release {
+            0.0
+        } else {
+            self.held_amplitude(hold) * (1.0 - rt / self.release)
+        }
+    }
+
+    /// Total duration of a note held during `hold` seconds
+    pub fn duration(&self, hold: f32) -> f32 {
+        hold + self.release
+    }
+
+    fn held_amplitude(&self, t: f32) -> f32 {
+        if t < self.attack {
+            t / self.attack
+        } else if t < self.attack + self.decay {
+            1.0 - (1.0 - self.sustain) * (t - self.attack) / self.decay
+        } else {
+            self.sustain
+        }
+    }
+}
+
+/// Two operators frequency modulation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fm {
+    /// Modulator frequency relative to the carrier frequency
+    pub ratio: f32,
+    /// Modulation index, in radians
+    pub index: f32,
+}
+
+/// A single note : an oscillator shaped by an envelope, optionally frequency modulated.
+#[derive(Debug, Clone)]
+pub struct Tone {
+    pub osc: Oscillator,
+    pub envelope: Envelope,
+    pub fm: Option<Fm>,
+    /// How long the note is held before its release, in seconds
+    pub hold: f32,
+    pub volume: f32,
+
+    modulator: Oscillator,
+    t: f32,
+}
+
+impl Tone {
+    pub fn new(waveform: Waveform, frequency: f32, hold: f32) -> Tone {
+        Tone {
+            osc: Oscillator::new(waveform, frequency),
+            envelope: Envelope::default(),
+            fm: None,
+            hold,
+            volume: 1.0,
+            modulator: Oscillator::new(Waveform::Sine, frequency),
+            t: 0.0,
+        }
+    }
+
+    pub fn with_envelope(mut self, envelope: Envelope) -> Tone {
+        self.envelope = envelope;
+        self
+    }
+
+    pub fn with_fm(mut self, fm: Fm) -> Tone {
+        self.fm = Some(fm);
+        self
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Tone {
+        self.volume = volume;
+        self
+    }
+}
+
+impl Voice for Tone {
+    fn next_value(&mut self, sample_rate: f32) -> Option<f32> {
+        if self.t >= self.envelope.duration(self.hold) {
+            return None;
+        }
+
+        let amp = self.envelope.amplitude(self.t, self.hold);
+        let v = match self.fm {
+            None => self.osc.next_value(sample_rate),
+            Some(fm) => {
+                self.modulator.frequency = self.osc.frequency * fm.ratio;
+                let m = self.modulator.next_value(sample_rate) * fm.index;
+                self.osc.next_value_with_offset(sample_rate, m / (2.0 * PI))
+            }
+        };
+
+        self.t += 1.0 / sample_rate;
+
+        Some(v * amp * self.volume)
+    }
+}
+
+/// sfxr internal sample rate
+const SFXR_SAMPLE_RATE: usize = 44100;
+const SFXR_SUPERSAMPLING: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SfxrWave {
+    Square,
+    Saw,
+    Sine,
+    Noise,
+}
+
+/// Parameters of a sfxr effect.
+/// Like in the original sfxr, values are in the 0.0..1.0 range (-1.0..1.0 for ramps).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SfxrParams {
+    pub wave: SfxrWave,
+
+    pub base_freq: f32,
+    pub freq_limit: f32,
+    pub freq_ramp: f32,
+    pub freq_dramp: f32,
+
+    pub duty: f32,
+    pub duty_ramp: f32,
+
+    pub vib_strength: f32,
+    pub vib_speed: f32,
+
+    pub env_attack: f32,
+    pub env_sustain: f32,
+    pub env_decay: f32,
+    pub env_punch: f32,
+
+    pub lpf_resonance: f32,
+    pub lpf_freq: f32,
+    pub lpf_ramp: f32,
+    pub hpf_freq: f32,
+    pub hpf_ramp: f32,
+
+    pub repeat_speed: f32,
+
+    pub arp_speed: f32,
+    pub arp_mod: f32,
+
+    pub volume: f32,
+    /// Seed of the noise generator
+    pub seed: u32,
+}
+
+impl Default for SfxrParams {
+    fn default() -> SfxrParams {
+        SfxrParams {
+            wave: SfxrWave::Square,
+            base_freq: 0.3,
+            freq_limit: 0.0,
+            freq_ramp: 0.0,
+            freq_dramp: 0.0,
+            duty: 0.0,
+            duty_ramp: 0.0,
+            vib_strength: 0.0,
+            vib_speed: 0.0,
+            env_attack: 0.0,
+            env_sustain: 0.3,
+            env_decay: 0.4,
+            env_punch: 0.0,
+            lpf_resonance: 0.0,
+            lpf_freq: 1.0,
+            lpf_ramp: 0.0,
+            hpf_freq: 0.0,
+            hpf_ramp: 0.0,
+            repeat_speed: 0.0,
+            arp_speed: 0.0,
+            arp_mod: 0.0,
+            volume: 0.5,
+            seed: 0,
+        }
+    }
+}
+
+impl SfxrParams {
+    pub fn pickup(seed: u32) -> SfxrParams {
+        let mut r = Rng::new(seed);
+        let mut p = SfxrParams::default();
+        p.seed = seed;
+
+        p.base_freq = 0.4 + r.frnd(0.5);
+        p.env_attack = 0.0;
+        p.env_sustain = r.frnd(0.1);
+        p.env_decay = 0.1 + r.frnd(0.4);
+        p.env_punch = 0.3 + r.frnd(0.3);
+        if r.rnd(1) == 1 {
+            p.arp_speed = 0.5 + r.frnd(0.2);
+            p.arp_mod = 0.2 + r.frnd(0.4);
+        }
+        p
+    }
+
+    pub fn laser(seed: u32) -> SfxrParams {
+        let mut r = Rng::new(seed);
+        let mut p = SfxrParams::default();
+        p.seed = seed;
+
+        p.wave = match r.rnd(2) {
+            0 => SfxrWave::Square,
+            1 => SfxrWave::Saw,
+            _ => if r.rnd(1) == 1 {
+                SfxrWave::Square
+            } else {
+                SfxrWave::Sine
+            },
+        };
+        p.base_freq = 0.5 + r.frnd(0.5);
+        p.freq_limit = (p.base_freq - 0.2 - r.frnd(0.6)).max(0.2);
+        p.freq_ramp = -0.15 - r.frnd(0.2);
+        if r.rnd(2) == 0 {
+            p.base_freq = 0.3 + r.frnd(0.6);
+            p.freq_limit = r.frnd(0.1);
+            p.freq_ramp = -0.35 - r.frnd(0.3);
+        }
+        if r.rnd(1) == 1 {
+            p.duty = r.frnd(0.5);
+            p.duty_ramp = r.frnd(0.2);
+        } else {
+            p.duty = 0.4 + r.frnd(0.5);
+            p.duty_ramp = -r.frnd(0.7);
+        }
+        p.env_attack = 0.0;
+        p.env_sustain = 0.1 + r.frnd(0.2);
+        p.env_decay = r.frnd(0.4);
+        if r.rnd(1) == 1 {
+            p.env_punch = r.frnd(0.3);
+        }
+        if r.rnd(1) == 1 {
+            p.hpf_freq = r.frnd(0.3);
+        }
+        p
+    }
+
+    pub fn explosion(seed: u32) -> SfxrParams {
+        let mut r = Rng::new(seed);
+        let mut p = SfxrParams::default();
+        p.seed = seed;
+
+        p.wave = SfxrWave::Noise;
+        if r.rnd(1) == 1 {
+            p.base_freq = 0.1 + r.frnd(0.4);
+            p.freq_ramp = -0.1 + r.frnd(0.4);
+        } else {
+            p.base_freq = 0.2 + r.frnd(0.7);
+            p.freq_ramp = -0.2 - r.frnd(0.2);
+        }
+        p.base_freq *= p.base_freq;
+        if r.rnd(4) == 0 {
+            p.freq_ramp = 0.0;
+        }
+        if r.rnd(2) == 0 {
+            p.repeat_speed = 0.3 + r.frnd(0.5);
+        }
+        p.env_attack = 0.0;
+        p.env_sustain = 0.1 + r.frnd(0.3);
+        p.env_decay = r.frnd(0.5);
+        p.env_punch = 0.2 + r.frnd(0.6);
+        if r.rnd(1) == 1 {
+            p.vib_strength = r.frnd(0.7);
+            p.vib_speed = r.frnd(0.6);
+        }
+        if r.rnd(2) == 0 {
+            p.arp_speed = 0.6 + r.frnd(0.3);
+            p.arp_mod = 0.8 - r.frnd(1.6);
+        }
+        p
+    }
+
+    pub fn powerup(seed: u32) -> SfxrParams {
+        let mut r = Rng::new(seed);
+        let mut p = SfxrParams::default();
+        p.seed = seed;
+
+        if r.rnd(1) == 1 {
+            p.wave = SfxrWave::Saw;
+        } else {
+            p.duty = r.frnd(0.6);
+        }
+        if r.rnd(1) == 1 {
+            p.base_freq = 0.2 + r.frnd(0.3);
+            p.freq_ramp = 0.1 + r.frnd(0.4);
+            p.repeat_speed = 0.4 + r.frnd(0.4);
+        } else {
+            p.base_freq = 0.2 + r.frnd(0.3);
+            p.freq_ramp = 0.05 + r.frnd(0.2);
+            if r.rnd(1) == 1 {
+                p.vib_strength = r.frnd(0.7);
+                p.vib_speed = r.frnd(0.6);
+            }
+        }
+        p.env_attack = 0.0;
+        p.env_sustain = r.frnd(0.4);
+        p.env_decay = 0.1 + r.frnd(0.4);
+        p
+    }
+
+    pub fn hit(seed: u32) -> SfxrParams {
+        let mut r = Rng::new(seed);
+        let mut p = SfxrParams::default();
+        p.seed = seed;
+
+        p.wave = match r.rnd(2) {
+            0 => SfxrWave::Square,
+            1 => SfxrWave::Saw,
+            _ => SfxrWave::Noise,
+        };
+        if p.wave == SfxrWave::Square {
+            p.duty = r.frnd(0.6);
+        }
+        p.base_freq = 0.2 + r.frnd(0.6);
+        p.freq_ramp = -0.3 - r.frnd(0.4);
+        p.env_attack = 0.0;
+        p.env_sustain = r.frnd(0.1);
+        p.env_decay = 0.1 + r.frnd(0.2);
+        if r.rnd(1) == 1 {
+            p.hpf_freq = r.frnd(0.3);
+        }
+        p
+    }
+
+    pub fn jump(seed: u32) -> SfxrParams {
+        let mut r = Rng::new(seed);
+        let mut p = SfxrParams::default();
+        p.seed = seed;
+
+        p.duty = r.frnd(0.6);
+        p.base_freq = 0.3 + r.frnd(0.3);
+        p.freq_ramp = 0.1 + r.frnd(0.2);
+        p.env_attack = 0.0;
+        p.env_sustain = 0.1 + r.frnd(0.3);
+        p.env_decay = 0.1 + r.frnd(0.2);
+        if r.rnd(1) == 1 {
+            p.hpf_freq = r.frnd(0.3);
+        }
+        if r.rnd(1) == 1 {
+            p.lpf_freq = 1.0 - r.frnd(0.6);
+        }
+        p
+    }
+
+    pub fn blip(seed: u32) -> SfxrParams {
+        let mut r = Rng::new(seed);
+        let mut p = SfxrParams::default();
+        p.seed = seed;
+
+        if r.rnd(1) == 1 {
+            p.wave = SfxrWave::Saw;
+        } else {
+            p.duty = r.frnd(0.6);
+        }
+        p.base_freq = 0.2 + r.frnd(0.4);
+        p.env_attack = 0.0;
+        p.env_sustain = 0.1 + r.frnd(0.1);
+        p.env_decay = r.frnd(0.2);
+        p.hpf_freq = 0.1;
+        p
+    }
+
+    pub fn voice(&self) -> SfxrVoice {
+        SfxrVoice::new(self.clone())
+    }
+
+    /// Render the whole effect into a mono buffer at 44100Hz
+    pub fn to_buffer(&self) -> SoundBuffer {
+        let mut voice = self.voice();
+        // sfxr envelopes are at most 3 * 100000 samples long
+        render(&mut voice, SFXR_SAMPLE_RATE, 8.0)
+    }
+}
+
+/// A playing sfxr effect
+#[derive(Debug, Clone)]
+pub struct SfxrVoice {
+    p: SfxrParams,
+    rng: Rng,
+    playing: bool,
+
+    phase: i32,
+    fperiod: f64,
+    fmaxperiod: f64,
+    fslide: f64,
+    fdslide: f64,
+    period: i32,
+    square_duty: f32,
+    square_slide: f32,
+
+    env_stage: usize,
+    env_time: i32,
+    env_length: [i32; 3],
+    env_vol: f32,
+
+    fltp: f32,
+    fltdp: f32,
+    fltw: f32,
+    fltw_d: f32,
+    fltdmp: f32,
+    fltphp: f32,
+    flthp: f32,
+    flthp_d: f32,
+
+    vib_phase: f32,
+    vib_speed: f32,
+    vib_amp: f32,
+
+    rep_time: i32,
+    rep_limit: i32,
+    arp_time: i32,
+    arp_limit: i32,
+    arp_mod: f64,
+
+    noise_buffer: [f32; 32],
+
+    /// Fractional position when the output sample rate differs from 44100Hz
+    clock: f32,
+    last_value: f32,
+}
+
+impl SfxrVoice {
+    fn new(p: SfxrParams) -> SfxrVoice {
+        let mut v = SfxrVoice {
+            rng: Rng::new(p.seed),
+            p,
+            playing: true,
+            phase: 0,
+            fperiod: 0.0,
+            fmaxperiod: 0.0,
+            fslide: 0.0,
+            fdslide: 0.0,
+            period: 0,
+            square_duty: 0.0,
+            square_slide: 0.0,
+            env_stage: 0,
+            env_time: 0,
+            env_length: [0; 3],
+            env_vol: 0.0,
+            fltp: 0.0,
+            fltdp: 0.0,
+            fltw: 0.0,
+            fltw_d: 0.0,
+            fltdmp: 0.0,
+            fltphp: 0.0,
+            flthp: 0.0,
+            flthp_d: 0.0,
+            vib_phase: 0.0,
+            vib_speed: 0.0,
+            vib_amp: 0.0,
+            rep_time: 0,
+            rep_limit: 0,
+            arp_time: 0,
+            arp_limit: 0,
+            arp_mod: 0.0,
+            noise_buffer: [0.0; 32],
+            clock: 0.0,
+            last_value: 0.0,
+        };
+
+        v.reset(false);
+        v
+    }
+
+    fn reset(&mut self, restart: bool) {
+        let p = &self.p;
+
+        if !restart {
+            self.phase = 0;
+        }
+        self.fperiod = 100.0 / ((p.base_freq * p.base_freq) as f64 + 0.001);
+        self.period = self.fperiod as i32;
+        self.fmaxperiod = 100.0 / ((p.freq_limit * p.freq_limit) as f64 + 0.001);
+        self.fslide = 1.0 - (p.freq_ramp as f64).powi(3) * 0.01;
+        self.fdslide = -(p.freq_dramp as f64).powi(3) * 0.000001;
+        self.square_duty = 0.5 - p.duty * 0.5;
+        self.square_slide = -p.duty_ramp * 0.00005;
+        self.arp_mod = if p.arp_mod >= 0.0 {
+            1.0 - (p.arp_mod as f64).powi(2) * 0.9
+        } else {
+            1.0 + (p.arp_mod as f64).powi(2) * 10.0
+        };
+        self.arp_time = 0;
+        self.arp_limit = if p.arp_speed == 1.0 {
+            0
+        } else {
+            ((1.0 - p.arp_speed).powi(2) * 20000.0 + 32.0) as i32
+        };
+
+        if restart {
+            return;
+        }
+
+        // reset filter
+        self.fltp = 0.0;
+        self.fltdp = 0.0;
+        self.fltw = p.lpf_freq.powi(3) * 0.1;
+        self.fltw_d = 1.0 + p.lpf_ramp * 0.0001;
+        self.fltdmp = (5.0 / (1.0 + p.lpf_resonance.powi(2) * 20.0) * (0.01 + self.fltw)).min(0.8);
+        self.fltphp = 0.0;
+        self.flthp = p.hpf_freq.powi(2) * 0.1;
+        self.flthp_d = 1.0 + p.hpf_ramp * 0.0003;
+
+        // reset vibrato
+        self.vib_phase = 0.0;
+        self.vib_speed = p.vib_speed.powi(2) * 0.01;
+        self.vib_amp = p.vib_strength * 0.5;
+
+        // reset envelope
+        self.env_vol = 0.0;
+        self.env_stage = 0;
+        self.env_time = 0;
+        self.env_length = [
+            (p.env_attack * p.env_attack * 100000.0) as i32,
+            (p.env_sustain * p.env_sustain * 100000.0) as i32,
+            (p.env_decay * p.env_decay * 100000.0) as i32,
+        ];
+
+        self.rep_time = 0;
+        self.rep_limit = if p.repeat_speed == 0.0 {
+            0
+        } else {
+            ((1.0 - p.repeat_speed).powi(2) * 20000.0 + 32.0) as i32
+        };
+
+        for n in self.noise_buffer.iter_mut() {
+            *n = self.rng.frnd(2.0) - 1.0;
+        }
+    }
+
+    /// Synthesize one sample at 44100Hz
+    fn synth_sample(&mut self) -> f32 {
+        self.rep_time += 1;
+        if self.rep_limit != 0 && self.rep_time >= self.rep_limit {
+            self.rep_time = 0;
+            self.reset(true);
+        }
+
+        // frequency envelopes/arpeggios
+        self.arp_time += 1;
+        if self.arp_limit != 0 && self.arp_time >= self.arp_limit {
+            self.arp_limit = 0;
+            self.fperiod *= self.arp_mod;
+        }
+        self.fslide += self.fdslide;
+        self.fperiod *= self.fslide;
+        if self.fperiod > self.fmaxperiod {
+            self.fperiod = self.fmaxperiod;
+            if self.p.freq_limit > 0.0 {
+                self.playing = false;
+            }
+        }
+        let mut rfperiod = self.fperiod;
+        if self.vib_amp > 0.0 {
+            self.vib_phase += self.vib_speed;
+            rfperiod = self.fperiod * (1.0 + (self.vib_phase.sin() * self.vib_amp) as f64);
+        }
+        self.period = (rfperiod as i32).max(8);
+        self.square_duty = (self.square_duty + self.square_slide).max(0.0).min(0.5);
+
+        // volume envelope
+        self.env_time += 1;
+        if self.env_time > self.env_length[self.env_stage] {
+            self.env_time = 0;
+            self.env_stage += 1;
+            if self.env_stage == 3 {
+                self.playing = false;
+                return 0.0;
+            }
+        }
+        let stage_pos = if self.env_length[self.env_stage] > 0 {
+            self.env_time as f32 / self.env_length[self.env_stage] as f32
+        } else {
+            1.0
+        };
+        self.env_vol = match self.env_stage {
+            0 => stage_pos,
+            1 => 1.0 + (1.0 - stage_pos) * 2.0 * self.p.env_punch,
+            _ => 1.0 - stage_pos,
+        };
+
+        let mut ssample = 0.0;
+        for _ in 0..SFXR_SUPERSAMPLING {
+            self.phase += 1;
+            if self.phase >= self.period {
+                self.phase %= self.period;
+                if self.p.wave == SfxrWave::Noise {
+                    for n in self.noise_buffer.iter_mut() {
+                        *n = self.rng.frnd(2.0) - 1.0;
+                    }
+                }
+            }
+
+            // base waveform
+            let fp = self.phase as f32 / self.period as f32;
+            let mut sample = match self.p.wave {
+                SfxrWave::Square => if fp < self.square_duty {
+                    0.5
+                } else {
+                    -0.5
+                },
+                SfxrWave::Saw => 1.0 - fp * 2.0,
+                SfxrWave::Sine => (fp * 2.0 * PI).sin(),
+                SfxrWave::Noise => {
+                    self.noise_buffer[(self.phase * 32 / self.period) as usize % 32]
+                }
+            };
+
+            // low pass filter
+            let pp = self.fltp;
+            self.fltw = (self.fltw * self.fltw_d).max(0.0).min(0.1);
+            if self.p.lpf_freq != 1.0 {
+                self.fltdp += (sample - self.fltp) * self.fltw;
+                self.fltdp -= self.fltdp * self.fltdmp;
+            } else {
+                self.fltp = sample;
+                self.fltdp = 0.0;
+            }
+            self.fltp += self.fltdp;
+
+            // high pass filter
+            if self.flthp_d != 1.0 {
+                self.flthp = (self.flthp * self.flthp_d).max(0.00001).min(0.1);
+            }
+            self.fltphp += self.fltp - pp;
+            self.fltphp -= self.fltphp * self.flthp;
+            sample = self.fltphp;
+
+            ssample += sample * self.env_vol;
+        }
+
+        ssample = ssample / SFXR_SUPERSAMPLING as f32 * self.p.volume * 2.0;
+        ssample.max(-1.0).min(1.0)
+    }
+}
+
+impl Voice for SfxrVoice {
+    fn next_value(&mut self, sample_rate: f32) -> Option<f32> {
+        // advance the 44100Hz synthesizer as many steps as needed
+        self.clock += SFXR_SAMPLE_RATE as f32 / sample_rate;
+        while self.clock >= 1.0 {
+            if !self.playing {
+                return None;
+            }
+            self.clock -= 1.0;
+            self.last_value = self.synth_sample();
+        }
+
+        Some(self.last_value)
+    }
+}
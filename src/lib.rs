@@ -2,15 +2,19 @@
 #![feature(fnbox)]
 #![recursion_limit = "512"]
 #![feature(integer_atomics)]
+#![feature(core_intrinsics)]
 #![cfg_attr(feature = "flame_it", feature(plugin, custom_attribute))]
 #![cfg_attr(feature = "flame_it", plugin(flamer))]
 
 /* common */
+extern crate deflate;
 extern crate fnv;
 extern crate futures;
 extern crate hound;
 extern crate image;
+extern crate inflate;
 extern crate obj;
+extern crate rusttype;
 extern crate typed_arena;
 extern crate uni_app;
 extern crate uni_glsl;
@@ -21,20 +25,22 @@ extern crate webgl;
 #[macro_use]
 extern crate unrust_derive;
 
-#[macro_use]
-extern crate bitflags;
-
 #[cfg(feature = "flame_it")]
 extern crate flame;
 
+#[cfg(not(target_arch = "wasm32"))]
+extern crate notify;
+
 // This is here so that our procedural macros
 // can work within the crate.
 pub(crate) mod unrust {
     pub use super::*;
 }
 
-pub mod actors;
+// engine goes first, so its macros are visible in the other modules
+#[macro_use]
 pub mod engine;
+pub mod actors;
 pub mod world;
 
 pub mod math {
@@ -1,4 +1,5 @@
-use engine::{Engine, File, FileFuture, FileIoError, FileSystem};
+use engine::{Engine, File, FileFuture, FileIoError, FileSystem, OverlayFileSystem, Pack,
+             PackFileSystem};
 use uni_app::fs;
 
 use futures::{Async, Future};
@@ -7,10 +8,76 @@ use std::collections::BTreeSet;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+#[cfg(not(target_arch = "wasm32"))]
+use world::file_watcher::FileWatcher;
+
+/// The folder of the assets, relative to the working directory natively
+/// and to the page on the web
+#[cfg(not(target_arch = "wasm32"))]
+pub const DEFAULT_ASSET_ROOT: &'static str = "static";
+#[cfg(target_arch = "wasm32")]
+pub const DEFAULT_ASSET_ROOT: &'static str = "";
+
 // unrust engine support different file system.
-#[derive(Default)]
 pub struct AppFileSystem {
+    /// prefix of the file names, ends with a `/` unless it is empty
+    root: String,
     loading_files: Rc<RefCell<BTreeSet<String>>>,
+
+    /// the files of the mounted packs are served before the loose files
+    packs: PackFileSystem,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    watcher: Option<FileWatcher>,
+}
+
+fn normalize_root(root: &str) -> String {
+    let root = root.replace("\\", "/");
+    let root = root.trim_right_matches('/');
+
+    if root.is_empty() {
+        String::new()
+    } else {
+        root.to_string() + "/"
+    }
+}
+
+impl AppFileSystem {
+    /// Read the files under `root`, a folder natively or an url prefix on the web
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(root: &str) -> AppFileSystem {
+        let root = normalize_root(root);
+
+        // the assets are only hot reloaded in debug builds, and not from the working
+        // directory, which would watch the whole project
+        let watcher = if cfg!(debug_assertions) && !root.is_empty() {
+            FileWatcher::new(root.trim_right_matches('/'))
+        } else {
+            None
+        };
+
+        AppFileSystem {
+            root,
+            loading_files: Default::default(),
+            packs: Default::default(),
+            watcher,
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn new(root: &str) -> AppFileSystem {
+        AppFileSystem {
+            root: normalize_root(root),
+            loading_files: Default::default(),
+            packs: Default::default(),
+        }
+    }
+}
+
+impl Default for AppFileSystem {
+    fn default() -> Self {
+        AppFileSystem::new(DEFAULT_ASSET_ROOT)
+    }
 }
 
 pub struct AppFile(String, fs::File, Rc<RefCell<BTreeSet<String>>>);
@@ -38,13 +105,11 @@ impl FileSystem for AppFileSystem {
     type File = AppFile;
 
     fn open(&self, filename: &str) -> FileFuture {
-        let mut abs_filename = filename.to_string();
-
-        if cfg!(not(target_arch = "wasm32")) {
-            abs_filename = "static/".to_string() + filename;
+        if self.packs.contains(&filename.replace("\\", "/")) {
+            return self.packs.open(filename);
         }
 
-        abs_filename = abs_filename.replace("\\", "/");
+        let abs_filename = (self.root.clone() + filename).replace("\\", "/");
 
         let f = fs::FileSystem::open(&abs_filename)
             .map_err(|_| FileIoError::NoSuchFile(filename.to_string()));
@@ -69,6 +134,17 @@ impl FileSystem for AppFileSystem {
             .map(|s| s.clone())
             .collect()
     }
+
+    fn mount(&self, pack: Rc<Pack>) -> bool {
+        FileSystem::mount(&self.packs, pack)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn changed_files(&self) -> Vec<String> {
+        self.watcher
+            .as_ref()
+            .map_or(Vec::new(), |w| w.changed_files())
+    }
 }
 
 impl File for AppFile {
@@ -87,4 +163,6 @@ impl AppFile {
     }
 }
 
-pub type AppEngine = Engine<AppFileSystem, AppFile>;
+/// The assets of the world are read from the file systems given to the `WorldBuilder`,
+/// then from the asset root
+pub type AppEngine = Engine<OverlayFileSystem, Box<File>>;
@@ -0,0 +1,535 @@
+//! Developer console
+//!
+//! A drop-down console toggled by the back quote key, which runs commands
+//! registered by the actors and changes console variables (cvars):
+//!
+//! ```text
+//! world.console().register("spawn", |args, world| { .. });
+//! let speed = world.console().cvar("fpc_speed", 10.0f32);
+//! ```
+//!
+//! Typing `fpc_speed 20` sets the cvar, `fpc_speed` alone prints it.
+//! Up / Down browse the history, Tab completes the names, PageUp / PageDown scroll.
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Display;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use engine::imgui;
+use uni_app::{AppEvent, PIXELS_PER_LINE};
+use world::World;
+
+const MAX_LINES: usize = 500;
+const MAX_HISTORY: usize = 100;
+
+/// Height of a line of the default bitmap font, in pixels
+const LINE_HEIGHT: f32 = 16.0;
+/// Height of the panel title and margins, in pixels
+const PANEL_MARGIN: f32 = 36.0;
+
+pub type CommandFn = Rc<Fn(&[&str], &mut World)>;
+
+/// Type which can be stored in a cvar
+pub trait CVarValue: Clone + 'static {
+    fn parse(args: &[&str]) -> Result<Self, String>;
+    fn show(&self) -> String;
+}
+
+fn parse_single<T>(args: &[&str]) -> Result<T, String>
+where
+    T: FromStr,
+{
+    match args {
+        &[s] => s.parse::<T>().map_err(|_| format!("invalid value \"{}\"", s)),
+        _ => Err("expected a single value".into()),
+    }
+}
+
+macro_rules! impl_cvar_value {
+    ($t:ty) => {
+        impl CVarValue for $t {
+            fn parse(args: &[&str]) -> Result<Self, String> {
+                parse_single(args)
+            }
+
+            fn show(&self) -> String {
+                self.to_string()
+            }
+        }
+    };
+}
+
+impl_cvar_value!(f32);
+impl_cvar_value!(f64);
+impl_cvar_value!(i32);
+impl_cvar_value!(u32);
+impl_cvar_value!(usize);
+
+impl CVarValue for bool {
+    fn parse(args: &[&str]) -> Result<bool, String> {
+        match args {
+            &["1"] | &["true"] | &["on"] => Ok(true),
+            &["0"] | &["false"] | &["off"] => Ok(false),
+            _ => Err("expected 0 or 1".into()),
+        }
+    }
+
+    fn show(&self) -> String {
+        (if *self { "1" } else { "0" }).into()
+    }
+}
+
+impl CVarValue for String {
+    fn parse(args: &[&str]) -> Result<String, String> {
+        Ok(args.join(" "))
+    }
+
+    fn show(&self) -> String {
+        self.clone()
+    }
+}
+
+fn show_list<T: Display>(list: &[T]) -> String {
+    let items: Vec<String> = list.iter().map(|v| v.to_string()).collect();
+    items.join(" ")
+}
+
+impl CVarValue for [f32; 4] {
+    fn parse(args: &[&str]) -> Result<[f32; 4], String> {
+        if args.len() != 4 {
+            return Err("expected 4 values".into());
+        }
+
+        let mut r = [0.0; 4];
+        for (i, a) in args.iter().enumerate() {
+            r[i] = parse_single(&[*a])?;
+        }
+        Ok(r)
+    }
+
+    fn show(&self) -> String {
+        show_list(self)
+    }
+}
+
+struct CVarCell<T> {
+    value: RefCell<T>,
+    changed: Cell<bool>,
+}
+
+/// Handle to a cvar, kept by the actor which uses it
+pub struct CVar<T>(Rc<CVarCell<T>>);
+
+impl<T> Clone for CVar<T> {
+    fn clone(&self) -> CVar<T> {
+        CVar(self.0.clone())
+    }
+}
+
+impl<T: CVarValue> CVar<T> {
+    pub fn get(&self) -> T {
+        self.0.value.borrow().clone()
+    }
+
+    pub fn set(&self, v: T) {
+        *self.0.value.borrow_mut() = v;
+        self.0.changed.set(true);
+    }
+
+    /// The new value, if it was changed since the last call
+    pub fn take_changed(&self) -> Option<T> {
+        if self.0.changed.replace(false) {
+            Some(self.get())
+        } else {
+            None
+        }
+    }
+}
+
+/// A cvar whatever its type
+trait AnyCVar {
+    fn set_args(&self, args: &[&str]) -> Result<(), String>;
+    fn show(&self) -> String;
+}
+
+impl<T: CVarValue> AnyCVar for CVarCell<T> {
+    fn set_args(&self, args: &[&str]) -> Result<(), String> {
+        *self.value.borrow_mut() = T::parse(args)?;
+        self.changed.set(true);
+        Ok(())
+    }
+
+    fn show(&self) -> String {
+        self.value.borrow().show()
+    }
+}
+
+struct CVarEntry {
+    cvar: Rc<AnyCVar>,
+    /// the typed `CVar<T>` handle
+    handle: Box<Any>,
+}
+
+pub struct Console {
+    open: bool,
+    toggle_key: String,
+
+    lines: VecDeque<String>,
+    /// lines scrolled up from the bottom
+    scroll: usize,
+
+    input: String,
+    history: Vec<String>,
+    history_pos: Option<usize>,
+
+    commands: BTreeMap<String, CommandFn>,
+    cvars: BTreeMap<String, CVarEntry>,
+
+    /// lines entered during this frame, run by the `World`
+    pending: Vec<String>,
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            open: false,
+            toggle_key: "Backquote".into(),
+            lines: VecDeque::new(),
+            scroll: 0,
+            input: String::new(),
+            history: Vec::new(),
+            history_pos: None,
+            commands: BTreeMap::new(),
+            cvars: BTreeMap::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Key code which opens and closes the console, "Backquote" by default
+    pub fn set_toggle_key(&mut self, code: &str) {
+        self.toggle_key = code.into();
+    }
+
+    /// Add a line to the scrollback
+    pub fn log<S: Into<String>>(&mut self, s: S) {
+        for line in s.into().lines() {
+            self.lines.push_back(line.to_string());
+        }
+
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.scroll = 0;
+    }
+
+    /// Register the command `name`, it is called with the words typed after it
+    pub fn register<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(&[&str], &mut World) + 'static,
+    {
+        self.commands.insert(name.to_string(), Rc::new(f));
+    }
+
+    pub fn unregister(&mut self, name: &str) {
+        self.commands.remove(name);
+    }
+
+    /// The cvar `name`, created with `default` if it doesn't exist yet.
+    /// Panics if it exists with another type.
+    pub fn cvar<T: CVarValue>(&mut self, name: &str, default: T) -> CVar<T> {
+        if let Some(entry) = self.cvars.get(name) {
+            return match entry.handle.downcast_ref::<CVar<T>>() {
+                Some(handle) => handle.clone(),
+                None => panic!("cvar {} is registered with another type", name),
+            };
+        }
+
+        let cell = Rc::new(CVarCell {
+            value: RefCell::new(default),
+            changed: Cell::new(false),
+        });
+
+        self.cvars.insert(
+            name.to_string(),
+            CVarEntry {
+                cvar: cell.clone(),
+                handle: Box::new(CVar(cell.clone())),
+            },
+        );
+        CVar(cell)
+    }
+
+    /// Run `line` during the next frame, as if it was typed
+    pub fn execute(&mut self, line: &str) {
+        self.pending.push(line.to_string());
+    }
+
+    pub fn take_pending(&mut self) -> Vec<String> {
+        ::std::mem::replace(&mut self.pending, Vec::new())
+    }
+
+    pub fn command(&self, name: &str) -> Option<CommandFn> {
+        self.commands.get(name).cloned()
+    }
+
+    /// Run the builtin commands and the cvars, returns false if `name` is unknown
+    pub fn run_builtin(&mut self, name: &str, args: &[&str]) -> bool {
+        match name {
+            "help" => {
+                let commands: Vec<String> = self.commands.keys().cloned().collect();
+                let cvars: Vec<String> = self.cvars
+                    .iter()
+                    .map(|(k, v)| format!("{} = {}", k, v.cvar.show()))
+                    .collect();
+
+                self.log(format!("commands: clear help {}", commands.join(" ")));
+                self.log(format!("cvars: {}", cvars.join(", ")));
+                true
+            }
+            "clear" => {
+                self.clear();
+                true
+            }
+            _ => {
+                let cvar = match self.cvars.get(name) {
+                    Some(entry) => entry.cvar.clone(),
+                    None => return false,
+                };
+
+                if args.len() > 0 {
+                    if let Err(e) = cvar.set_args(args) {
+                        self.log(format!("{}: {}", name, e));
+                        return true;
+                    }
+                }
+
+                self.log(format!("{} = {}", name, cvar.show()));
+                true
+            }
+        }
+    }
+
+    fn names(&self) -> Vec<&str> {
+        let mut names = vec!["clear", "help"];
+        names.extend(self.commands.keys().map(|s| s.as_str()));
+        names.extend(self.cvars.keys().map(|s| s.as_str()));
+        names.sort();
+        names
+    }
+
+    /// Complete the first word of the input with the command and cvar names
+    fn complete(&mut self) {
+        if self.input.contains(' ') {
+            return;
+        }
+
+        let candidates: Vec<String> = self.names()
+            .into_iter()
+            .filter(|n| n.starts_with(self.input.as_str()))
+            .map(|n| n.to_string())
+            .collect();
+
+        match candidates.len() {
+            0 => (),
+            1 => self.input = format!("{} ", candidates[0]),
+            _ => {
+                // longest common prefix
+                let mut prefix = candidates[0].clone();
+                for c in candidates.iter() {
+                    while !c.starts_with(prefix.as_str()) {
+                        prefix.pop();
+                    }
+                }
+
+                self.input = prefix;
+                self.log(candidates.join(" "));
+            }
+        }
+    }
+
+    fn browse_history(&mut self, up: bool) {
+        if self.history.len() == 0 {
+            return;
+        }
+
+        let last = self.history.len() - 1;
+        self.history_pos = match (self.history_pos, up) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(0), true) => Some(0),
+            (Some(i), true) => Some(i - 1),
+            (Some(i), false) if i >= last => None,
+            (Some(i), false) => Some(i + 1),
+        };
+
+        self.input = match self.history_pos {
+            Some(i) => self.history[i].clone(),
+            None => String::new(),
+        };
+    }
+
+    fn submit(&mut self) {
+        let line = ::std::mem::replace(&mut self.input, String::new());
+        self.history_pos = None;
+        self.scroll = 0;
+
+        if line.trim().len() == 0 {
+            return;
+        }
+
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+
+        self.pending.push(line);
+    }
+
+    /// True for the events used by the console while it is open
+    pub fn takes_event(&self, evt: &AppEvent) -> bool {
+        match evt {
+            &AppEvent::KeyDown(_) | &AppEvent::KeyUp(_) | &AppEvent::TextInput(_) => self.open,
+            _ => false,
+        }
+    }
+
+    /// Handle the keys of this frame, called by the `World`
+    pub fn handle_events(&mut self, events: &[AppEvent]) {
+        // the web sends the text of a key before the key itself
+        let toggled = events.iter().any(|evt| match evt {
+            &AppEvent::KeyDown(ref k) => k.code == self.toggle_key,
+            _ => false,
+        });
+
+        for evt in events.iter() {
+            match evt {
+                &AppEvent::KeyDown(ref k) if k.code == self.toggle_key => {
+                    self.open = !self.open;
+                }
+                _ if !self.open => (),
+
+                &AppEvent::KeyDown(ref k) => match k.code.as_str() {
+                    "Enter" | "NumpadEnter" => self.submit(),
+                    "Backspace" => {
+                        self.input.pop();
+                    }
+                    "Escape" => self.open = false,
+                    "Tab" => self.complete(),
+                    "ArrowUp" => self.browse_history(true),
+                    "ArrowDown" => self.browse_history(false),
+                    "PageUp" => self.scroll += 5,
+                    "PageDown" => self.scroll = self.scroll.saturating_sub(5),
+                    _ => (),
+                },
+                // the character of the toggle key is not typed
+                &AppEvent::TextInput(ref s) if !toggled => {
+                    self.input.extend(s.chars().filter(|c| !c.is_control()));
+                }
+                &AppEvent::MouseWheel(ref e) => {
+                    let lines = (e.delta_y * PIXELS_PER_LINE / LINE_HEIGHT).round() as isize;
+                    self.scroll = (self.scroll as isize + lines).max(0) as usize;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Draw the console on the top half of the screen, `screen_size` is in logical pixels
+    pub fn render(&mut self, screen_size: (f32, f32)) {
+        if !self.open {
+            return;
+        }
+
+        use engine::imgui::Metric::*;
+
+        let height = screen_size.1 * 0.5;
+        let rows = (((height - PANEL_MARGIN) / LINE_HEIGHT) as usize)
+            .saturating_sub(1)
+            .max(1);
+
+        let max_scroll = self.lines.len().saturating_sub(rows);
+        self.scroll = self.scroll.min(max_scroll);
+
+        let end = self.lines.len() - self.scroll;
+        let start = end.saturating_sub(rows);
+        let shown: Vec<&str> = self.lines
+            .iter()
+            .skip(start)
+            .take(end - start)
+            .map(|s| s.as_str())
+            .collect();
+
+        imgui::pivot((0.0, 0.0));
+        imgui::begin_panel("Console", Native(0.0, 0.0), Native(1.0, 0.5));
+
+        // the input line stays at the bottom
+        let empty_rows = (rows - shown.len()) as f32;
+        imgui::label(
+            Pixel(0.0, empty_rows * LINE_HEIGHT),
+            &shown.join("\n"),
+        );
+        imgui::label(Pixel(0.0, 0.0), &format!("> {}_", self.input));
+
+        imgui::end_panel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uni_app::KeyDownEvent;
+
+    fn key_down(code: &str) -> AppEvent {
+        AppEvent::KeyDown(KeyDownEvent {
+            code: code.to_string(),
+            key: String::new(),
+            shift: false,
+            alt: false,
+            ctrl: false,
+        })
+    }
+
+    fn toggle(text_first: bool) -> Vec<AppEvent> {
+        let text = AppEvent::TextInput("`".to_string());
+        if text_first {
+            vec![text, key_down("Backquote")]
+        } else {
+            vec![key_down("Backquote"), text]
+        }
+    }
+
+    #[test]
+    fn toggle_key_is_not_typed() {
+        for &text_first in [false, true].iter() {
+            let mut console = Console::new();
+
+            console.handle_events(&toggle(text_first));
+            assert!(console.is_open());
+
+            console.handle_events(&[AppEvent::TextInput("ls".to_string())]);
+            console.handle_events(&toggle(text_first));
+            assert!(!console.is_open());
+
+            console.handle_events(&toggle(text_first));
+            assert!(console.is_open());
+            assert_eq!(console.input, "ls", "text first: {}", text_first);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+/// Watch the files under a directory, to reload the assets when they change
+pub struct FileWatcher {
+    root: PathBuf,
+    abs_root: PathBuf,
+    rx: Receiver<DebouncedEvent>,
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    pub fn new(root: &str) -> Option<FileWatcher> {
+        let (tx, rx) = channel();
+
+        let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_millis(100)).ok()?;
+        watcher.watch(root, RecursiveMode::Recursive).ok()?;
+
+        Some(FileWatcher {
+            root: PathBuf::from(root),
+            abs_root: Path::new(root).canonicalize().ok()?,
+            rx,
+            _watcher: watcher,
+        })
+    }
+
+    /// The files written since the last call, relative to the root
+    pub fn changed_files(&self) -> Vec<String> {
+        let mut files = Vec::new();
+
+        while let Ok(evt) = self.rx.try_recv() {
+            let path = match evt {
+                DebouncedEvent::Write(p) | DebouncedEvent::Create(p) => p,
+                // most editors save to another file and rename it
+                DebouncedEvent::Rename(_, p) => p,
+                _ => continue,
+            };
+
+            let rel = path.strip_prefix(&self.abs_root)
+                .or_else(|_| path.strip_prefix(&self.root));
+
+            if let Ok(rel) = rel {
+                let name = rel.to_string_lossy().replace("\\", "/");
+                if !files.contains(&name) {
+                    files.push(name);
+                }
+            }
+        }
+
+        files
+    }
+}
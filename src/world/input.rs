@@ -0,0 +1,387 @@
+//! Input action mapping
+//!
+//! Game code queries named actions and axes ("jump", "move_x") instead of raw
+//! key codes. Each name is bound to a list of keys, mouse buttons or gamepad
+//! buttons/axes with a scale, which can be changed at runtime or loaded from
+//! a config file:
+//!
+//! ```text
+//! # name = binding[*scale], ...
+//! jump = key:Space, pad:0:button:0
+//! move_x = key:KeyA*-1, key:KeyD, pad:0:axis:0
+//! fire = mouse:0
+//! ```
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use engine::{AssetError, AssetSystem};
+use futures::Future;
+use uni_app;
+use uni_app::AppEvent;
+use uni_pad as pad;
+
+/// Value above which an action is considered pressed
+const PRESS_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding {
+    /// Keyboard scan code, e.g. "KeyA", "Space", "ArrowUp"
+    Key(String),
+    MouseButton(usize),
+    /// Gamepad button, numbered as in `uni_pad::mapping`.
+    /// Triggers are analog, their value is between 0.0 and 1.0
+    PadButton { player: i32, button: i32 },
+    /// Gamepad axis, numbered as in `uni_pad::mapping`, its value is between -1.0 and 1.0
+    PadAxis { player: i32, axis: i32 },
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Binding::Key(ref code) => write!(f, "key:{}", code),
+            &Binding::MouseButton(b) => write!(f, "mouse:{}", b),
+            &Binding::PadButton { player, button } => write!(f, "pad:{}:button:{}", player, button),
+            &Binding::PadAxis { player, axis } => write!(f, "pad:{}:axis:{}", player, axis),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Binding, String> {
+        let parts: Vec<&str> = s.trim().split(':').map(|p| p.trim()).collect();
+        let num = |s: &str| {
+            s.parse::<i32>()
+                .map_err(|_| format!("invalid number \"{}\"", s))
+        };
+
+        match parts.as_slice() {
+            &["key", code] if code.len() > 0 => Ok(Binding::Key(code.to_string())),
+            &["mouse", b] => Ok(Binding::MouseButton(num(b)? as usize)),
+            &["pad", player, "button", button] => Ok(Binding::PadButton {
+                player: num(player)?,
+                button: num(button)?,
+            }),
+            &["pad", player, "axis", axis] => Ok(Binding::PadAxis {
+                player: num(player)?,
+                axis: num(axis)?,
+            }),
+            _ => Err(format!("unknown binding \"{}\"", s.trim())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    Parse { line: usize, reason: String },
+    Asset(AssetError),
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct ActionState {
+    value: f32,
+    pressed: bool,
+    last_pressed: bool,
+}
+
+type BindingList = Vec<(Binding, f32)>;
+
+/// Parse bindings from the config format described in the module documentation
+pub fn parse_bindings(s: &str) -> Result<BTreeMap<String, BindingList>, InputError> {
+    let mut result = BTreeMap::new();
+
+    for (i, line) in s.lines().enumerate() {
+        let err = |reason: String| InputError::Parse { line: i + 1, reason };
+
+        let line = line.split('#').next().unwrap().trim();
+        if line.len() == 0 {
+            continue;
+        }
+
+        let mut kv = line.splitn(2, '=');
+        let name = kv.next().unwrap().trim();
+        let bindings = kv.next().ok_or(err("missing \"=\"".into()))?;
+
+        if name.len() == 0 {
+            return Err(err("missing name".into()));
+        }
+
+        let mut list = Vec::new();
+        for b in bindings.split(',').filter(|b| b.trim().len() > 0) {
+            let mut bs = b.splitn(2, '*');
+            let binding = bs.next().unwrap().parse::<Binding>().map_err(&err)?;
+            let scale = match bs.next() {
+                None => 1.0,
+                Some(scale) => scale
+                    .trim()
+                    .parse::<f32>()
+                    .map_err(|_| err(format!("invalid scale \"{}\"", scale.trim())))?,
+            };
+            list.push((binding, scale));
+        }
+
+        result.insert(name.to_string(), list);
+    }
+
+    Ok(result)
+}
+
+#[derive(Default)]
+pub struct Input {
+    bindings: BTreeMap<String, BindingList>,
+    states: BTreeMap<String, ActionState>,
+
+    keys_down: HashSet<String>,
+    mouse_down: HashSet<usize>,
+    // pressed and released in the same frame, they still count as held for this frame
+    keys_tapped: HashSet<String>,
+    mouse_tapped: HashSet<usize>,
+
+    last_input: Option<Binding>,
+    pad_events: Vec<pad::GamepadEvent>,
+
+    // the ui takes the clicks over it, and the keys and gamepad while it has the focus
+    ui_mouse: bool,
+    ui_keyboard: bool,
+
+    pending_load: Rc<RefCell<Option<Result<BTreeMap<String, BindingList>, InputError>>>>,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input::default()
+    }
+
+    /// Bind `binding` to the action `name`, in addition to its current bindings
+    pub fn bind(&mut self, name: &str, binding: Binding) {
+        self.bind_scaled(name, binding, 1.0);
+    }
+
+    /// Bind `binding` to the axis `name`, its value is multiplied by `scale`
+    pub fn bind_scaled(&mut self, name: &str, binding: Binding, scale: f32) {
+        self.bindings
+            .entry(name.to_string())
+            .or_insert_with(Vec::new)
+            .push((binding, scale));
+    }
+
+    /// Only bind if nothing is bound to `name` yet,
+    /// so that defaults do not override loaded bindings
+    pub fn bind_default(&mut self, name: &str, bindings: Vec<(Binding, f32)>) {
+        if !self.bindings.contains_key(name) {
+            self.bindings.insert(name.to_string(), bindings);
+        }
+    }
+
+    /// Replace all bindings of `name`
+    pub fn rebind(&mut self, name: &str, bindings: Vec<(Binding, f32)>) {
+        self.bindings.insert(name.to_string(), bindings);
+    }
+
+    pub fn unbind(&mut self, name: &str) {
+        self.bindings.remove(name);
+        self.states.remove(name);
+    }
+
+    pub fn bindings(&self, name: &str) -> Option<&Vec<(Binding, f32)>> {
+        self.bindings.get(name)
+    }
+
+    /// Replace the bindings by the content of a config file
+    pub fn load_bindings(&mut self, s: &str) -> Result<(), InputError> {
+        self.bindings = parse_bindings(s)?;
+        self.states.clear();
+        Ok(())
+    }
+
+    /// Load a config file through the asset system, the bindings are
+    /// replaced as soon as the file is loaded.
+    pub fn load_bindings_file(&self, asys: &AssetSystem, filename: &str) {
+        let pending = self.pending_load.clone();
+        let f = asys.new_file(filename)
+            .then(move |r| -> Result<(), AssetError> {
+                let r = r.and_then(|mut file| file.read_binary())
+                    .map_err(|e| InputError::Asset(AssetError::FileIoError(e)))
+                    .and_then(|buf| {
+                        String::from_utf8(buf).map_err(|_| InputError::Parse {
+                            line: 0,
+                            reason: "file is not valid utf8".into(),
+                        })
+                    })
+                    .and_then(|s| parse_bindings(&s));
+
+                *pending.borrow_mut() = Some(r);
+                Ok(())
+            });
+
+        asys.execute(Box::new(f));
+    }
+
+    /// Save the bindings in the config file format
+    pub fn save_bindings(&self) -> String {
+        let mut s = String::new();
+        for (name, list) in self.bindings.iter() {
+            let bindings: Vec<String> = list.iter()
+                .map(|&(ref b, scale)| {
+                    if scale == 1.0 {
+                        b.to_string()
+                    } else {
+                        format!("{}*{}", b, scale)
+                    }
+                })
+                .collect();
+            s += &format!("{} = {}\n", name, bindings.join(", "));
+        }
+        s
+    }
+
+    /// True while the action is held
+    pub fn pressed(&self, name: &str) -> bool {
+        self.states.get(name).map(|s| s.pressed).unwrap_or(false)
+    }
+
+    /// True on the frame the action started to be held
+    pub fn just_pressed(&self, name: &str) -> bool {
+        self.states
+            .get(name)
+            .map(|s| s.pressed && !s.last_pressed)
+            .unwrap_or(false)
+    }
+
+    /// True on the frame the action stopped to be held
+    pub fn just_released(&self, name: &str) -> bool {
+        self.states
+            .get(name)
+            .map(|s| !s.pressed && s.last_pressed)
+            .unwrap_or(false)
+    }
+
+    /// Value of the axis, between -1.0 and 1.0
+    pub fn value(&self, name: &str) -> f32 {
+        self.states.get(name).map(|s| s.value).unwrap_or(0.0)
+    }
+
+    /// The last key or mouse button pressed during this frame,
+    /// useful to let the player choose a new binding.
+    pub fn last_input(&self) -> Option<&Binding> {
+        self.last_input.as_ref()
+    }
+
+    pub fn is_key_down(&self, code: &str) -> bool {
+        self.keys_down.contains(code)
+    }
+
+    /// Gamepads connected or disconnected during this frame
+    pub fn gamepad_events(&self) -> &Vec<pad::GamepadEvent> {
+        &self.pad_events
+    }
+
+    /// Set by the `World` before `update`, from `imgui::wants_mouse` and `imgui::wants_keyboard`.
+    /// The buttons pressed while the ui wants them are ignored.
+    pub fn set_ui_capture(&mut self, mouse: bool, keyboard: bool) {
+        self.ui_mouse = mouse;
+        self.ui_keyboard = keyboard;
+    }
+
+    /// Vibrate the gamepad of `player`, see `uni_pad::gamepad_rumble`
+    pub fn rumble(&self, player: i32, strong: f32, weak: f32, duration_ms: u32) -> bool {
+        pad::gamepad_rumble(player, strong, weak, duration_ms)
+    }
+
+    fn binding_value(&self, b: &Binding) -> f32 {
+        let held = |b: bool| if b { 1.0 } else { 0.0 };
+
+        match b {
+            &Binding::Key(ref code) => {
+                held(self.keys_down.contains(code) || self.keys_tapped.contains(code))
+            }
+            &Binding::MouseButton(button) => {
+                held(self.mouse_down.contains(&button) || self.mouse_tapped.contains(&button))
+            }
+            &Binding::PadButton { .. } | &Binding::PadAxis { .. } if self.ui_keyboard => 0.0,
+            &Binding::PadButton { player, button } => pad::gamepad_button_value(player, button),
+            &Binding::PadAxis { player, axis } => pad::gamepad_axis_value(player, axis),
+        }
+    }
+
+    /// Update the states from this frame events, called once per frame by the `World`
+    pub fn update(&mut self, events: &Vec<AppEvent>) {
+        if let Some(r) = self.pending_load.borrow_mut().take() {
+            match r {
+                Ok(bindings) => {
+                    self.bindings = bindings;
+                    self.states.clear();
+                }
+                Err(e) => uni_app::App::print(format!(
+                    "Fail to load input bindings, reason {:?}\n",
+                    e
+                )),
+            }
+        }
+
+        self.keys_tapped.clear();
+        self.mouse_tapped.clear();
+        self.last_input = None;
+        self.pad_events = pad::gamepad_events();
+
+        let mut keys_pressed = HashSet::new();
+        let mut mouse_pressed = HashSet::new();
+
+        for evt in events.iter() {
+            match evt {
+                // the releases go to another window, nothing is held anymore
+                &AppEvent::Focused(false) => {
+                    self.keys_down.clear();
+                    self.mouse_down.clear();
+                }
+                &AppEvent::KeyDown(_) if self.ui_keyboard => (),
+                &AppEvent::MouseDown(_) if self.ui_mouse => (),
+                &AppEvent::KeyDown(ref key) => {
+                    if self.keys_down.insert(key.code.clone()) {
+                        keys_pressed.insert(key.code.clone());
+                        self.last_input = Some(Binding::Key(key.code.clone()));
+                    }
+                }
+                &AppEvent::KeyUp(ref key) => {
+                    if self.keys_down.remove(&key.code) && keys_pressed.contains(&key.code) {
+                        self.keys_tapped.insert(key.code.clone());
+                    }
+                }
+                &AppEvent::MouseDown(ref e) => {
+                    if self.mouse_down.insert(e.button) {
+                        mouse_pressed.insert(e.button);
+                        self.last_input = Some(Binding::MouseButton(e.button));
+                    }
+                }
+                &AppEvent::MouseUp(ref e) => {
+                    if self.mouse_down.remove(&e.button) && mouse_pressed.contains(&e.button) {
+                        self.mouse_tapped.insert(e.button);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut states = BTreeMap::new();
+        for (name, list) in self.bindings.iter() {
+            let mut state = self.states.get(name).cloned().unwrap_or_default();
+
+            let value = list.iter()
+                .map(|&(ref b, scale)| self.binding_value(b) * scale)
+                .fold(0.0, |acc, v| acc + v);
+
+            state.last_pressed = state.pressed;
+            state.value = value.max(-1.0).min(1.0);
+            state.pressed = list.iter()
+                .any(|&(ref b, scale)| self.binding_value(b) * scale > PRESS_THRESHOLD);
+
+            states.insert(name.clone(), state);
+        }
+        self.states = states;
+    }
+}
@@ -0,0 +1,271 @@
+//! Scene inspector
+//!
+//! A window on the right of the screen, toggled by the `inspector` console command.
+//! It lists the game objects of the scene tree, and shows the transform, the
+//! `active` flag and the components of the selected one.
+//!
+//! Components are edited by the inspector registered for their type,
+//! Camera, Light and Mesh (the params of its materials) have one by default:
+//!
+//! ```text
+//! #[derive(Component, Inspect)]
+//! struct Spin { #[inspect] speed: f32 }
+//!
+//! world.inspector().register::<Spin>();
+//! ```
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::{Rc, Weak};
+
+use engine::imgui;
+use engine::imgui::Metric::*;
+use engine::{field_row, inspect_material, Camera, Component, GameObject, Inspect, InspectValue,
+             Light, Mesh, SceneTree};
+use math::*;
+
+/// Width of the window, in pixels
+const WINDOW_WIDTH: f32 = 320.0;
+const ROW_HEIGHT: f32 = 18.0;
+const EXPAND_SIZE: f32 = 18.0;
+const INDENT: f32 = 12.0;
+const ROW_WIDTH: f32 = 200.0;
+const FIELD_WIDTH: f32 = 60.0;
+/// change of the rotation angles per pixel dragged
+const DEGREES_PER_PIXEL: f32 = 0.5;
+
+pub type InspectorFn = Rc<Fn(&Component) -> bool>;
+
+pub struct SceneInspector {
+    open: bool,
+    selected: Option<Weak<RefCell<GameObject>>>,
+    /// nodes whose children are listed
+    expanded: HashSet<u64>,
+    inspectors: HashMap<TypeId, InspectorFn>,
+}
+
+/// Type name without the module paths, e.g. `Box<Actor>`
+pub fn short_type_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut path = String::new();
+
+    for c in name.chars().chain(Some('\0')) {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            path.push(c);
+            continue;
+        }
+
+        result.push_str(path.rsplit("::").next().unwrap_or(""));
+        path.clear();
+
+        if c != '\0' {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// Euler angles, which need a larger step than the other vectors
+fn drag_degrees(name: &str, v: &mut Vector3<f32>) -> bool {
+    field_row(name, |pos| {
+        let size = Pixel(FIELD_WIDTH, ROW_HEIGHT);
+        imgui::drag_value(pos, size, &mut v.x, DEGREES_PER_PIXEL)
+            | imgui::drag_value(Pixel(0.0, 0.0), size, &mut v.y, DEGREES_PER_PIXEL)
+            | imgui::drag_value(Pixel(0.0, 0.0), size, &mut v.z, DEGREES_PER_PIXEL)
+    })
+}
+
+impl SceneInspector {
+    pub fn new() -> SceneInspector {
+        let mut inspector = SceneInspector {
+            open: false,
+            selected: None,
+            expanded: HashSet::new(),
+            inspectors: HashMap::new(),
+        };
+
+        inspector.register::<Camera>();
+        inspector.register::<Light>();
+        inspector.register_with(|mesh: &mut Mesh| {
+            let mut changed = false;
+            for (i, surface) in mesh.surfaces.iter().enumerate() {
+                imgui::label(Pixel(0.0, 0.0), &format!("material {}", i));
+                changed |= inspect_material(&surface.material);
+            }
+            changed
+        });
+
+        inspector
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Edit the components of type `T` with their `Inspect` implementation
+    pub fn register<T: Inspect + 'static>(&mut self) {
+        self.register_with(|c: &mut T| c.inspect());
+    }
+
+    /// Edit the components of type `T` with `f`, which returns true when it changes them
+    pub fn register_with<T, F>(&mut self, f: F)
+    where
+        T: 'static,
+        F: Fn(&mut T) -> bool + 'static,
+    {
+        self.inspectors.insert(
+            TypeId::of::<T>(),
+            Rc::new(move |c: &Component| match c.try_as::<T>() {
+                Some(t) => f(&mut t.borrow_mut()),
+                None => false,
+            }),
+        );
+    }
+
+    pub fn selected(&self) -> Option<Rc<RefCell<GameObject>>> {
+        self.selected.as_ref().and_then(|w| w.upgrade())
+    }
+
+    pub fn select(&mut self, go: Option<&Rc<RefCell<GameObject>>>) {
+        self.selected = go.map(Rc::downgrade);
+    }
+
+    pub fn render(&mut self, tree: &SceneTree) {
+        if !self.open {
+            return;
+        }
+
+        imgui::pivot((1.0, 0.0));
+        imgui::begin_panel(
+            "Inspector",
+            Native(1.0, 0.0),
+            Native(0.0, 1.0) + Pixel(WINDOW_WIDTH, 0.0),
+        );
+        imgui::pivot((0.0, 0.0));
+
+        imgui::begin_scroll(Pixel(0.0, 0.0), Native(1.0, 0.4));
+        let root_id = tree.root().transform.node_id();
+        self.hierarchy(tree, root_id, 0);
+        imgui::end_scroll();
+
+        imgui::begin_scroll(Pixel(0.0, 0.0), Native(1.0, 0.55));
+        match self.selected() {
+            Some(go) => self.details(&go),
+            None => imgui::label(Pixel(0.0, 0.0), "no selection"),
+        }
+        imgui::end_scroll();
+
+        imgui::end_panel();
+    }
+
+    /// Rows of the children of `node_id`, and of their children when they are expanded
+    fn hierarchy(&mut self, tree: &SceneTree, node_id: u64, depth: usize) {
+        for child in tree.get_childen(node_id) {
+            let (id, name) = {
+                let go = child.borrow();
+                let name = match go.components().first() {
+                    Some(c) => short_type_name(c.type_name()),
+                    None => "GameObject".into(),
+                };
+                (go.transform.node_id(), name)
+            };
+
+            let has_children = tree.get_childen(id).len() > 0;
+            let expanded = self.expanded.contains(&id);
+            let indent = depth as f32 * INDENT;
+
+            imgui::begin_horizontal();
+
+            if has_children {
+                let sign = if expanded { "-" } else { "+" };
+                if imgui::button(Pixel(indent, 0.0), Pixel(EXPAND_SIZE, EXPAND_SIZE), sign) {
+                    if expanded {
+                        self.expanded.remove(&id);
+                    } else {
+                        self.expanded.insert(id);
+                    }
+                }
+            }
+
+            let offset = if has_children {
+                0.0
+            } else {
+                indent + EXPAND_SIZE + imgui::style().spacing
+            };
+
+            let selected = self.selected().map_or(false, |s| Rc::ptr_eq(&s, &child));
+            if selected {
+                let mut style = imgui::style();
+                style.frame = style.accent;
+                imgui::push_style(style);
+            }
+
+            let label = format!("#{} {}", id, name);
+            if imgui::button(Pixel(offset, 0.0), Pixel(ROW_WIDTH, ROW_HEIGHT), &label) {
+                self.select(Some(&child));
+            }
+
+            if selected {
+                imgui::pop_style();
+            }
+
+            imgui::end_horizontal();
+
+            if has_children && self.expanded.contains(&id) {
+                self.hierarchy(tree, id, depth + 1);
+            }
+        }
+    }
+
+    /// Transform, active flag and components of the selected game object
+    fn details(&mut self, go: &Rc<RefCell<GameObject>>) {
+        let components: Vec<_> = {
+            let mut go = go.borrow_mut();
+            go.active.inspect_value("active");
+
+            let mut local = go.transform.local();
+            let mut changed = local.disp.inspect_value("position");
+
+            let euler = Euler::from(local.rot);
+            let mut degrees = Vector3::new(
+                Deg::from(euler.x).0,
+                Deg::from(euler.y).0,
+                Deg::from(euler.z).0,
+            );
+            if drag_degrees("rotation", &mut degrees) {
+                local.rot = Quaternion::from(Euler::new(
+                    Deg(degrees.x),
+                    Deg(degrees.y),
+                    Deg(degrees.z),
+                ));
+                changed = true;
+            }
+
+            if changed {
+                go.transform.set_local(local);
+            }
+
+            let mut scale = go.transform.local_scale();
+            if scale.inspect_value("scale") {
+                go.transform.set_local_scale(scale);
+            }
+
+            go.components().iter().cloned().collect()
+        };
+
+        for c in components.iter() {
+            let name = short_type_name(c.type_name());
+            imgui::label(Pixel(0.0, 0.0), &name);
+
+            if let Some(f) = self.inspectors.get(&c.typeid()).cloned() {
+                f(c.as_ref());
+            }
+        }
+    }
+}
@@ -4,11 +4,22 @@ mod fps;
 mod actor;
 mod type_watcher;
 mod processor;
+mod input;
+mod console;
+mod inspector;
+mod profiler_overlay;
+#[cfg(not(target_arch = "wasm32"))]
+mod file_watcher;
 
 pub use self::actor::Actor;
+pub use self::app_fs::{AppEngine, AppFileSystem, DEFAULT_ASSET_ROOT};
 pub use self::world::{Handle, World, WorldBuilder};
 
 pub use self::processor::{Processor, ProcessorContext};
+pub use self::input::{Binding, Input, InputError};
+pub use self::console::{CVar, CVarValue, Console};
+pub use self::inspector::SceneInspector;
+pub use self::profiler_overlay::ProfilerOverlay;
 
 // Just reexport all engine modules
 pub use engine::*;
@@ -17,4 +28,5 @@ pub use engine::*;
 pub mod events {
     pub use uni_app::events::*;
     pub use uni_app::AppEvent;
+    pub use uni_pad::GamepadEvent;
 }
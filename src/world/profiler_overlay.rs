@@ -0,0 +1,146 @@
+//! Profiler overlay
+//!
+//! A window at the bottom-left of the screen, toggled by the `profiler` console command.
+//! It draws the cpu time of the last frames as a graph, and the scopes, gpu passes
+//! and render counters of the last frame. `profiler_export [file]` saves the
+//! history in the Chrome trace format.
+
+use engine::imgui;
+use engine::imgui::Metric::*;
+use engine::profiler::{self, FrameProfile};
+
+/// Frames drawn in the graph
+const GRAPH_FRAMES: usize = 60;
+const GRAPH_HEIGHT: f32 = 60.0;
+const BAR_WIDTH: f32 = 4.0;
+/// Frame time at the top of the graph, in milliseconds
+const GRAPH_MAX_MS: f64 = 50.0;
+
+const WINDOW_SIZE: (f32, f32) = (300.0, 400.0);
+
+const GOOD: imgui::Color = [0x40, 0xc0, 0x40, 0xff];
+const SLOW: imgui::Color = [0xe0, 0xc0, 0x40, 0xff];
+const BAD: imgui::Color = [0xe0, 0x40, 0x40, 0xff];
+
+pub struct ProfilerOverlay {
+    open: bool,
+}
+
+/// Color of a frame time, against 60 and 30 frames per second
+fn bar_color(ms: f64) -> imgui::Color {
+    if ms <= 1000.0 / 60.0 {
+        GOOD
+    } else if ms <= 1000.0 / 30.0 {
+        SLOW
+    } else {
+        BAD
+    }
+}
+
+/// Scopes, gpu passes and counters of a frame
+fn frame_text(frame: &FrameProfile) -> String {
+    let mut lines = vec![format!("frame {} cpu: {:.2}ms", frame.index, frame.duration)];
+
+    for scope in frame.scopes.iter() {
+        lines.push(format!(
+            "{}{} {:.2}ms",
+            "  ".repeat(scope.depth + 1),
+            scope.name,
+            scope.duration
+        ));
+    }
+
+    if frame.gpu.len() > 0 {
+        let total: f64 = frame.gpu.iter().map(|&(_, ms)| ms).sum();
+        lines.push(format!("gpu: {:.2}ms", total));
+
+        for &(name, ms) in frame.gpu.iter() {
+            lines.push(format!("  {} {:.2}ms", name, ms));
+        }
+    }
+
+    let c = &frame.counters;
+    lines.push(format!(
+        "passes:{} draws:{}\nprog:{} tex:{} mesh:{}",
+        c.passes, c.draw_calls, c.switch_prog, c.switch_tex, c.switch_mesh
+    ));
+
+    lines.join("\n")
+}
+
+impl ProfilerOverlay {
+    pub fn new() -> ProfilerOverlay {
+        ProfilerOverlay { open: false }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    pub fn render(&mut self) {
+        if !self.open {
+            return;
+        }
+
+        let frames = profiler::frames();
+
+        imgui::pivot((0.0, 1.0));
+        imgui::begin_panel(
+            "Profiler",
+            Native(0.0, 1.0),
+            Pixel(WINDOW_SIZE.0, WINDOW_SIZE.1),
+        );
+        imgui::pivot((0.0, 0.0));
+
+        // bars are bottom-aligned and packed
+        let mut style = imgui::style();
+        style.spacing = 1.0;
+        imgui::push_style(style);
+        imgui::begin_horizontal();
+
+        let first = frames.len().saturating_sub(GRAPH_FRAMES);
+        for frame in frames[first..].iter() {
+            let h = (frame.duration / GRAPH_MAX_MS).min(1.0) as f32 * GRAPH_HEIGHT;
+            imgui::rect(
+                Pixel(0.0, GRAPH_HEIGHT - h),
+                Pixel(BAR_WIDTH, h.max(1.0)),
+                bar_color(frame.duration),
+            );
+        }
+
+        imgui::end_horizontal();
+        imgui::pop_style();
+
+        let text = match frames.last() {
+            Some(frame) => frame_text(frame),
+            None => "profiler disabled".into(),
+        };
+
+        let graph = GRAPH_HEIGHT + imgui::style().spacing;
+        imgui::begin_scroll(Pixel(0.0, 0.0), Native(1.0, 1.0) + Pixel(0.0, -graph));
+        imgui::label(Pixel(0.0, 0.0), &text);
+        imgui::end_scroll();
+
+        imgui::end_panel();
+    }
+}
+
+/// Save the frames of the profiler history in the Chrome trace format
+pub fn export_trace(path: &str) -> Result<(), String> {
+    let json = profiler::chrome_trace(&profiler::frames());
+
+    if cfg!(target_arch = "wasm32") {
+        return Err("saving files is not supported on the web".into());
+    }
+
+    use std::fs::File;
+    use std::io::Write;
+
+    File::create(path)
+        .and_then(|mut f| f.write_all(json.as_bytes()))
+        .map_err(|e| format!("{}", e))
+}
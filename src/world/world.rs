@@ -4,10 +4,11 @@ use std::rc::Rc;
 use std::sync::Arc;
 
 use engine::{
-    AssetSystem, Camera, ClearOption, Component, ComponentBased, ComponentType, Engine, GameObject,
-    IEngine, SceneTree,
+    AssetDatabase, AssetError, AssetSystem, Camera, ClearOption, Component, ComponentBased,
+    ComponentType, Engine, FileSystem, FileSystemLayer, GameObject, IEngine, OverlayFileSystem,
+    SceneTree,
 };
-use world::app_fs::AppEngine;
+use world::app_fs::{AppEngine, AppFileSystem, DEFAULT_ASSET_ROOT};
 
 use engine::imgui;
 use engine::SoundSystem;
@@ -15,6 +16,11 @@ use world::fps::FPS;
 use world::processor::{IProcessorBuilder, Processor};
 use world::type_watcher::{ActorWatcher, TypeWatcher, TypeWatcherBuilder};
 use world::Actor;
+use world::input::Input;
+use world::console::Console;
+use world::inspector::SceneInspector;
+use world::profiler_overlay::{self, ProfilerOverlay};
+use engine::profiler;
 
 use std::default::Default;
 use std::marker::PhantomData;
@@ -25,6 +31,11 @@ pub type Handle<T> = Rc<RefCell<T>>;
 
 pub struct World {
     pub sound: SoundSystem,
+    pub input: Input,
+
+    console: Console,
+    inspector: SceneInspector,
+    profiler: ProfilerOverlay,
 
     app_ref: Option<&'static mut App>,
 
@@ -33,6 +44,8 @@ pub struct World {
     watcher: Rc<TypeWatcher>,
     shown_stats: bool,
     events: Rc<RefCell<Vec<AppEvent>>>,
+    /// the assets which failed to load, logged in the console
+    asset_errors: Rc<RefCell<Vec<String>>>,
     golist: Vec<Handle<GameObject>>,
     processor_builders: Vec<Rc<Box<IProcessorBuilder>>>,
 
@@ -50,6 +63,8 @@ pub struct WorldBuilder<'a> {
     shown_stats: Option<bool>,
     watcher_builder: TypeWatcherBuilder,
     processor_builders: Vec<Rc<Box<IProcessorBuilder>>>,
+    asset_root: String,
+    file_systems: Vec<Box<FileSystemLayer>>,
 }
 
 impl<'a> WorldBuilder<'a> {
@@ -62,6 +77,8 @@ impl<'a> WorldBuilder<'a> {
             fullscreen: false,
             watcher_builder: TypeWatcherBuilder::new(),
             processor_builders: Vec::new(),
+            asset_root: DEFAULT_ASSET_ROOT.to_string(),
+            file_systems: Vec::new(),
         }
     }
 
@@ -85,6 +102,22 @@ impl<'a> WorldBuilder<'a> {
         self
     }
 
+    /// Read the assets under `path` instead of `static`, an url prefix on the web
+    pub fn with_asset_root(mut self, path: &str) -> WorldBuilder<'a> {
+        self.asset_root = path.to_string();
+        self
+    }
+
+    /// Read the assets from `fs` before the asset root, e.g. a `MemoryFileSystem` in the tests.
+    /// The file systems are tried in the order they are added.
+    pub fn with_file_system<FS>(mut self, fs: FS) -> WorldBuilder<'a>
+    where
+        FS: FileSystem + 'static,
+    {
+        self.file_systems.push(Box::new(fs));
+        self
+    }
+
     pub fn with_actor<T: Actor + 'static>(mut self) -> WorldBuilder<'a> {
         self.watcher_builder = self.watcher_builder.add_watcher(ActorWatcher::<T>::new());
         self
@@ -107,13 +140,19 @@ impl<'a> WorldBuilder<'a> {
         let app = App::new(config);
 
         let hidpi = app.hidpi_factor();
-        let engine = Engine::new(
+
+        let mut file_systems = self.file_systems;
+        file_systems.push(Box::new(AppFileSystem::new(&self.asset_root)));
+        let asset_db = AssetDatabase::with_file_system(OverlayFileSystem::new(file_systems));
+
+        let engine = Engine::with_asset_system(
             app.canvas(),
             (
                 ((size.0 as f32) * hidpi) as u32,
                 ((size.1 as f32) * hidpi) as u32,
             ),
             hidpi,
+            asset_db,
         );
         let events = app.events.clone();
         let main_tree = engine.new_scene_tree();
@@ -126,8 +165,22 @@ impl<'a> WorldBuilder<'a> {
 
         let asys = engine.asset_system.clone();
 
+        let asset_errors = Rc::new(RefCell::new(Vec::new()));
+        {
+            let asset_errors = asset_errors.clone();
+            engine.asset_system().set_error_handler(Box::new(move |err: &AssetError| {
+                asset_errors
+                    .borrow_mut()
+                    .push(format!("asset error: {:?}", err));
+            }));
+        }
+
         let mut w = World {
             sound: SoundSystem::new(asys),
+            input: Input::new(),
+            console: Console::new(),
+            inspector: SceneInspector::new(),
+            profiler: ProfilerOverlay::new(),
             engine,
             app_instance: Some(app),
             main_tree: main_tree.clone(),
@@ -135,11 +188,35 @@ impl<'a> WorldBuilder<'a> {
             shown_stats: self.shown_stats.unwrap_or(false),
             fps: FPS::new(),
             events: events,
+            asset_errors,
             golist: Vec::new(),
             processor_builders: self.processor_builders.clone(),
             app_ref: None,
         };
 
+        w.console.register("stats", |_, world| {
+            world.shown_stats = !world.shown_stats;
+        });
+        w.console.register("fullscreen", |args, world| {
+            let b = args.get(0) != Some(&"0");
+            world.set_fullscreen(b);
+        });
+        w.console.register("inspector", |_, world| {
+            let open = world.inspector.is_open();
+            world.inspector.set_open(!open);
+        });
+        w.console.register("profiler", |_, world| {
+            let open = world.profiler.is_open();
+            world.profiler.set_open(!open);
+        });
+        w.console.register("profiler_export", |args, world| {
+            let path = args.get(0).cloned().unwrap_or("profile.json");
+            match profiler_overlay::export_trace(path) {
+                Ok(_) => world.console.log(format!("profile saved to {}", path)),
+                Err(e) => world.console.log(format!("profiler_export: {}", e)),
+            }
+        });
+
         // add all processor into the scenes
         let go = w.new_game_object();
         for builder in self.processor_builders.into_iter() {
@@ -260,6 +337,31 @@ impl<'a> World {
         self.fps.delta_time()
     }
 
+    pub fn console(&mut self) -> &mut Console {
+        &mut self.console
+    }
+
+    pub fn inspector(&mut self) -> &mut SceneInspector {
+        &mut self.inspector
+    }
+
+    /// Run a console command line, e.g. "fpc_speed 20"
+    pub fn run_command(&mut self, line: &str) {
+        self.console.log(format!("> {}", line));
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (name, args) = match words.split_first() {
+            Some((name, args)) => (*name, args),
+            None => return,
+        };
+
+        if let Some(f) = self.console.command(name) {
+            f(args, self);
+        } else if !self.console.run_builtin(name, args) {
+            self.console.log(format!("unknown command: {}", name));
+        }
+    }
+
     #[cfg_attr(feature = "flame_it", flame)]
     fn step(&mut self) {
         for evt in self.events.borrow().iter() {
@@ -267,12 +369,47 @@ impl<'a> World {
                 &AppEvent::Resized(size) => self.engine.resize(size),
                 _ => (),
             }
+        }
+
+        // the keyboard goes to the console while it is open
+        self.console.handle_events(&self.events.borrow());
+        let ui_events: Vec<AppEvent> = self.events
+            .borrow()
+            .iter()
+            .filter(|e| !self.console.takes_event(e))
+            .cloned()
+            .collect();
+
+        imgui::handle_events(
+            &ui_events,
+            self.engine.screen_size(),
+            self.engine.hidpi_factor(),
+        );
+        self.input.set_ui_capture(
+            imgui::wants_mouse(),
+            imgui::wants_keyboard() || self.console.is_open(),
+        );
+        self.input.update(&self.events.borrow());
+
+        if !self.ui_wants_input() {
+            for evt in self.events.borrow().iter() {
+                profile::dump(evt);
+            }
+        }
 
-            profile::dump(evt);
+        {
+            profile_scope!("actors");
+            let watcher = self.watcher.clone();
+            watcher.step(self);
         }
 
-        let watcher = self.watcher.clone();
-        watcher.step(self);
+        for line in self.console.take_pending() {
+            self.run_command(&line);
+        }
+
+        for err in self.asset_errors.borrow_mut().drain(..) {
+            self.console.log(err);
+        }
 
         self.sound.step();
 
@@ -313,12 +450,38 @@ impl<'a> World {
                 ),
             );
         }
+
+        // the assets which failed to reload or to compile keep their old data
+        let asset_errors = self.engine().asset_system().errors();
+        if asset_errors.len() > 0 {
+            imgui::pivot((0.0, 1.0));
+            imgui::label(
+                Native(0.0, 1.0) + Pixel(8.0, -8.0),
+                &asset_errors.join("\n"),
+            );
+        }
+
+        self.inspector.render(&self.main_tree);
+        self.profiler.render();
+
+        // the console is drawn above the ui of the actors
+        let (sw, sh) = self.engine.screen_size();
+        let hidpi = self.engine.hidpi_factor();
+        self.console
+            .render((sw as f32 / hidpi, sh as f32 / hidpi));
     }
 
     pub fn events(&self) -> Ref<Vec<AppEvent>> {
         self.events.borrow()
     }
 
+    /// True when the ui or the console uses the mouse or the keyboard during this frame,
+    /// actors reading `events()` directly should ignore them in that case.
+    /// `input` already ignores the clicks and keys taken by the ui.
+    pub fn ui_wants_input(&self) -> bool {
+        imgui::wants_mouse() || imgui::wants_keyboard() || self.console.is_open()
+    }
+
     pub fn asset_system<'b>(&'b self) -> &'b AssetSystem {
         self.engine.asset_system()
     }
@@ -356,12 +519,21 @@ impl<'a> World {
         // We can make sure the lifetime of the App will longer then engine itself
         self.app_ref = Some(unsafe { &mut *app });
 
+        profiler::begin_frame();
+
         self.begin();
-        self.step();
-        self.pre_render();
+        {
+            profile_scope!("step");
+            self.step();
+        }
+        {
+            profile_scope!("pre_render");
+            self.pre_render();
+        }
         self.render();
         self.end();
 
+        profiler::end_frame();
         profile::clear();
 
         self.app_ref = None;
@@ -409,4 +581,9 @@ impl<'a> World {
     pub fn set_fullscreen(&mut self, b: bool) {
         self.app_ref.as_mut().unwrap().set_fullscreen(b);
     }
+
+    /// Hide and lock the cursor, see `AppEvent::MouseMotion` for the mouse movements
+    pub fn set_cursor_grab(&mut self, b: bool) {
+        self.app_ref.as_mut().unwrap().set_cursor_grab(b);
+    }
 }
@@ -9,6 +9,7 @@ out vec4 FragColor;
 
 #include "unrust/phong_light.glsl"
 #include "unrust/shadow_utils.glsl"
+#include "unrust/lod_fade.glsl"
 
 struct Material {
     vec3 ambient;    
@@ -47,6 +48,8 @@ vec3 CalcDirectionalLight(DirectionalLight light, vec3 normal, vec3 viewDir, Mat
 vec3 CalcPointLight(PointLight light, vec3 normal, vec3 fragPos, vec3 viewDir, MaterialColor color);
 
 void main(void) {
+    LodFadeDiscard();
+
     vec3 norm = normalize(vNormal);
     vec3 viewDir = normalize(uViewPos - vFragPos);
 
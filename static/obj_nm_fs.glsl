@@ -10,6 +10,7 @@ out vec4 FragColor;
 #define UNI_POINT_LIGHTS 4
 #include "unrust/phong_light.glsl"
 #include "unrust/shadow_utils.glsl"
+#include "unrust/lod_fade.glsl"
 
 struct Material {
     vec3 ambient;    
@@ -65,6 +66,8 @@ vec3 decode_normalmap(vec3 n) {
 }
 
 void main(void) {
+    LodFadeDiscard();
+
     vec3 norm = normalize(vNormal);
 
     if(!uNoNormalMap) 
@@ -7,6 +7,7 @@ out vec4 FragColor;
 
 #define UNI_POINT_LIGHTS 4
 #include "unrust/phong_light.glsl"
+#include "unrust/lod_fade.glsl"
 
 struct Material {
     sampler2D diffuse;
@@ -28,6 +29,8 @@ vec3 CalcDirectionalLight(DirectionalLight light, vec3 normal, vec3 viewDir);
 vec3 CalcPointLight(PointLight light, vec3 normal, vec3 fragPos, vec3 viewDir);
 
 void main(void) {
+    LodFadeDiscard();
+
     vec3 norm = normalize(vNormal);
     vec3 viewDir = normalize(uViewPos - vFragPos);
 
@@ -0,0 +1,13 @@
+// Cross-fade between two levels of a LodGroup.
+// The outgoing level discards a part of its pixels, the incoming one keeps only them.
+uniform float uLodFade;
+
+void LodFadeDiscard()
+{
+    if (uLodFade == 0.0)
+        return;
+
+    float d = fract(sin(dot(floor(gl_FragCoord.xy), vec2(12.9898, 78.233))) * 43758.5453);
+    if (uLodFade > 0.0 ? d < uLodFade : d >= -uLodFade)
+        discard;
+}
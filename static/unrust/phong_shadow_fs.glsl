@@ -8,6 +8,7 @@ out vec4 FragColor;
 
 #include "unrust/phong_light.glsl"
 #include "unrust/shadow_utils.glsl"
+#include "unrust/lod_fade.glsl"
 
 struct Material {
     sampler2D diffuse;
@@ -29,6 +30,8 @@ vec3 CalcDirectionalLight(DirectionalLight light, vec3 normal, vec3 viewDir);
 vec3 CalcPointLight(PointLight light, vec3 normal, vec3 fragPos, vec3 viewDir);
 
 void main(void) {
+    LodFadeDiscard();
+
     vec3 norm = normalize(vNormal);
     vec3 viewDir = normalize(uViewPos - vFragPos);
 
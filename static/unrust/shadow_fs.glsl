@@ -5,6 +5,9 @@
 out vec4 FragColor;
 #endif
 
+#include "unrust/lod_fade.glsl"
+
 void main()
 {
-}         
\ No newline at end of file
+    LodFadeDiscard();
+}         
\ No newline at end of file
@@ -0,0 +1,51 @@
+extern crate futures;
+extern crate unrust;
+
+use futures::Future;
+use unrust::engine;
+use unrust::engine::{FileIoError, FileSystem, MemoryFileSystem, OverlayFileSystem};
+
+fn read<FS: FileSystem>(fs: &FS, name: &str) -> Result<Vec<u8>, FileIoError> {
+    fs.open(name).wait()?.read_binary()
+}
+
+#[test]
+fn test_memory_fs() {
+    let fs = MemoryFileSystem::new();
+    fs.insert("shaders/a.glsl", b"void main() {}".to_vec());
+
+    assert_eq!(read(&fs, "shaders/a.glsl").unwrap(), b"void main() {}".to_vec());
+    assert_eq!(read(&fs, "shaders\\a.glsl").unwrap(), b"void main() {}".to_vec());
+
+    match read(&fs, "b.glsl") {
+        Err(FileIoError::NoSuchFile(_)) => (),
+        r => panic!("expected NoSuchFile, got {:?}", r),
+    }
+
+    // replacing a file reloads the assets using it
+    assert!(fs.changed_files().is_empty());
+    fs.insert("shaders/a.glsl", b"".to_vec());
+    assert_eq!(fs.changed_files(), vec!["shaders/a.glsl".to_string()]);
+}
+
+#[test]
+fn test_overlay_fs() {
+    let top = MemoryFileSystem::new();
+    top.insert("a.txt", b"top".to_vec());
+
+    let bottom = MemoryFileSystem::new();
+    bottom.insert("a.txt", b"bottom".to_vec());
+    bottom.insert("b.txt", b"bottom".to_vec());
+
+    let fs = OverlayFileSystem::new(vec![
+        Box::new(top.clone()) as Box<engine::FileSystemLayer>,
+        Box::new(bottom.clone()),
+    ]);
+
+    assert_eq!(read(&fs, "a.txt").unwrap(), b"top".to_vec());
+    assert_eq!(read(&fs, "b.txt").unwrap(), b"bottom".to_vec());
+    assert!(read(&fs, "c.txt").is_err());
+
+    bottom.insert("b.txt", b"changed".to_vec());
+    assert_eq!(fs.changed_files(), vec!["b.txt".to_string()]);
+}
@@ -0,0 +1,49 @@
+extern crate futures;
+extern crate unrust;
+
+use futures::Future;
+use unrust::engine::{FileIoError, FileSystem, Pack, PackFileSystem, PackWriter};
+
+fn read<FS: FileSystem>(fs: &FS, name: &str) -> Result<Vec<u8>, FileIoError> {
+    fs.open(name).wait()?.read_binary()
+}
+
+fn make_pack(name: &str, files: &[(&str, &[u8])], compress: bool) -> Pack {
+    let mut w = PackWriter::new();
+    for &(file, data) in files {
+        w.add(file, data.to_vec(), compress);
+    }
+
+    let mut buf = Vec::new();
+    w.write(&mut buf).unwrap();
+    Pack::from_bytes(name, buf).unwrap()
+}
+
+#[test]
+fn test_pack_fs() {
+    let text = [b'a'; 1000];
+    let base = make_pack(
+        "base.pak",
+        &[("a.txt", &text[..]), ("dir/b.txt", &b"base"[..])],
+        true,
+    );
+
+    // repeated bytes are compressed, the short file is stored as is
+    assert!(base.entries()["a.txt"].compressed);
+    assert!(!base.entries()["dir/b.txt"].compressed);
+
+    let fs = PackFileSystem::default();
+    fs.mount(base);
+    assert_eq!(read(&fs, "a.txt").unwrap(), text.to_vec());
+    assert_eq!(read(&fs, "dir/b.txt").unwrap(), b"base".to_vec());
+
+    // the pack mounted last overrides the files of the previous ones
+    fs.mount(make_pack("patch.pak", &[("dir/b.txt", &b"patch"[..])], false));
+    assert_eq!(read(&fs, "dir/b.txt").unwrap(), b"patch".to_vec());
+    assert_eq!(read(&fs, "a.txt").unwrap(), text.to_vec());
+
+    fs.unmount("patch.pak");
+    assert_eq!(read(&fs, "dir/b.txt").unwrap(), b"base".to_vec());
+
+    assert!(Pack::from_bytes("bad.pak", b"not a pack".to_vec()).is_err());
+}
@@ -62,6 +62,33 @@ pub mod events {
         pub button: usize,
     }
 
+    /// Number of pixels scrolled by one wheel notch, used to convert pixel deltas to lines
+    pub const PIXELS_PER_LINE: f32 = 16.0;
+
+    #[derive(Debug, Clone)]
+    pub struct MouseWheelEvent {
+        /// horizontal scroll in lines, positive to the right
+        pub delta_x: f32,
+        /// vertical scroll in lines, positive when scrolling up
+        pub delta_y: f32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum TouchPhase {
+        Started,
+        Moved,
+        Ended,
+        Cancelled,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct TouchEvent {
+        /// identify a finger until its touch is ended or cancelled
+        pub id: u64,
+        pub phase: TouchPhase,
+        pub pos: (i32, i32),
+    }
+
     #[derive(Clone)]
     pub struct KeyDownEvent {
         pub code: String,
@@ -127,5 +154,16 @@ pub enum AppEvent {
     KeyDown(KeyDownEvent),
     KeyUp(KeyUpEvent),
     Resized((u32, u32)),
+    /// Cursor position in physical pixels, relative to the top-left of the window
     MousePos((i32, i32)),
+    /// Raw relative mouse motion, still reported when the cursor is grabbed
+    MouseMotion((f32, f32)),
+    MouseWheel(MouseWheelEvent),
+    Touch(TouchEvent),
+    /// Text typed by the user, including characters committed by an input method
+    TextInput(String),
+    /// The window gained (true) or lost (false) the keyboard focus
+    Focused(bool),
+    /// The user asked to close the window, it is closed at the end of this frame
+    CloseRequested,
 }
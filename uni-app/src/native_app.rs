@@ -1,7 +1,7 @@
 mod native_keycode;
 
 use glutin;
-use glutin::{ElementState, Event, MouseButton, WindowEvent};
+use glutin::{DeviceEvent, ElementState, Event, MouseButton, MouseScrollDelta, WindowEvent};
 use std::cell::RefCell;
 use std::env;
 use std::os::raw::c_void;
@@ -13,6 +13,7 @@ use AppEvent;
 
 use self::native_keycode::{translate_scan_code, translate_virtual_key};
 use super::events;
+use super::events::PIXELS_PER_LINE;
 
 enum WindowContext {
     Normal(glutin::GlWindow),
@@ -54,6 +55,8 @@ pub struct App {
     window: WindowContext,
     events_loop: glutin::EventsLoop,
     exiting: bool,
+    focused: bool,
+    show_cursor: bool,
     pub events: Rc<RefCell<Vec<AppEvent>>>,
 }
 
@@ -74,7 +77,24 @@ fn get_scan_code(input: glutin::KeyboardInput) -> String {
     translate_scan_code(input.scancode).into()
 }
 
+fn translate_touch_phase(phase: glutin::TouchPhase) -> events::TouchPhase {
+    match phase {
+        glutin::TouchPhase::Started => events::TouchPhase::Started,
+        glutin::TouchPhase::Moved => events::TouchPhase::Moved,
+        glutin::TouchPhase::Ended => events::TouchPhase::Ended,
+        glutin::TouchPhase::Cancelled => events::TouchPhase::Cancelled,
+    }
+}
+
 fn translate_event(e: glutin::Event) -> Option<AppEvent> {
+    if let Event::DeviceEvent {
+        event: DeviceEvent::MouseMotion { delta },
+        ..
+    } = e
+    {
+        return Some(AppEvent::MouseMotion((delta.0 as f32, delta.1 as f32)));
+    }
+
     if let Event::WindowEvent {
         event: winevent, ..
     } = e
@@ -111,6 +131,24 @@ fn translate_event(e: glutin::Event) -> Option<AppEvent> {
                 })),
             },
             WindowEvent::Resized(w, h) => Some(AppEvent::Resized((w, h))),
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (delta_x, delta_y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(x, y) => (x / PIXELS_PER_LINE, y / PIXELS_PER_LINE),
+                };
+                Some(AppEvent::MouseWheel(events::MouseWheelEvent { delta_x, delta_y }))
+            }
+            WindowEvent::Touch(touch) => Some(AppEvent::Touch(events::TouchEvent {
+                id: touch.id,
+                phase: translate_touch_phase(touch.phase),
+                pos: (touch.location.0 as i32, touch.location.1 as i32),
+            })),
+            // backspace, enter... are handled as key events
+            WindowEvent::ReceivedCharacter(c) if !c.is_control() => {
+                Some(AppEvent::TextInput(c.to_string()))
+            }
+            WindowEvent::Focused(b) => Some(AppEvent::Focused(b)),
+            WindowEvent::CloseRequested => Some(AppEvent::CloseRequested),
 
             _ => None,
         }
@@ -169,6 +207,8 @@ impl App {
             window: window,
             events_loop,
             exiting: false,
+            focused: true,
+            show_cursor: config.show_cursor,
             events: Rc::new(RefCell::new(Vec::new())),
         }
     }
@@ -189,6 +229,25 @@ impl App {
         }
     }
 
+    /// Hide the cursor and keep it inside the window, `AppEvent::MouseMotion`
+    /// still reports the mouse movements
+    pub fn set_cursor_grab(&self, b: bool) {
+        use glutin::CursorState;
+
+        if let WindowContext::Normal(ref glwindow) = self.window {
+            let state = if b {
+                CursorState::Grab
+            } else if self.show_cursor {
+                CursorState::Normal
+            } else {
+                CursorState::Hide
+            };
+            if let Err(e) = glwindow.set_cursor_state(state) {
+                App::print(format!("error : could not set cursor state : {}\n", e));
+            }
+        }
+    }
+
     pub fn print<T: Into<String>>(msg: T) {
         print!("{}", msg.into());
     }
@@ -213,12 +272,20 @@ impl App {
         use glutin::*;
         let mut running = true;
 
-        let (window, events_loop, events) = (&self.window, &mut self.events_loop, &mut self.events);
+        let (window, events_loop, events, exiting, focused) = (
+            &self.window,
+            &mut self.events_loop,
+            &mut self.events,
+            &mut self.exiting,
+            &mut self.focused,
+        );
 
         events_loop.poll_events(|event| {
             match event {
                 glutin::Event::WindowEvent { ref event, .. } => match event {
-                    &glutin::WindowEvent::CloseRequested => running = false,
+                    // let the application see the event before closing
+                    &glutin::WindowEvent::CloseRequested => *exiting = true,
+                    &glutin::WindowEvent::Focused(b) => *focused = b,
                     &glutin::WindowEvent::Resized(w, h) => {
                         // Fixed for Windows which minimized to emit a Resized(0,0) event
                         if w != 0 && h != 0 {
@@ -238,6 +305,8 @@ impl App {
                     }
                     _ => (),
                 },
+                // device events are received even when the window is not focused
+                glutin::Event::DeviceEvent { .. } if !*focused => return,
                 _ => (),
             };
             translate_event(event).map(|evt| events.borrow_mut().push(evt));
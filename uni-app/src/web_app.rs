@@ -4,8 +4,8 @@ use AppConfig;
 use stdweb::traits::IEvent;
 use stdweb::unstable::TryInto;
 use stdweb::web::event::{
-    IKeyboardEvent, IMouseEvent, KeyDownEvent, KeyUpEvent, MouseButton, MouseDownEvent,
-    MouseMoveEvent, MouseUpEvent, ResizeEvent,
+    BlurEvent, FocusEvent, IKeyboardEvent, IMouseEvent, KeyDownEvent, KeyUpEvent, MouseButton,
+    MouseDownEvent, MouseMoveEvent, MouseUpEvent, ResizeEvent,
 };
 use stdweb::web::html_element::CanvasElement;
 use stdweb::web::window;
@@ -24,6 +24,7 @@ pub struct App {
 }
 
 use super::events;
+use super::events::PIXELS_PER_LINE;
 
 macro_rules! map_event {
     ($events:expr, $x:ident, $y:ident, $ee:ident, $e:expr, $prevent:expr) => {{
@@ -155,8 +156,12 @@ impl App {
             true
         });
 
+        // positions are in physical pixels, like on native
+        let dpr = self.device_pixel_ratio;
+
         canvas.add_event_listener({
             let canvas = canvas.clone();
+            let events = self.events.clone();
             let canvas_x: i32 = js! {
             return @{&canvas}.getBoundingClientRect().left; }
                 .try_into()
@@ -165,29 +170,45 @@ impl App {
             return @{&canvas}.getBoundingClientRect().top; }
                 .try_into()
                 .unwrap();
-            map_event!{
-                self.events,
-                MouseMoveEvent,
-                MousePos,
-                e,
-                (e.client_x() - canvas_x,e.client_y() - canvas_y),
-                true
+            move |e: MouseMoveEvent| {
+                e.prevent_default();
+                let movement: Vec<f64> = js! {
+                    var e = @{&e};
+                    return [e.movementX || 0, e.movementY || 0];
+                }.try_into()
+                    .unwrap();
+
+                let mut events = events.borrow_mut();
+                events.push(AppEvent::MousePos((
+                    ((e.client_x() - canvas_x) as f32 * dpr) as i32,
+                    ((e.client_y() - canvas_y) as f32 * dpr) as i32,
+                )));
+                events.push(AppEvent::MouseMotion((movement[0] as f32, movement[1] as f32)));
             }
         });
 
-        canvas.add_event_listener(map_event!{
-            self.events,
-            KeyDownEvent,
-            KeyDown,
-            e,
-            events::KeyDownEvent {
-                code: e.code(),
-                key: e.key(),
-                shift: e.shift_key(),
-                alt: e.alt_key(),
-                ctrl: e.ctrl_key(),
-            },
-            true
+        canvas.add_event_listener({
+            let events = self.events.clone();
+            move |e: KeyDownEvent| {
+                e.prevent_default();
+                let key = e.key();
+                let mut events = events.borrow_mut();
+
+                // the default action is prevented, so there is no keypress event :
+                // printable keys are reported as text input here.
+                // Input methods are not supported on the web.
+                if key.chars().count() == 1 && !e.ctrl_key() && !e.alt_key() {
+                    events.push(AppEvent::TextInput(key.clone()));
+                }
+
+                events.push(AppEvent::KeyDown(events::KeyDownEvent {
+                    code: e.code(),
+                    key,
+                    shift: e.shift_key(),
+                    alt: e.alt_key(),
+                    ctrl: e.ctrl_key(),
+                }));
+            }
         });
 
         // canvas.add_event_listener(map_event!{
@@ -225,6 +246,85 @@ impl App {
                 (canvas.offset_width() as u32, canvas.offset_height() as u32)
             }
         });
+
+        canvas.add_event_listener(map_event!{ self.events, FocusEvent, Focused, true });
+        canvas.add_event_listener(map_event!{ self.events, BlurEvent, Focused, false });
+
+        let on_wheel = {
+            let events = self.events.clone();
+            move |delta_x: f64, delta_y: f64| {
+                events
+                    .borrow_mut()
+                    .push(AppEvent::MouseWheel(events::MouseWheelEvent {
+                        delta_x: delta_x as f32,
+                        delta_y: delta_y as f32,
+                    }));
+            }
+        };
+
+        let on_touch = {
+            let events = self.events.clone();
+            move |id: f64, phase: i32, x: i32, y: i32| {
+                let phase = match phase {
+                    0 => events::TouchPhase::Started,
+                    1 => events::TouchPhase::Moved,
+                    2 => events::TouchPhase::Ended,
+                    _ => events::TouchPhase::Cancelled,
+                };
+                events.borrow_mut().push(AppEvent::Touch(events::TouchEvent {
+                    id: id as u64,
+                    phase,
+                    pos: (x, y),
+                }));
+            }
+        };
+
+        let on_close = {
+            let events = self.events.clone();
+            move || {
+                events.borrow_mut().push(AppEvent::CloseRequested);
+            }
+        };
+
+        js! {
+            var canvas = @{canvas};
+            var on_wheel = @{on_wheel};
+            var on_touch = @{on_touch};
+            var on_close = @{on_close};
+
+            canvas.addEventListener("wheel", function(e) {
+                e.preventDefault();
+                // convert to lines, scrolling up is positive
+                var scale = 1.0;
+                if (e.deltaMode == 0) {
+                    scale = 1.0 / @{PIXELS_PER_LINE};
+                } else if (e.deltaMode == 2) {
+                    scale = canvas.clientHeight / @{PIXELS_PER_LINE};
+                }
+                on_wheel(e.deltaX * scale, -e.deltaY * scale);
+            });
+
+            function touch_listener(phase) {
+                return function(e) {
+                    e.preventDefault();
+                    var rect = canvas.getBoundingClientRect();
+                    for (var i = 0; i < e.changedTouches.length; i++) {
+                        var t = e.changedTouches[i];
+                        on_touch(t.identifier, phase,
+                            Math.round((t.clientX - rect.left) * window.devicePixelRatio),
+                            Math.round((t.clientY - rect.top) * window.devicePixelRatio));
+                    }
+                };
+            }
+            canvas.addEventListener("touchstart", touch_listener(0));
+            canvas.addEventListener("touchmove", touch_listener(1));
+            canvas.addEventListener("touchend", touch_listener(2));
+            canvas.addEventListener("touchcancel", touch_listener(3));
+
+            window.addEventListener("beforeunload", function() {
+                on_close();
+            });
+        };
     }
 
     pub fn print<T: Into<String>>(msg: T) {
@@ -274,6 +374,21 @@ impl App {
         stdweb::event_loop();
     }
 
+    /// Lock the pointer to the canvas, `AppEvent::MouseMotion` still reports the mouse movements.
+    /// Browsers only allow it shortly after a user input, e.g. a click.
+    pub fn set_cursor_grab(&self, b: bool) {
+        js! {
+            var c = @{&self.window};
+            if (@{b}) {
+                if (c.requestPointerLock) {
+                    c.requestPointerLock();
+                }
+            } else if (document.exitPointerLock) {
+                document.exitPointerLock();
+            }
+        };
+    }
+
     pub fn set_fullscreen(&mut self, _b: bool) {
         // unimplemented!();
     }
@@ -6,6 +6,10 @@
 #[macro_use]
 extern crate stdweb;
 
+// NOT wasm-unknown-unknown
+#[cfg(not(target_arch = "wasm32"))]
+extern crate gilrs;
+
 #[cfg(target_arch = "wasm32")]
 #[path = "web_pad.rs"]
 pub mod pad;
@@ -16,3 +20,42 @@ pub mod pad;
 pub mod pad;
 
 pub use self::pad::*;
+
+/// Button and axis numbers follow the web `Gamepad` standard mapping
+/// (https://w3c.github.io/gamepad/#remapping) on every target.
+pub mod mapping {
+    /// bottom face button (A on xbox, cross on playstation)
+    pub const BUTTON_SOUTH: i32 = 0;
+    pub const BUTTON_EAST: i32 = 1;
+    pub const BUTTON_WEST: i32 = 2;
+    pub const BUTTON_NORTH: i32 = 3;
+    pub const BUTTON_LEFT_BUMPER: i32 = 4;
+    pub const BUTTON_RIGHT_BUMPER: i32 = 5;
+    /// analog triggers, use `gamepad_button_value` to get their position
+    pub const BUTTON_LEFT_TRIGGER: i32 = 6;
+    pub const BUTTON_RIGHT_TRIGGER: i32 = 7;
+    pub const BUTTON_SELECT: i32 = 8;
+    pub const BUTTON_START: i32 = 9;
+    pub const BUTTON_LEFT_STICK: i32 = 10;
+    pub const BUTTON_RIGHT_STICK: i32 = 11;
+    pub const BUTTON_DPAD_UP: i32 = 12;
+    pub const BUTTON_DPAD_DOWN: i32 = 13;
+    pub const BUTTON_DPAD_LEFT: i32 = 14;
+    pub const BUTTON_DPAD_RIGHT: i32 = 15;
+    pub const BUTTON_MODE: i32 = 16;
+    pub const BUTTON_COUNT: i32 = 17;
+
+    /// stick axes are between -1.0 and 1.0, left/up is negative
+    pub const AXIS_LEFT_X: i32 = 0;
+    pub const AXIS_LEFT_Y: i32 = 1;
+    pub const AXIS_RIGHT_X: i32 = 2;
+    pub const AXIS_RIGHT_Y: i32 = 3;
+    pub const AXIS_COUNT: i32 = 4;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamepadEvent {
+    /// player number and gamepad name
+    Connected(i32, String),
+    Disconnected(i32),
+}
@@ -1,10 +1,213 @@
-// TODO
-pub fn gamepad_init() {}
+use std::cell::RefCell;
+use std::collections::HashMap;
 
-pub fn gamepad_axis(_player_num: i32) -> (f32, f32) {
-    (0.0, 0.0)
+use gilrs::ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Replay, Ticks};
+use gilrs::{Axis, Button, EventType, Gilrs};
+use uni_app::App;
+
+use super::mapping::*;
+use super::GamepadEvent;
+
+struct PadState {
+    gilrs: Gilrs,
+    events: Vec<GamepadEvent>,
+    /// running rumble effects, an effect stops when it is dropped
+    effects: HashMap<usize, Effect>,
+}
+
+thread_local!(static STATE: RefCell<Option<PadState>> = RefCell::new(None));
+
+const BUTTONS: [Button; BUTTON_COUNT as usize] = [
+    Button::South,
+    Button::East,
+    Button::West,
+    Button::North,
+    Button::LeftTrigger,
+    Button::RightTrigger,
+    Button::LeftTrigger2,
+    Button::RightTrigger2,
+    Button::Select,
+    Button::Start,
+    Button::LeftThumb,
+    Button::RightThumb,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+    Button::Mode,
+];
+
+pub fn gamepad_init() {
+    STATE.with(|s| {
+        if s.borrow().is_some() {
+            return;
+        }
+
+        match Gilrs::new() {
+            Ok(gilrs) => {
+                let mut events = Vec::new();
+                for (id, gamepad) in gilrs.gamepads() {
+                    App::print(format!("gamepad[{}] id {} connected.\n", id, gamepad.name()));
+                    events.push(GamepadEvent::Connected(id as i32, gamepad.name().to_string()));
+                }
+                *s.borrow_mut() = Some(PadState {
+                    gilrs,
+                    events,
+                    effects: HashMap::new(),
+                });
+            }
+            Err(e) => App::print(format!("warning : no gamepad support : {}\n", e)),
+        }
+    });
+}
+
+/// Process the pending gilrs events, so that the gamepads state is up to date
+fn with_state<R, F>(default: R, f: F) -> R
+where
+    F: FnOnce(&mut PadState) -> R,
+{
+    STATE.with(|s| match *s.borrow_mut() {
+        Some(ref mut state) => {
+            while let Some(evt) = state.gilrs.next_event() {
+                match evt.event {
+                    EventType::Connected => {
+                        let name = state.gilrs[evt.id].name().to_string();
+                        App::print(format!("gamepad[{}] id {} connected.\n", evt.id, name));
+                        state
+                            .events
+                            .push(GamepadEvent::Connected(evt.id as i32, name));
+                    }
+                    EventType::Disconnected => {
+                        App::print(format!("gamepad[{}] disconnected.\n", evt.id));
+                        state.effects.remove(&evt.id);
+                        state.events.push(GamepadEvent::Disconnected(evt.id as i32));
+                    }
+                    _ => (),
+                }
+            }
+            f(state)
+        }
+        None => default,
+    })
+}
+
+fn connected_pad(state: &PadState, player_num: i32) -> Option<&::gilrs::Gamepad> {
+    if player_num < 0 {
+        return None;
+    }
+    state.gilrs.connected_gamepad(player_num as usize)
+}
+
+/// Left stick position
+pub fn gamepad_axis(player_num: i32) -> (f32, f32) {
+    (
+        gamepad_axis_value(player_num, AXIS_LEFT_X),
+        gamepad_axis_value(player_num, AXIS_LEFT_Y),
+    )
 }
 
-pub fn gamepad_button(_player_num: i32, _button_num: i32) -> bool {
-    false
+/// Value of a standard mapping axis, between -1.0 and 1.0
+pub fn gamepad_axis_value(player_num: i32, axis_num: i32) -> f32 {
+    with_state(0.0, |state| match connected_pad(state, player_num) {
+        Some(pad) => match axis_num {
+            AXIS_LEFT_X => pad.value(Axis::LeftStickX),
+            // gilrs y axes point up, the standard mapping points down
+            AXIS_LEFT_Y => -pad.value(Axis::LeftStickY),
+            AXIS_RIGHT_X => pad.value(Axis::RightStickX),
+            AXIS_RIGHT_Y => -pad.value(Axis::RightStickY),
+            _ => 0.0,
+        },
+        None => 0.0,
+    })
+}
+
+/// Value of a standard mapping button, between 0.0 and 1.0.
+/// Only the triggers have intermediate values.
+pub fn gamepad_button_value(player_num: i32, button_num: i32) -> f32 {
+    if button_num < 0 || button_num >= BUTTON_COUNT {
+        return 0.0;
+    }
+    let button = BUTTONS[button_num as usize];
+
+    with_state(0.0, |state| match connected_pad(state, player_num) {
+        Some(pad) => {
+            let value = pad.button_data(button).map(|d| d.value()).unwrap_or(0.0);
+            // some pads report the dpad as a hat axis
+            let dpad = match button_num {
+                BUTTON_DPAD_UP => pad.value(Axis::DPadY) > 0.5,
+                BUTTON_DPAD_DOWN => pad.value(Axis::DPadY) < -0.5,
+                BUTTON_DPAD_LEFT => pad.value(Axis::DPadX) < -0.5,
+                BUTTON_DPAD_RIGHT => pad.value(Axis::DPadX) > 0.5,
+                _ => false,
+            };
+            if dpad || pad.is_pressed(button) {
+                value.max(1.0)
+            } else {
+                value
+            }
+        }
+        None => 0.0,
+    })
+}
+
+pub fn gamepad_button(player_num: i32, button_num: i32) -> bool {
+    gamepad_button_value(player_num, button_num) > 0.5
+}
+
+pub fn gamepad_connected(player_num: i32) -> bool {
+    with_state(false, |state| connected_pad(state, player_num).is_some())
+}
+
+/// Connection events since the last call
+pub fn gamepad_events() -> Vec<GamepadEvent> {
+    with_state(Vec::new(), |state| state.events.drain(..).collect())
+}
+
+/// Vibrate the gamepad, `strong` and `weak` are the low and high frequency
+/// motors magnitude between 0.0 and 1.0.
+/// Returns false if the gamepad does not support force feedback.
+pub fn gamepad_rumble(player_num: i32, strong: f32, weak: f32, duration_ms: u32) -> bool {
+    with_state(false, |state| {
+        let supported = connected_pad(state, player_num)
+            .map(|pad| pad.is_ff_supported())
+            .unwrap_or(false);
+        if !supported {
+            return false;
+        }
+
+        let magnitude = |v: f32| (v.max(0.0).min(1.0) * u16::max_value() as f32) as u16;
+        let replay = Replay {
+            play_for: Ticks::from_ms(duration_ms),
+            ..Default::default()
+        };
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong {
+                    magnitude: magnitude(strong),
+                },
+                scheduling: replay,
+                ..Default::default()
+            })
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Weak {
+                    magnitude: magnitude(weak),
+                },
+                scheduling: replay,
+                ..Default::default()
+            })
+            .gamepads(&[player_num as usize])
+            .finish(&mut state.gilrs);
+
+        match effect.and_then(|effect| effect.play().map(|_| effect)) {
+            Ok(effect) => {
+                // replaces and stops the previous effect of this gamepad
+                state.effects.insert(player_num as usize, effect);
+                true
+            }
+            Err(e) => {
+                App::print(format!("error : gamepad[{}] rumble : {}\n", player_num, e));
+                false
+            }
+        }
+    })
 }
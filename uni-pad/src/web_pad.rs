@@ -1,8 +1,13 @@
 use stdweb::unstable::TryInto;
+use stdweb::Value;
+
+use super::mapping::*;
+use super::GamepadEvent;
 
 pub fn gamepad_init() {
     js! {
         window.pads=[];
+        window.pad_events=[];
         if (navigator.getGamepads === undefined) {
             console.log("warning : no gamepad support on this browser");
         } else {
@@ -10,20 +15,23 @@ pub fn gamepad_init() {
                 if (e.gamepad) {
                     console.log("gamepad["+e.gamepad.index+"] id "+e.gamepad.id+" connected.");
                     window.pads[e.gamepad.index] = e.gamepad;
+                    window.pad_events.push([e.gamepad.index, e.gamepad.id]);
                 }
             });
             window.addEventListener("gamepaddisconnected", function(e) {
                 if (e.gamepad) {
                     console.log("gamepad["+e.gamepad.index+"] id "+e.gamepad.id+" disconnected.");
                     window.pads[e.gamepad.index] = undefined;
+                    window.pad_events.push([e.gamepad.index, null]);
                 }
             });
         }
     };
 }
 
-pub fn gamepad_axis(player_num: i32) -> (f32, f32) {
-    let x: f64 = js! {
+fn refresh_pads() {
+    js! {
+        // chrome only updates the gamepads state through getGamepads
         if (navigator.userAgent.toLowerCase().indexOf("chrome") != -1) {
             var gp = navigator.getGamepads();
             for (var i=0; i < gp.length; i++) {
@@ -32,40 +40,103 @@ pub fn gamepad_axis(player_num: i32) -> (f32, f32) {
                 }
             }
         }
-        if ( window.pads[@{player_num}] ) {
-            return window.pads[@{player_num}].axes[0];
+    };
+}
+
+/// Left stick position
+pub fn gamepad_axis(player_num: i32) -> (f32, f32) {
+    (
+        gamepad_axis_value(player_num, AXIS_LEFT_X),
+        gamepad_axis_value(player_num, AXIS_LEFT_Y),
+    )
+}
+
+/// Value of a standard mapping axis, between -1.0 and 1.0
+pub fn gamepad_axis_value(player_num: i32, axis_num: i32) -> f32 {
+    refresh_pads();
+    let ret: f64 = js! {
+        var pad = window.pads[@{player_num}];
+        if ( pad && pad.axes[@{axis_num}] !== undefined ) {
+            return pad.axes[@{axis_num}];
         } else {
             return 0.0;
         }
     }.try_into()
         .unwrap();
-    let y: f64 = js! {
-        if ( window.pads[@{player_num}] ) {
-            return window.pads[@{player_num}].axes[1];
+    ret as f32
+}
+
+/// Value of a standard mapping button, between 0.0 and 1.0.
+/// Only the triggers have intermediate values.
+pub fn gamepad_button_value(player_num: i32, button_num: i32) -> f32 {
+    refresh_pads();
+    let ret: f64 = js! {
+        var pad = window.pads[@{player_num}];
+        if ( pad && pad.buttons[@{button_num}] !== undefined ) {
+            var button = pad.buttons[@{button_num}];
+            if (typeof button == "object") {
+                return button.pressed ? Math.max(button.value, 1.0) : button.value;
+            } else {
+                return button;
+            }
         } else {
             return 0.0;
         }
     }.try_into()
         .unwrap();
-    (x as f32, y as f32)
+    ret as f32
 }
+
 pub fn gamepad_button(player_num: i32, button_num: i32) -> bool {
+    gamepad_button_value(player_num, button_num) > 0.5
+}
+
+pub fn gamepad_connected(player_num: i32) -> bool {
+    refresh_pads();
     let ret = js! {
-        if (navigator.userAgent.toLowerCase().indexOf("chrome") != -1) {
-            var gp = navigator.getGamepads();
-            for (var i=0; i < gp.length; i++) {
-                if (gp[i]!=null) {
-                    window.pads[gp[i].index]=gp[i];
-                }
-            }
-        }
-        if ( window.pads[@{player_num}] ) {
-            var button = window.pads[@{player_num}].buttons[@{button_num}];
-            if (typeof button == "object") {
-                return button.pressed;
-            } else {
-                return button == 1.0;
-            }
+        var pad = window.pads[@{player_num}];
+        return pad !== undefined && pad.connected;
+    }.try_into()
+        .unwrap();
+    ret
+}
+
+/// Connection events since the last call
+pub fn gamepad_events() -> Vec<GamepadEvent> {
+    let events: Vec<Value> = js! {
+        var events = window.pad_events || [];
+        window.pad_events = [];
+        return events;
+    }.try_into()
+        .unwrap();
+
+    events
+        .into_iter()
+        .filter_map(|evt| {
+            let evt: Vec<Value> = evt.try_into().ok()?;
+            let index: i32 = evt[0].clone().try_into().ok()?;
+            Some(match evt[1] {
+                Value::String(ref name) => GamepadEvent::Connected(index, name.clone()),
+                _ => GamepadEvent::Disconnected(index),
+            })
+        })
+        .collect()
+}
+
+/// Vibrate the gamepad, `strong` and `weak` are the low and high frequency
+/// motors magnitude between 0.0 and 1.0.
+/// Returns false if the gamepad does not support force feedback.
+pub fn gamepad_rumble(player_num: i32, strong: f32, weak: f32, duration_ms: u32) -> bool {
+    refresh_pads();
+    let ret = js! {
+        var pad = window.pads[@{player_num}];
+        if ( pad && pad.vibrationActuator ) {
+            pad.vibrationActuator.playEffect("dual-rumble", {
+                duration: @{duration_ms},
+                strongMagnitude: @{strong},
+                weakMagnitude: @{weak}
+            });
+            return true;
         } else {
             return false;
         }
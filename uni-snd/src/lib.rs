@@ -30,7 +30,9 @@ pub enum SoundError {
 }
 
 pub trait SoundGenerator<T>: Send {
-    fn init(&mut self, sample_rate: f32);
+    /// Called once the device is ready. `output_count` is the number of
+    /// interleaved device channels `next_value` is called for on each frame.
+    fn init(&mut self, sample_rate: f32, output_count: usize);
     fn handle_event(&mut self, evt: T);
     fn next_value(&mut self) -> f32;
 }
@@ -71,6 +71,10 @@ impl<T: Send + 'static> SoundDriver<T> {
             tx.send(event).unwrap();
         }
     }
+    /// Sample rate of the device, None without a device
+    pub fn sample_rate(&self) -> Option<f32> {
+        self.format.as_ref().map(|fmt| fmt.sample_rate.0 as f32)
+    }
     fn get_sample_rate(&self) -> f32 {
         if let Some(ref fmt) = self.format {
             fmt.sample_rate.0 as f32
@@ -78,18 +82,26 @@ impl<T: Send + 'static> SoundDriver<T> {
             1.0
         }
     }
+    fn get_output_count(&self) -> usize {
+        if let Some(ref fmt) = self.format {
+            fmt.channels as usize
+        } else {
+            2
+        }
+    }
     pub fn frame(&mut self) {}
     pub fn start(&mut self) {
         let (tx, rx) = channel();
         self.tx = Some(tx);
         let stream_id = self.stream_id.take().unwrap();
         let sample_rate = self.get_sample_rate();
+        let output_count = self.get_output_count();
         let mut generator = self.generator.take().unwrap();
         if let Some(evt) = self.event_loop.take() {
             thread::spawn(move || {
                 App::print("starting audio loop\n");
                 evt.play_stream(stream_id);
-                generator.init(sample_rate);
+                generator.init(sample_rate, output_count);
                 evt.run(move |_stream_id, stream_data| {
                     for event in rx.try_iter() {
                         generator.handle_event(event);
@@ -79,6 +79,19 @@ impl<T> SoundDriver<T> {
             Some(v) => if v == -1.0 {GameStatus::Paused} else {GameStatus::Resumed(v)}
         }
     }
+    /// Sample rate of the audio context, None without web audio
+    pub fn sample_rate(&self) -> Option<f32> {
+        match self.err {
+            SoundError::NoDevice => None,
+            _ => {
+                let sample_rate: f64 = js!{
+                    return @{&self.ctx}.sampleRate;
+                }.try_into()
+                    .unwrap();
+                Some(sample_rate as f32)
+            }
+        }
+    }
     pub fn send_event(&mut self, event: T) {
         if let Some(ref mut gen) = self.generator {
             gen.handle_event(event);
@@ -135,7 +148,8 @@ impl<T> SoundDriver<T> {
                 "sound device : Web audio context. sample_rate: {}\n",
                 sample_rate
             ));
-            gen.init(sample_rate as f32);
+            // the web audio buffers are always filled as interleaved stereo
+            gen.init(sample_rate as f32, 2);
         }
     }
 }
@@ -47,3 +47,50 @@ fn impl_actor(ast: &syn::DeriveInput) -> quote::Tokens {
         }
     }
 }
+
+#[proc_macro_derive(Inspect, attributes(inspect))]
+pub fn inspect(input: TokenStream) -> TokenStream {
+    let ast = syn::parse(input).unwrap();
+
+    let gen = impl_inspect(&ast);
+
+    gen.into()
+}
+
+fn is_inspected(field: &syn::Field) -> bool {
+    field.attrs.iter().any(|attr| match attr.interpret_meta() {
+        Some(meta) => meta.name() == "inspect",
+        None => false,
+    })
+}
+
+fn impl_inspect(ast: &syn::DeriveInput) -> quote::Tokens {
+    let name = &ast.ident;
+
+    let fields: Vec<syn::Ident> = match ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(ref fields),
+            ..
+        }) => fields
+            .named
+            .iter()
+            .filter(|f| is_inspected(f))
+            .filter_map(|f| f.ident.clone())
+            .collect(),
+        _ => panic!("#[derive(Inspect)] is only supported for structs with named fields"),
+    };
+
+    let labels: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+
+    quote!{
+        impl ::unrust::engine::Inspect for #name {
+            fn inspect(&mut self) -> bool {
+                let mut changed = false;
+                #(
+                    changed |= ::unrust::engine::InspectValue::inspect_value(&mut self.#fields, #labels);
+                )*
+                changed
+            }
+        }
+    }
+}
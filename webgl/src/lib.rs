@@ -112,6 +112,15 @@ pub mod common {
         }
     }
 
+    #[derive(Debug)]
+    pub struct WebGLQuery(pub Reference);
+    impl Deref for WebGLQuery {
+        type Target = Reference;
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
     #[derive(Debug)]
     pub struct WebGLFrameBuffer(pub Reference);
     impl Deref for WebGLFrameBuffer {
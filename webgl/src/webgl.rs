@@ -161,6 +161,16 @@ impl GLContext {
         };
     }
 
+    pub fn buffer_sub_data(&self, kind: BufferKind, offset: u32, data: &[u8]) {
+        self.log("buffer_sub_data");
+
+        js! {
+            @(no_return)
+            var ctx = Module.gl.get(@{self.reference});
+            ctx.bufferSubData(@{kind as u32},@{offset},@{ TypedArray::from(data) })
+        };
+    }
+
     pub fn bind_buffer(&self, kind: BufferKind, buffer: &WebGLBuffer) {
         self.log("bind_buffer");
         js! {
@@ -216,6 +226,39 @@ impl GLContext {
         };
     }
 
+    pub fn get_shader_parameter(&self, shader: &WebGLShader, pname: ShaderParameter) -> i32 {
+        let res = js! {
+            var ctx = Module.gl.get(@{&self.reference});
+            var shader = Module.gl.get(@{shader.deref()});
+
+            return +ctx.getShaderParameter(shader, @{pname as u32});
+        };
+
+        res.try_into().unwrap()
+    }
+
+    pub fn get_shader_info_log(&self, shader: &WebGLShader) -> String {
+        let res = js! {
+            var ctx = Module.gl.get(@{&self.reference});
+            var shader = Module.gl.get(@{shader.deref()});
+
+            return ctx.getShaderInfoLog(shader) || "";
+        };
+
+        res.into_string().unwrap()
+    }
+
+    pub fn delete_shader(&self, shader: &WebGLShader) {
+        self.log("delete_shader");
+        js! {
+            @(no_return)
+            var ctx = Module.gl.get(@{&self.reference});
+            var shader = Module.gl.get(@{shader.deref()});
+            ctx.deleteShader(shader);
+            Module.gl.remove(@{shader.deref()});
+        };
+    }
+
     pub fn create_program(&self) -> WebGLProgram {
         self.log("create_program");
         let value = js! {
@@ -242,6 +285,17 @@ impl GLContext {
         };
     }
 
+    pub fn delete_program(&self, program: &WebGLProgram) {
+        self.log("delete_program");
+        js! {
+            @(no_return)
+            var ctx = Module.gl.get(@{&self.reference});
+            var h = Module.gl.get(@{program.deref()});
+            ctx.deleteProgram(h.prog);
+            Module.gl.remove(@{program.deref()});
+        };
+    }
+
     pub fn use_program(&self, program: &WebGLProgram) {
         self.log("use_program");
         js! {
@@ -425,6 +479,17 @@ impl GLContext {
         };
     }
 
+    pub fn scissor(&self, x: i32, y: i32, width: u32, height: u32) {
+        self.log("scissor");
+        let params = js! { return [@{x},@{y},@{width},@{height}] };
+        js! {
+            @(no_return)
+            var ctx = Module.gl.get(@{&self.reference});
+            var p = @{params};
+            ctx.scissor(p[0],p[1],p[2],p[3]);
+        };
+    }
+
     pub fn draw_elements(&self, mode: Primitives, count: usize, kind: DataType, offset: u32) {
         self.log("draw_elemnts");
         js_raw!({
@@ -872,6 +937,17 @@ impl GLContext {
         }
     }
 
+    pub fn get_program_info_log(&self, program: &WebGLProgram) -> String {
+        let res = js! {
+            var h = Module.gl.get(@{program.deref()});
+            var ctx = Module.gl.get(@{self.reference});
+
+            return ctx.getProgramInfoLog(h.prog) || "";
+        };
+
+        res.into_string().unwrap()
+    }
+
     pub fn get_program_parameter(&self, program: &WebGLProgram, pname: ShaderParameter) -> i32 {
         let res = js! {
             var h = Module.gl.get(@{program.deref()});
@@ -1002,4 +1078,129 @@ impl GLContext {
             ctx.bindFramebuffer(@{buffer as u32},null)
         }
     }
+
+    /// True when 32 bits indices can be drawn, always on WebGL2
+    /// and with OES_element_index_uint on WebGL1
+    pub fn element_index_uint_supported(&self) -> bool {
+        if self.is_webgl2 {
+            return true;
+        }
+
+        let supported = js! {
+            var ctx = Module.gl.get(@{self.reference});
+            if (Module.gl.uint_ext === undefined) {
+                Module.gl.uint_ext = ctx.getExtension("OES_element_index_uint");
+            }
+            return !!Module.gl.uint_ext;
+        };
+        supported.try_into().unwrap()
+    }
+
+    /// True when EXT_disjoint_timer_query is available
+    pub fn timer_query_supported(&self) -> bool {
+        let supported = js! {
+            var ctx = Module.gl.get(@{self.reference});
+            if (Module.gl.timer_ext === undefined) {
+                Module.gl.timer_ext = ctx.getExtension("EXT_disjoint_timer_query_webgl2") ||
+                    ctx.getExtension("EXT_disjoint_timer_query");
+            }
+            return !!Module.gl.timer_ext;
+        };
+        supported.try_into().unwrap()
+    }
+
+    pub fn create_query(&self) -> WebGLQuery {
+        self.log("create_query");
+        let val = js! {
+            var ctx = Module.gl.get(@{self.reference});
+            var ext = Module.gl.timer_ext;
+            var query = ctx.createQuery ? ctx.createQuery() : ext.createQueryEXT();
+            return Module.gl.add(query);
+        };
+        WebGLQuery(val.try_into().unwrap())
+    }
+
+    pub fn delete_query(&self, query: &WebGLQuery) {
+        self.log("delete_query");
+        js! {
+            @(no_return)
+            var ctx = Module.gl.get(@{self.reference});
+            var ext = Module.gl.timer_ext;
+            var query = Module.gl.get(@{query.0});
+            if (ctx.deleteQuery) {
+                ctx.deleteQuery(query);
+            } else {
+                ext.deleteQueryEXT(query);
+            }
+            Module.gl.remove(@{query.0});
+        };
+    }
+
+    /// Start measuring the gpu time of the following commands
+    pub fn begin_time_query(&self, query: &WebGLQuery) {
+        self.log("begin_time_query");
+        js! {
+            @(no_return)
+            var ctx = Module.gl.get(@{self.reference});
+            var ext = Module.gl.timer_ext;
+            var query = Module.gl.get(@{query.0});
+            if (ctx.beginQuery) {
+                ctx.beginQuery(ext.TIME_ELAPSED_EXT, query);
+            } else {
+                ext.beginQueryEXT(ext.TIME_ELAPSED_EXT, query);
+            }
+        };
+    }
+
+    pub fn end_time_query(&self) {
+        self.log("end_time_query");
+        js! {
+            @(no_return)
+            var ctx = Module.gl.get(@{self.reference});
+            var ext = Module.gl.timer_ext;
+            if (ctx.endQuery) {
+                ctx.endQuery(ext.TIME_ELAPSED_EXT);
+            } else {
+                ext.endQueryEXT(ext.TIME_ELAPSED_EXT);
+            }
+        };
+    }
+
+    pub fn query_result_available(&self, query: &WebGLQuery) -> bool {
+        let val = js! {
+            var ctx = Module.gl.get(@{self.reference});
+            var ext = Module.gl.timer_ext;
+            var query = Module.gl.get(@{query.0});
+            if (ctx.getQueryParameter) {
+                return !!ctx.getQueryParameter(query, ctx.QUERY_RESULT_AVAILABLE);
+            }
+            return !!ext.getQueryObjectEXT(query, ext.QUERY_RESULT_AVAILABLE_EXT);
+        };
+        val.try_into().unwrap()
+    }
+
+    /// Result of a time query in nanoseconds
+    pub fn query_result(&self, query: &WebGLQuery) -> u64 {
+        let val = js! {
+            var ctx = Module.gl.get(@{self.reference});
+            var ext = Module.gl.timer_ext;
+            var query = Module.gl.get(@{query.0});
+            if (ctx.getQueryParameter) {
+                return ctx.getQueryParameter(query, ctx.QUERY_RESULT);
+            }
+            return ext.getQueryObjectEXT(query, ext.QUERY_RESULT_EXT);
+        };
+        let ns: f64 = val.try_into().unwrap();
+        ns as u64
+    }
+
+    /// True when the results of the time queries are invalid, e.g. the gpu changed its frequency
+    pub fn gpu_disjoint(&self) -> bool {
+        let val = js! {
+            var ctx = Module.gl.get(@{self.reference});
+            var ext = Module.gl.timer_ext;
+            return !!ctx.getParameter(ext.GPU_DISJOINT_EXT);
+        };
+        val.try_into().unwrap()
+    }
 }
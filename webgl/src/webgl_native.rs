@@ -50,6 +50,42 @@ fn get_string(param: u32) -> String {
     };
 }
 
+/// Whether the context is OpenGL ES, with its major and minor version
+fn gl_version() -> (bool, u32, u32) {
+    // e.g. "4.5.0 NVIDIA 390.48" or "OpenGL ES 2.0 Mesa 18.0.5"
+    let version = get_string(gl::VERSION);
+    let mut numbers = version
+        .trim_left_matches(|c: char| !c.is_digit(10))
+        .split(|c: char| !c.is_digit(10))
+        .map(|n| n.parse().unwrap_or(0));
+
+    let major = numbers.next().unwrap_or(0);
+    let minor = numbers.next().unwrap_or(0);
+    (version.starts_with("OpenGL ES"), major, minor)
+}
+
+/// True when the context has the extension `name`, e.g. "GL_ARB_timer_query"
+fn has_extension(name: &str) -> bool {
+    let (_, major, _) = gl_version();
+
+    // the extensions string is removed from the core profiles
+    if major >= 3 {
+        let mut count = 0;
+        unsafe {
+            gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        }
+
+        return (0..count.max(0) as u32).any(|i| unsafe {
+            let ext = gl::GetStringi(gl::EXTENSIONS, i);
+            !ext.is_null() && CStr::from_ptr(ext as *const _).to_bytes() == name.as_bytes()
+        });
+    }
+
+    get_string(gl::EXTENSIONS)
+        .split_whitespace()
+        .any(|ext| ext == name)
+}
+
 pub type WebGLContext<'p> = Box<'p + for<'a> FnMut(&'a str) -> *const c_void>;
 
 impl WebGLRenderingContext {
@@ -144,32 +180,49 @@ impl GLContext {
     pub fn compile_shader(&self, shader: &WebGLShader) {
         unsafe {
             gl::CompileShader(shader.0);
+        }
 
-            // Get the compile status
-            let mut status = gl::FALSE as gl::types::GLint;
-            gl::GetShaderiv(shader.0, gl::COMPILE_STATUS, &mut status);
-
-            // Fail on error
-            if status != (gl::TRUE as gl::types::GLint) {
-                let mut len = 0;
-                gl::GetShaderiv(shader.0, gl::INFO_LOG_LENGTH, &mut len);
-                let mut buf = Vec::with_capacity(len as usize);
-                buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
-                gl::GetShaderInfoLog(
-                    shader.0,
-                    len,
-                    ptr::null_mut(),
-                    buf.as_mut_ptr() as *mut gl::types::GLchar,
-                );
-
-                match String::from_utf8(buf) {
-                    Ok(s) => panic!(s),
-                    Err(_) => panic!("Compile shader fail, reason unknown"),
-                }
+        check_gl_error("compile_shader");
+    }
+
+    /// The status of the compilation is read with `get_shader_parameter`
+    /// and the errors with `get_shader_info_log`
+    pub fn get_shader_parameter(&self, shader: &WebGLShader, pname: ShaderParameter) -> i32 {
+        let mut res = 0;
+        unsafe {
+            gl::GetShaderiv(shader.0, pname as _, &mut res);
+        }
+
+        check_gl_error("get_shader_parameter");
+        res
+    }
+
+    pub fn get_shader_info_log(&self, shader: &WebGLShader) -> String {
+        unsafe {
+            let mut len = 0;
+            gl::GetShaderiv(shader.0, gl::INFO_LOG_LENGTH, &mut len);
+            if len <= 1 {
+                return String::new();
             }
+
+            let mut buf = Vec::with_capacity(len as usize);
+            buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
+            gl::GetShaderInfoLog(
+                shader.0,
+                len,
+                ptr::null_mut(),
+                buf.as_mut_ptr() as *mut gl::types::GLchar,
+            );
+
+            String::from_utf8_lossy(&buf).into_owned()
         }
+    }
 
-        check_gl_error("compile_shader");
+    pub fn delete_shader(&self, shader: &WebGLShader) {
+        unsafe {
+            gl::DeleteShader(shader.0);
+        }
+        check_gl_error("delete_shader");
     }
 
     pub fn create_program(&self) -> WebGLProgram {
@@ -181,32 +234,38 @@ impl GLContext {
     pub fn link_program(&self, program: &WebGLProgram) {
         unsafe {
             gl::LinkProgram(program.0);
-            // Get the link status
-            let mut status = gl::FALSE as gl::types::GLint;
-            gl::GetProgramiv(program.0, gl::LINK_STATUS, &mut status);
-
-            // Fail on error
-            if status != (gl::TRUE as gl::types::GLint) {
-                let mut len = 0;
-                gl::GetProgramiv(program.0, gl::INFO_LOG_LENGTH, &mut len);
-                let mut buf = Vec::with_capacity(len as usize);
-                buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
-                gl::GetProgramInfoLog(
-                    program.0,
-                    len,
-                    ptr::null_mut(),
-                    buf.as_mut_ptr() as *mut gl::types::GLchar,
-                );
-
-                match String::from_utf8(buf) {
-                    Ok(s) => panic!(s),
-                    Err(_) => panic!("Link program fail, reason unknown"),
-                }
-            }
         }
         check_gl_error("link_program");
     }
 
+    pub fn delete_program(&self, program: &WebGLProgram) {
+        unsafe {
+            gl::DeleteProgram(program.0);
+        }
+        check_gl_error("delete_program");
+    }
+
+    pub fn get_program_info_log(&self, program: &WebGLProgram) -> String {
+        unsafe {
+            let mut len = 0;
+            gl::GetProgramiv(program.0, gl::INFO_LOG_LENGTH, &mut len);
+            if len <= 1 {
+                return String::new();
+            }
+
+            let mut buf = Vec::with_capacity(len as usize);
+            buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
+            gl::GetProgramInfoLog(
+                program.0,
+                len,
+                ptr::null_mut(),
+                buf.as_mut_ptr() as *mut gl::types::GLchar,
+            );
+
+            String::from_utf8_lossy(&buf).into_owned()
+        }
+    }
+
     pub fn use_program(&self, program: &WebGLProgram) {
         unsafe {
             gl::UseProgram(program.0);
@@ -357,6 +416,13 @@ impl GLContext {
         check_gl_error("viewport");
     }
 
+    pub fn scissor(&self, x: i32, y: i32, width: u32, height: u32) {
+        unsafe {
+            gl::Scissor(x, y, width as _, height as _);
+        };
+        check_gl_error("scissor");
+    }
+
     pub fn draw_elements(&self, mode: Primitives, count: usize, kind: DataType, offset: u32) {
         unsafe {
             gl::DrawElements(mode as _, count as _, kind as _, offset as _);
@@ -824,4 +890,81 @@ impl GLContext {
 
         check_gl_error("unbind_framebuffer");
     }
+
+    /// True when 32 bits indices can be drawn, always on desktop OpenGL and OpenGL ES 3,
+    /// and with OES_element_index_uint on OpenGL ES 2
+    pub fn element_index_uint_supported(&self) -> bool {
+        let (es, major, _) = gl_version();
+        !es || major >= 3 || has_extension("GL_OES_element_index_uint")
+    }
+
+    /// True when the time elapsed queries are available, from OpenGL 3.3,
+    /// with ARB_timer_query or with EXT_disjoint_timer_query on OpenGL ES.
+    /// The loaders return a stub for any function, so they are only checked in addition.
+    pub fn timer_query_supported(&self) -> bool {
+        let (es, major, minor) = gl_version();
+        let supported = if es {
+            has_extension("GL_EXT_disjoint_timer_query")
+        } else {
+            (major, minor) >= (3, 3) || has_extension("GL_ARB_timer_query")
+        };
+
+        supported && gl::BeginQuery::is_loaded() && gl::GetQueryObjectui64v::is_loaded()
+    }
+
+    pub fn create_query(&self) -> WebGLQuery {
+        let mut query = WebGLQuery(0);
+        unsafe {
+            gl::GenQueries(1, &mut query.0);
+        }
+        check_gl_error("create_query");
+        query
+    }
+
+    pub fn delete_query(&self, query: &WebGLQuery) {
+        unsafe {
+            gl::DeleteQueries(1, &query.0);
+        }
+        check_gl_error("delete_query");
+    }
+
+    /// Start measuring the gpu time of the following commands
+    pub fn begin_time_query(&self, query: &WebGLQuery) {
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, query.0);
+        }
+        check_gl_error("begin_time_query");
+    }
+
+    pub fn end_time_query(&self) {
+        unsafe {
+            gl::EndQuery(gl::TIME_ELAPSED);
+        }
+        check_gl_error("end_time_query");
+    }
+
+    pub fn query_result_available(&self, query: &WebGLQuery) -> bool {
+        let mut available = 0;
+        unsafe {
+            gl::GetQueryObjectiv(query.0, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        check_gl_error("query_result_available");
+        available != 0
+    }
+
+    /// Result of a time query in nanoseconds
+    pub fn query_result(&self, query: &WebGLQuery) -> u64 {
+        let mut ns: u64 = 0;
+        unsafe {
+            gl::GetQueryObjectui64v(query.0, gl::QUERY_RESULT, &mut ns);
+        }
+        check_gl_error("query_result");
+        ns
+    }
+
+    /// True when the results of the time queries are invalid, e.g. the gpu changed its frequency.
+    /// It doesn't happen with desktop OpenGL.
+    pub fn gpu_disjoint(&self) -> bool {
+        false
+    }
 }